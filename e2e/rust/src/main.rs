@@ -112,7 +112,8 @@ pub async fn test() {
             }
             Err(err) => ic_cdk::trap(&format!("error in `eth_getBlockByNumber`: {:?}", err)),
         },
-        MultiRpcResult::Inconsistent(results) => ic_cdk::trap(&format!(
+        MultiRpcResult::Inconsistent(results)
+        | MultiRpcResult::InconsistentWithDiagnostics { results, .. } => ic_cdk::trap(&format!(
             "inconsistent results in `eth_getBlockByNumber`: {}",
             debug_inconsistent(&results)
         )),
@@ -632,6 +632,15 @@ pub trait EvmRpcConfig {
 
     /// Return a new RPC config with the given response consensus.
     fn with_response_consensus(self, response_consensus: ConsensusStrategy) -> Self;
+
+    /// Return a new RPC config with diagnostics for inconsistent results enabled or disabled.
+    fn with_diagnostics(self, diagnostics: bool) -> Self;
+
+    /// Return a new RPC config with fallback providers enabled or disabled.
+    fn with_fallback_providers(self, fallback_providers: bool) -> Self;
+
+    /// Return a new RPC config with replicated or non-replicated HTTP outcalls.
+    fn with_is_replicated(self, is_replicated: bool) -> Self;
 }
 
 impl EvmRpcConfig for RpcConfig {
@@ -648,6 +657,27 @@ impl EvmRpcConfig for RpcConfig {
             ..self
         }
     }
+
+    fn with_diagnostics(self, diagnostics: bool) -> Self {
+        Self {
+            diagnostics: Some(diagnostics),
+            ..self
+        }
+    }
+
+    fn with_fallback_providers(self, fallback_providers: bool) -> Self {
+        Self {
+            fallback_providers: Some(fallback_providers),
+            ..self
+        }
+    }
+
+    fn with_is_replicated(self, is_replicated: bool) -> Self {
+        Self {
+            is_replicated: Some(is_replicated),
+            ..self
+        }
+    }
 }
 
 impl EvmRpcConfig for GetLogsRpcConfig {
@@ -664,6 +694,27 @@ impl EvmRpcConfig for GetLogsRpcConfig {
             ..self
         }
     }
+
+    fn with_diagnostics(self, diagnostics: bool) -> Self {
+        Self {
+            diagnostics: Some(diagnostics),
+            ..self
+        }
+    }
+
+    fn with_fallback_providers(self, fallback_providers: bool) -> Self {
+        Self {
+            fallback_providers: Some(fallback_providers),
+            ..self
+        }
+    }
+
+    fn with_is_replicated(self, is_replicated: bool) -> Self {
+        Self {
+            is_replicated: Some(is_replicated),
+            ..self
+        }
+    }
 }
 
 impl<
@@ -697,6 +748,39 @@ impl<
         );
         self
     }
+
+    /// Enable or disable diagnostics for inconsistent results of that request.
+    pub fn with_diagnostics(mut self, diagnostics: bool) -> Self {
+        self.request.rpc_config = Some(
+            self.request
+                .rpc_config
+                .unwrap_or_default()
+                .with_diagnostics(diagnostics),
+        );
+        self
+    }
+
+    /// Enable or disable fallback providers for that request.
+    pub fn with_fallback_providers(mut self, fallback_providers: bool) -> Self {
+        self.request.rpc_config = Some(
+            self.request
+                .rpc_config
+                .unwrap_or_default()
+                .with_fallback_providers(fallback_providers),
+        );
+        self
+    }
+
+    /// Make replicated (default) or non-replicated HTTP outcalls for that request.
+    pub fn with_is_replicated(mut self, is_replicated: bool) -> Self {
+        self.request.rpc_config = Some(
+            self.request
+                .rpc_config
+                .unwrap_or_default()
+                .with_is_replicated(is_replicated),
+        );
+        self
+    }
 }
 
 /// A request which can be executed with `EvmRpcClient::execute_request` or `EvmRpcClient::execute_query_request`.
@@ -100,7 +100,8 @@ where
 
             match result {
                 MultiRpcResult::Consistent(result) => is_too_few_cycles_error(result),
-                MultiRpcResult::Inconsistent(results) => results
+                MultiRpcResult::Inconsistent(results)
+                | MultiRpcResult::InconsistentWithDiagnostics { results, .. } => results
                     .iter()
                     .any(|(_, result)| is_too_few_cycles_error(result)),
             }
@@ -18,19 +18,30 @@ use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
 
 pub use canlog::{LogFilter, RegexString, RegexSubstitution};
-pub use lifecycle::{InstallArgs, OverrideProvider};
+pub use lifecycle::{
+    AuditAction, AuditLogEntry, CallerLimits, CallerUsage, Config, GetAuditLogArgs, InstallArgs,
+    OverrideProvider,
+};
 pub use request::{
     AccessList, AccessListEntry, BatchRequest, BlockTag, CallArgs, FeeHistoryArgs, GetLogsArgs,
-    GetLogsBatchArgs, GetTransactionCountArgs, TransactionRequest,
+    GetLogsBatchArgs, GetTransactionCountArgs, SubscribeLogsArgs, TransactionRequest,
+    WatchTransactionArgs,
+};
+pub use response::{
+    Block, BlockHeader, ChainHead, FeeHistory, GetLogsPage, LogEntry, LogSubscription,
+    LogsNotification, SendRawTransactionStatus, TransactionNotification, TransactionOutcome,
+    TransactionReceipt, TransactionWatch,
 };
-pub use response::{Block, FeeHistory, LogEntry, SendRawTransactionStatus, TransactionReceipt};
 pub use result::{
-    BatchResult, HttpOutcallError, JsonRpcError, LegacyRejectionCode, MultiRpcResult,
-    ProviderError, RpcError, RpcResult, ValidationError,
+    BatchResult, CallerLimit, HttpOutcallError, InconsistencyDiagnostics, JsonRpcError,
+    LegacyRejectionCode, MultiRpcResult, ProviderError, RpcError, RpcResult, SubscribeError,
+    ValidationError, WithdrawError,
 };
 pub use rpc_client::{
-    ConsensusStrategy, EthMainnetService, EthSepoliaService, GetLogsRpcConfig, HttpHeader,
-    L2MainnetService, Provider, RpcAccess, RpcApi, RpcAuth, RpcConfig, RpcService, RpcServices,
+    ApiKeyInfo, ApiKeyUpdate, Chain, CircuitBreakerState, ConsensusStrategy, EthHoodiService,
+    EthMainnetService, EthSepoliaService, GetLogsRpcConfig, HeadTracker, HttpHeader,
+    JsonRpcGateway, L2MainnetService, L2TestnetService, Provider, ProviderArgs, ProviderHealth,
+    RpcAccess, RpcApi, RpcAuth, RpcConfig, RpcService, RpcServices,
 };
 
 /// A `Nat` that is guaranteed to fit in 256 bits.
@@ -20,3 +20,151 @@ pub struct OverrideProvider {
     #[serde(rename = "overrideUrl")]
     pub override_url: Option<RegexSubstitution>,
 }
+
+/// Effective configuration of the canister.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct Config {
+    pub demo: bool,
+    #[serde(rename = "manageApiKeys")]
+    pub manage_api_keys: Vec<Principal>,
+    #[serde(rename = "logFilter")]
+    pub log_filter: LogFilter,
+    /// Override provider, where the replacement of the URL is redacted since it may contain credentials.
+    #[serde(rename = "overrideProvider")]
+    pub override_provider: OverrideProvider,
+    #[serde(rename = "nodesInSubnet")]
+    pub nodes_in_subnet: u32,
+    /// Whether all methods making HTTP outcalls are suspended.
+    #[serde(rename = "maintenanceMode")]
+    pub maintenance_mode: bool,
+    /// Methods making HTTP outcalls that are suspended, e.g. `eth_sendRawTransaction`.
+    #[serde(rename = "suspendedMethods")]
+    pub suspended_methods: Vec<String>,
+    #[serde(rename = "callerLimits")]
+    pub caller_limits: CallerLimits,
+    /// Principals that are not subject to the caller limits.
+    #[serde(rename = "callerLimitExemptions")]
+    pub caller_limit_exemptions: Vec<Principal>,
+}
+
+/// Limits on the usage of the canister by a single caller in a time window.
+///
+/// A caller that reached one of its limits in the current time window is rejected until the next
+/// time window starts.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct CallerLimits {
+    /// Length of a time window in seconds.
+    #[serde(rename = "windowSecs")]
+    pub window_secs: u64,
+    /// Maximum number of calls to methods making HTTP outcalls. No limit if `None`.
+    #[serde(rename = "maxRequests")]
+    pub max_requests: Option<u64>,
+    /// Maximum number of HTTP outcalls. No limit if `None`.
+    #[serde(rename = "maxOutcalls")]
+    pub max_outcalls: Option<u64>,
+    /// Maximum number of cycles charged. No limit if `None`.
+    #[serde(rename = "maxCycles")]
+    pub max_cycles: Option<u128>,
+}
+
+/// Usage of the canister by a caller in the current time window.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct CallerUsage {
+    /// Start of the current time window, in nanoseconds since the UNIX epoch.
+    #[serde(rename = "windowStart")]
+    pub window_start: u64,
+    pub requests: u64,
+    pub outcalls: u64,
+    pub cycles: u128,
+    /// Whether the caller is exempted from the caller limits.
+    pub exempt: bool,
+}
+
+/// Entry of the audit log of administrative actions.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    /// Index of the entry in the audit log.
+    pub id: u64,
+    /// Time at which the action was performed, in nanoseconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Principal that performed the action.
+    pub caller: Principal,
+    pub action: AuditAction,
+}
+
+/// Administrative action recorded in the audit log.
+///
+/// API keys themselves are never recorded.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub enum AuditAction {
+    /// API keys of the given providers were replaced or removed with `updateApiKeys`.
+    UpdateApiKeys {
+        #[serde(rename = "providerIds")]
+        provider_ids: Vec<u64>,
+    },
+    AddApiKey {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+        #[serde(rename = "keyId")]
+        key_id: u64,
+    },
+    RemoveApiKey {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+        #[serde(rename = "keyId")]
+        key_id: u64,
+    },
+    AddProvider {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+    },
+    UpdateProvider {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+    },
+    RemoveProvider {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+    },
+    SetChain {
+        #[serde(rename = "chainId")]
+        chain_id: u64,
+    },
+    RemoveChain {
+        #[serde(rename = "chainId")]
+        chain_id: u64,
+    },
+    /// Installation or upgrade argument `demo`.
+    SetDemo(bool),
+    /// Installation or upgrade argument `manageApiKeys`.
+    SetApiKeyPrincipals(Vec<Principal>),
+    /// Installation or upgrade argument `logFilter`.
+    SetLogFilter(LogFilter),
+    /// Installation or upgrade argument `overrideProvider`.
+    SetOverrideProvider(OverrideProvider),
+    /// Installation or upgrade argument `nodesInSubnet`.
+    SetNodesInSubnet(u32),
+    SetMaintenanceMode(bool),
+    SetSuspendedMethods(Vec<String>),
+    SetCallerLimits(CallerLimits),
+    SetCallerLimitExemptions(Vec<Principal>),
+    /// Chains whose head is tracked, see `setHeadTrackers`.
+    SetHeadTrackers {
+        #[serde(rename = "chainIds")]
+        chain_ids: Vec<u64>,
+    },
+    /// Chains served over HTTP, see `setJsonRpcGateways`.
+    SetJsonRpcGateways {
+        #[serde(rename = "chainIds")]
+        chain_ids: Vec<u64>,
+    },
+}
+
+/// Arguments to read a page of the audit log.
+#[derive(Clone, Debug, Default, PartialEq, Eq, CandidType, Serialize, Deserialize)]
+pub struct GetAuditLogArgs {
+    /// ID of the first entry to return. Defaults to the first entry of the audit log.
+    pub start: Option<u64>,
+    /// Maximum number of entries to return. Defaults to, and is capped at, 100 entries.
+    pub limit: Option<u64>,
+}
@@ -4,7 +4,7 @@ mod tests;
 #[cfg(feature = "alloy")]
 mod alloy;
 
-use crate::{Hex, Hex20, Hex32, HexByte, Nat256};
+use crate::{GetLogsRpcConfig, Hex, Hex20, Hex32, HexByte, Nat256, RpcConfig, RpcServices};
 use candid::CandidType;
 use serde::Deserialize;
 #[cfg(test)]
@@ -85,6 +85,56 @@ impl<T: IntoIterator<Item = S>, S: Into<Hex20>> From<T> for GetLogsArgs {
     }
 }
 
+/// Subscription to the logs matching a filter, which are pushed to the subscriber as new blocks
+/// are confirmed, see `subscribeLogs`.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct SubscribeLogsArgs {
+    pub source: RpcServices,
+
+    pub config: Option<GetLogsRpcConfig>,
+
+    /// Contract addresses from which logs should originate.
+    pub addresses: Vec<Hex20>,
+
+    /// Topics of the logs, see [`GetLogsArgs::topics`].
+    pub topics: Option<Vec<Vec<Hex32>>>,
+
+    /// First block whose logs are delivered.
+    #[serde(rename = "fromBlock")]
+    pub from_block: Nat256,
+
+    /// Number of blocks that must be mined on top of a block before its logs are delivered,
+    /// so that logs of blocks that are reorganized away are never delivered.
+    pub confirmations: u64,
+
+    /// Method of the subscriber canister that is called with a `LogsNotification`.
+    pub callback: String,
+}
+
+/// Watch of a sent transaction until it is confirmed, dropped or replaced, which is pushed to
+/// the watcher, see `watchTransaction`.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct WatchTransactionArgs {
+    pub source: RpcServices,
+
+    pub config: Option<RpcConfig>,
+
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Hex32,
+
+    /// Sender and nonce of the transaction, used to detect that the transaction was replaced
+    /// by another transaction with the same nonce.
+    pub sender: Option<Hex20>,
+    pub nonce: Option<Nat256>,
+
+    /// Number of blocks that must be mined on top of the block containing the transaction.
+    /// If `None`, the block containing the transaction must be finalized.
+    pub confirmations: Option<u64>,
+
+    /// Method of the watcher canister that is called with a `TransactionNotification`.
+    pub callback: String,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
 pub struct GetTransactionCountArgs {
     pub address: Hex20,
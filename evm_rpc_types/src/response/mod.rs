@@ -4,8 +4,8 @@ mod test;
 #[cfg(feature = "alloy")]
 mod alloy;
 
-use crate::{Hex, Hex20, Hex256, Hex32, HexByte, Nat256};
-use candid::CandidType;
+use crate::{GetLogsRpcConfig, Hex, Hex20, Hex256, Hex32, HexByte, Nat256, RpcConfig, RpcServices};
+use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, CandidType)]
@@ -74,6 +74,150 @@ pub struct LogEntry {
     pub removed: bool,
 }
 
+/// Logs of a block range that may have been only partially queried, see `eth_getLogsPaginated`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, CandidType)]
+pub struct GetLogsPage {
+    /// Logs of the queried part of the block range, without duplicates.
+    pub logs: Vec<LogEntry>,
+
+    /// First block of the remaining part of the block range, which was not queried because
+    /// the attached cycles or the maximum response size were exhausted.
+    /// None if the whole block range was queried.
+    #[serde(rename = "nextFromBlock")]
+    pub next_from_block: Option<Nat256>,
+}
+
+/// Header of a recent block of a tracked chain, see `getRecentHeaders`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, CandidType)]
+pub struct BlockHeader {
+    pub number: Nat256,
+
+    pub hash: Hex32,
+
+    #[serde(rename = "parentHash")]
+    pub parent_hash: Hex32,
+}
+
+/// Head of a tracked chain, see `getChainHead`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, CandidType)]
+pub struct ChainHead {
+    pub latest: BlockHeader,
+
+    /// Not set if the providers do not support the `safe` block tag.
+    pub safe: Option<BlockHeader>,
+
+    /// Not set if the providers do not support the `finalized` block tag.
+    pub finalized: Option<BlockHeader>,
+
+    /// Time at which the head was last updated, in nanoseconds since the UNIX epoch.
+    #[serde(rename = "updatedAt")]
+    pub updated_at: u64,
+}
+
+/// A log subscription, see `subscribeLogs`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, CandidType)]
+pub struct LogSubscription {
+    pub id: u64,
+
+    /// Subscriber canister, whose prepaid cycles account is charged for polling the logs.
+    pub owner: Principal,
+
+    pub source: RpcServices,
+
+    pub config: Option<GetLogsRpcConfig>,
+
+    pub addresses: Vec<Hex20>,
+
+    pub topics: Option<Vec<Vec<Hex32>>>,
+
+    pub confirmations: u64,
+
+    pub callback: String,
+
+    /// First block whose logs were not delivered yet.
+    #[serde(rename = "nextBlock")]
+    pub next_block: Nat256,
+
+    /// Error of the last attempt to poll or deliver the logs, if it failed.
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+}
+
+/// Logs of a block range delivered to the subscriber of a log subscription.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, CandidType)]
+pub struct LogsNotification {
+    #[serde(rename = "subscriptionId")]
+    pub subscription_id: u64,
+
+    /// Logs of the block range, which are delivered at most once.
+    pub logs: Vec<LogEntry>,
+
+    #[serde(rename = "fromBlock")]
+    pub from_block: Nat256,
+
+    #[serde(rename = "toBlock")]
+    pub to_block: Nat256,
+}
+
+/// A watch of a sent transaction, see `watchTransaction`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, CandidType)]
+pub struct TransactionWatch {
+    pub id: u64,
+
+    /// Watcher canister, whose prepaid cycles account is charged for polling the transaction.
+    pub owner: Principal,
+
+    pub source: RpcServices,
+
+    pub config: Option<RpcConfig>,
+
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Hex32,
+
+    pub sender: Option<Hex20>,
+
+    pub nonce: Option<Nat256>,
+
+    pub confirmations: Option<u64>,
+
+    pub callback: String,
+
+    /// Time at which the watch was created, in nanoseconds since the UNIX epoch.
+    #[serde(rename = "createdAt")]
+    pub created_at: u64,
+
+    /// Last receipt of the transaction, which may still be reorganized away.
+    pub receipt: Option<TransactionReceipt>,
+
+    /// Error of the last attempt to poll the transaction or notify the watcher, if it failed.
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+}
+
+/// Final outcome of a watched transaction.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, CandidType)]
+pub enum TransactionOutcome {
+    /// The block containing the transaction has enough confirmations or is finalized.
+    /// The transaction may still have failed, see [`TransactionReceipt::status`].
+    Confirmed(TransactionReceipt),
+    /// The transaction was not included in a block before the watch timed out.
+    Dropped,
+    /// Another transaction with the same sender and nonce was finalized.
+    Replaced,
+}
+
+/// Outcome of a watched transaction delivered to the watcher.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, CandidType)]
+pub struct TransactionNotification {
+    #[serde(rename = "watchId")]
+    pub watch_id: u64,
+
+    #[serde(rename = "transactionHash")]
+    pub transaction_hash: Hex32,
+
+    pub outcome: TransactionOutcome,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, CandidType)]
 pub struct TransactionReceipt {
     /// The hash of the block containing the transaction.
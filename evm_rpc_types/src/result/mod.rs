@@ -19,72 +19,94 @@ pub type RpcResult<T> = Result<T, RpcError>;
 pub enum MultiRpcResult<T> {
     Consistent(RpcResult<T>),
     Inconsistent(Vec<(RpcService, RpcResult<T>)>),
+    /// Same as [`MultiRpcResult::Inconsistent`], together with a summary of the differences
+    /// between the results. Only returned when diagnostics are enabled in the
+    /// [`RpcConfig`](crate::RpcConfig).
+    InconsistentWithDiagnostics {
+        results: Vec<(RpcService, RpcResult<T>)>,
+        diagnostics: InconsistencyDiagnostics,
+    },
+}
+
+/// Summary of the differences between inconsistent results.
+#[derive(Clone, Debug, Default, Eq, PartialEq, CandidType, Deserialize)]
+pub struct InconsistencyDiagnostics {
+    /// Providers grouped by identical results, largest group first.
+    #[serde(rename = "agreeingGroups")]
+    pub agreeing_groups: Vec<Vec<RpcService>>,
+    /// Paths of the fields that differ between the successful results,
+    /// e.g. `$.hash` or `$[2].blockNumber`.
+    #[serde(rename = "differingFields")]
+    pub differing_fields: Vec<String>,
 }
 
 impl<T> MultiRpcResult<T> {
     /// Maps a [`MultiRpcResult`] containing values of type `T` to a [`MultiRpcResult`] containing
     /// values of type `R` by an infallible map.
     pub fn map<R: PartialEq>(self, mut f: impl FnMut(T) -> R) -> MultiRpcResult<R> {
-        match self {
-            MultiRpcResult::Consistent(result) => MultiRpcResult::Consistent(result.map(f)),
-            MultiRpcResult::Inconsistent(results) => MultiRpcResult::Inconsistent(
-                results
-                    .into_iter()
-                    .map(|(service, result)| {
-                        (
-                            service,
-                            match result {
-                                Ok(ok) => Ok(f(ok)),
-                                Err(err) => Err(err),
-                            },
-                        )
-                    })
-                    .collect(),
-            )
-            .collapse(),
-        }
+        self.and_then(|value| Ok(f(value)))
     }
 
     /// Maps a [`MultiRpcResult`] containing values of type `T` to a [`MultiRpcResult`] containing
     /// values of type `R` by a fallible map.
     pub fn and_then<R: PartialEq>(self, mut f: impl FnMut(T) -> RpcResult<R>) -> MultiRpcResult<R> {
+        fn map_results<T, R>(
+            results: Vec<(RpcService, RpcResult<T>)>,
+            f: &mut impl FnMut(T) -> RpcResult<R>,
+        ) -> Vec<(RpcService, RpcResult<R>)> {
+            results
+                .into_iter()
+                .map(|(service, result)| {
+                    (
+                        service,
+                        match result {
+                            Ok(ok) => f(ok),
+                            Err(err) => Err(err),
+                        },
+                    )
+                })
+                .collect()
+        }
+
         match self {
             MultiRpcResult::Consistent(result) => MultiRpcResult::Consistent(result.and_then(f)),
-            MultiRpcResult::Inconsistent(results) => MultiRpcResult::Inconsistent(
-                results
-                    .into_iter()
-                    .map(|(service, result)| {
-                        (
-                            service,
-                            match result {
-                                Ok(ok) => f(ok),
-                                Err(err) => Err(err),
-                            },
-                        )
-                    })
-                    .collect(),
-            )
+            MultiRpcResult::Inconsistent(results) => {
+                MultiRpcResult::Inconsistent(map_results(results, &mut f)).collapse()
+            }
+            MultiRpcResult::InconsistentWithDiagnostics {
+                results,
+                diagnostics,
+            } => MultiRpcResult::InconsistentWithDiagnostics {
+                results: map_results(results, &mut f),
+                diagnostics,
+            }
             .collapse(),
         }
     }
 }
 
 impl<T: PartialEq> MultiRpcResult<T> {
-    /// Collapses an [`Inconsistent`](MultiRpcResult::Inconsistent) into
+    /// Collapses an [`Inconsistent`](MultiRpcResult::Inconsistent) or
+    /// [`InconsistentWithDiagnostics`](MultiRpcResult::InconsistentWithDiagnostics) into
     /// [`Consistent`](MultiRpcResult::Consistent) if all results match.
     /// Otherwise, returns the value unchanged.
     fn collapse(self) -> MultiRpcResult<T> {
+        fn all_equal<T: PartialEq>(results: &[(RpcService, RpcResult<T>)]) -> bool {
+            match results.first() {
+                Some((_, first)) => results.iter().all(|(_, result)| result == first),
+                None => false,
+            }
+        }
+
         match self {
-            MultiRpcResult::Consistent(r) => MultiRpcResult::Consistent(r),
-            MultiRpcResult::Inconsistent(v) => {
-                if let Some((_, first)) = v.first() {
-                    if v.iter().all(|(_, result)| result == first) {
-                        let (_, value) = v.into_iter().next().unwrap();
-                        return MultiRpcResult::Consistent(value);
-                    }
-                }
-                MultiRpcResult::Inconsistent(v)
+            MultiRpcResult::Inconsistent(results)
+            | MultiRpcResult::InconsistentWithDiagnostics { results, .. }
+                if all_equal(&results) =>
+            {
+                let (_, value) = results.into_iter().next().unwrap();
+                MultiRpcResult::Consistent(value)
             }
+            result => result,
         }
     }
 }
@@ -93,7 +115,11 @@ impl<T: Debug> MultiRpcResult<T> {
     pub fn expect_consistent(self) -> RpcResult<T> {
         match self {
             MultiRpcResult::Consistent(result) => result,
-            MultiRpcResult::Inconsistent(inconsistent_result) => {
+            MultiRpcResult::Inconsistent(inconsistent_result)
+            | MultiRpcResult::InconsistentWithDiagnostics {
+                results: inconsistent_result,
+                ..
+            } => {
                 panic!("Expected consistent, but got: {:?}", inconsistent_result)
             }
         }
@@ -104,7 +130,8 @@ impl<T: Debug> MultiRpcResult<T> {
             MultiRpcResult::Consistent(consistent_result) => {
                 panic!("Expected inconsistent:, but got: {:?}", consistent_result)
             }
-            MultiRpcResult::Inconsistent(results) => results,
+            MultiRpcResult::Inconsistent(results)
+            | MultiRpcResult::InconsistentWithDiagnostics { results, .. } => results,
         }
     }
 }
@@ -150,6 +177,30 @@ pub enum ProviderError {
     MissingRequiredProvider,
     #[error("Invalid RPC config: {0}")]
     InvalidRpcConfig(String),
+    /// The method was suspended by the controllers, e.g. during a provider incident.
+    #[error("Method {method} is temporarily suspended")]
+    Suspended { method: String },
+    /// The caller exceeded one of its limits in the current time window.
+    #[error("Too many {limit} by the caller in the current time window")]
+    CallerLimitExceeded { limit: CallerLimit },
+}
+
+/// Limit on the usage of the canister by a single caller in a time window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, CandidType, Deserialize)]
+pub enum CallerLimit {
+    Requests,
+    Outcalls,
+    Cycles,
+}
+
+impl std::fmt::Display for CallerLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CallerLimit::Requests => write!(f, "requests"),
+            CallerLimit::Outcalls => write!(f, "HTTP outcalls"),
+            CallerLimit::Cycles => write!(f, "cycles"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, CandidType, Deserialize, Error)]
@@ -179,6 +230,32 @@ pub struct JsonRpcError {
     pub message: String,
 }
 
+/// Error when withdrawing cycles from a prepaid cycles account.
+#[derive(Clone, Debug, Eq, PartialEq, CandidType, Deserialize, Error)]
+pub enum WithdrawError {
+    #[error("Cannot withdraw less than {minimum} cycles")]
+    AmountTooSmall { minimum: u128 },
+    #[error("Insufficient balance: {balance} cycles")]
+    InsufficientBalance { balance: u128 },
+    /// The cycles could not be deposited to the caller, e.g. because the caller is not a canister.
+    /// The balance of the caller is unchanged.
+    #[error("Failed to deposit cycles: {0}")]
+    DepositFailed(String),
+}
+
+/// Error when subscribing to logs or watching a transaction.
+#[derive(Clone, Debug, Eq, PartialEq, CandidType, Deserialize, Error)]
+pub enum SubscribeError {
+    #[error("Cannot have more than {maximum} subscriptions")]
+    TooManySubscriptions { maximum: u64 },
+    #[error("Invalid subscription: {0}")]
+    InvalidSubscription(RpcError),
+    /// The prepaid cycles balance of the subscriber, including the cycles attached to the call,
+    /// is below the minimum required to pay for polling.
+    #[error("Insufficient balance: {balance} cycles, at least {minimum} cycles required")]
+    InsufficientBalance { balance: u128, minimum: u128 },
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, PartialOrd, Ord, CandidType, Deserialize, Error)]
 pub enum ValidationError {
     #[error("Custom: {0}")]
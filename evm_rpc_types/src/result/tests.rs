@@ -1,4 +1,4 @@
-use crate::result::{BatchResult, ProviderError, RpcError, RpcResult};
+use crate::result::{BatchResult, InconsistencyDiagnostics, ProviderError, RpcError, RpcResult};
 use crate::{EthMainnetService, MultiRpcResult, RpcService, ValidationError};
 use candid::{CandidType, Decode, Deserialize, Encode};
 
@@ -203,6 +203,45 @@ fn test_multi_rpc_result_and_then() {
     );
 }
 
+#[test]
+fn test_multi_rpc_result_map_with_diagnostics() {
+    let diagnostics = InconsistencyDiagnostics {
+        agreeing_groups: vec![
+            vec![RpcService::EthMainnet(EthMainnetService::Ankr)],
+            vec![RpcService::EthMainnet(EthMainnetService::Llama)],
+        ],
+        differing_fields: vec!["$".to_string()],
+    };
+    assert_eq!(
+        MultiRpcResult::InconsistentWithDiagnostics {
+            results: vec![
+                (RpcService::EthMainnet(EthMainnetService::Ankr), Ok(5)),
+                (RpcService::EthMainnet(EthMainnetService::Llama), Ok(6))
+            ],
+            diagnostics: diagnostics.clone(),
+        }
+        .map(|n| n + 1),
+        MultiRpcResult::InconsistentWithDiagnostics {
+            results: vec![
+                (RpcService::EthMainnet(EthMainnetService::Ankr), Ok(6)),
+                (RpcService::EthMainnet(EthMainnetService::Llama), Ok(7))
+            ],
+            diagnostics,
+        }
+    );
+    assert_eq!(
+        MultiRpcResult::InconsistentWithDiagnostics {
+            results: vec![
+                (RpcService::EthMainnet(EthMainnetService::Ankr), Ok(2)),
+                (RpcService::EthMainnet(EthMainnetService::Llama), Ok(3))
+            ],
+            diagnostics: InconsistencyDiagnostics::default(),
+        }
+        .map(|n| n / 2),
+        MultiRpcResult::Consistent(Ok(1))
+    );
+}
+
 mod batch_result_backwards_compatibility {
     use super::*;
     use crate::{
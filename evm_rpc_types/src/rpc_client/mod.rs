@@ -7,16 +7,36 @@ use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 use strum::VariantArray;
 
-#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize, Serialize)]
 pub struct RpcConfig {
     #[serde(rename = "responseSizeEstimate")]
     pub response_size_estimate: Option<u64>,
 
     #[serde(rename = "responseConsensus")]
     pub response_consensus: Option<ConsensusStrategy>,
+
+    /// Whether inconsistent results should be returned together with a summary of the
+    /// differences between providers, see [`InconsistencyDiagnostics`](crate::InconsistencyDiagnostics).
+    pub diagnostics: Option<bool>,
+
+    /// Whether additional supported providers of the same chain should be queried if too few
+    /// providers returned a successful result to reach the threshold of a
+    /// [`ConsensusStrategy::Threshold`] or [`ConsensusStrategy::BlockLag`] strategy.
+    ///
+    /// The additional providers are only queried if the remaining attached cycles cover their cost,
+    /// which is included in the cost returned by the `*CyclesCost` endpoints.
+    #[serde(rename = "fallbackProviders")]
+    pub fallback_providers: Option<bool>,
+
+    /// Whether HTTP outcalls are replicated, i.e. made by all nodes of the subnet which must then
+    /// agree on the response (default), or made by a single node.
+    ///
+    /// Non-replicated outcalls are much cheaper, but the response of a single node is trusted.
+    #[serde(rename = "isReplicated")]
+    pub is_replicated: Option<bool>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize, Serialize)]
 pub struct GetLogsRpcConfig {
     #[serde(rename = "responseSizeEstimate")]
     pub response_size_estimate: Option<u64>,
@@ -26,6 +46,17 @@ pub struct GetLogsRpcConfig {
 
     #[serde(rename = "maxBlockRange")]
     pub max_block_range: Option<u32>,
+
+    /// See [`RpcConfig::diagnostics`].
+    pub diagnostics: Option<bool>,
+
+    /// See [`RpcConfig::fallback_providers`].
+    #[serde(rename = "fallbackProviders")]
+    pub fallback_providers: Option<bool>,
+
+    /// See [`RpcConfig::is_replicated`].
+    #[serde(rename = "isReplicated")]
+    pub is_replicated: Option<bool>,
 }
 
 impl From<GetLogsRpcConfig> for RpcConfig {
@@ -33,6 +64,9 @@ impl From<GetLogsRpcConfig> for RpcConfig {
         Self {
             response_size_estimate: config.response_size_estimate,
             response_consensus: config.response_consensus,
+            diagnostics: config.diagnostics,
+            fallback_providers: config.fallback_providers,
+            is_replicated: config.is_replicated,
         }
     }
 }
@@ -43,6 +77,9 @@ impl From<RpcConfig> for GetLogsRpcConfig {
             response_size_estimate: config.response_size_estimate,
             response_consensus: config.response_consensus,
             max_block_range: None,
+            diagnostics: config.diagnostics,
+            fallback_providers: config.fallback_providers,
+            is_replicated: config.is_replicated,
         }
     }
 }
@@ -55,7 +92,7 @@ impl GetLogsRpcConfig {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, Default, CandidType, Deserialize, Serialize)]
 pub enum ConsensusStrategy {
     /// All providers must return the same non-error result.
     #[default]
@@ -72,9 +109,29 @@ pub enum ConsensusStrategy {
         /// Minimum number of providers that must return the same (non-error) result.
         min: u8,
     },
+
+    /// A subset of providers must return results that agree up to a bounded block lag.
+    ///
+    /// This strategy only differs from [`ConsensusStrategy::Threshold`] for block-indexed results:
+    /// * `eth_getBlockByNumber`: the returned blocks may be at most `max_block_lag` blocks apart
+    ///   and the block with the lowest number returned by at least `min` providers is returned.
+    /// * `eth_getLogs` with `toBlock: Latest`: the latest block number of each provider is queried
+    ///   beforehand and only the logs up to the lowest of these block numbers are kept and compared.
+    ///   This doubles the number of HTTP outcalls.
+    BlockLag {
+        /// Total number of providers to be queried, see [`ConsensusStrategy::Threshold`].
+        total: Option<u8>,
+
+        /// Minimum number of providers whose results must agree.
+        min: u8,
+
+        /// Maximum number of blocks by which the results of agreeing providers may differ.
+        #[serde(rename = "maxBlockLag")]
+        max_block_lag: u64,
+    },
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize, Serialize)]
 pub enum RpcServices {
     Custom {
         #[serde(rename = "chainId")]
@@ -86,6 +143,52 @@ pub enum RpcServices {
     ArbitrumOne(Option<Vec<L2MainnetService>>),
     BaseMainnet(Option<Vec<L2MainnetService>>),
     OptimismMainnet(Option<Vec<L2MainnetService>>),
+    EthHoodi(Option<Vec<EthHoodiService>>),
+    ArbitrumSepolia(Option<Vec<L2TestnetService>>),
+    BaseSepolia(Option<Vec<L2TestnetService>>),
+    OptimismSepolia(Option<Vec<L2TestnetService>>),
+    /// A chain from the chain registry, see [`Chain`].
+    Chain {
+        #[serde(rename = "chainId")]
+        chain_id: u64,
+        /// Providers to query. If `None`, the default providers of the chain are used.
+        services: Option<Vec<u64>>,
+    },
+}
+
+/// An EVM chain in the chain registry.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct Chain {
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+    pub name: String,
+    /// Providers queried when no providers are specified, in order of preference.
+    #[serde(rename = "defaultProviders")]
+    pub default_providers: Vec<u64>,
+    /// Default response size estimate for `eth_getBlockByNumber`.
+    #[serde(rename = "blockSizeEstimate")]
+    pub block_size_estimate: Option<u64>,
+    /// Default maximum block range for `eth_getLogs`, see [`GetLogsRpcConfig::max_block_range`].
+    #[serde(rename = "maxBlockRange")]
+    pub max_block_range: Option<u32>,
+}
+
+/// Providers used to track the head of a chain, see `setHeadTrackers`.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct HeadTracker {
+    pub source: RpcServices,
+    pub config: Option<RpcConfig>,
+}
+
+/// Providers used to serve the JSON-RPC requests of a chain sent over HTTP to `/rpc/{chainId}`,
+/// see `setJsonRpcGateways`.
+///
+/// The requests are charged to the dedicated prepaid cycles account of the gateway of the chain,
+/// see `depositJsonRpcGateway`.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct JsonRpcGateway {
+    pub source: RpcServices,
+    pub config: Option<RpcConfig>,
 }
 
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Hash, Serialize, Deserialize, CandidType)]
@@ -194,6 +297,58 @@ impl L2MainnetService {
     }
 }
 
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Serialize,
+    Deserialize,
+    CandidType,
+    VariantArray,
+)]
+pub enum EthHoodiService {
+    Alchemy,
+    Ankr,
+    PublicNode,
+}
+
+impl EthHoodiService {
+    pub const fn all() -> &'static [Self] {
+        EthHoodiService::VARIANTS
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Serialize,
+    Deserialize,
+    CandidType,
+    VariantArray,
+)]
+pub enum L2TestnetService {
+    Alchemy,
+    Ankr,
+    PublicNode,
+}
+
+impl L2TestnetService {
+    pub const fn all() -> &'static [Self] {
+        L2TestnetService::VARIANTS
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Ord, PartialOrd, Hash, Serialize, Deserialize, CandidType)]
 pub enum RpcService {
     Provider(u64),
@@ -203,6 +358,10 @@ pub enum RpcService {
     ArbitrumOne(L2MainnetService),
     BaseMainnet(L2MainnetService),
     OptimismMainnet(L2MainnetService),
+    EthHoodi(EthHoodiService),
+    ArbitrumSepolia(L2TestnetService),
+    BaseSepolia(L2TestnetService),
+    OptimismSepolia(L2TestnetService),
 }
 
 impl Debug for RpcService {
@@ -215,6 +374,10 @@ impl Debug for RpcService {
             RpcService::ArbitrumOne(service)
             | RpcService::BaseMainnet(service)
             | RpcService::OptimismMainnet(service) => write!(f, "{:?}", service),
+            RpcService::EthHoodi(service) => write!(f, "{:?}", service),
+            RpcService::ArbitrumSepolia(service)
+            | RpcService::BaseSepolia(service)
+            | RpcService::OptimismSepolia(service) => write!(f, "{:?}", service),
         }
     }
 }
@@ -227,6 +390,88 @@ pub struct Provider {
     pub chain_id: u64,
     pub access: RpcAccess,
     pub alias: Option<RpcService>,
+    /// State of the circuit breaker of the provider, if the provider is one of the supported providers.
+    #[serde(rename = "circuitBreaker")]
+    pub circuit_breaker: Option<CircuitBreakerState>,
+}
+
+/// Arguments to add or update a provider in the provider registry.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ProviderArgs {
+    #[serde(rename = "chainId")]
+    pub chain_id: u64,
+    pub access: RpcAccess,
+    /// Supported RPC service (e.g. `EthMainnet(Alchemy)`) that should be resolved to this provider.
+    ///
+    /// Each supported RPC service can be the alias of at most one provider.
+    pub alias: Option<RpcService>,
+}
+
+/// Change to the API keys of a provider.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum ApiKeyUpdate {
+    /// Add an API key, which is used after the existing API keys of the provider.
+    Add {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+        #[serde(rename = "apiKey")]
+        api_key: String,
+    },
+    /// Remove the API key with the given ID.
+    Remove {
+        #[serde(rename = "providerId")]
+        provider_id: u64,
+        #[serde(rename = "keyId")]
+        key_id: u64,
+    },
+}
+
+/// API key of a provider, identified by its ID so that the key itself is never exposed.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ApiKeyInfo {
+    #[serde(rename = "keyId")]
+    pub key_id: u64,
+    /// Number of requests sent with the API key since the last canister upgrade.
+    pub requests: u64,
+    /// Number of non-successful HTTP responses for requests sent with the API key since the last canister upgrade.
+    pub errors: u64,
+    /// Whether the API key is skipped because the provider rate limited it.
+    #[serde(rename = "rateLimited")]
+    pub rate_limited: bool,
+}
+
+/// State of the circuit breaker of a provider.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub enum CircuitBreakerState {
+    /// The provider is chosen as usual.
+    Closed,
+    /// The provider failed too many times in a row and is not chosen for default providers
+    /// until the cool-down period ends.
+    Open,
+    /// The cool-down period ended and the next call to the provider decides whether the
+    /// circuit breaker closes again or opens for another cool-down period.
+    HalfOpen,
+}
+
+/// Health of a provider, computed from the outcomes of its calls in the last 20 minutes.
+#[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
+pub struct ProviderHealth {
+    pub service: RpcService,
+    #[serde(rename = "okResults")]
+    pub ok_results: u64,
+    #[serde(rename = "httpErrors")]
+    pub http_errors: u64,
+    #[serde(rename = "jsonRpcErrors")]
+    pub json_rpc_errors: u64,
+    #[serde(rename = "responseTooLarge")]
+    pub response_too_large: u64,
+    /// Number of successful results that differed from the result of the majority of providers.
+    pub disagreements: u64,
+    /// Providers with a higher score are preferred when no providers are specified.
+    pub score: i64,
+    /// Whether the provider is only chosen if not enough other providers are available,
+    /// because it keeps disagreeing with the majority of providers.
+    pub excluded: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, CandidType, Deserialize, Serialize)]
@@ -252,4 +497,14 @@ pub enum RpcAuth {
         #[serde(rename = "urlPattern")]
         url_pattern: String,
     },
+    /// API key will be used as the value of a custom header, e.g., `x-api-key: API_KEY`.
+    /// The `Content-Type`, `Host` and `Authorization` headers cannot be used.
+    Header {
+        url: String,
+        #[serde(rename = "headerName")]
+        header_name: String,
+    },
+    /// API key of the form `USERNAME:PASSWORD` will be used for HTTP basic authentication, i.e.,
+    /// `Authorization: Basic BASE64(USERNAME:PASSWORD)`
+    BasicAuth { url: String },
 }
@@ -1,4 +1,7 @@
-use evm_rpc_types::{EthMainnetService, EthSepoliaService, L2MainnetService, RpcApi, RpcServices};
+use evm_rpc_types::{
+    EthHoodiService, EthMainnetService, EthSepoliaService, L2MainnetService, L2TestnetService,
+    RpcApi, RpcServices,
+};
 use ic_management_canister_types::HttpHeader;
 use proptest::arbitrary::any;
 use proptest::collection::{vec, SizeRange};
@@ -13,6 +16,10 @@ pub fn arb_rpc_services() -> impl Strategy<Value = RpcServices> {
         option::of(arb_l2_mainnet_services()).prop_map(RpcServices::ArbitrumOne),
         option::of(arb_l2_mainnet_services()).prop_map(RpcServices::BaseMainnet),
         option::of(arb_l2_mainnet_services()).prop_map(RpcServices::OptimismMainnet),
+        option::of(arb_eth_hoodi_services()).prop_map(RpcServices::EthHoodi),
+        option::of(arb_l2_testnet_services()).prop_map(RpcServices::ArbitrumSepolia),
+        option::of(arb_l2_testnet_services()).prop_map(RpcServices::BaseSepolia),
+        option::of(arb_l2_testnet_services()).prop_map(RpcServices::OptimismSepolia),
     ]
 }
 
@@ -52,3 +59,17 @@ fn arb_l2_mainnet_services() -> impl Strategy<Value = Vec<L2MainnetService>> {
     (0..=max_num_services, Just(services).prop_shuffle())
         .prop_map(|(num_services, services)| services.into_iter().take(num_services).collect())
 }
+
+fn arb_eth_hoodi_services() -> impl Strategy<Value = Vec<EthHoodiService>> {
+    let services = EthHoodiService::all().to_owned();
+    let max_num_services = services.len();
+    (0..=max_num_services, Just(services).prop_shuffle())
+        .prop_map(|(num_services, services)| services.into_iter().take(num_services).collect())
+}
+
+fn arb_l2_testnet_services() -> impl Strategy<Value = Vec<L2TestnetService>> {
+    let services = L2TestnetService::all().to_owned();
+    let max_num_services = services.len();
+    (0..=max_num_services, Just(services).prop_shuffle())
+        .prop_map(|(num_services, services)| services.into_iter().take(num_services).collect())
+}
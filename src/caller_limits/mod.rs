@@ -0,0 +1,145 @@
+//! Limit the usage of the canister by a single caller in a time window.
+
+#[cfg(test)]
+mod tests;
+
+use candid::Principal;
+use evm_rpc_types::{CallerLimit, CallerLimits, CallerUsage};
+use std::collections::BTreeMap;
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Usage of the canister by each caller in the current time window.
+///
+/// Time windows are aligned on multiples of their length, so that the time windows of all callers
+/// start at the same time and the usage of all callers is reset together.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallerUsages {
+    window_start: u64,
+    usages: BTreeMap<Principal, Usage>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Usage {
+    requests: u64,
+    outcalls: u64,
+    cycles: u128,
+}
+
+impl CallerUsages {
+    /// Record a call of the caller to a method making HTTP outcalls.
+    ///
+    /// Returns an error without recording the call if the caller already reached one of its limits
+    /// in the current time window, unless it is exempted from the limits.
+    pub fn try_record_request(
+        &mut self,
+        caller: Principal,
+        limits: &CallerLimits,
+        exempt: bool,
+        now: u64,
+    ) -> Result<(), CallerLimit> {
+        let usage = self.current_usage(caller, limits, now);
+        if !exempt {
+            if limits.max_requests.is_some_and(|max| usage.requests >= max) {
+                return Err(CallerLimit::Requests);
+            }
+            if limits.max_outcalls.is_some_and(|max| usage.outcalls >= max) {
+                return Err(CallerLimit::Outcalls);
+            }
+            if limits.max_cycles.is_some_and(|max| usage.cycles >= max) {
+                return Err(CallerLimit::Cycles);
+            }
+        }
+        usage.requests += 1;
+        Ok(())
+    }
+
+    /// Returns an error if making the given number of HTTP outcalls costing the given amount of
+    /// cycles would exceed one of the limits of the caller in the current time window, unless it
+    /// is exempted from the limits.
+    ///
+    /// Outcalls are checked and recorded as they are charged, so that concurrent calls of the same
+    /// caller, which were all admitted by [`CallerUsages::try_record_request`], cannot together
+    /// exceed the limits.
+    pub fn ensure_within_limits(
+        &self,
+        caller: &Principal,
+        outcalls: u64,
+        cycles: u128,
+        limits: &CallerLimits,
+        exempt: bool,
+        now: u64,
+    ) -> Result<(), CallerLimit> {
+        if exempt {
+            return Ok(());
+        }
+        let usage = self.get(caller, limits, exempt, now);
+        if outcalls > 0
+            && limits
+                .max_outcalls
+                .is_some_and(|max| usage.outcalls.saturating_add(outcalls) > max)
+        {
+            return Err(CallerLimit::Outcalls);
+        }
+        if cycles > 0
+            && limits
+                .max_cycles
+                .is_some_and(|max| usage.cycles.saturating_add(cycles) > max)
+        {
+            return Err(CallerLimit::Cycles);
+        }
+        Ok(())
+    }
+
+    pub fn record_outcall(&mut self, caller: Principal, limits: &CallerLimits, now: u64) {
+        self.current_usage(caller, limits, now).outcalls += 1;
+    }
+
+    pub fn record_cycles(
+        &mut self,
+        caller: Principal,
+        cycles: u128,
+        limits: &CallerLimits,
+        now: u64,
+    ) {
+        let usage = self.current_usage(caller, limits, now);
+        usage.cycles = usage.cycles.saturating_add(cycles);
+    }
+
+    /// Returns the usage of the caller in the current time window.
+    pub fn get(
+        &self,
+        caller: &Principal,
+        limits: &CallerLimits,
+        exempt: bool,
+        now: u64,
+    ) -> CallerUsage {
+        let window_start = window_start(limits, now);
+        let usage = if self.window_start == window_start {
+            self.usages.get(caller).copied().unwrap_or_default()
+        } else {
+            Usage::default()
+        };
+        CallerUsage {
+            window_start,
+            requests: usage.requests,
+            outcalls: usage.outcalls,
+            cycles: usage.cycles,
+            exempt,
+        }
+    }
+
+    fn current_usage(&mut self, caller: Principal, limits: &CallerLimits, now: u64) -> &mut Usage {
+        let window_start = window_start(limits, now);
+        if self.window_start != window_start {
+            self.window_start = window_start;
+            self.usages.clear();
+        }
+        self.usages.entry(caller).or_default()
+    }
+}
+
+fn window_start(limits: &CallerLimits, now: u64) -> u64 {
+    let window = limits.window_secs.saturating_mul(NANOS_PER_SEC).max(1);
+    now - now % window
+}
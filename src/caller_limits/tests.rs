@@ -0,0 +1,137 @@
+use crate::caller_limits::CallerUsages;
+use candid::Principal;
+use evm_rpc_types::{CallerLimit, CallerLimits};
+
+const ALICE: Principal = Principal::from_slice(&[1]);
+const BOB: Principal = Principal::from_slice(&[2]);
+const MINUTE: u64 = 60_000_000_000;
+
+#[test]
+fn should_reject_caller_over_request_limit() {
+    let limits = limits(Some(2), None, None);
+    let mut usages = CallerUsages::default();
+
+    assert_eq!(usages.try_record_request(ALICE, &limits, false, 0), Ok(()));
+    assert_eq!(usages.try_record_request(ALICE, &limits, false, 1), Ok(()));
+    assert_eq!(
+        usages.try_record_request(ALICE, &limits, false, 2),
+        Err(CallerLimit::Requests)
+    );
+    assert_eq!(usages.try_record_request(BOB, &limits, false, 3), Ok(()));
+    assert_eq!(usages.get(&ALICE, &limits, false, 4).requests, 2);
+}
+
+#[test]
+fn should_reject_caller_over_outcall_and_cycles_limits() {
+    let mut usages = CallerUsages::default();
+
+    let outcall_limits = limits(None, Some(1), None);
+    assert_eq!(
+        usages.try_record_request(ALICE, &outcall_limits, false, 0),
+        Ok(())
+    );
+    usages.record_outcall(ALICE, &outcall_limits, 0);
+    assert_eq!(
+        usages.try_record_request(ALICE, &outcall_limits, false, 1),
+        Err(CallerLimit::Outcalls)
+    );
+
+    let cycles_limits = limits(None, None, Some(1_000));
+    assert_eq!(
+        usages.try_record_request(BOB, &cycles_limits, false, 0),
+        Ok(())
+    );
+    usages.record_cycles(BOB, 1_000, &cycles_limits, 0);
+    assert_eq!(
+        usages.try_record_request(BOB, &cycles_limits, false, 1),
+        Err(CallerLimit::Cycles)
+    );
+}
+
+#[test]
+fn should_reject_outcalls_of_concurrent_requests_over_limits() {
+    let limits = limits(None, Some(2), Some(1_000));
+    let mut usages = CallerUsages::default();
+
+    // Both requests are admitted before any of their outcalls is made.
+    assert_eq!(usages.try_record_request(ALICE, &limits, false, 0), Ok(()));
+    assert_eq!(usages.try_record_request(ALICE, &limits, false, 0), Ok(()));
+
+    assert_eq!(
+        usages.ensure_within_limits(&ALICE, 1, 400, &limits, false, 1),
+        Ok(())
+    );
+    usages.record_outcall(ALICE, &limits, 1);
+    usages.record_cycles(ALICE, 400, &limits, 1);
+    assert_eq!(
+        usages.ensure_within_limits(&ALICE, 1, 700, &limits, false, 2),
+        Err(CallerLimit::Cycles)
+    );
+    assert_eq!(
+        usages.ensure_within_limits(&ALICE, 1, 600, &limits, false, 2),
+        Ok(())
+    );
+    usages.record_outcall(ALICE, &limits, 2);
+    usages.record_cycles(ALICE, 600, &limits, 2);
+    assert_eq!(
+        usages.ensure_within_limits(&ALICE, 1, 0, &limits, false, 3),
+        Err(CallerLimit::Outcalls)
+    );
+    assert_eq!(
+        usages.ensure_within_limits(&ALICE, 1, 1, &limits, true, 3),
+        Ok(())
+    );
+    assert_eq!(
+        usages.ensure_within_limits(&BOB, 1, 1_000, &limits, false, 3),
+        Ok(())
+    );
+}
+
+#[test]
+fn should_not_reject_exempt_caller() {
+    let limits = limits(Some(1), None, None);
+    let mut usages = CallerUsages::default();
+
+    for now in 0..10 {
+        assert_eq!(usages.try_record_request(ALICE, &limits, true, now), Ok(()));
+    }
+    let usage = usages.get(&ALICE, &limits, true, 10);
+    assert_eq!(usage.requests, 10);
+    assert!(usage.exempt);
+}
+
+#[test]
+fn should_reset_usage_in_next_time_window() {
+    let limits = limits(Some(1), None, None);
+    let mut usages = CallerUsages::default();
+
+    assert_eq!(
+        usages.try_record_request(ALICE, &limits, false, MINUTE - 1),
+        Ok(())
+    );
+    assert_eq!(
+        usages.try_record_request(ALICE, &limits, false, MINUTE - 1),
+        Err(CallerLimit::Requests)
+    );
+
+    let usage = usages.get(&ALICE, &limits, false, MINUTE);
+    assert_eq!(usage.window_start, MINUTE);
+    assert_eq!(usage.requests, 0);
+    assert_eq!(
+        usages.try_record_request(ALICE, &limits, false, MINUTE),
+        Ok(())
+    );
+}
+
+fn limits(
+    max_requests: Option<u64>,
+    max_outcalls: Option<u64>,
+    max_cycles: Option<u128>,
+) -> CallerLimits {
+    CallerLimits {
+        window_secs: 60,
+        max_requests,
+        max_outcalls,
+        max_cycles,
+    }
+}
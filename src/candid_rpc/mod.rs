@@ -1,22 +1,35 @@
+#[cfg(test)]
+mod tests;
+
 use crate::{
+    add_metric_entry,
+    constants::{
+        MAX_GET_LOGS_BISECTION_DEPTH, MAX_GET_LOGS_BISECTION_REQUESTS,
+        MAX_GET_LOGS_PAGINATED_CHUNKS, MAX_GET_LOGS_PAGINATED_RESPONSE_BYTES,
+    },
+    http::Payer,
+    memory::get_chain,
     rpc_client::{
+        eth_rpc_error::is_get_logs_range_too_large_error,
         json::{
             requests::{
                 BlockSpec, EthCallParams, FeeHistoryParams, GetLogsParams,
                 GetTransactionCountParams,
             },
+            responses::LogEntry,
             Hash,
         },
         EthRpcClient,
     },
-    types::RpcMethod,
+    types::{MetricRpcMethod, RpcMethod},
 };
 use candid::Nat;
 use canhttp::{http::json::JsonRpcRequest, multi::Timestamp};
 use ethers_core::{types::Transaction, utils::rlp};
 use evm_rpc_types::{
-    BlockTag, Hex, Hex32, MultiRpcResult, Nat256, RpcError, RpcResult, ValidationError,
+    BlockTag, GetLogsPage, Hex, Hex32, MultiRpcResult, Nat256, RpcError, RpcResult, ValidationError,
 };
+use std::collections::{BTreeSet, VecDeque};
 
 /// Adapt the `EthRpcClient` to the `Candid` interface used by the EVM-RPC canister.
 pub struct CandidRpcClient {
@@ -34,20 +47,183 @@ impl CandidRpcClient {
         })
     }
 
+    /// Charge the HTTP outcalls to the given payer instead of the caller.
+    pub fn with_payer(mut self, payer: Payer) -> Self {
+        self.client = self.client.with_payer(payer);
+        self
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.client.chain_id()
+    }
+
+    /// Whether responses may be recorded as the state of the chain,
+    /// see [`EthRpcClient::can_record_chain_state`].
+    pub fn can_record_chain_state(&self) -> bool {
+        self.client.can_record_chain_state()
+    }
+
+    /// Cycles available to the payer of the HTTP outcalls.
+    fn available_cycles(&self) -> u128 {
+        self.client.payer().available_cycles()
+    }
+
     pub async fn eth_get_logs(
         self,
         args: evm_rpc_types::GetLogsArgs,
     ) -> MultiRpcResult<Vec<evm_rpc_types::LogEntry>> {
-        self.client
-            .eth_get_logs(GetLogsParams::from(args))
-            .send_and_reduce()
-            .await
-            .map(|entries| {
-                entries
-                    .into_iter()
-                    .map(evm_rpc_types::LogEntry::from)
-                    .collect()
-            })
+        self.get_logs_with_bisection(args).await.map(|entries| {
+            entries
+                .into_iter()
+                .map(evm_rpc_types::LogEntry::from)
+                .collect()
+        })
+    }
+
+    /// Query the logs of the block range in consecutive chunks of at most `max_block_range`
+    /// blocks, as long as the attached cycles and the maximum response size allow it.
+    ///
+    /// Only the result of the first chunk may be inconsistent or an error: the logs of the
+    /// chunks queried so far are returned as soon as a later chunk fails.
+    pub async fn eth_get_logs_paginated(
+        self,
+        args: evm_rpc_types::GetLogsArgs,
+        max_block_range: u32,
+    ) -> MultiRpcResult<GetLogsPage> {
+        let (from_block, to_block) = match validate_get_logs_paginated_block_range(&args) {
+            Ok(range) => range,
+            Err(err) => return MultiRpcResult::Consistent(Err(err)),
+        };
+        let chunk_size = u64::from(max_block_range.max(1));
+        let mut page = PaginatedLogs::new(from_block, to_block);
+
+        for chunk in 0..MAX_GET_LOGS_PAGINATED_CHUNKS {
+            let Some((chunk_from_block, chunk_to_block)) = page.next_chunk(chunk_size) else {
+                break;
+            };
+            let chunk_args = get_logs_args_with_range(&args, chunk_from_block, chunk_to_block);
+            // The first chunk is always queried, so that the caller gets an error if the
+            // attached cycles are insufficient.
+            if chunk > 0 {
+                let cycles_cost = self
+                    .client
+                    .clone()
+                    .eth_get_logs(GetLogsParams::from(chunk_args.clone()))
+                    .cycles_cost()
+                    .await;
+                match cycles_cost {
+                    Ok(cycles_cost) if cycles_cost <= self.available_cycles() => {}
+                    _ => break,
+                }
+            }
+            match self.get_logs_with_bisection(chunk_args).await {
+                MultiRpcResult::Consistent(Ok(entries)) => {
+                    let entries = entries.into_iter().map(evm_rpc_types::LogEntry::from);
+                    if !page.add_chunk(chunk_to_block, entries) {
+                        break;
+                    }
+                }
+                result if chunk == 0 => {
+                    return result.map(|entries| GetLogsPage {
+                        logs: entries
+                            .into_iter()
+                            .map(evm_rpc_types::LogEntry::from)
+                            .collect(),
+                        next_from_block: page.block_after(chunk_to_block).map(Nat256::from),
+                    })
+                }
+                _ => break,
+            }
+        }
+
+        MultiRpcResult::Consistent(Ok(page.into_page()))
+    }
+
+    /// Query the logs and, if the block range is too large for the providers, query both halves
+    /// of the block range instead, up to [`MAX_GET_LOGS_BISECTION_DEPTH`] times and with at most
+    /// [`MAX_GET_LOGS_BISECTION_REQUESTS`] requests in total.
+    ///
+    /// The parts of the block range are queried one after the other. A block range is only split
+    /// if the available cycles cover the cost of querying both halves and the parts still to be
+    /// queried, since the logs of the parts queried so far are discarded if a part fails.
+    /// If a part fails, its result is returned.
+    async fn get_logs_with_bisection(
+        &self,
+        args: evm_rpc_types::GetLogsArgs,
+    ) -> MultiRpcResult<Vec<LogEntry>> {
+        let Some((from, to)) = get_logs_block_range(&args) else {
+            return self
+                .client
+                .clone()
+                .eth_get_logs(GetLogsParams::from(args))
+                .send_and_reduce()
+                .await;
+        };
+
+        let mut ranges = VecDeque::from([(from, to, 0)]);
+        let mut num_requests = 0;
+        let mut logs = Vec::new();
+        while let Some((from, to, depth)) = ranges.pop_front() {
+            let result = self
+                .client
+                .clone()
+                .eth_get_logs(GetLogsParams::from(get_logs_args_with_range(
+                    &args, from, to,
+                )))
+                .send_and_reduce()
+                .await;
+            num_requests += 1;
+            if from < to
+                && depth < MAX_GET_LOGS_BISECTION_DEPTH
+                && num_requests + ranges.len() + 2 <= MAX_GET_LOGS_BISECTION_REQUESTS
+                && is_range_too_large(&result)
+            {
+                let middle = from + (to - from) / 2;
+                let halves = [(middle + 1, to, depth + 1), (from, middle, depth + 1)];
+                let remaining = halves.iter().chain(ranges.iter());
+                if self
+                    .get_logs_cycles_cost_for_ranges(&args, remaining)
+                    .await
+                    .is_ok_and(|cycles_cost| cycles_cost <= self.available_cycles())
+                {
+                    add_metric_entry!(
+                        get_logs_bisections,
+                        MetricRpcMethod::from(RpcMethod::EthGetLogs),
+                        1
+                    );
+                    for half in halves {
+                        ranges.push_front(half);
+                    }
+                    continue;
+                }
+            }
+            match result {
+                MultiRpcResult::Consistent(Ok(entries)) => logs.extend(entries),
+                result => return result,
+            }
+        }
+        MultiRpcResult::Consistent(Ok(logs))
+    }
+
+    /// Cycles cost of querying the logs of each of the given block ranges.
+    async fn get_logs_cycles_cost_for_ranges(
+        &self,
+        args: &evm_rpc_types::GetLogsArgs,
+        ranges: impl Iterator<Item = &(u64, u64, u8)>,
+    ) -> RpcResult<u128> {
+        let mut cycles_cost: u128 = 0;
+        for (from, to, _depth) in ranges {
+            cycles_cost = cycles_cost.saturating_add(
+                self.client
+                    .clone()
+                    .eth_get_logs(GetLogsParams::from(get_logs_args_with_range(
+                        args, *from, *to,
+                    )))
+                    .cycles_cost()
+                    .await?,
+            );
+        }
+        Ok(cycles_cost)
     }
 
     pub async fn eth_get_logs_cycles_cost(
@@ -60,6 +236,28 @@ impl CandidRpcClient {
             .await
     }
 
+    /// Maximum cycles cost of `eth_getLogs`, which includes the cost of the additional requests
+    /// made if the block range must be split because it is too large for the providers, see
+    /// [`CandidRpcClient::get_logs_with_bisection`].
+    pub async fn eth_get_logs_max_cycles_cost(
+        self,
+        args: evm_rpc_types::GetLogsArgs,
+    ) -> RpcResult<u128> {
+        let max_num_requests = get_logs_block_range(&args)
+            .map_or(1, |(from, to)| max_get_logs_bisection_requests(from, to));
+        self.eth_get_logs_cycles_cost(args)
+            .await
+            .map(|cycles_cost| cycles_cost.saturating_mul(max_num_requests))
+    }
+
+    pub async fn eth_block_number(self) -> MultiRpcResult<Nat256> {
+        self.client
+            .eth_block_number()
+            .send_and_reduce()
+            .await
+            .map(Nat256::from)
+    }
+
     pub async fn eth_get_block_by_number(
         self,
         block_tag: BlockTag,
@@ -214,6 +412,20 @@ fn get_transaction_hash(raw_signed_transaction_hex: &Hex) -> Option<Hex32> {
     Some(Hex32::from(transaction.hash.0))
 }
 
+/// Use the maximum block range of the chain, unless specified.
+pub fn resolve_get_logs_rpc_config(
+    source: &evm_rpc_types::RpcServices,
+    config: Option<evm_rpc_types::GetLogsRpcConfig>,
+) -> evm_rpc_types::GetLogsRpcConfig {
+    let mut config = config.unwrap_or_default();
+    if let evm_rpc_types::RpcServices::Chain { chain_id, .. } = source {
+        config.max_block_range = config
+            .max_block_range
+            .or_else(|| get_chain(*chain_id).and_then(|chain| chain.max_block_range));
+    }
+    config
+}
+
 pub fn validate_get_logs_block_range(
     args: &evm_rpc_types::GetLogsArgs,
     max_block_range: u32,
@@ -235,6 +447,167 @@ pub fn validate_get_logs_block_range(
     Ok(())
 }
 
+/// Returns the first and last block of the block range of `eth_getLogsPaginated`,
+/// which must both be block numbers.
+fn validate_get_logs_paginated_block_range(
+    args: &evm_rpc_types::GetLogsArgs,
+) -> RpcResult<(u64, u64)> {
+    let (Some(BlockTag::Number(from)), Some(BlockTag::Number(to))) =
+        (&args.from_block, &args.to_block)
+    else {
+        return Err(ValidationError::Custom(
+            "fromBlock and toBlock must be block numbers".to_string(),
+        )
+        .into());
+    };
+    let from = u64::try_from(from.clone())?;
+    let to = u64::try_from(to.clone())?;
+    if from > to {
+        return Err(ValidationError::Custom(format!(
+            "fromBlock {from} is greater than toBlock {to}"
+        ))
+        .into());
+    }
+    Ok((from, to))
+}
+
+/// Logs of the consecutive chunks of the block range queried by `eth_getLogsPaginated`.
+#[derive(Debug)]
+struct PaginatedLogs {
+    logs: Vec<evm_rpc_types::LogEntry>,
+    /// Block hash and log index of the logs, to skip logs returned for several chunks.
+    seen: BTreeSet<(String, String)>,
+    response_bytes: usize,
+    /// First block of the next chunk, if any.
+    next_from_block: Option<u64>,
+    to_block: u64,
+}
+
+impl PaginatedLogs {
+    fn new(from_block: u64, to_block: u64) -> Self {
+        Self {
+            logs: Vec::new(),
+            seen: BTreeSet::new(),
+            response_bytes: 0,
+            next_from_block: Some(from_block).filter(|from_block| *from_block <= to_block),
+            to_block,
+        }
+    }
+
+    /// Block range of the next chunk of at most `chunk_size` blocks, if any.
+    fn next_chunk(&self, chunk_size: u64) -> Option<(u64, u64)> {
+        let from_block = self.next_from_block?;
+        let to_block = from_block
+            .saturating_add(chunk_size.max(1) - 1)
+            .min(self.to_block);
+        Some((from_block, to_block))
+    }
+
+    /// First block of the block range after the given block, if any.
+    fn block_after(&self, block: u64) -> Option<u64> {
+        block
+            .checked_add(1)
+            .filter(|next_block| *next_block <= self.to_block)
+    }
+
+    /// Add the logs of the next chunk, which ends at `chunk_to_block`.
+    ///
+    /// Returns `false` without adding the logs if they would exceed
+    /// [`MAX_GET_LOGS_PAGINATED_RESPONSE_BYTES`], in which case the chunk is the first chunk
+    /// of the next page. The logs are always added if no logs were added before, so that each
+    /// page makes progress.
+    fn add_chunk(
+        &mut self,
+        chunk_to_block: u64,
+        entries: impl IntoIterator<Item = evm_rpc_types::LogEntry>,
+    ) -> bool {
+        let mut chunk_logs = Vec::new();
+        let mut chunk_seen = Vec::new();
+        let mut chunk_bytes = 0;
+        for entry in entries {
+            if let (Some(block_hash), Some(log_index)) = (&entry.block_hash, &entry.log_index) {
+                let id = (block_hash.to_string(), log_index.to_string());
+                if self.seen.contains(&id) || chunk_seen.contains(&id) {
+                    continue;
+                }
+                chunk_seen.push(id);
+            }
+            chunk_bytes += serde_json::to_vec(&entry).map_or(0, |bytes| bytes.len());
+            chunk_logs.push(entry);
+        }
+        let response_bytes = self.response_bytes.saturating_add(chunk_bytes);
+        if !self.logs.is_empty() && response_bytes > MAX_GET_LOGS_PAGINATED_RESPONSE_BYTES {
+            return false;
+        }
+        self.logs.extend(chunk_logs);
+        self.seen.extend(chunk_seen);
+        self.response_bytes = response_bytes;
+        self.next_from_block = self.block_after(chunk_to_block);
+        true
+    }
+
+    fn into_page(self) -> GetLogsPage {
+        GetLogsPage {
+            logs: self.logs,
+            next_from_block: self.next_from_block.map(Nat256::from),
+        }
+    }
+}
+
+/// First and last block of the block range, if both are block numbers.
+fn get_logs_block_range(args: &evm_rpc_types::GetLogsArgs) -> Option<(u64, u64)> {
+    match (&args.from_block, &args.to_block) {
+        (Some(BlockTag::Number(from)), Some(BlockTag::Number(to))) => u64::try_from(from.clone())
+            .ok()
+            .zip(u64::try_from(to.clone()).ok()),
+        _ => None,
+    }
+}
+
+/// Maximum number of requests made by [`CandidRpcClient::get_logs_with_bisection`] for the
+/// given block range.
+fn max_get_logs_bisection_requests(from: u64, to: u64) -> u128 {
+    if from >= to {
+        return 1;
+    }
+    // Each split replaces a part of the block range by its two halves
+    let max_num_parts = u128::from(to - from) + 1;
+    let max_num_parts_at_max_depth = 1_u128 << MAX_GET_LOGS_BISECTION_DEPTH;
+    (2 * max_num_parts.min(max_num_parts_at_max_depth) - 1)
+        .min(MAX_GET_LOGS_BISECTION_REQUESTS as u128)
+}
+
+fn get_logs_args_with_range(
+    args: &evm_rpc_types::GetLogsArgs,
+    from_block: u64,
+    to_block: u64,
+) -> evm_rpc_types::GetLogsArgs {
+    evm_rpc_types::GetLogsArgs {
+        from_block: Some(BlockTag::Number(Nat256::from(from_block))),
+        to_block: Some(BlockTag::Number(Nat256::from(to_block))),
+        addresses: args.addresses.clone(),
+        topics: args.topics.clone(),
+    }
+}
+
+/// Whether the result of `eth_getLogs` indicates that the block range is too large
+/// for at least one provider.
+fn is_range_too_large<T>(result: &MultiRpcResult<T>) -> bool {
+    match result {
+        MultiRpcResult::Consistent(result) => result
+            .as_ref()
+            .is_err_and(is_get_logs_range_too_large_error),
+        MultiRpcResult::Inconsistent(results)
+        | MultiRpcResult::InconsistentWithDiagnostics { results, .. } => {
+            results.iter().any(|(_, result)| {
+                result
+                    .as_ref()
+                    .is_err_and(is_get_logs_range_too_large_error)
+            })
+        }
+    }
+}
+
 fn try_into_json_rpc_request(
     json_rpc_payload: String,
 ) -> RpcResult<JsonRpcRequest<serde_json::Value>> {
@@ -0,0 +1,189 @@
+use crate::candid_rpc::{
+    max_get_logs_bisection_requests, validate_get_logs_paginated_block_range, PaginatedLogs,
+};
+use crate::constants::MAX_GET_LOGS_BISECTION_REQUESTS;
+use evm_rpc_types::{BlockTag, GetLogsArgs, Hex, Hex20, Hex32, LogEntry, Nat256};
+
+mod get_logs_paginated_block_range {
+    use super::*;
+
+    #[test]
+    fn should_accept_block_numbers() {
+        assert_eq!(
+            validate_get_logs_paginated_block_range(&args(
+                Some(BlockTag::Number(Nat256::from(1_u64))),
+                Some(BlockTag::Number(Nat256::from(1_u64)))
+            )),
+            Ok((1, 1))
+        );
+        assert_eq!(
+            validate_get_logs_paginated_block_range(&args(
+                Some(BlockTag::Number(Nat256::from(0_u64))),
+                Some(BlockTag::Number(Nat256::from(u64::MAX)))
+            )),
+            Ok((0, u64::MAX))
+        );
+    }
+
+    #[test]
+    fn should_reject_block_tags() {
+        for (from_block, to_block) in [
+            (None, Some(BlockTag::Number(Nat256::from(1_u64)))),
+            (Some(BlockTag::Number(Nat256::from(1_u64))), None),
+            (
+                Some(BlockTag::Earliest),
+                Some(BlockTag::Number(Nat256::from(1_u64))),
+            ),
+            (
+                Some(BlockTag::Number(Nat256::from(1_u64))),
+                Some(BlockTag::Latest),
+            ),
+        ] {
+            assert!(validate_get_logs_paginated_block_range(&args(from_block, to_block)).is_err());
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_block_range() {
+        assert!(validate_get_logs_paginated_block_range(&args(
+            Some(BlockTag::Number(Nat256::from(2_u64))),
+            Some(BlockTag::Number(Nat256::from(1_u64)))
+        ))
+        .is_err());
+        assert!(validate_get_logs_paginated_block_range(&args(
+            Some(BlockTag::Number(Nat256::from(1_u64))),
+            Some(BlockTag::Number(Nat256::from(u128::from(u64::MAX) + 1)))
+        ))
+        .is_err());
+    }
+
+    fn args(from_block: Option<BlockTag>, to_block: Option<BlockTag>) -> GetLogsArgs {
+        GetLogsArgs {
+            from_block,
+            to_block,
+            addresses: vec![Hex20::from([1; 20])],
+            topics: None,
+        }
+    }
+}
+
+mod paginated_logs {
+    use super::*;
+
+    #[test]
+    fn should_split_block_range_into_chunks() {
+        let mut page = PaginatedLogs::new(10, 34);
+
+        assert_eq!(page.next_chunk(10), Some((10, 19)));
+        assert!(page.add_chunk(19, [log_entry(10, 0, 0)]));
+        assert_eq!(page.next_chunk(10), Some((20, 29)));
+        assert!(page.add_chunk(29, []));
+        assert_eq!(page.next_chunk(10), Some((30, 34)));
+        assert!(page.add_chunk(34, [log_entry(34, 0, 0)]));
+        assert_eq!(page.next_chunk(10), None);
+
+        let page = page.into_page();
+        assert_eq!(page.logs, vec![log_entry(10, 0, 0), log_entry(34, 0, 0)]);
+        assert_eq!(page.next_from_block, None);
+    }
+
+    #[test]
+    fn should_return_next_block_of_partially_queried_range() {
+        let mut page = PaginatedLogs::new(10, 34);
+
+        assert!(page.add_chunk(19, [log_entry(10, 0, 0)]));
+
+        let page = page.into_page();
+        assert_eq!(page.logs, vec![log_entry(10, 0, 0)]);
+        assert_eq!(page.next_from_block, Some(Nat256::from(20_u64)));
+    }
+
+    #[test]
+    fn should_not_overflow_at_last_block() {
+        let mut page = PaginatedLogs::new(u64::MAX - 1, u64::MAX);
+
+        assert_eq!(page.next_chunk(10), Some((u64::MAX - 1, u64::MAX)));
+        assert_eq!(page.block_after(u64::MAX), None);
+        assert!(page.add_chunk(u64::MAX, [log_entry(u64::MAX, 0, 0)]));
+        assert_eq!(page.next_chunk(10), None);
+        assert_eq!(page.into_page().next_from_block, None);
+    }
+
+    #[test]
+    fn should_skip_duplicate_logs() {
+        let mut page = PaginatedLogs::new(10, 29);
+
+        assert!(page.add_chunk(19, [log_entry(19, 0, 0), log_entry(19, 0, 0)]));
+        assert!(page.add_chunk(29, [log_entry(19, 0, 0), log_entry(20, 1, 0)]));
+
+        assert_eq!(
+            page.into_page().logs,
+            vec![log_entry(19, 0, 0), log_entry(20, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn should_drop_chunk_exceeding_maximum_response_size() {
+        const DATA_BYTES: usize = 400_000;
+        let mut page = PaginatedLogs::new(10, 39);
+
+        assert!(page.add_chunk(19, [log_entry(10, 0, DATA_BYTES)]));
+        assert!(!page.add_chunk(29, [log_entry(20, 0, DATA_BYTES)]));
+        assert_eq!(page.next_chunk(10), Some((20, 29)));
+
+        let page = page.into_page();
+        assert_eq!(page.logs, vec![log_entry(10, 0, DATA_BYTES)]);
+        assert_eq!(page.next_from_block, Some(Nat256::from(20_u64)));
+    }
+
+    #[test]
+    fn should_keep_first_chunk_exceeding_maximum_response_size() {
+        const DATA_BYTES: usize = 1_000_000;
+        let mut page = PaginatedLogs::new(10, 39);
+
+        assert!(page.add_chunk(19, [log_entry(10, 0, DATA_BYTES)]));
+        assert!(!page.add_chunk(29, [log_entry(20, 0, 1)]));
+
+        let page = page.into_page();
+        assert_eq!(page.logs, vec![log_entry(10, 0, DATA_BYTES)]);
+        assert_eq!(page.next_from_block, Some(Nat256::from(20_u64)));
+    }
+
+    fn log_entry(block_number: u64, log_index: u64, data_bytes: usize) -> LogEntry {
+        let block_hash = {
+            let mut hash = [0; 32];
+            hash[..8].copy_from_slice(&block_number.to_be_bytes());
+            hash
+        };
+        LogEntry {
+            address: Hex20::from([1; 20]),
+            topics: vec![],
+            data: Hex::from(vec![0; data_bytes]),
+            block_number: Some(Nat256::from(block_number)),
+            transaction_hash: Some(Hex32::from([2; 32])),
+            transaction_index: Some(Nat256::from(0_u64)),
+            block_hash: Some(Hex32::from(block_hash)),
+            log_index: Some(Nat256::from(log_index)),
+            removed: false,
+        }
+    }
+}
+
+mod get_logs_bisection {
+    use super::*;
+
+    #[test]
+    fn should_bound_number_of_requests() {
+        assert_eq!(max_get_logs_bisection_requests(10, 10), 1);
+        assert_eq!(max_get_logs_bisection_requests(10, 5), 1);
+        // A block range of 2 blocks is split at most once into 2 parts
+        assert_eq!(max_get_logs_bisection_requests(10, 11), 3);
+        assert_eq!(max_get_logs_bisection_requests(10, 13), 7);
+        for (from, to) in [(0, 499), (0, u64::MAX)] {
+            assert_eq!(
+                max_get_logs_bisection_requests(from, to),
+                MAX_GET_LOGS_BISECTION_REQUESTS as u128
+            );
+        }
+    }
+}
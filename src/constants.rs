@@ -5,9 +5,51 @@ pub const DEFAULT_MAX_RESPONSE_BYTES: u64 = 2_000_000;
 // as processing fee.
 pub const COLLATERAL_CYCLES_PER_NODE: u128 = 10_000_000;
 
+// Minimum number of cycles that can be withdrawn from a prepaid cycles account,
+// so that the cost of the withdrawal is negligible.
 pub const MINIMUM_WITHDRAWAL_CYCLES: u128 = 1_000_000_000;
 
+// Cycles charged for a response served from the cache of immutable responses,
+// instead of the cost of HTTP outcalls.
+pub const CACHE_HIT_CYCLES: u128 = 10_000_000;
+
+// Cycles charged to a coalesced request for each round it waits for the result of the identical
+// in-flight request, which covers the cost of the call made to wait for the next round.
+pub const COALESCING_ROUND_CYCLES: u128 = 10_000_000;
+
+// Maximum number of block ranges queried by a single call to `eth_getLogsPaginated`,
+// and maximum size in bytes of the returned logs, which must fit in a reply.
+pub const MAX_GET_LOGS_PAGINATED_CHUNKS: usize = 20;
+pub const MAX_GET_LOGS_PAGINATED_RESPONSE_BYTES: usize = 1_500_000;
+
+// Maximum number of times the block range of `eth_getLogs` is halved when it is too large,
+// and maximum number of requests made in total for the parts of the block range.
+pub const MAX_GET_LOGS_BISECTION_DEPTH: u8 = 5;
+pub const MAX_GET_LOGS_BISECTION_REQUESTS: usize = 15;
+
+// Interval at which log subscriptions and transaction watches are polled, and maximum number
+// of log subscriptions and of transaction watches of a single subscriber.
+pub const SUBSCRIPTIONS_POLLING_INTERVAL_SECS: u64 = 60;
+pub const MAX_LOG_SUBSCRIPTIONS_PER_OWNER: u64 = 10;
+pub const MAX_TRANSACTION_WATCHES_PER_OWNER: u64 = 100;
+
+// Minimum prepaid cycles balance of a subscriber, below which its subscriptions are removed
+// instead of being polled.
+pub const MINIMUM_SUBSCRIPTION_BALANCE_CYCLES: u128 = 10_000_000_000;
+
+// Interval at which the head of tracked chains is polled.
+pub const HEAD_TRACKING_INTERVAL_SECS: u64 = 15;
+
+// Maximum number of requests in a JSON-RPC batch sent over HTTP.
+pub const MAX_JSON_RPC_BATCH_SIZE: usize = 10;
+
+// Time after which a watched transaction without receipt is considered dropped.
+pub const DROPPED_TRANSACTION_TIMEOUT_SECS: u64 = 60 * 60;
+
 pub const API_KEY_MAX_SIZE: u32 = 512;
+pub const MAX_API_KEYS_PER_PROVIDER: usize = 10;
+pub const MAX_AUDIT_LOG_PAGE_SIZE: u64 = 100;
+pub const DEFAULT_CALLER_LIMITS_WINDOW_SECS: u64 = 60;
 pub const PROVIDER_MAX_SIZE: u32 = 256;
 pub const MESSAGE_FILTER_MAX_SIZE: u32 = 1000;
 pub const RPC_SERVICE_MAX_SIZE: u32 = 256;
@@ -16,7 +58,22 @@ pub const WASM_PAGE_SIZE: u64 = 65536;
 
 pub const NODES_IN_SUBNET: u32 = 34;
 
+/// Methods making HTTP outcalls that can be suspended by the controllers.
+pub const SUSPENDABLE_METHODS: &[&str] = &[
+    "eth_getLogs",
+    "eth_getLogsPaginated",
+    "eth_getBlockByNumber",
+    "eth_getTransactionReceipt",
+    "eth_getTransactionCount",
+    "eth_feeHistory",
+    "eth_sendRawTransaction",
+    "eth_call",
+    "multi_request",
+    "request",
+];
+
 pub const API_KEY_REPLACE_STRING: &str = "{API_KEY}";
+pub const REDACTED_STRING: &str = "<redacted>";
 pub const VALID_API_KEY_CHARS: &str =
     "0123456789ABCDEFGHIJKLMNOPQRTSUVWXYZabcdefghijklmnopqrstuvwxyz$-_.+!*";
 
@@ -28,3 +85,7 @@ pub const ETH_SEPOLIA_CHAIN_ID: u64 = 11155111;
 pub const ARBITRUM_ONE_CHAIN_ID: u64 = 42161;
 pub const BASE_MAINNET_CHAIN_ID: u64 = 8453;
 pub const OPTIMISM_MAINNET_CHAIN_ID: u64 = 10;
+pub const ETH_HOODI_CHAIN_ID: u64 = 560048;
+pub const ARBITRUM_SEPOLIA_CHAIN_ID: u64 = 421614;
+pub const BASE_SEPOLIA_CHAIN_ID: u64 = 84532;
+pub const OPTIMISM_SEPOLIA_CHAIN_ID: u64 = 11155420;
@@ -0,0 +1,545 @@
+//! Serve standard Ethereum JSON-RPC requests sent over HTTP to `/rpc/{chainId}`, so that
+//! Ethereum tooling can use the canister as a regular JSON-RPC endpoint.
+//!
+//! Requests for the supported methods are served like the corresponding Candid endpoint of the
+//! canister, e.g. `eth_getBlockByNumber`. Since HTTP requests are made by the anonymous principal,
+//! the HTTP outcalls are charged to the dedicated prepaid cycles account of the gateway of the
+//! chain, see [`gateway_account`].
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    add_metric_entry,
+    candid_rpc::{resolve_get_logs_rpc_config, validate_get_logs_block_range, CandidRpcClient},
+    constants::{CONTENT_TYPE_VALUE, MAX_JSON_RPC_BATCH_SIZE},
+    http::Payer,
+    memory::{get_json_rpc_gateway, is_method_suspended, try_record_caller_request},
+    rpc_client::{
+        json::{
+            requests::{BlockSpec, TransactionRequest},
+            responses::{Block, FeeHistory, LogEntry, TransactionReceipt},
+        },
+        numeric::{BlockNumber, ChainId, NumBlocks, TransactionCount},
+    },
+    types::{MetricCallerLimit, MetricRpcMethod},
+};
+use candid::{CandidType, Principal};
+use canhttp::multi::Timestamp;
+use evm_rpc_types::{
+    BlockTag, CallArgs, FeeHistoryArgs, GetLogsArgs, GetLogsRpcConfig, GetTransactionCountArgs,
+    Hex, Hex20, Hex32, JsonRpcError, JsonRpcGateway, MultiRpcResult, Nat256, ProviderError,
+    RpcConfig, RpcError, SendRawTransactionStatus,
+};
+use ic_http_types::{HttpRequest, HttpResponseBuilder};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use serde_json::{json, Value};
+
+/// Path prefix of the JSON-RPC requests, which is followed by the chain ID.
+pub const JSON_RPC_PATH_PREFIX: &str = "/rpc/";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// Response to an HTTP request, which may ask the HTTP gateway to upgrade the query call
+/// to an update call, see `http_request_update`.
+#[derive(Clone, Debug, PartialEq, Eq, CandidType, Deserialize)]
+pub struct HttpResponse {
+    pub status_code: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: ByteBuf,
+    pub upgrade: Option<bool>,
+}
+
+impl HttpResponse {
+    pub fn upgrade() -> Self {
+        Self {
+            status_code: 200,
+            headers: vec![],
+            body: ByteBuf::new(),
+            upgrade: Some(true),
+        }
+    }
+
+    /// Response to a CORS preflight request, so that JSON-RPC requests can be sent from a browser.
+    pub fn preflight() -> Self {
+        Self {
+            status_code: 204,
+            headers: vec![
+                ("Access-Control-Allow-Origin".to_string(), "*".to_string()),
+                (
+                    "Access-Control-Allow-Methods".to_string(),
+                    "POST".to_string(),
+                ),
+                (
+                    "Access-Control-Allow-Headers".to_string(),
+                    "Content-Type".to_string(),
+                ),
+            ],
+            body: ByteBuf::new(),
+            upgrade: None,
+        }
+    }
+}
+
+impl From<ic_http_types::HttpResponse> for HttpResponse {
+    fn from(response: ic_http_types::HttpResponse) -> Self {
+        Self {
+            status_code: response.status_code,
+            headers: response.headers,
+            body: response.body,
+            upgrade: None,
+        }
+    }
+}
+
+/// Whether the HTTP request is a JSON-RPC request, which must be served by an update call.
+pub fn is_json_rpc_request(request: &HttpRequest) -> bool {
+    request.path().starts_with(JSON_RPC_PATH_PREFIX)
+}
+
+/// Serve a single or a batch JSON-RPC request with the gateway of the chain given in the path.
+pub async fn serve_json_rpc_request(request: HttpRequest) -> ic_http_types::HttpResponse {
+    if request.method != "POST" {
+        return HttpResponseBuilder::bad_request()
+            .with_body_and_content_length("JSON-RPC requests must be sent with POST")
+            .build();
+    }
+    let Some((chain_id, gateway)) = find_gateway(request.path()) else {
+        return HttpResponseBuilder::not_found()
+            .with_body_and_content_length("No JSON-RPC gateway for this chain")
+            .build();
+    };
+    let response = match serde_json::from_slice::<Value>(&request.body) {
+        Ok(Value::Array(requests)) if requests.is_empty() => Some(json_rpc_response(
+            Value::Null,
+            Err(invalid_request("Empty batch")),
+        )),
+        Ok(Value::Array(requests)) if requests.len() > MAX_JSON_RPC_BATCH_SIZE => {
+            Some(json_rpc_response(
+                Value::Null,
+                Err(invalid_request(format!(
+                    "Batch too large: at most {MAX_JSON_RPC_BATCH_SIZE} requests are allowed"
+                ))),
+            ))
+        }
+        Ok(Value::Array(requests)) => {
+            let mut responses = Vec::with_capacity(requests.len());
+            for request in requests {
+                responses.extend(handle_json_rpc_request(chain_id, &gateway, request).await);
+            }
+            // A batch of notifications is not answered at all.
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        Ok(request) => handle_json_rpc_request(chain_id, &gateway, request).await,
+        Err(e) => Some(json_rpc_response(
+            Value::Null,
+            Err(JsonRpcError {
+                code: PARSE_ERROR,
+                message: format!("Parse error: {e}"),
+            }),
+        )),
+    };
+    match response {
+        Some(response) => HttpResponseBuilder::ok()
+            .header("Content-Type", CONTENT_TYPE_VALUE)
+            .header("Access-Control-Allow-Origin", "*")
+            .with_body_and_content_length(
+                serde_json::to_vec(&response).expect("BUG: failed to serialize JSON-RPC response"),
+            )
+            .build(),
+        None => HttpResponseBuilder::ok()
+            .header("Access-Control-Allow-Origin", "*")
+            .with_body_and_content_length(Vec::new())
+            .build(),
+    }
+}
+
+fn find_gateway(path: &str) -> Option<(u64, JsonRpcGateway)> {
+    let chain_id = path
+        .strip_prefix(JSON_RPC_PATH_PREFIX)?
+        .parse::<u64>()
+        .ok()?;
+    get_json_rpc_gateway(chain_id).map(|gateway| (chain_id, gateway))
+}
+
+#[derive(Deserialize)]
+struct JsonRpcRequest {
+    jsonrpc: String,
+    method: String,
+    #[serde(default)]
+    params: Vec<Value>,
+    #[serde(default)]
+    id: Value,
+}
+
+/// Serve a single JSON-RPC request and return its response, unless the request is a notification,
+/// i.e. a request without `id`, which is served but not answered.
+async fn handle_json_rpc_request(
+    chain_id: u64,
+    gateway: &JsonRpcGateway,
+    request: Value,
+) -> Option<Value> {
+    let is_notification = request
+        .as_object()
+        .is_some_and(|fields| !fields.contains_key("id"));
+    let request = match JsonRpcRequest::deserialize(request) {
+        Ok(request) if request.jsonrpc == "2.0" => request,
+        Ok(request) => {
+            return Some(json_rpc_response(
+                request.id,
+                Err(invalid_request("Only JSON-RPC 2.0 is supported")),
+            ))
+        }
+        Err(e) => return Some(json_rpc_response(Value::Null, Err(invalid_request(e)))),
+    };
+    let result = match GatewayRequest::parse(&request.method, &request.params) {
+        Ok(parsed) => {
+            add_metric_entry!(
+                json_rpc_gateway_requests,
+                MetricRpcMethod {
+                    method: request.method,
+                    is_manual_request: false,
+                },
+                1
+            );
+            execute(chain_id, gateway, parsed).await
+        }
+        Err(e) => Err(e),
+    };
+    (!is_notification).then(|| json_rpc_response(request.id, result))
+}
+
+/// JSON-RPC request of a supported method, with its parameters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GatewayRequest {
+    ChainId,
+    BlockNumber,
+    GetBlockByNumber(BlockTag),
+    GetTransactionReceipt(Hex32),
+    GetTransactionCount(GetTransactionCountArgs),
+    GetLogs(GetLogsArgs),
+    FeeHistory(FeeHistoryArgs),
+    SendRawTransaction(Hex),
+    Call(CallArgs),
+}
+
+impl GatewayRequest {
+    /// Candid endpoint serving the request, whose suspension also applies to the request,
+    /// if the request makes HTTP outcalls.
+    pub fn endpoint(&self) -> Option<&'static str> {
+        match self {
+            Self::ChainId => None,
+            Self::BlockNumber | Self::GetBlockByNumber(_) => Some("eth_getBlockByNumber"),
+            Self::GetTransactionReceipt(_) => Some("eth_getTransactionReceipt"),
+            Self::GetTransactionCount(_) => Some("eth_getTransactionCount"),
+            Self::GetLogs(_) => Some("eth_getLogs"),
+            Self::FeeHistory(_) => Some("eth_feeHistory"),
+            Self::SendRawTransaction(_) => Some("eth_sendRawTransaction"),
+            Self::Call(_) => Some("eth_call"),
+        }
+    }
+
+    pub fn parse(method: &str, params: &[Value]) -> Result<Self, JsonRpcError> {
+        match method {
+            "eth_chainId" => Ok(Self::ChainId),
+            "eth_blockNumber" => Ok(Self::BlockNumber),
+            "eth_getBlockByNumber" => {
+                let block: BlockSpec = param(params, 0)?;
+                if optional_param::<bool>(params, 1)?.unwrap_or(false) {
+                    return Err(invalid_params("Full transactions are not supported"));
+                }
+                Ok(Self::GetBlockByNumber(BlockTag::from(block)))
+            }
+            "eth_getTransactionReceipt" => Ok(Self::GetTransactionReceipt(param(params, 0)?)),
+            "eth_getTransactionCount" => Ok(Self::GetTransactionCount(GetTransactionCountArgs {
+                address: param(params, 0)?,
+                block: optional_param::<BlockSpec>(params, 1)?
+                    .map(BlockTag::from)
+                    .unwrap_or_default(),
+            })),
+            "eth_getLogs" => param::<LogFilter>(params, 0)?.try_into().map(Self::GetLogs),
+            "eth_feeHistory" => Ok(Self::FeeHistory(FeeHistoryArgs {
+                block_count: Nat256::from(param::<NumBlocks>(params, 0)?),
+                newest_block: BlockTag::from(param::<BlockSpec>(params, 1)?),
+                reward_percentiles: optional_param(params, 2)?,
+            })),
+            "eth_sendRawTransaction" => Ok(Self::SendRawTransaction(param(params, 0)?)),
+            "eth_call" => Ok(Self::Call(CallArgs {
+                transaction: parse_transaction_request(param(params, 0)?)?,
+                block: optional_param::<BlockSpec>(params, 1)?.map(BlockTag::from),
+            })),
+            _ => Err(JsonRpcError {
+                code: METHOD_NOT_FOUND,
+                message: format!("The method {method} is not supported"),
+            }),
+        }
+    }
+}
+
+/// Filter of `eth_getLogs`, where addresses and topics may be given either as a single value
+/// or as a list of values.
+#[derive(Deserialize)]
+struct LogFilter {
+    #[serde(rename = "fromBlock")]
+    from_block: Option<BlockSpec>,
+    #[serde(rename = "toBlock")]
+    to_block: Option<BlockSpec>,
+    address: Option<OneOrMany<Hex20>>,
+    topics: Option<Vec<Option<OneOrMany<Hex32>>>>,
+    #[serde(rename = "blockHash")]
+    block_hash: Option<Hex32>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OneOrMany<T> {
+    One(T),
+    Many(Vec<T>),
+}
+
+impl<T> From<OneOrMany<T>> for Vec<T> {
+    fn from(value: OneOrMany<T>) -> Self {
+        match value {
+            OneOrMany::One(value) => vec![value],
+            OneOrMany::Many(values) => values,
+        }
+    }
+}
+
+impl TryFrom<LogFilter> for GetLogsArgs {
+    type Error = JsonRpcError;
+
+    fn try_from(filter: LogFilter) -> Result<Self, Self::Error> {
+        if filter.block_hash.is_some() {
+            return Err(invalid_params(
+                "Filtering logs by block hash is not supported",
+            ));
+        }
+        Ok(Self {
+            from_block: filter.from_block.map(BlockTag::from),
+            to_block: filter.to_block.map(BlockTag::from),
+            addresses: filter.address.map(Vec::from).unwrap_or_default(),
+            topics: filter.topics.map(|topics| {
+                topics
+                    .into_iter()
+                    // A `null` topic matches any topic.
+                    .map(|topic| topic.map(Vec::from).unwrap_or_default())
+                    .collect()
+            }),
+        })
+    }
+}
+
+/// Parse the transaction of `eth_call`, whose input data is also commonly given as `data`.
+fn parse_transaction_request(
+    mut transaction: Value,
+) -> Result<evm_rpc_types::TransactionRequest, JsonRpcError> {
+    if let Some(fields) = transaction.as_object_mut() {
+        if !fields.contains_key("input") {
+            if let Some(data) = fields.remove("data") {
+                fields.insert("input".to_string(), data);
+            }
+        }
+    }
+    TransactionRequest::deserialize(transaction)
+        .map(evm_rpc_types::TransactionRequest::from)
+        .map_err(|e| invalid_params(format!("Invalid transaction: {e}")))
+}
+
+fn param<T: DeserializeOwned>(params: &[Value], index: usize) -> Result<T, JsonRpcError> {
+    optional_param(params, index)?
+        .ok_or_else(|| invalid_params(format!("Missing parameter at position {index}")))
+}
+
+fn optional_param<T: DeserializeOwned>(
+    params: &[Value],
+    index: usize,
+) -> Result<Option<T>, JsonRpcError> {
+    match params.get(index) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => T::deserialize(value)
+            .map(Some)
+            .map_err(|e| invalid_params(format!("Invalid parameter at position {index}: {e}"))),
+    }
+}
+
+async fn execute(
+    chain_id: u64,
+    gateway: &JsonRpcGateway,
+    request: GatewayRequest,
+) -> Result<Value, JsonRpcError> {
+    if let Some(endpoint) = request.endpoint() {
+        admit_request(endpoint, chain_id)?;
+    }
+    let client = || gateway_client(chain_id, gateway, gateway.config.clone());
+    match request {
+        GatewayRequest::ChainId => to_value(ChainId::from(chain_id)),
+        GatewayRequest::BlockNumber => {
+            let block_number = consistent(client()?.eth_block_number().await)?;
+            to_value(BlockNumber::from(block_number))
+        }
+        GatewayRequest::GetBlockByNumber(block) => {
+            let block = consistent(client()?.eth_get_block_by_number(block).await)?;
+            to_value(Block::from(block))
+        }
+        GatewayRequest::GetTransactionReceipt(hash) => {
+            let receipt = consistent(client()?.eth_get_transaction_receipt(hash).await)?;
+            to_value(receipt.map(TransactionReceipt::from))
+        }
+        GatewayRequest::GetTransactionCount(args) => {
+            let count = consistent(client()?.eth_get_transaction_count(args).await)?;
+            to_value(TransactionCount::from(count))
+        }
+        GatewayRequest::GetLogs(args) => {
+            let config = resolve_get_logs_rpc_config(
+                &gateway.source,
+                gateway.config.clone().map(GetLogsRpcConfig::from),
+            );
+            validate_get_logs_block_range(&args, config.max_block_range_or_default())
+                .map_err(to_json_rpc_error)?;
+            let client = gateway_client(chain_id, gateway, Some(RpcConfig::from(config)))?;
+            let logs = consistent(client.eth_get_logs(args).await)?;
+            to_value(logs.into_iter().map(LogEntry::from).collect::<Vec<_>>())
+        }
+        GatewayRequest::FeeHistory(args) => {
+            let fee_history = consistent(client()?.eth_fee_history(args).await)?;
+            to_value(FeeHistory::from(fee_history))
+        }
+        GatewayRequest::SendRawTransaction(raw_transaction) => {
+            let status = consistent(
+                client()?
+                    .eth_send_raw_transaction(raw_transaction.clone())
+                    .await,
+            )?;
+            send_raw_transaction_result(status, &raw_transaction)
+        }
+        GatewayRequest::Call(args) => {
+            let data = consistent(client()?.eth_call(args).await)?;
+            to_value(data)
+        }
+    }
+}
+
+/// Check that the endpoint serving the request is not suspended and that the account of the
+/// gateway did not exceed its limits, and record the request, like for calls to the endpoint.
+fn admit_request(endpoint: &str, chain_id: u64) -> Result<(), JsonRpcError> {
+    if is_method_suspended(endpoint) {
+        return Err(to_json_rpc_error(
+            ProviderError::Suspended {
+                method: endpoint.to_string(),
+            }
+            .into(),
+        ));
+    }
+    if let Err(limit) = try_record_caller_request(gateway_account(chain_id), ic_cdk::api::time()) {
+        add_metric_entry!(caller_limit_rejections, MetricCallerLimit::from(limit), 1);
+        return Err(to_json_rpc_error(
+            ProviderError::CallerLimitExceeded { limit }.into(),
+        ));
+    }
+    Ok(())
+}
+
+fn consistent<T>(result: MultiRpcResult<T>) -> Result<T, JsonRpcError> {
+    match result {
+        MultiRpcResult::Consistent(result) => result.map_err(to_json_rpc_error),
+        MultiRpcResult::Inconsistent(_) | MultiRpcResult::InconsistentWithDiagnostics { .. } => {
+            Err(server_error("Providers returned inconsistent results"))
+        }
+    }
+}
+
+/// Dedicated prepaid cycles account of the gateway of the given chain, which is charged for
+/// the HTTP outcalls of its requests.
+///
+/// The account is a derived principal, which is never the caller of any call, so that its
+/// balance can only be spent by the gateway. Cycles are deposited with `depositJsonRpcGateway`.
+pub fn gateway_account(chain_id: u64) -> Principal {
+    const DERIVED_ID_CLASS: u8 = 0x03;
+    let mut bytes = b"json-rpc-gateway".to_vec();
+    bytes.extend_from_slice(&chain_id.to_be_bytes());
+    bytes.push(DERIVED_ID_CLASS);
+    Principal::from_slice(&bytes)
+}
+
+/// Result of `eth_sendRawTransaction`, which is the transaction hash or the error returned
+/// by Ethereum clients.
+pub fn send_raw_transaction_result(
+    status: SendRawTransactionStatus,
+    raw_transaction: &Hex,
+) -> Result<Value, JsonRpcError> {
+    match status {
+        SendRawTransactionStatus::Ok(hash) => to_value(hash.unwrap_or_else(|| {
+            Hex32::from(ethers_core::utils::keccak256(raw_transaction.as_ref()))
+        })),
+        SendRawTransactionStatus::InsufficientFunds => {
+            Err(server_error("insufficient funds for gas * price + value"))
+        }
+        SendRawTransactionStatus::NonceTooLow => Err(server_error("nonce too low")),
+        SendRawTransactionStatus::NonceTooHigh => Err(server_error("nonce too high")),
+    }
+}
+
+pub fn json_rpc_response(id: Value, result: Result<Value, JsonRpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(JsonRpcError { code, message }) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": code, "message": message}
+        }),
+    }
+}
+
+fn to_json_rpc_error(error: RpcError) -> JsonRpcError {
+    match error {
+        RpcError::JsonRpcError(error) => error,
+        RpcError::ValidationError(error) => invalid_params(error),
+        error => server_error(error),
+    }
+}
+
+fn to_value<T: Serialize>(value: T) -> Result<Value, JsonRpcError> {
+    Ok(serde_json::to_value(value).expect("BUG: failed to serialize JSON-RPC result"))
+}
+
+fn invalid_request(message: impl ToString) -> JsonRpcError {
+    JsonRpcError {
+        code: INVALID_REQUEST,
+        message: message.to_string(),
+    }
+}
+
+fn invalid_params(message: impl ToString) -> JsonRpcError {
+    JsonRpcError {
+        code: INVALID_PARAMS,
+        message: message.to_string(),
+    }
+}
+
+fn server_error(message: impl ToString) -> JsonRpcError {
+    JsonRpcError {
+        code: SERVER_ERROR,
+        message: message.to_string(),
+    }
+}
+
+/// Client with the given config, whose HTTP outcalls are charged to the account of the gateway.
+fn gateway_client(
+    chain_id: u64,
+    gateway: &JsonRpcGateway,
+    config: Option<RpcConfig>,
+) -> Result<CandidRpcClient, JsonRpcError> {
+    CandidRpcClient::new(gateway.source.clone(), config, now())
+        .map(|client| client.with_payer(Payer::Account(gateway_account(chain_id))))
+        .map_err(to_json_rpc_error)
+}
+
+fn now() -> Timestamp {
+    Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time())
+}
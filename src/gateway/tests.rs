@@ -0,0 +1,270 @@
+use crate::{
+    gateway::{gateway_account, json_rpc_response, send_raw_transaction_result, GatewayRequest},
+    rpc_client::json::responses::{Block, TransactionReceipt},
+};
+use evm_rpc_types::{
+    BlockTag, CallArgs, FeeHistoryArgs, GetLogsArgs, GetTransactionCountArgs, Hex, Hex20, Hex32,
+    JsonRpcError, Nat256, SendRawTransactionStatus, TransactionRequest,
+};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+const ADDRESS: &str = "0xdd2851cdd40ae6536831558dd46db62fac7a844d";
+const TOPIC: &str = "0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef";
+
+fn parse(method: &str, params: Value) -> Result<GatewayRequest, JsonRpcError> {
+    let params = match params {
+        Value::Array(params) => params,
+        _ => panic!("BUG: params must be an array"),
+    };
+    GatewayRequest::parse(method, &params)
+}
+
+fn error_code<T: std::fmt::Debug>(result: Result<T, JsonRpcError>) -> i64 {
+    result.unwrap_err().code
+}
+
+#[test]
+fn should_parse_supported_methods() {
+    assert_eq!(parse("eth_chainId", json!([])), Ok(GatewayRequest::ChainId));
+    assert_eq!(
+        parse("eth_blockNumber", json!([])),
+        Ok(GatewayRequest::BlockNumber)
+    );
+    assert_eq!(
+        parse("eth_getBlockByNumber", json!(["0x10", false])),
+        Ok(GatewayRequest::GetBlockByNumber(BlockTag::Number(
+            Nat256::from(16_u8)
+        )))
+    );
+    assert_eq!(
+        parse("eth_getBlockByNumber", json!(["finalized"])),
+        Ok(GatewayRequest::GetBlockByNumber(BlockTag::Finalized))
+    );
+    assert_eq!(
+        parse("eth_getTransactionCount", json!([ADDRESS])),
+        Ok(GatewayRequest::GetTransactionCount(
+            GetTransactionCountArgs {
+                address: Hex20::from_str(ADDRESS).unwrap(),
+                block: BlockTag::Latest,
+            }
+        ))
+    );
+    assert_eq!(
+        parse("eth_feeHistory", json!(["0x4", "latest", [25, 75]])),
+        Ok(GatewayRequest::FeeHistory(FeeHistoryArgs {
+            block_count: Nat256::from(4_u8),
+            newest_block: BlockTag::Latest,
+            reward_percentiles: Some(vec![25, 75]),
+        }))
+    );
+    assert_eq!(
+        parse("eth_sendRawTransaction", json!(["0x02f8"])),
+        Ok(GatewayRequest::SendRawTransaction(
+            Hex::from_str("0x02f8").unwrap()
+        ))
+    );
+}
+
+#[test]
+fn should_parse_get_logs_filter() {
+    assert_eq!(
+        parse(
+            "eth_getLogs",
+            json!([{"fromBlock": "0x1", "address": ADDRESS, "topics": [TOPIC, null, [TOPIC]]}])
+        ),
+        Ok(GatewayRequest::GetLogs(GetLogsArgs {
+            from_block: Some(BlockTag::Number(Nat256::from(1_u8))),
+            to_block: None,
+            addresses: vec![Hex20::from_str(ADDRESS).unwrap()],
+            topics: Some(vec![
+                vec![Hex32::from_str(TOPIC).unwrap()],
+                vec![],
+                vec![Hex32::from_str(TOPIC).unwrap()],
+            ]),
+        }))
+    );
+    assert_eq!(
+        parse("eth_getLogs", json!([{"address": [ADDRESS, ADDRESS]}])),
+        Ok(GatewayRequest::GetLogs(GetLogsArgs {
+            from_block: None,
+            to_block: None,
+            addresses: vec![Hex20::from_str(ADDRESS).unwrap(); 2],
+            topics: None,
+        }))
+    );
+}
+
+#[test]
+fn should_parse_call_with_data_or_input() {
+    let expected = Ok(GatewayRequest::Call(CallArgs {
+        transaction: TransactionRequest {
+            to: Some(Hex20::from_str(ADDRESS).unwrap()),
+            input: Some(Hex::from_str("0x70a08231").unwrap()),
+            ..Default::default()
+        },
+        block: Some(BlockTag::Safe),
+    }));
+
+    assert_eq!(
+        parse(
+            "eth_call",
+            json!([{"to": ADDRESS, "data": "0x70a08231"}, "safe"])
+        ),
+        expected
+    );
+    assert_eq!(
+        parse(
+            "eth_call",
+            json!([{"to": ADDRESS, "input": "0x70a08231"}, "safe"])
+        ),
+        expected
+    );
+}
+
+#[test]
+fn should_reject_invalid_requests() {
+    assert_eq!(
+        error_code(parse("eth_getBalance", json!([ADDRESS]))),
+        -32601
+    );
+    assert_eq!(
+        error_code(parse("eth_getTransactionReceipt", json!([]))),
+        -32602
+    );
+    assert_eq!(
+        error_code(parse("eth_getTransactionReceipt", json!(["0x1234"]))),
+        -32602
+    );
+    assert_eq!(
+        error_code(parse("eth_getBlockByNumber", json!(["latest", true]))),
+        -32602
+    );
+    assert_eq!(
+        error_code(parse("eth_getLogs", json!([{"blockHash": TOPIC}]))),
+        -32602
+    );
+}
+
+#[test]
+fn should_serialize_json_rpc_response() {
+    assert_eq!(
+        json_rpc_response(json!(1), Ok(json!("0x1"))),
+        json!({"jsonrpc": "2.0", "id": 1, "result": "0x1"})
+    );
+    assert_eq!(
+        json_rpc_response(
+            json!("a"),
+            Err(JsonRpcError {
+                code: -32000,
+                message: "nonce too low".to_string()
+            })
+        ),
+        json!({"jsonrpc": "2.0", "id": "a", "error": {"code": -32000, "message": "nonce too low"}})
+    );
+}
+
+#[test]
+fn should_return_transaction_hash_or_error() {
+    let raw_transaction = Hex::from_str("0x02f8").unwrap();
+    let hash = Hex32::from_str(TOPIC).unwrap();
+
+    assert_eq!(
+        send_raw_transaction_result(
+            SendRawTransactionStatus::Ok(Some(hash.clone())),
+            &raw_transaction
+        ),
+        Ok(json!(TOPIC))
+    );
+    assert_eq!(
+        send_raw_transaction_result(SendRawTransactionStatus::Ok(None), &raw_transaction),
+        Ok(json!(Hex32::from(ethers_core::utils::keccak256(
+            raw_transaction.as_ref()
+        ))
+        .to_string()))
+    );
+    assert_eq!(
+        error_code(send_raw_transaction_result(
+            SendRawTransactionStatus::NonceTooLow,
+            &raw_transaction
+        )),
+        -32000
+    );
+}
+
+#[test]
+fn should_serialize_results_as_standard_json() {
+    let receipt: TransactionReceipt = serde_json::from_value(json!({
+        "transactionHash": "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+        "blockHash": "0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4",
+        "blockNumber": "0x4132ec",
+        "logs": [{
+            "address": ADDRESS,
+            "topics": [TOPIC],
+            "data": "0x01",
+            "blockNumber": "0x4132ec",
+            "transactionHash": "0x0e59bd032b9b22aca5e2784e4cf114783512db00988c716cf17a1cc755a0a93d",
+            "transactionIndex": "0x32",
+            "blockHash": "0x82005d2f17b251900968f01b0ed482cb49b7e1d797342bc504904d442b64dbe4",
+            "logIndex": "0x8",
+            "removed": false
+        }],
+        "contractAddress": null,
+        "effectiveGasPrice": "0xfefbee3e",
+        "cumulativeGasUsed": "0x8b2e10",
+        "from": "0x1789f79e95324a47c5fd6693071188e82e9a3558",
+        "gasUsed": "0x5208",
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "status": "0x1",
+        "to": ADDRESS,
+        "transactionIndex": "0x32",
+        "type": "0x2"
+    }))
+    .unwrap();
+    assert_eq!(
+        TransactionReceipt::from(evm_rpc_types::TransactionReceipt::from(receipt.clone())),
+        receipt
+    );
+
+    let block: Block = serde_json::from_value(json!({
+        "baseFeePerGas": "0x3e4f64de7",
+        "number": "0x12e0a63",
+        "difficulty": "0x0",
+        "extraData": "0x6265617665726275696c642e6f7267",
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0xf4b4b8",
+        "hash": "0x47302c9e68a1fc50b7e8bd3b8aab3be2b6d1a5e53c1b5d4e76d0ff2b31ec5a1e",
+        "logsBloom": format!("0x{}", "00".repeat(256)),
+        "miner": ADDRESS,
+        "mixHash": TOPIC,
+        "nonce": "0x0",
+        "parentHash": TOPIC,
+        "receiptsRoot": TOPIC,
+        "sha3Uncles": TOPIC,
+        "size": "0x1d5ff",
+        "stateRoot": TOPIC,
+        "timestamp": "0x656f9f2f",
+        "transactions": [TOPIC],
+        "transactionsRoot": TOPIC,
+        "uncles": []
+    }))
+    .unwrap();
+    assert_eq!(
+        Block::from(evm_rpc_types::Block::from(block.clone())),
+        block
+    );
+}
+
+#[test]
+fn should_derive_dedicated_account_per_chain() {
+    let mainnet = gateway_account(1);
+    let sepolia = gateway_account(11155111);
+
+    assert_ne!(mainnet, sepolia);
+    assert_eq!(mainnet, gateway_account(1));
+    for account in [mainnet, sepolia] {
+        // Derived principals are never the caller of a call
+        assert_eq!(account.as_slice().last(), Some(&0x03));
+        assert_ne!(account, candid::Principal::anonymous());
+    }
+}
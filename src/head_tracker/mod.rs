@@ -0,0 +1,281 @@
+//! Track the head of chains and detect reorganizations, so that the head of a chain can be
+//! queried without HTTP outcalls.
+//!
+//! A reorganization is detected when the parent hash of a new header does not match the hash of
+//! the recent header with the previous number. The parents of the new header are then fetched
+//! until they connect to the recent headers again, which gives the depth of the reorganization.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    add_metric_entry,
+    candid_rpc::CandidRpcClient,
+    http::Payer,
+    logs::Priority,
+    memory::{
+        get_head_trackers, is_method_suspended, missing_parent_header, record_chain_headers,
+        record_finalized_block, stop_tracking_heads, try_start_tracking_heads,
+    },
+    rpc_client::numeric::BlockNumber,
+    types::MetricChainId,
+};
+use canhttp::multi::Timestamp;
+use canlog::log;
+use evm_rpc_types::{BlockTag, HeadTracker, Hex32, MultiRpcResult, Nat256};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Number of recent headers kept per chain.
+const MAX_RECENT_HEADERS: usize = 64;
+/// Maximum number of parent headers fetched per chain and per polling.
+pub const MAX_BACKFILLED_HEADERS: u64 = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Header {
+    pub number: u64,
+    pub hash: Hex32,
+    pub parent_hash: Hex32,
+}
+
+impl TryFrom<evm_rpc_types::Block> for Header {
+    type Error = evm_rpc_types::RpcError;
+
+    fn try_from(block: evm_rpc_types::Block) -> Result<Self, Self::Error> {
+        Ok(Self {
+            number: u64::try_from(block.number)?,
+            hash: block.hash,
+            parent_hash: block.parent_hash,
+        })
+    }
+}
+
+impl From<Header> for evm_rpc_types::BlockHeader {
+    fn from(header: Header) -> Self {
+        Self {
+            number: Nat256::from(header.number),
+            hash: header.hash,
+            parent_hash: header.parent_hash,
+        }
+    }
+}
+
+/// Recent headers of a chain, by increasing block number, together with its safe and finalized
+/// headers.
+///
+/// Recent headers are contiguous, unless more than [`MAX_BACKFILLED_HEADERS`] blocks were mined
+/// between two pollings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainHeaders {
+    recent: VecDeque<Header>,
+    safe: Option<Header>,
+    finalized: Option<Header>,
+    updated_at: u64,
+}
+
+impl ChainHeaders {
+    /// Number of the parent of the given header that must be fetched for the header to connect
+    /// to the recent headers, if any.
+    pub fn missing_parent(&self, header: &Header) -> Option<u64> {
+        let tip = self.recent.back()?;
+        let parent_number = header.number.checked_sub(1)?;
+        if parent_number > tip.number {
+            return (parent_number - tip.number <= MAX_BACKFILLED_HEADERS).then_some(parent_number);
+        }
+        match self.get(parent_number) {
+            Some(parent) if parent.hash != header.parent_hash => Some(parent_number),
+            _ => None,
+        }
+    }
+
+    /// Insert a header, replacing the recent headers with the same or a higher number.
+    ///
+    /// Returns the number of replaced headers, i.e. the depth of the reorganization if the header
+    /// is not already known.
+    pub fn insert(&mut self, header: Header) -> u64 {
+        if self.get(header.number) == Some(&header) {
+            return 0;
+        }
+        // Headers are only known from the oldest recent header onwards.
+        if self
+            .recent
+            .front()
+            .is_some_and(|oldest| header.number < oldest.number)
+        {
+            return 0;
+        }
+        let num_replaced = self
+            .recent
+            .iter()
+            .filter(|recent| recent.number >= header.number)
+            .count();
+        self.recent.retain(|recent| recent.number < header.number);
+        self.recent.push_back(header);
+        while self.recent.len() > MAX_RECENT_HEADERS {
+            self.recent.pop_front();
+        }
+        num_replaced as u64
+    }
+
+    pub fn latest(&self) -> Option<&Header> {
+        self.recent.back()
+    }
+
+    pub fn recent(&self) -> impl Iterator<Item = &Header> {
+        self.recent.iter()
+    }
+
+    fn get(&self, number: u64) -> Option<&Header> {
+        self.recent.iter().find(|header| header.number == number)
+    }
+}
+
+/// Recent headers of each tracked chain, by chain ID.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChainHeads {
+    chains: BTreeMap<u64, ChainHeaders>,
+}
+
+impl ChainHeads {
+    pub fn get(&self, chain_id: u64) -> Option<&ChainHeaders> {
+        self.chains.get(&chain_id)
+    }
+
+    /// Record the headers of a chain, by increasing block number, and return the depth
+    /// of the reorganization, if any.
+    pub fn record(
+        &mut self,
+        chain_id: u64,
+        headers: impl IntoIterator<Item = Header>,
+        safe: Option<Header>,
+        finalized: Option<Header>,
+        now: u64,
+    ) -> u64 {
+        let chain = self.chains.entry(chain_id).or_default();
+        let depth = headers.into_iter().map(|header| chain.insert(header)).sum();
+        chain.safe = safe.or(chain.safe.take());
+        chain.finalized = finalized.or(chain.finalized.take());
+        chain.updated_at = now;
+        depth
+    }
+
+    /// Forget the headers of the chains that are no longer tracked.
+    pub fn retain(&mut self, chain_ids: &[u64]) {
+        self.chains
+            .retain(|chain_id, _| chain_ids.contains(chain_id));
+    }
+
+    pub fn chain_head(&self, chain_id: u64) -> Option<evm_rpc_types::ChainHead> {
+        let chain = self.chains.get(&chain_id)?;
+        Some(evm_rpc_types::ChainHead {
+            latest: chain.latest()?.clone().into(),
+            safe: chain.safe.clone().map(Into::into),
+            finalized: chain.finalized.clone().map(Into::into),
+            updated_at: chain.updated_at,
+        })
+    }
+}
+
+/// Poll the head of all tracked chains, one after the other.
+///
+/// Tracking is paused while `eth_getBlockByNumber` is suspended by the controllers.
+pub async fn poll_head_trackers() {
+    if is_method_suspended("eth_getBlockByNumber") {
+        return;
+    }
+    let Some(_guard) = TrackingGuard::new() else {
+        return;
+    };
+    for tracker in get_head_trackers() {
+        if let Err(error) = poll_head_tracker(&tracker).await {
+            log!(
+                Priority::Info,
+                "[poll_head_trackers]: failed to track head of {:?}: {error}",
+                tracker.source
+            );
+        }
+    }
+}
+
+async fn poll_head_tracker(tracker: &HeadTracker) -> Result<(), String> {
+    let client = new_client(tracker)?;
+    let chain_id = client.chain_id();
+    let latest = fetch_header(tracker, BlockTag::Latest).await?;
+    let mut headers = vec![latest];
+    for _ in 0..MAX_BACKFILLED_HEADERS {
+        let lowest = headers.last().expect("BUG: headers are not empty");
+        let Some(parent_number) = missing_parent_header(chain_id, lowest) else {
+            break;
+        };
+        let parent = fetch_header(tracker, BlockTag::Number(Nat256::from(parent_number))).await?;
+        headers.push(parent);
+    }
+    // Not all chains or providers support the `safe` and `finalized` block tags.
+    let safe = fetch_header(tracker, BlockTag::Safe).await.ok();
+    let finalized = fetch_header(tracker, BlockTag::Finalized).await.ok();
+    // Like cached responses, the finalized block of custom providers or of non-replicated
+    // HTTP outcalls is not recorded.
+    if let Some(finalized) = finalized
+        .as_ref()
+        .filter(|_| client.can_record_chain_state())
+    {
+        record_finalized_block(chain_id, BlockNumber::from(finalized.number));
+    }
+
+    let depth = record_chain_headers(
+        chain_id,
+        headers.into_iter().rev(),
+        safe,
+        finalized,
+        ic_cdk::api::time(),
+    );
+    if depth > 0 {
+        log!(
+            Priority::Info,
+            "[poll_head_tracker]: reorganization of {depth} blocks on chain {chain_id}"
+        );
+        add_metric_entry!(chain_reorgs, MetricChainId::from(chain_id), 1);
+        add_metric_entry!(chain_reorged_blocks, MetricChainId::from(chain_id), depth);
+    }
+    Ok(())
+}
+
+async fn fetch_header(tracker: &HeadTracker, block_tag: BlockTag) -> Result<Header, String> {
+    match new_client(tracker)?
+        .eth_get_block_by_number(block_tag)
+        .await
+    {
+        MultiRpcResult::Consistent(Ok(block)) => {
+            Header::try_from(block).map_err(|e| format!("{e}"))
+        }
+        MultiRpcResult::Consistent(Err(e)) => Err(format!("{e}")),
+        MultiRpcResult::Inconsistent(_) | MultiRpcResult::InconsistentWithDiagnostics { .. } => {
+            Err("Providers returned inconsistent results".to_string())
+        }
+    }
+}
+
+fn new_client(tracker: &HeadTracker) -> Result<CandidRpcClient, String> {
+    CandidRpcClient::new(
+        tracker.source.clone(),
+        tracker.config.clone(),
+        Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time()),
+    )
+    .map(|client| client.with_payer(Payer::Canister))
+    .map_err(|e| format!("{e}"))
+}
+
+/// Prevents the chain heads from being polled concurrently, e.g. if polling takes longer
+/// than the polling interval.
+struct TrackingGuard;
+
+impl TrackingGuard {
+    fn new() -> Option<Self> {
+        try_start_tracking_heads().then_some(TrackingGuard)
+    }
+}
+
+impl Drop for TrackingGuard {
+    fn drop(&mut self) {
+        stop_tracking_heads();
+    }
+}
@@ -0,0 +1,126 @@
+use crate::head_tracker::{ChainHeaders, ChainHeads, Header, MAX_BACKFILLED_HEADERS};
+use evm_rpc_types::{Hex32, Nat256};
+
+const MAINNET: u64 = 1;
+
+fn header(number: u64, fork: u8) -> Header {
+    Header {
+        number,
+        hash: hash(number, fork),
+        parent_hash: hash(number - 1, fork),
+    }
+}
+
+fn hash(number: u64, fork: u8) -> Hex32 {
+    let mut hash = [0_u8; 32];
+    hash[..8].copy_from_slice(&number.to_be_bytes());
+    hash[31] = fork;
+    Hex32::from(hash)
+}
+
+fn chain(numbers: std::ops::RangeInclusive<u64>) -> ChainHeaders {
+    let mut chain = ChainHeaders::default();
+    for number in numbers {
+        assert_eq!(chain.insert(header(number, 0)), 0);
+    }
+    chain
+}
+
+#[test]
+fn should_extend_recent_headers() {
+    let mut chain = chain(100..=102);
+
+    assert_eq!(chain.missing_parent(&header(103, 0)), None);
+    assert_eq!(chain.insert(header(103, 0)), 0);
+    // A provider lagging behind returns a known header.
+    assert_eq!(chain.insert(header(101, 0)), 0);
+
+    let numbers: Vec<_> = chain.recent().map(|header| header.number).collect();
+    assert_eq!(numbers, vec![100, 101, 102, 103]);
+    assert_eq!(chain.latest(), Some(&header(103, 0)));
+}
+
+#[test]
+fn should_request_parents_to_fill_small_gaps() {
+    let chain = chain(100..=102);
+
+    assert_eq!(chain.missing_parent(&header(105, 0)), Some(104));
+    assert_eq!(chain.missing_parent(&header(104, 0)), Some(103));
+    assert_eq!(chain.missing_parent(&header(103, 0)), None);
+    assert_eq!(
+        chain.missing_parent(&header(103 + MAX_BACKFILLED_HEADERS, 0)),
+        Some(102 + MAX_BACKFILLED_HEADERS)
+    );
+    assert_eq!(
+        chain.missing_parent(&header(104 + MAX_BACKFILLED_HEADERS, 0)),
+        None
+    );
+}
+
+#[test]
+fn should_detect_reorganization() {
+    let mut chain = chain(100..=105);
+
+    // Blocks 104 and 105 were reorganized away.
+    assert_eq!(chain.missing_parent(&header(106, 1)), Some(105));
+    assert_eq!(chain.missing_parent(&header(105, 1)), Some(104));
+    let ancestor = Header {
+        number: 104,
+        hash: hash(104, 1),
+        parent_hash: hash(103, 0),
+    };
+    assert_eq!(chain.missing_parent(&ancestor), None);
+
+    assert_eq!(chain.insert(ancestor.clone()), 2);
+    assert_eq!(chain.insert(header(105, 1)), 0);
+    assert_eq!(chain.insert(header(106, 1)), 0);
+
+    let recent: Vec<_> = chain.recent().cloned().collect();
+    assert_eq!(
+        recent,
+        vec![
+            header(100, 0),
+            header(101, 0),
+            header(102, 0),
+            header(103, 0),
+            ancestor,
+            header(105, 1),
+            header(106, 1),
+        ]
+    );
+}
+
+#[test]
+fn should_keep_limited_number_of_recent_headers() {
+    let chain = chain(1..=100);
+
+    let numbers: Vec<_> = chain.recent().map(|header| header.number).collect();
+    assert_eq!(numbers, (37..=100).collect::<Vec<_>>());
+}
+
+#[test]
+fn should_record_chain_heads() {
+    let mut heads = ChainHeads::default();
+    assert_eq!(heads.chain_head(MAINNET), None);
+
+    let depth = heads.record(
+        MAINNET,
+        (100..=102).map(|number| header(number, 0)),
+        Some(header(90, 0)),
+        Some(header(80, 0)),
+        1_000,
+    );
+    assert_eq!(depth, 0);
+    let depth = heads.record(MAINNET, [header(102, 1)], None, None, 2_000);
+    assert_eq!(depth, 1);
+
+    let head = heads.chain_head(MAINNET).unwrap();
+    assert_eq!(head.latest.number, Nat256::from(102_u64));
+    assert_eq!(head.latest.hash, hash(102, 1));
+    assert_eq!(head.safe.unwrap().number, Nat256::from(90_u64));
+    assert_eq!(head.finalized.unwrap().number, Nat256::from(80_u64));
+    assert_eq!(head.updated_at, 2_000);
+
+    heads.retain(&[]);
+    assert_eq!(heads.chain_head(MAINNET), None);
+}
@@ -11,7 +11,7 @@ use canhttp::{
     HttpsOutcallError, IcError,
 };
 use evm_rpc_types::{
-    HttpOutcallError, LegacyRejectionCode, ProviderError, RpcError, ValidationError,
+    CallerLimit, HttpOutcallError, LegacyRejectionCode, ProviderError, RpcError, ValidationError,
 };
 use ic_error_types::RejectCode;
 use thiserror::Error;
@@ -23,7 +23,7 @@ pub enum HttpClientError {
     #[error("unknown error (most likely sign of a bug): {0}")]
     NotHandledError(String),
     #[error("cycles accounting error: {0}")]
-    CyclesAccountingError(ChargeCallerError),
+    CyclesAccountingError(ChargeCyclesError),
     #[error("HTTP response was not successful: {0}")]
     UnsuccessfulHttpResponse(FilterNonSuccessfulHttpResponseError<Vec<u8>>),
     #[error("Error converting response to JSON: {0}")]
@@ -32,6 +32,21 @@ pub enum HttpClientError {
     InvalidJsonResponseId(ConsistentResponseIdFilterError),
 }
 
+/// Error charging the cycles of an HTTP outcall.
+#[derive(Clone, Debug, Error)]
+pub enum ChargeCyclesError {
+    #[error("{0}")]
+    InsufficientCycles(ChargeCallerError),
+    #[error("caller limit exceeded: {0:?}")]
+    CallerLimitExceeded(CallerLimit),
+}
+
+impl From<ChargeCallerError> for ChargeCyclesError {
+    fn from(value: ChargeCallerError) -> Self {
+        ChargeCyclesError::InsufficientCycles(value)
+    }
+}
+
 impl From<IcError> for HttpClientError {
     fn from(value: IcError) -> Self {
         HttpClientError::IcError(value)
@@ -57,8 +72,8 @@ impl From<JsonResponseConversionError> for HttpClientError {
     }
 }
 
-impl From<ChargeCallerError> for HttpClientError {
-    fn from(value: ChargeCallerError) -> Self {
+impl From<ChargeCyclesError> for HttpClientError {
+    fn from(value: ChargeCyclesError) -> Self {
         HttpClientError::CyclesAccountingError(value)
     }
 }
@@ -109,9 +124,12 @@ impl From<HttpClientError> for RpcError {
             HttpClientError::NotHandledError(e) => {
                 RpcError::ValidationError(ValidationError::Custom(e))
             }
-            HttpClientError::CyclesAccountingError(
+            HttpClientError::CyclesAccountingError(ChargeCyclesError::InsufficientCycles(
                 ChargeCallerError::InsufficientCyclesError { expected, received },
-            ) => RpcError::ProviderError(ProviderError::TooFewCycles { expected, received }),
+            )) => RpcError::ProviderError(ProviderError::TooFewCycles { expected, received }),
+            HttpClientError::CyclesAccountingError(ChargeCyclesError::CallerLimitExceeded(
+                limit,
+            )) => RpcError::ProviderError(ProviderError::CallerLimitExceeded { limit }),
             HttpClientError::InvalidJsonResponse(
                 JsonResponseConversionError::InvalidJsonResponse {
                     status,
@@ -137,6 +155,16 @@ impl From<HttpClientError> for RpcError {
     }
 }
 
+/// Whether the error was caused by a response exceeding the maximum response size.
+pub fn is_response_too_large_error(error: &RpcError) -> bool {
+    match error {
+        RpcError::HttpOutcallError(HttpOutcallError::IcError { code, message }) => {
+            code == &LegacyRejectionCode::SysFatal && message.contains("size limit")
+        }
+        _ => false,
+    }
+}
+
 pub fn is_consensus_error(error: &IcError) -> bool {
     match error {
         IcError::CallRejected { code, message } => {
@@ -1,11 +1,22 @@
+#[cfg(test)]
+mod tests;
+
 use crate::{
-    constants::{COLLATERAL_CYCLES_PER_NODE, CONTENT_TYPE_VALUE},
-    memory::{get_num_subnet_nodes, is_demo_active, next_request_id},
+    add_metric_entry,
+    constants::{
+        CACHE_HIT_CYCLES, COALESCING_ROUND_CYCLES, COLLATERAL_CYCLES_PER_NODE, CONTENT_TYPE_VALUE,
+    },
+    memory::{
+        ensure_caller_within_limits, get_balance, get_num_subnet_nodes, is_demo_active,
+        next_request_id, record_caller_cycles, record_caller_outcall, try_debit_balance,
+    },
+    types::MetricCallerLimit,
     util::canonicalize_json,
 };
+use candid::Principal;
 use canhttp::{
     convert::ConvertRequestLayer,
-    cycles::{ChargeCaller, CyclesAccounting},
+    cycles::{ChargeCaller, ChargeCallerError, CyclesAccounting, CyclesChargingPolicy},
     http::{
         json::{
             CreateJsonRpcIdFilter, HttpJsonRpcRequest, HttpJsonRpcResponse, JsonRequestConverter,
@@ -17,7 +28,7 @@ use canhttp::{
     retry::DoubleMaxResponseBytes,
     ConvertServiceBuilder,
 };
-use error::HttpClientError;
+use error::{ChargeCyclesError, HttpClientError};
 use evm_rpc_types::RpcError;
 use http::{header::CONTENT_TYPE, HeaderValue};
 use ic_management_canister_types::{
@@ -25,9 +36,10 @@ use ic_management_canister_types::{
 };
 use observability::{
     observe_http_client_error, observe_http_json_rpc_request, observe_http_json_rpc_response,
+    observe_http_response_body_size,
 };
 use serde::{de::DeserializeOwned, Serialize};
-use std::fmt::Debug;
+use std::{cell::Cell, fmt::Debug, rc::Rc};
 use tower::{
     layer::util::{Identity, Stack},
     retry::RetryLayer,
@@ -40,8 +52,12 @@ pub mod error;
 pub mod legacy;
 mod observability;
 
+/// HTTP client charging the cycles of each HTTP outcall to the given payer, which are added to
+/// `charged_cycles`.
 pub fn http_client<I, O>(
     retry: bool,
+    charged_cycles: ChargedCycles,
+    payer: Payer,
 ) -> impl Service<HttpJsonRpcRequest<I>, Response = HttpJsonRpcResponse<O>, Error = RpcError>
 where
     I: Serialize + Clone + Debug,
@@ -70,9 +86,14 @@ where
         .filter_response(CreateJsonRpcIdFilter::new())
         .layer(service_request_builder())
         .convert_response(JsonResponseConverter::new())
+        .map_response(observe_http_response_body_size)
         .convert_response(FilterNonSuccessfulHttpResponse)
         .convert_response(HttpResponseConverter)
-        .convert_request(CyclesAccounting::new(charging_policy_with_collateral()))
+        .convert_request(CyclesAccounting::new(
+            charging_policy_with_collateral()
+                .with_charged_cycles(charged_cycles)
+                .with_payer(payer),
+        ))
         .service(canhttp::Client::new_with_error::<HttpClientError>())
 }
 
@@ -106,17 +127,216 @@ pub fn service_request_builder<I>() -> JsonRpcServiceBuilder<I> {
 }
 
 pub fn charging_policy_with_collateral(
-) -> ChargeCaller<impl Fn(&IcHttpRequest, u128) -> u128 + Clone> {
+) -> ChargeCallerOrAccount<impl Fn(&IcHttpRequest, u128) -> u128 + Clone> {
     let charge_caller = if is_demo_active() {
         |_request: &IcHttpRequest, _request_cost| 0
     } else {
-        |_request: &IcHttpRequest, request_cost| {
-            let collateral_cycles =
-                COLLATERAL_CYCLES_PER_NODE.saturating_mul(get_num_subnet_nodes() as u128);
+        |request: &IcHttpRequest, request_cost| {
+            // A non-replicated HTTP outcall is made by a single node.
+            let num_nodes = if request.is_replicated == Some(false) {
+                1
+            } else {
+                get_num_subnet_nodes()
+            };
+            let collateral_cycles = COLLATERAL_CYCLES_PER_NODE.saturating_mul(num_nodes as u128);
             request_cost + collateral_cycles
         }
     };
-    ChargeCaller::new(charge_caller)
+    ChargeCallerOrAccount {
+        charge_caller: ChargeCaller::new(charge_caller),
+        charged_cycles: ChargedCycles::default(),
+        payer: Payer::default(),
+    }
+}
+
+/// Account paying for the HTTP outcalls of a request.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Payer {
+    /// The caller pays with the cycles attached to the call or, if not enough cycles are
+    /// attached, with its prepaid cycles balance.
+    #[default]
+    Caller,
+    /// The given account pays with its prepaid cycles balance, e.g. the owner of a log
+    /// subscription polled by the canister itself.
+    Account(Principal),
+    /// The canister pays with its own cycles, e.g. to track the head of chains configured by
+    /// the controllers.
+    Canister,
+}
+
+impl Payer {
+    /// Principal whose usage is limited and recorded.
+    pub fn principal(&self) -> Principal {
+        match self {
+            Payer::Caller => ic_cdk::api::msg_caller(),
+            Payer::Account(account) => *account,
+            Payer::Canister => ic_cdk::api::canister_self(),
+        }
+    }
+
+    /// Amount of cycles available to pay for the request.
+    pub fn available_cycles(&self) -> u128 {
+        match self {
+            Payer::Caller => ic_cdk::api::msg_cycles_available()
+                .saturating_add(get_balance(&ic_cdk::api::msg_caller())),
+            Payer::Account(account) => get_balance(account),
+            Payer::Canister => ic_cdk::api::canister_cycle_balance(),
+        }
+    }
+}
+
+/// Total amount of cycles charged for the HTTP outcalls of a single request.
+///
+/// Unlike the decrease of the attached cycles or of the balance of the caller, it does not
+/// include the cycles charged for other calls of the same caller made concurrently.
+#[derive(Clone, Debug, Default)]
+pub struct ChargedCycles(Rc<Cell<u128>>);
+
+impl ChargedCycles {
+    pub fn get(&self) -> u128 {
+        self.0.get()
+    }
+
+    fn add(&self, cycles: u128) {
+        self.0.set(self.0.get().saturating_add(cycles));
+    }
+}
+
+/// Charge the payer for a response served from the cache, which is much cheaper than
+/// the HTTP outcalls to the providers.
+pub fn charge_cache_hit(payer: Payer) -> Result<(), RpcError> {
+    let cycles = if is_demo_active() {
+        0
+    } else {
+        CACHE_HIT_CYCLES
+    };
+    charge_cycles(payer, cycles).map(|_| ())
+}
+
+/// Charge the payer for a round of waiting for the result of an identical in-flight request,
+/// which covers the cost of the call made to wait for the next round.
+pub fn charge_coalescing_round(payer: Payer) -> Result<(), RpcError> {
+    let cycles = if is_demo_active() {
+        0
+    } else {
+        COALESCING_ROUND_CYCLES
+    };
+    charge_cycles(payer, cycles).map(|_| ())
+}
+
+/// Charge the payer the given amount of cycles, see [`Payer`], and return the amount of cycles
+/// actually debited, which is 0 if the canister pays.
+pub fn charge_cycles(payer: Payer, cycles: u128) -> Result<u128, RpcError> {
+    charging_policy_with_collateral()
+        .with_payer(payer)
+        .charge(false, cycles)
+        .map_err(|e| RpcError::from(HttpClientError::from(e)))?;
+    Ok(match payer {
+        Payer::Caller | Payer::Account(_) => cycles,
+        Payer::Canister => 0,
+    })
+}
+
+/// Charge the caller with the cycles attached to the call or, if not enough cycles are attached,
+/// with the prepaid cycles balance of the caller, unless another account pays, see [`Payer`].
+#[derive(Clone)]
+pub struct ChargeCallerOrAccount<F> {
+    charge_caller: ChargeCaller<F>,
+    charged_cycles: ChargedCycles,
+    payer: Payer,
+}
+
+impl<F> ChargeCallerOrAccount<F>
+where
+    F: Fn(&IcHttpRequest, u128) -> u128,
+{
+    /// Add the charged cycles to the given total.
+    pub fn with_charged_cycles(mut self, charged_cycles: ChargedCycles) -> Self {
+        self.charged_cycles = charged_cycles;
+        self
+    }
+
+    /// Charge the given payer instead of the caller.
+    pub fn with_payer(mut self, payer: Payer) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    /// Charge the payer the given amount of cycles, e.g. for an HTTP outcall, after checking that
+    /// it does not exceed the limits of the payer, and record the usage of the payer.
+    fn charge(&self, is_outcall: bool, cycles_to_charge: u128) -> Result<(), ChargeCyclesError> {
+        let payer = self.payer.principal();
+        let now = ic_cdk::api::time();
+        let outcalls = u64::from(is_outcall);
+        if let Err(limit) = ensure_caller_within_limits(&payer, outcalls, cycles_to_charge, now) {
+            add_metric_entry!(caller_limit_rejections, MetricCallerLimit::from(limit), 1);
+            return Err(ChargeCyclesError::CallerLimitExceeded(limit));
+        }
+        match self.payer {
+            Payer::Caller => self.charge_caller_or_account(payer, cycles_to_charge)?,
+            Payer::Account(account) => charge_account(&account, cycles_to_charge)?,
+            Payer::Canister => {}
+        }
+        if is_outcall {
+            record_caller_outcall(payer, now);
+        }
+        record_caller_cycles(payer, cycles_to_charge, now);
+        self.charged_cycles.add(cycles_to_charge);
+        Ok(())
+    }
+
+    fn charge_caller_or_account(
+        &self,
+        caller: Principal,
+        cycles_to_charge: u128,
+    ) -> Result<(), ChargeCallerError> {
+        let attached_cycles = ic_cdk::api::msg_cycles_available();
+        if try_debit_balance_for_missing_cycles(&caller, cycles_to_charge, attached_cycles) {
+            return Ok(());
+        }
+        self.charge_caller.charge_cycles(cycles_to_charge)
+    }
+}
+
+/// Debit the prepaid cycles balance of the account with the cycles to charge, which fails
+/// if the balance does not cover them.
+fn charge_account(account: &Principal, cycles_to_charge: u128) -> Result<(), ChargeCallerError> {
+    if try_debit_balance(account, cycles_to_charge) {
+        return Ok(());
+    }
+    Err(ChargeCallerError::InsufficientCyclesError {
+        expected: cycles_to_charge,
+        received: get_balance(account),
+    })
+}
+
+/// Debit the prepaid cycles balance of the caller with the cycles to charge if not enough cycles
+/// are attached to the call, and return whether the balance was debited.
+///
+/// The balance is left untouched if it does not cover the cycles to charge, in which case
+/// the caller is charged with the attached cycles, which fails.
+fn try_debit_balance_for_missing_cycles(
+    caller: &Principal,
+    cycles_to_charge: u128,
+    attached_cycles: u128,
+) -> bool {
+    cycles_to_charge > attached_cycles && try_debit_balance(caller, cycles_to_charge)
+}
+
+impl<F> CyclesChargingPolicy for ChargeCallerOrAccount<F>
+where
+    F: Fn(&IcHttpRequest, u128) -> u128,
+{
+    type Error = ChargeCyclesError;
+
+    fn cycles_to_charge(&self, request: &IcHttpRequest, attached_cycles: u128) -> u128 {
+        self.charge_caller
+            .cycles_to_charge(request, attached_cycles)
+    }
+
+    fn charge_cycles(&self, cycles_to_charge: u128) -> Result<(), Self::Error> {
+        self.charge(true, cycles_to_charge)
+    }
 }
 
 pub fn transform_http_request(args: TransformArgs) -> IcHttpResponse {
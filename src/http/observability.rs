@@ -2,7 +2,9 @@ use crate::{
     add_metric_entry,
     http::error::{is_consensus_error, HttpClientError},
     logs::Priority,
-    types::{MetricRpcMethod, MetricRpcService},
+    memory::{record_api_key_request, record_api_key_status, record_response_size},
+    rpc_client::response_sizes::ResponseSizeKey,
+    types::{ApiKeyRef, MetricApiKey, MetricRpcMethod, MetricRpcService},
 };
 use canhttp::{
     http::{
@@ -12,7 +14,7 @@ use canhttp::{
         },
         FilterNonSuccessfulHttpResponseError,
     },
-    HttpsOutcallError, IcError,
+    HttpsOutcallError, IcError, MaxResponseBytesRequestExtension,
 };
 use canlog::log;
 use evm_rpc_types::{LegacyRejectionCode, RpcService};
@@ -25,6 +27,10 @@ pub fn observe_http_json_rpc_request<I: Debug>(req: &HttpJsonRpcRequest<I>) -> M
         (req_data.method.clone(), req_data.service.clone()),
         1
     );
+    if let Some(api_key) = req_data.api_key {
+        record_api_key_request(api_key);
+        add_metric_entry!(api_key_requests, MetricApiKey::from(api_key), 1);
+    }
     log!(
         Priority::TraceHttp,
         "JSON-RPC request with id `{}` to {}: {:?}",
@@ -35,10 +41,38 @@ pub fn observe_http_json_rpc_request<I: Debug>(req: &HttpJsonRpcRequest<I>) -> M
     req_data
 }
 
+/// Margin added to the size of a transformed response body, in percent, to bound the size of
+/// the response body returned by the provider, since the transform strips whitespace and
+/// the fields that are not needed (e.g. the withdrawals of a block).
+const STRIPPED_FIELDS_MARGIN_PERCENT: u64 = 100;
+
+/// Upper bound of the size in bytes of the response body returned by the provider, which is
+/// derived from the size of the transformed response body and stored as an extension of
+/// the response before the body is parsed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResponseBodySize(pub u64);
+
+pub fn observe_http_response_body_size(
+    mut response: http::Response<Vec<u8>>,
+) -> http::Response<Vec<u8>> {
+    let num_bytes = response.body().len() as u64;
+    let margin = num_bytes.saturating_mul(STRIPPED_FIELDS_MARGIN_PERCENT) / 100;
+    response
+        .extensions_mut()
+        .insert(ResponseBodySize(num_bytes.saturating_add(margin)));
+    response
+}
+
 pub fn observe_http_json_rpc_response<O: Debug>(
     req_data: MetricData,
     response: &HttpJsonRpcResponse<O>,
 ) {
+    if let (Some(key), Some(ResponseBodySize(num_bytes))) = (
+        &req_data.response_size_key,
+        response.extensions().get::<ResponseBodySize>(),
+    ) {
+        record_response_size(key.method.clone(), key.service, *num_bytes);
+    }
     log!(
         Priority::TraceHttp,
         "Got response for request with id `{}`. Response with status {}: {:?}",
@@ -46,17 +80,19 @@ pub fn observe_http_json_rpc_response<O: Debug>(
         response.status(),
         response.body()
     );
-    add_status_code_metric(
-        req_data.method,
-        req_data.service,
-        response.status().as_u16(),
-    );
+    add_status_code_metric(req_data, response.status().as_u16());
 }
 
 pub fn observe_http_client_error(req_data: MetricData, error: &HttpClientError) {
     match error {
         HttpClientError::IcError(error) => {
             if error.is_response_too_large() {
+                // The response body is at least as large as the maximum response size.
+                if let (Some(key), Some(max_response_bytes)) =
+                    (&req_data.response_size_key, req_data.max_response_bytes)
+                {
+                    record_response_size(key.method.clone(), key.service, max_response_bytes);
+                }
                 add_metric_entry!(
                     err_max_response_size_exceeded,
                     (req_data.method, req_data.service),
@@ -98,11 +134,7 @@ pub fn observe_http_client_error(req_data: MetricData, error: &HttpClientError)
                 response.status(),
                 String::from_utf8_lossy(response.body())
             );
-            add_status_code_metric(
-                req_data.method,
-                req_data.service,
-                response.status().as_u16(),
-            );
+            add_status_code_metric(req_data, response.status().as_u16());
         }
         HttpClientError::InvalidJsonResponse(
             JsonResponseConversionError::InvalidJsonResponse {
@@ -117,7 +149,7 @@ pub fn observe_http_client_error(req_data: MetricData, error: &HttpClientError)
                 req_data.request_id,
                 error
             );
-            add_status_code_metric(req_data.method, req_data.service, *status);
+            add_status_code_metric(req_data, *status);
         }
         HttpClientError::InvalidJsonResponseId(
             ConsistentResponseIdFilterError::InconsistentId {
@@ -132,7 +164,7 @@ pub fn observe_http_client_error(req_data: MetricData, error: &HttpClientError)
                 req_data.request_id,
                 error
             );
-            add_status_code_metric(req_data.method, req_data.service, *status);
+            add_status_code_metric(req_data, *status);
         }
         // TODO DEFI-2565: Improve log message once `req_data` supports JSON-RPC batch requests
         HttpClientError::InvalidJsonResponseId(
@@ -147,7 +179,7 @@ pub fn observe_http_client_error(req_data: MetricData, error: &HttpClientError)
                 "Invalid JSON RPC batch response: {}",
                 error
             );
-            add_status_code_metric(req_data.method, req_data.service, *status);
+            add_status_code_metric(req_data, *status);
         }
         HttpClientError::NotHandledError(e) => {
             log!(Priority::Info, "BUG: Unexpected error: {}", e);
@@ -160,6 +192,9 @@ pub struct MetricData {
     method: MetricRpcMethod,
     service: MetricRpcService,
     request_id: Id,
+    api_key: Option<ApiKeyRef>,
+    response_size_key: Option<ResponseSizeKey>,
+    max_response_bytes: Option<u64>,
 }
 
 fn from_request<I>(request: &HttpJsonRpcRequest<I>) -> MetricData {
@@ -182,14 +217,44 @@ fn from_request<I>(request: &HttpJsonRpcRequest<I>) -> MetricData {
         is_supported: !matches!(rpc_service, RpcService::Custom(_)),
     };
     let request_id = request.body().id().clone();
+    let api_key = request.extensions().get::<ApiKeyRef>().copied();
+    let response_size_key = request.extensions().get::<ResponseSizeKey>().cloned();
     MetricData {
         method,
         service,
         request_id,
+        api_key,
+        response_size_key,
+        max_response_bytes: request.get_max_response_bytes(),
     }
 }
 
-fn add_status_code_metric(method: MetricRpcMethod, host: MetricRpcService, status: u16) {
+fn add_status_code_metric(req_data: MetricData, status: u16) {
+    if let Some(api_key) = req_data.api_key {
+        observe_api_key_status(api_key, status);
+    }
     let status: u32 = status as u32;
-    add_metric_entry!(responses, (method, host, status.into()), 1);
+    add_metric_entry!(
+        responses,
+        (req_data.method, req_data.service, status.into()),
+        1
+    );
+}
+
+fn observe_api_key_status(api_key: ApiKeyRef, status: u16) {
+    if !http::StatusCode::from_u16(status).is_ok_and(|status| status.is_success()) {
+        add_metric_entry!(
+            api_key_errors,
+            (MetricApiKey::from(api_key), (status as u32).into()),
+            1
+        );
+    }
+    if record_api_key_status(api_key, status) {
+        log!(
+            Priority::Info,
+            "API key {} of provider {} was rate limited, rotating to the next API key",
+            api_key.key_id,
+            api_key.provider_id
+        );
+    }
 }
@@ -0,0 +1,156 @@
+use crate::{
+    http::{
+        charge_account,
+        error::{ChargeCyclesError, HttpClientError},
+        observability::{observe_http_response_body_size, ResponseBodySize},
+        try_debit_balance_for_missing_cycles,
+    },
+    memory::{credit_balance, get_balance},
+    rpc_client::eth_rpc::{ResponseTransform, ResponseTransformEnvelope},
+};
+use candid::Principal;
+use canhttp::cycles::ChargeCallerError;
+use evm_rpc_types::{CallerLimit, ProviderError, RpcError};
+use serde_json::json;
+
+const ALICE: Principal = Principal::from_slice(&[1]);
+const BOB: Principal = Principal::from_slice(&[2]);
+
+mod charge_cycles {
+    use super::*;
+
+    #[test]
+    fn should_charge_balance_when_too_few_cycles_attached() {
+        credit_balance(ALICE, 1_000);
+
+        assert!(try_debit_balance_for_missing_cycles(&ALICE, 400, 399));
+        assert_eq!(get_balance(&ALICE), 600);
+
+        assert!(try_debit_balance_for_missing_cycles(&ALICE, 600, 0));
+        assert_eq!(get_balance(&ALICE), 0);
+    }
+
+    #[test]
+    fn should_not_charge_balance_when_enough_cycles_attached() {
+        credit_balance(ALICE, 1_000);
+
+        assert!(!try_debit_balance_for_missing_cycles(&ALICE, 400, 400));
+        assert!(!try_debit_balance_for_missing_cycles(&ALICE, 400, 1_000));
+        assert_eq!(get_balance(&ALICE), 1_000);
+    }
+
+    #[test]
+    fn should_not_charge_balance_of_other_principals_or_insufficient_balance() {
+        credit_balance(ALICE, 1_000);
+        credit_balance(BOB, 100);
+
+        assert!(!try_debit_balance_for_missing_cycles(&BOB, 400, 0));
+        assert_eq!(get_balance(&BOB), 100);
+        assert_eq!(get_balance(&ALICE), 1_000);
+    }
+
+    #[test]
+    fn should_charge_account_balance() {
+        credit_balance(ALICE, 1_000);
+
+        assert!(charge_account(&ALICE, 400).is_ok());
+        assert_eq!(get_balance(&ALICE), 600);
+
+        assert!(matches!(
+            charge_account(&ALICE, 601),
+            Err(ChargeCallerError::InsufficientCyclesError {
+                expected: 601,
+                received: 600,
+            })
+        ));
+        assert_eq!(get_balance(&ALICE), 600);
+
+        assert!(charge_account(&ALICE, 600).is_ok());
+        assert_eq!(get_balance(&ALICE), 0);
+    }
+
+    #[test]
+    fn should_convert_charging_errors() {
+        assert_eq!(
+            RpcError::from(HttpClientError::from(ChargeCyclesError::from(
+                ChargeCallerError::InsufficientCyclesError {
+                    expected: 400,
+                    received: 100,
+                }
+            ))),
+            RpcError::ProviderError(ProviderError::TooFewCycles {
+                expected: 400,
+                received: 100,
+            })
+        );
+        assert_eq!(
+            RpcError::from(HttpClientError::from(
+                ChargeCyclesError::CallerLimitExceeded(CallerLimit::Outcalls)
+            )),
+            RpcError::ProviderError(ProviderError::CallerLimitExceeded {
+                limit: CallerLimit::Outcalls
+            })
+        );
+    }
+}
+
+mod response_body_size {
+    use super::*;
+
+    #[test]
+    fn should_bound_size_of_response_with_stripped_fields() {
+        let transactions: Vec<_> = (0..100).map(|i| format!("0x{i:064x}")).collect();
+        let withdrawals: Vec<_> = (0..16)
+            .map(|i| {
+                json!({
+                    "index": format!("0x{:x}", 0x4a3f_1b00 + i),
+                    "validatorIndex": format!("0x{:x}", 0x10_f3a5 + i),
+                    "address": "0xb9d7934878b5fb9610b3fe8a5e441e8fad7e293f",
+                    "amount": "0x11f2e4a"
+                })
+            })
+            .collect();
+        let raw_body = serde_json::to_vec(&json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "result": {
+                "number": "0x10eb3c6",
+                "hash": "0x85db6d6ad071d127795df4c5f1b04863629d7c2832c89550aa2771bf81c40c85",
+                "transactions": transactions,
+                "difficulty": "0x0",
+                "extraData": "0x6275696c64657230783639",
+                "gasLimit": "0x1c9c380",
+                "gasUsed": "0xd447a0",
+                "logsBloom": format!("0x{}", "0".repeat(512)),
+                "miner": "0x690b9a9e9aa1c9db991c7721a92d351db4fac990",
+                "mixHash": "0x4dd122a99169327413ec6533fd70a9a9a9cbfad627d356d9b1dc67a47f61b936",
+                "nonce": "0x0000000000000000",
+                "parentHash": "0xeb080e615e8d1583a5e5cbe3daaed23cf408ae64da2c7352691e00b6e1ffdf89",
+                "receiptsRoot": "0xb07ebab433f52fd6dc24297a7804a40578ae0201060aa5938a5a57f4a3a05e03",
+                "sha3Uncles": "0x1dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347",
+                "size": "0x29ade",
+                "stateRoot": "0x675aa943df0011c3b47038b8365db65ce2d41fcdf3e4bcfb2076f1dfd2dabca4",
+                "timestamp": "0x64ba5557",
+                "totalDifficulty": "0xc70d815d562d3cfa955",
+                "transactionsRoot": "0x42bdb666db19f89d6b6d16e125c49bd15143e062665e00287da5fda10e0d95c0",
+                "uncles": [],
+                "baseFeePerGas": "0x4b85a0fcd",
+                "withdrawalsRoot": "0xedaa8043cdce8101ef827863eb0d808277d200a7a0ee77961934bd235dcb82c6",
+                "withdrawals": withdrawals,
+                "blobGasUsed": "0x60000",
+                "excessBlobGas": "0x4b80000",
+                "parentBeaconBlockRoot": "0x2b7a4c1b5a3e8f6d4c2b1a0f9e8d7c6b5a4f3e2d1c0b9a8f7e6d5c4b3a2f1e0d"
+            }
+        }))
+        .unwrap();
+        let mut transformed_body = raw_body.clone();
+        ResponseTransformEnvelope::Single(ResponseTransform::GetBlockByNumber)
+            .apply(&mut transformed_body);
+        assert!(transformed_body.len() < raw_body.len());
+
+        let response = observe_http_response_body_size(http::Response::new(transformed_body));
+
+        let ResponseBodySize(num_bytes) = response.extensions().get::<ResponseBodySize>().unwrap();
+        assert!(*num_bytes >= raw_body.len() as u64);
+    }
+}
@@ -1,11 +1,15 @@
+pub mod caller_limits;
 pub mod candid_rpc;
 pub mod constants;
+pub mod gateway;
+pub mod head_tracker;
 pub mod http;
 pub mod logs;
 pub mod memory;
 pub mod metrics;
 pub mod providers;
 pub mod rpc_client;
+pub mod subscriptions;
 pub mod types;
 pub mod util;
 pub mod validate;
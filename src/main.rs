@@ -1,23 +1,50 @@
 use canhttp::{cycles::CyclesChargingPolicy, multi::Timestamp};
-use canlog::{log, Log, Sort};
+use canlog::{log, Log, LogFilter, Sort};
 use evm_rpc::{
-    candid_rpc::{validate_get_logs_block_range, CandidRpcClient},
+    add_metric_entry,
+    candid_rpc::{resolve_get_logs_rpc_config, validate_get_logs_block_range, CandidRpcClient},
+    constants::{
+        HEAD_TRACKING_INTERVAL_SECS, MAX_API_KEYS_PER_PROVIDER, MAX_AUDIT_LOG_PAGE_SIZE,
+        MINIMUM_WITHDRAWAL_CYCLES, SUBSCRIPTIONS_POLLING_INTERVAL_SECS, SUSPENDABLE_METHODS,
+    },
+    gateway::{self, is_json_rpc_request, serve_json_rpc_request},
+    head_tracker::poll_head_trackers,
     http::{
         charging_policy_with_collateral, http_client, legacy, service_request_builder,
-        transform_http_request,
+        transform_http_request, ChargedCycles, Payer,
     },
     logs::Priority,
     memory::{
-        get_num_subnet_nodes, insert_api_key, is_api_key_principal, is_demo_active, remove_api_key,
-        set_api_key_principals, set_demo_active, set_log_filter, set_num_subnet_nodes,
-        set_override_provider,
+        add_api_key, append_audit_log_entry, credit_balance, get_api_key_infos,
+        get_api_key_principals, get_api_keys, get_audit_log, get_balance,
+        get_caller_limit_exemptions, get_caller_limits, get_caller_usage, get_chain_head,
+        get_chains, get_circuit_breaker_state, get_log_filter, get_log_subscription,
+        get_log_subscriptions, get_num_subnet_nodes, get_override_provider, get_providers,
+        get_providers_health, get_recent_headers, get_suspensions, get_transaction_watch,
+        get_transaction_watches, insert_api_key, insert_log_subscription, insert_transaction_watch,
+        is_api_key_principal, is_demo_active, is_method_suspended, remove_api_key,
+        remove_api_key_by_id, remove_log_subscription, remove_transaction_watch,
+        set_api_key_principals, set_caller_limit_exemptions, set_caller_limits, set_demo_active,
+        set_head_trackers, set_json_rpc_gateways, set_log_filter, set_num_subnet_nodes,
+        set_override_provider, set_suspensions, try_debit_balance, try_record_caller_request,
     },
     metrics::encode_metrics,
-    providers::{find_provider, PROVIDERS, SERVICE_PROVIDER_MAP},
-    types::{OverrideProvider, Provider, ProviderId, RpcAccess, RpcAuth},
+    providers::{
+        add_provider, find_provider, remove_chain, remove_provider, set_chain, update_provider,
+        SERVICE_PROVIDER_MAP,
+    },
+    subscriptions::{
+        poll_subscriptions, redacted_source, validate_subscriber_balance, validate_subscription,
+        validate_transaction_watch,
+    },
+    types::{
+        ApiKey, ApiKeyId, MetricCallerLimit, OverrideProvider, Provider, ProviderId, RpcAccess,
+        RpcAuth,
+    },
 };
 use evm_rpc_types::{
-    BatchResult, Hex32, HttpOutcallError, MultiRpcResult, RpcConfig, RpcResult, RpcServices,
+    AuditAction, BatchResult, Hex32, HttpOutcallError, MultiRpcResult, ProviderError, RpcConfig,
+    RpcResult, RpcServices,
 };
 use ic_cdk::{api::is_controller, query, update};
 use ic_http_types::{HttpRequest, HttpResponse, HttpResponseBuilder};
@@ -25,9 +52,17 @@ use ic_management_canister_types::{
     HttpRequestArgs as IcHttpRequest, HttpRequestResult as IcHttpResponse, TransformArgs,
 };
 use ic_metrics_encoder::MetricsEncoder;
-use std::str::FromStr;
+use std::{collections::BTreeMap, str::FromStr, time::Duration};
 use tower::Service;
 
+pub fn require_controller() -> Result<(), String> {
+    if is_controller(&ic_cdk::api::msg_caller()) {
+        Ok(())
+    } else {
+        Err("You are not authorized".to_string())
+    }
+}
+
 pub fn require_api_key_principal_or_controller() -> Result<(), String> {
     let caller = ic_cdk::api::msg_caller();
     if is_api_key_principal(&caller) || is_controller(&caller) {
@@ -43,7 +78,10 @@ pub async fn eth_get_logs(
     config: Option<evm_rpc_types::GetLogsRpcConfig>,
     args: evm_rpc_types::GetLogsArgs,
 ) -> MultiRpcResult<Vec<evm_rpc_types::LogEntry>> {
-    let config = match eth_get_logs_rpc_config(config, &args) {
+    if let Err(err) = admit_call("eth_getLogs") {
+        return MultiRpcResult::from(Err(err));
+    }
+    let config = match eth_get_logs_rpc_config(&source, config, &args) {
         Ok(config) => config,
         Err(err) => return MultiRpcResult::from(Err(err)),
     };
@@ -53,34 +91,96 @@ pub async fn eth_get_logs(
     }
 }
 
+/// Checks that the given method is not suspended and that the caller did not exceed its limits,
+/// and records the call.
+///
+/// The HTTP outcalls made by the call and the cycles charged for them are checked against the
+/// limits of the caller and recorded as they are charged.
+fn admit_call(method: &str) -> RpcResult<()> {
+    ensure_not_suspended(method)?;
+    let caller = ic_cdk::api::msg_caller();
+    if let Err(limit) = try_record_caller_request(caller, ic_cdk::api::time()) {
+        add_metric_entry!(caller_limit_rejections, MetricCallerLimit::from(limit), 1);
+        return Err(ProviderError::CallerLimitExceeded { limit }.into());
+    }
+    Ok(())
+}
+
+/// Returns an error if the given method was suspended by the controllers.
+fn ensure_not_suspended(method: &str) -> RpcResult<()> {
+    if is_method_suspended(method) {
+        Err(ProviderError::Suspended {
+            method: method.to_string(),
+        }
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
 #[query(name = "eth_getLogsCyclesCost")]
 pub async fn eth_get_logs_cycles_cost(
     source: RpcServices,
     config: Option<evm_rpc_types::GetLogsRpcConfig>,
     args: evm_rpc_types::GetLogsArgs,
 ) -> RpcResult<u128> {
-    match CandidRpcClient::new(source, Some(eth_get_logs_rpc_config(config, &args)?), now()) {
+    let config = eth_get_logs_rpc_config(&source, config, &args)?;
+    match CandidRpcClient::new(source, Some(config), now()) {
         Ok(source) => source.eth_get_logs_cycles_cost(args).await,
         Err(err) => Err(err),
     }
 }
 
+#[query(name = "eth_getLogsMaxCyclesCost")]
+pub async fn eth_get_logs_max_cycles_cost(
+    source: RpcServices,
+    config: Option<evm_rpc_types::GetLogsRpcConfig>,
+    args: evm_rpc_types::GetLogsArgs,
+) -> RpcResult<u128> {
+    let config = eth_get_logs_rpc_config(&source, config, &args)?;
+    match CandidRpcClient::new(source, Some(config), now()) {
+        Ok(source) => source.eth_get_logs_max_cycles_cost(args).await,
+        Err(err) => Err(err),
+    }
+}
+
 fn eth_get_logs_rpc_config(
+    source: &RpcServices,
     config: Option<evm_rpc_types::GetLogsRpcConfig>,
     args: &evm_rpc_types::GetLogsArgs,
 ) -> Result<RpcConfig, evm_rpc_types::RpcError> {
-    let config = config.unwrap_or_default();
+    let config = resolve_get_logs_rpc_config(source, config);
     let max_block_range = config.max_block_range_or_default();
     validate_get_logs_block_range(args, max_block_range)?;
     Ok(RpcConfig::from(config))
 }
 
+#[update(name = "eth_getLogsPaginated")]
+pub async fn eth_get_logs_paginated(
+    source: RpcServices,
+    config: Option<evm_rpc_types::GetLogsRpcConfig>,
+    args: evm_rpc_types::GetLogsArgs,
+) -> MultiRpcResult<evm_rpc_types::GetLogsPage> {
+    if let Err(err) = admit_call("eth_getLogsPaginated") {
+        return MultiRpcResult::from(Err(err));
+    }
+    let config = resolve_get_logs_rpc_config(&source, config);
+    let max_block_range = config.max_block_range_or_default();
+    match CandidRpcClient::new(source, Some(RpcConfig::from(config)), now()) {
+        Ok(source) => source.eth_get_logs_paginated(args, max_block_range).await,
+        Err(err) => Err(err).into(),
+    }
+}
+
 #[update(name = "eth_getBlockByNumber")]
 pub async fn eth_get_block_by_number(
     source: RpcServices,
     config: Option<RpcConfig>,
     block: evm_rpc_types::BlockTag,
 ) -> MultiRpcResult<evm_rpc_types::Block> {
+    if let Err(err) = admit_call("eth_getBlockByNumber") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.eth_get_block_by_number(block).await,
         Err(err) => Err(err).into(),
@@ -105,6 +205,9 @@ pub async fn eth_get_transaction_receipt(
     config: Option<RpcConfig>,
     tx_hash: Hex32,
 ) -> MultiRpcResult<Option<evm_rpc_types::TransactionReceipt>> {
+    if let Err(err) = admit_call("eth_getTransactionReceipt") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.eth_get_transaction_receipt(tx_hash).await,
         Err(err) => Err(err).into(),
@@ -133,6 +236,9 @@ pub async fn eth_get_transaction_count(
     config: Option<RpcConfig>,
     args: evm_rpc_types::GetTransactionCountArgs,
 ) -> MultiRpcResult<evm_rpc_types::Nat256> {
+    if let Err(err) = admit_call("eth_getTransactionCount") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.eth_get_transaction_count(args).await,
         Err(err) => Err(err).into(),
@@ -157,6 +263,9 @@ pub async fn eth_fee_history(
     config: Option<RpcConfig>,
     args: evm_rpc_types::FeeHistoryArgs,
 ) -> MultiRpcResult<evm_rpc_types::FeeHistory> {
+    if let Err(err) = admit_call("eth_feeHistory") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.eth_fee_history(args).await,
         Err(err) => Err(err).into(),
@@ -181,6 +290,9 @@ pub async fn eth_send_raw_transaction(
     config: Option<RpcConfig>,
     raw_signed_transaction_hex: evm_rpc_types::Hex,
 ) -> MultiRpcResult<evm_rpc_types::SendRawTransactionStatus> {
+    if let Err(err) = admit_call("eth_sendRawTransaction") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => {
             source
@@ -213,6 +325,9 @@ pub async fn eth_call(
     config: Option<RpcConfig>,
     args: evm_rpc_types::CallArgs,
 ) -> MultiRpcResult<evm_rpc_types::Hex> {
+    if let Err(err) = admit_call("eth_call") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.eth_call(args).await,
         Err(err) => Err(err).into(),
@@ -255,6 +370,9 @@ pub async fn multi_request(
     config: Option<RpcConfig>,
     args: String,
 ) -> MultiRpcResult<String> {
+    if let Err(err) = admit_call("multi_request") {
+        return MultiRpcResult::from(Err(err));
+    }
     match CandidRpcClient::new(source, config, now()) {
         Ok(source) => source.multi_request(args).await,
         Err(err) => Err(err).into(),
@@ -279,13 +397,18 @@ async fn request(
     json_rpc_payload: String,
     max_response_bytes: u64,
 ) -> RpcResult<String> {
-    let response = http_client::<serde_json::Value, serde_json::Value>(false)
-        .call(legacy::json_rpc_request(
-            service,
-            &json_rpc_payload,
-            max_response_bytes,
-        )?)
-        .await?;
+    admit_call("request")?;
+    let response = http_client::<serde_json::Value, serde_json::Value>(
+        false,
+        ChargedCycles::default(),
+        Payer::Caller,
+    )
+    .call(legacy::json_rpc_request(
+        service,
+        &json_rpc_payload,
+        max_response_bytes,
+    )?)
+    .await?;
     serde_json::to_string(response.body()).map_err(|e| {
         HttpOutcallError::InvalidHttpJsonRpcResponse {
             status: response.status().as_u16(),
@@ -329,6 +452,232 @@ async fn request_cost(
     }
 }
 
+#[update]
+/// Deposits the cycles attached to the call to the prepaid cycles account of the caller and
+/// returns the new balance.
+///
+/// Calls to which not enough cycles are attached are charged with the balance of the caller.
+///
+/// Panics if the caller is anonymous.
+fn deposit() -> u128 {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == candid::Principal::anonymous() {
+        panic!("The anonymous principal cannot deposit cycles");
+    }
+    let amount = ic_cdk::api::msg_cycles_accept(ic_cdk::api::msg_cycles_available());
+    log!(Priority::Info, "[{}] Depositing {} cycles", caller, amount);
+    credit_balance(caller, amount)
+}
+
+#[query(name = "getBalance")]
+/// Returns the prepaid cycles balance of the given principal, or of the caller if none is given.
+fn get_balance_endpoint(principal: Option<candid::Principal>) -> u128 {
+    get_balance(&principal.unwrap_or_else(ic_cdk::api::msg_caller))
+}
+
+#[update]
+/// Withdraws cycles from the prepaid cycles account of the caller, which must be a canister,
+/// and returns the new balance.
+async fn withdraw(amount: u128) -> Result<u128, evm_rpc_types::WithdrawError> {
+    if amount < MINIMUM_WITHDRAWAL_CYCLES {
+        return Err(evm_rpc_types::WithdrawError::AmountTooSmall {
+            minimum: MINIMUM_WITHDRAWAL_CYCLES,
+        });
+    }
+    let caller = ic_cdk::api::msg_caller();
+    if !try_debit_balance(&caller, amount) {
+        return Err(evm_rpc_types::WithdrawError::InsufficientBalance {
+            balance: get_balance(&caller),
+        });
+    }
+    log!(Priority::Info, "[{}] Withdrawing {} cycles", caller, amount);
+    let args = ic_management_canister_types::DepositCyclesArgs {
+        canister_id: caller,
+    };
+    match ic_cdk_management_canister::deposit_cycles(&args, amount).await {
+        Ok(()) => Ok(get_balance(&caller)),
+        Err(e) => {
+            credit_balance(caller, amount);
+            Err(evm_rpc_types::WithdrawError::DepositFailed(e.to_string()))
+        }
+    }
+}
+
+#[update(name = "subscribeLogs")]
+/// Subscribes the caller, which must be a canister, to the logs matching the given filter and
+/// returns the ID of the subscription.
+///
+/// The cycles attached to the call are deposited to the prepaid cycles account of the caller,
+/// which is charged for polling the logs. The balance must hold a minimum amount of cycles,
+/// otherwise the subscription is rejected, or removed once it has been created.
+///
+/// Panics if the caller is anonymous.
+fn subscribe_logs(
+    args: evm_rpc_types::SubscribeLogsArgs,
+) -> Result<u64, evm_rpc_types::SubscribeError> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == candid::Principal::anonymous() {
+        panic!("The anonymous principal cannot subscribe to logs");
+    }
+    let num_subscriptions = get_log_subscriptions()
+        .iter()
+        .filter(|subscription| subscription.owner == caller)
+        .count() as u64;
+    validate_subscription(&args, num_subscriptions, now())?;
+    validate_subscriber_balance(
+        get_balance(&caller).saturating_add(ic_cdk::api::msg_cycles_available()),
+    )?;
+    let amount = ic_cdk::api::msg_cycles_accept(ic_cdk::api::msg_cycles_available());
+    if amount > 0 {
+        credit_balance(caller, amount);
+    }
+    let id = insert_log_subscription(evm_rpc_types::LogSubscription {
+        id: 0,
+        owner: caller,
+        source: args.source,
+        config: args.config,
+        addresses: args.addresses,
+        topics: args.topics,
+        confirmations: args.confirmations,
+        callback: args.callback,
+        next_block: args.from_block,
+        last_error: None,
+    });
+    log!(Priority::Info, "[{}] Subscribing to logs: {}", caller, id);
+    Ok(id)
+}
+
+#[update(name = "unsubscribeLogs")]
+/// Removes a log subscription of the caller, or of any caller if the caller is a controller,
+/// and returns whether it was removed.
+fn unsubscribe_logs(id: u64) -> bool {
+    let caller = ic_cdk::api::msg_caller();
+    match get_log_subscription(id) {
+        Some(subscription) if subscription.owner == caller || is_controller(&caller) => {
+            log!(
+                Priority::Info,
+                "[{}] Unsubscribing from logs: {}",
+                caller,
+                id
+            );
+            remove_log_subscription(id).is_some()
+        }
+        _ => false,
+    }
+}
+
+#[query(name = "getLogSubscriptions")]
+/// Returns the log subscriptions of the given principal, or of the caller if none is given.
+/// Only the controllers can get the log subscriptions of another principal.
+///
+/// The URLs and the header values of custom providers are redacted.
+fn get_log_subscriptions_endpoint(
+    owner: Option<candid::Principal>,
+) -> Vec<evm_rpc_types::LogSubscription> {
+    let caller = ic_cdk::api::msg_caller();
+    let owner = owner.unwrap_or(caller);
+    if owner != caller && !is_controller(&caller) {
+        return vec![];
+    }
+    get_log_subscriptions()
+        .into_iter()
+        .filter(|subscription| subscription.owner == owner)
+        .map(|subscription| evm_rpc_types::LogSubscription {
+            source: redacted_source(subscription.source),
+            ..subscription
+        })
+        .collect()
+}
+
+#[update(name = "watchTransaction")]
+/// Watches a sent transaction on behalf of the caller, which must be a canister, and returns
+/// the ID of the watch. The caller is notified once the transaction is confirmed, dropped or
+/// replaced, after which the watch is removed.
+///
+/// The cycles attached to the call are deposited to the prepaid cycles account of the caller,
+/// which is charged for polling the transaction. The balance must hold a minimum amount of cycles,
+/// otherwise the watch is rejected, or removed once it has been created.
+///
+/// Panics if the caller is anonymous.
+fn watch_transaction(
+    args: evm_rpc_types::WatchTransactionArgs,
+) -> Result<u64, evm_rpc_types::SubscribeError> {
+    let caller = ic_cdk::api::msg_caller();
+    if caller == candid::Principal::anonymous() {
+        panic!("The anonymous principal cannot watch transactions");
+    }
+    let num_watches = get_transaction_watches()
+        .iter()
+        .filter(|watch| watch.owner == caller)
+        .count() as u64;
+    validate_transaction_watch(&args, num_watches, now())?;
+    validate_subscriber_balance(
+        get_balance(&caller).saturating_add(ic_cdk::api::msg_cycles_available()),
+    )?;
+    let amount = ic_cdk::api::msg_cycles_accept(ic_cdk::api::msg_cycles_available());
+    if amount > 0 {
+        credit_balance(caller, amount);
+    }
+    let id = insert_transaction_watch(evm_rpc_types::TransactionWatch {
+        id: 0,
+        owner: caller,
+        source: args.source,
+        config: args.config,
+        transaction_hash: args.transaction_hash,
+        sender: args.sender,
+        nonce: args.nonce,
+        confirmations: args.confirmations,
+        callback: args.callback,
+        created_at: ic_cdk::api::time(),
+        receipt: None,
+        last_error: None,
+    });
+    log!(Priority::Info, "[{}] Watching transaction: {}", caller, id);
+    Ok(id)
+}
+
+#[update(name = "unwatchTransaction")]
+/// Removes a transaction watch of the caller, or of any caller if the caller is a controller,
+/// and returns whether it was removed.
+fn unwatch_transaction(id: u64) -> bool {
+    let caller = ic_cdk::api::msg_caller();
+    match get_transaction_watch(id) {
+        Some(watch) if watch.owner == caller || is_controller(&caller) => {
+            log!(
+                Priority::Info,
+                "[{}] Unwatching transaction: {}",
+                caller,
+                id
+            );
+            remove_transaction_watch(id).is_some()
+        }
+        _ => false,
+    }
+}
+
+#[query(name = "getTransactionWatches")]
+/// Returns the transaction watches of the given principal, or of the caller if none is given.
+/// Only the controllers can get the transaction watches of another principal.
+///
+/// The URLs and the header values of custom providers are redacted.
+fn get_transaction_watches_endpoint(
+    owner: Option<candid::Principal>,
+) -> Vec<evm_rpc_types::TransactionWatch> {
+    let caller = ic_cdk::api::msg_caller();
+    let owner = owner.unwrap_or(caller);
+    if owner != caller && !is_controller(&caller) {
+        return vec![];
+    }
+    get_transaction_watches()
+        .into_iter()
+        .filter(|watch| watch.owner == owner)
+        .map(|watch| evm_rpc_types::TransactionWatch {
+            source: redacted_source(watch.source),
+            ..watch
+        })
+        .collect()
+}
+
 #[query(name = "getProviders")]
 fn get_providers() -> Vec<evm_rpc_types::Provider> {
     fn into_provider(provider: Provider) -> evm_rpc_types::Provider {
@@ -347,6 +696,15 @@ fn get_providers() -> Vec<evm_rpc_types::Provider> {
                                     url_pattern: url_pattern.to_string(),
                                 }
                             }
+                            RpcAuth::Header { url, header_name } => {
+                                evm_rpc_types::RpcAuth::Header {
+                                    url: url.to_string(),
+                                    header_name: header_name.to_string(),
+                                }
+                            }
+                            RpcAuth::BasicAuth { url } => evm_rpc_types::RpcAuth::BasicAuth {
+                                url: url.to_string(),
+                            },
                         },
                         public_url: public_url.map(|s| s.to_string()),
                     }
@@ -358,14 +716,36 @@ fn get_providers() -> Vec<evm_rpc_types::Provider> {
                 }
             },
             alias: provider.alias.map(evm_rpc_types::RpcService::from),
+            circuit_breaker: provider
+                .alias
+                .map(|service| get_circuit_breaker_state(service, now())),
         }
     }
-    PROVIDERS.iter().cloned().map(into_provider).collect()
+    get_providers().into_iter().map(into_provider).collect()
+}
+
+#[query(name = "getProviderHealth")]
+fn get_provider_health() -> Vec<evm_rpc_types::ProviderHealth> {
+    let services: Vec<_> = SERVICE_PROVIDER_MAP.with_borrow(|map| map.keys().copied().collect());
+    services
+        .iter()
+        .zip(get_providers_health(&services, now()))
+        .map(|(service, health)| evm_rpc_types::ProviderHealth {
+            service: evm_rpc_types::RpcService::from(*service),
+            ok_results: health.ok as u64,
+            http_errors: health.http_errors as u64,
+            json_rpc_errors: health.json_rpc_errors as u64,
+            response_too_large: health.response_too_large as u64,
+            disagreements: health.disagreements as u64,
+            score: health.score(),
+            excluded: health.is_excluded(),
+        })
+        .collect()
 }
 
 #[query(name = "getServiceProviderMap")]
 fn get_service_provider_map() -> Vec<(evm_rpc_types::RpcService, ProviderId)> {
-    SERVICE_PROVIDER_MAP.with(|map| {
+    SERVICE_PROVIDER_MAP.with_borrow(|map| {
         map.iter()
             .map(|(k, v)| (evm_rpc_types::RpcService::from(*k), *v))
             .collect()
@@ -383,11 +763,13 @@ fn get_nodes_in_subnet() -> u32 {
 )]
 /// Inserts or removes RPC provider API keys.
 ///
-/// For each element of `api_keys`, passing `(id, Some(key))` corresponds to inserting or updating
-/// an API key, while passing `(id, None)` indicates that the key should be removed from the canister.
+/// For each element of `api_keys`, passing `(id, Some(key))` replaces all API keys of the provider
+/// with `key`, while passing `(id, None)` indicates that all its keys should be removed from the canister.
+/// Use `updateApiKeysV2` to manage several API keys per provider.
 ///
 /// Panics if the list of provider IDs includes a nonexistent or "unauthenticated" (fully public) provider.
 async fn update_api_keys(api_keys: Vec<(ProviderId, Option<String>)>) {
+    let provider_ids = api_keys.iter().map(|(id, _)| *id).collect();
     log!(
         Priority::Info,
         "[{}] Updating API keys for providers: {}",
@@ -399,22 +781,207 @@ async fn update_api_keys(api_keys: Vec<(ProviderId, Option<String>)>) {
             .join(", ")
     );
     for (provider_id, api_key) in api_keys {
-        let provider = find_provider(|provider| provider.provider_id == provider_id)
-            .unwrap_or_else(|| panic!("Provider not found: {}", provider_id));
-        match provider.access {
-            RpcAccess::Authenticated { .. } => {}
-            RpcAccess::Unauthenticated { .. } => {
-                panic!(
-                    "Trying to set API key for unauthenticated provider: {}",
-                    provider_id
-                )
-            }
-        };
+        let auth = authenticated_provider_auth(provider_id);
         match api_key {
-            Some(key) => insert_api_key(provider_id, key.try_into().expect("Invalid API key")),
+            Some(key) => insert_api_key(
+                provider_id,
+                ApiKey::try_new(key, &auth).expect("Invalid API key"),
+            ),
             None => remove_api_key(provider_id),
         }
     }
+    audit(AuditAction::UpdateApiKeys { provider_ids });
+}
+
+/// Returns the authentication scheme of the given provider.
+///
+/// Panics if the provider does not exist or is "unauthenticated" (fully public).
+fn authenticated_provider_auth(provider_id: ProviderId) -> RpcAuth {
+    let provider = find_provider(|provider| provider.provider_id == provider_id)
+        .unwrap_or_else(|| panic!("Provider not found: {}", provider_id));
+    match provider.access {
+        RpcAccess::Authenticated { auth, .. } => auth,
+        RpcAccess::Unauthenticated { .. } => {
+            panic!(
+                "Trying to set API key for unauthenticated provider: {}",
+                provider_id
+            )
+        }
+    }
+}
+
+#[update(
+    name = "updateApiKeysV2",
+    guard = "require_api_key_principal_or_controller"
+)]
+/// Adds or removes individual RPC provider API keys and returns the IDs of the added API keys.
+///
+/// The API keys of a provider are used in a round-robin fashion, skipping API keys that were
+/// rate limited by the provider.
+///
+/// Panics if the list of updates includes a nonexistent or "unauthenticated" (fully public) provider,
+/// an invalid API key, or an API key ID that does not exist.
+fn update_api_keys_v2(updates: Vec<evm_rpc_types::ApiKeyUpdate>) -> Vec<ApiKeyId> {
+    let mut added_key_ids = Vec::new();
+    for update in updates {
+        match update {
+            evm_rpc_types::ApiKeyUpdate::Add {
+                provider_id,
+                api_key,
+            } => {
+                let auth = authenticated_provider_auth(provider_id);
+                if get_api_keys(provider_id).len() >= MAX_API_KEYS_PER_PROVIDER {
+                    panic!(
+                        "Too many API keys for provider {}: at most {} are allowed",
+                        provider_id, MAX_API_KEYS_PER_PROVIDER
+                    );
+                }
+                let api_key = ApiKey::try_new(api_key, &auth).expect("Invalid API key");
+                let key_id = add_api_key(provider_id, api_key);
+                log!(
+                    Priority::Info,
+                    "[{}] Added API key {} for provider {}",
+                    ic_cdk::api::msg_caller(),
+                    key_id,
+                    provider_id
+                );
+                audit(AuditAction::AddApiKey {
+                    provider_id,
+                    key_id,
+                });
+                added_key_ids.push(key_id);
+            }
+            evm_rpc_types::ApiKeyUpdate::Remove {
+                provider_id,
+                key_id,
+            } => {
+                if !remove_api_key_by_id(provider_id, key_id) {
+                    panic!("API key {} not found for provider {}", key_id, provider_id);
+                }
+                log!(
+                    Priority::Info,
+                    "[{}] Removed API key {} of provider {}",
+                    ic_cdk::api::msg_caller(),
+                    key_id,
+                    provider_id
+                );
+                audit(AuditAction::RemoveApiKey {
+                    provider_id,
+                    key_id,
+                });
+            }
+        }
+    }
+    added_key_ids
+}
+
+#[query(name = "getApiKeys", guard = "require_api_key_principal_or_controller")]
+/// Returns the IDs and usage of the API keys of the given provider, without the API keys themselves.
+fn get_api_keys_endpoint(provider_id: ProviderId) -> Vec<evm_rpc_types::ApiKeyInfo> {
+    get_api_key_infos(provider_id)
+}
+
+#[update(name = "addProvider", guard = "require_controller")]
+/// Adds a provider to the provider registry and returns its ID.
+///
+/// Panics if the provider is invalid, e.g. if one of its URLs does not use HTTPS.
+fn add_provider_endpoint(args: evm_rpc_types::ProviderArgs) -> ProviderId {
+    log!(
+        Priority::Info,
+        "[{}] Adding provider for chain {}",
+        ic_cdk::api::msg_caller(),
+        args.chain_id
+    );
+    let provider_id = add_provider(args).unwrap_or_else(|e| panic!("Invalid provider: {}", e));
+    audit(AuditAction::AddProvider { provider_id });
+    provider_id
+}
+
+#[update(name = "updateProvider", guard = "require_controller")]
+/// Replaces the provider with the given ID in the provider registry.
+///
+/// Panics if the provider does not exist or is invalid.
+fn update_provider_endpoint(provider_id: ProviderId, args: evm_rpc_types::ProviderArgs) {
+    log!(
+        Priority::Info,
+        "[{}] Updating provider {}",
+        ic_cdk::api::msg_caller(),
+        provider_id
+    );
+    update_provider(provider_id, args).unwrap_or_else(|e| panic!("Invalid provider: {}", e));
+    audit(AuditAction::UpdateProvider { provider_id });
+}
+
+#[update(name = "removeProvider", guard = "require_controller")]
+/// Removes the provider with the given ID and its API key.
+///
+/// Panics if the provider does not exist.
+fn remove_provider_endpoint(provider_id: ProviderId) {
+    log!(
+        Priority::Info,
+        "[{}] Removing provider {}",
+        ic_cdk::api::msg_caller(),
+        provider_id
+    );
+    remove_provider(provider_id).unwrap_or_else(|e| panic!("{}", e));
+    audit(AuditAction::RemoveProvider { provider_id });
+}
+
+#[query(name = "getChains")]
+fn get_chains_endpoint() -> Vec<evm_rpc_types::Chain> {
+    get_chains()
+}
+
+#[update(name = "setChain", guard = "require_controller")]
+/// Adds a chain to the chain registry or replaces the existing chain with the same ID.
+///
+/// Panics if the chain is invalid, e.g. if one of its default providers does not exist.
+fn set_chain_endpoint(chain: evm_rpc_types::Chain) {
+    log!(
+        Priority::Info,
+        "[{}] Setting chain {}",
+        ic_cdk::api::msg_caller(),
+        chain.chain_id
+    );
+    let chain_id = chain.chain_id;
+    set_chain(chain).unwrap_or_else(|e| panic!("Invalid chain: {}", e));
+    audit(AuditAction::SetChain { chain_id });
+}
+
+#[update(name = "removeChain", guard = "require_controller")]
+/// Removes the chain with the given ID from the chain registry.
+///
+/// Panics if the chain does not exist.
+fn remove_chain_endpoint(chain_id: u64) {
+    log!(
+        Priority::Info,
+        "[{}] Removing chain {}",
+        ic_cdk::api::msg_caller(),
+        chain_id
+    );
+    remove_chain(chain_id).unwrap_or_else(|e| panic!("{}", e));
+    audit(AuditAction::RemoveChain { chain_id });
+}
+
+#[query(
+    name = "getAuditLog",
+    guard = "require_api_key_principal_or_controller"
+)]
+/// Returns a page of the audit log of administrative actions, oldest entries first.
+fn get_audit_log_endpoint(
+    args: evm_rpc_types::GetAuditLogArgs,
+) -> Vec<evm_rpc_types::AuditLogEntry> {
+    get_audit_log(
+        args.start.unwrap_or_default(),
+        args.limit
+            .unwrap_or(MAX_AUDIT_LOG_PAGE_SIZE)
+            .min(MAX_AUDIT_LOG_PAGE_SIZE),
+    )
+}
+
+/// Records an administrative action of the caller in the audit log.
+fn audit(action: AuditAction) {
+    append_audit_log_entry(ic_cdk::api::msg_caller(), ic_cdk::api::time(), action);
 }
 
 #[query(name = "__transform_json_rpc", hidden = true)]
@@ -430,31 +997,313 @@ fn init(args: evm_rpc_types::InstallArgs) {
 #[ic_cdk::post_upgrade]
 fn post_upgrade(args: evm_rpc_types::InstallArgs) {
     if let Some(demo) = args.demo {
-        set_demo_active(demo);
+        set_demo(demo);
     }
     if let Some(principals) = args.manage_api_keys {
-        set_api_key_principals(principals);
+        set_api_key_principals_endpoint(principals);
     }
     if let Some(filter) = args.log_filter {
-        set_log_filter(filter);
+        set_log_filter_endpoint(filter);
     }
     if let Some(override_provider) = args.override_provider {
-        set_override_provider(
-            OverrideProvider::try_from(override_provider)
-                .expect("ERROR: invalid override provider"),
-        );
+        set_override_provider_endpoint(override_provider);
     }
     if let Some(nodes) = args.nodes_in_subnet {
-        set_num_subnet_nodes(nodes)
+        set_num_subnet_nodes_endpoint(nodes);
     }
+    // Timers are not preserved across upgrades.
+    ic_cdk_timers::set_timer_interval(
+        Duration::from_secs(SUBSCRIPTIONS_POLLING_INTERVAL_SECS),
+        || async { poll_subscriptions().await },
+    );
+    ic_cdk_timers::set_timer_interval(Duration::from_secs(HEAD_TRACKING_INTERVAL_SECS), || async {
+        poll_head_trackers().await
+    });
+}
+
+#[query(name = "getConfig")]
+/// Returns the effective configuration of the canister, with secrets redacted.
+fn get_config() -> evm_rpc_types::Config {
+    let suspensions = get_suspensions();
+    evm_rpc_types::Config {
+        demo: is_demo_active(),
+        manage_api_keys: get_api_key_principals(),
+        log_filter: get_log_filter(),
+        override_provider: get_override_provider().redacted(),
+        nodes_in_subnet: get_num_subnet_nodes(),
+        maintenance_mode: suspensions.all,
+        suspended_methods: suspensions.methods.into_iter().collect(),
+        caller_limits: get_caller_limits(),
+        caller_limit_exemptions: get_caller_limit_exemptions(),
+    }
+}
+
+#[update(name = "setDemo", guard = "require_controller")]
+/// Enables or disables demo mode, in which calls are not charged.
+fn set_demo(demo: bool) {
+    log!(
+        Priority::Info,
+        "[{}] Setting demo mode to {}",
+        ic_cdk::api::msg_caller(),
+        demo
+    );
+    set_demo_active(demo);
+    audit(AuditAction::SetDemo(demo));
+}
+
+#[update(name = "setApiKeyPrincipals", guard = "require_controller")]
+/// Replaces the principals allowed to manage API keys.
+fn set_api_key_principals_endpoint(principals: Vec<candid::Principal>) {
+    log!(
+        Priority::Info,
+        "[{}] Setting principals allowed to manage API keys",
+        ic_cdk::api::msg_caller()
+    );
+    set_api_key_principals(principals.clone());
+    audit(AuditAction::SetApiKeyPrincipals(principals));
+}
+
+#[update(name = "setLogFilter", guard = "require_controller")]
+/// Replaces the filter applied to log messages.
+fn set_log_filter_endpoint(filter: LogFilter) {
+    log!(
+        Priority::Info,
+        "[{}] Setting log filter",
+        ic_cdk::api::msg_caller()
+    );
+    set_log_filter(filter.clone());
+    audit(AuditAction::SetLogFilter(filter));
+}
+
+#[update(name = "setOverrideProvider", guard = "require_controller")]
+/// Replaces the override provider.
+///
+/// Panics if the regular expression of the override provider is invalid.
+fn set_override_provider_endpoint(override_provider: evm_rpc_types::OverrideProvider) {
+    log!(
+        Priority::Info,
+        "[{}] Setting override provider",
+        ic_cdk::api::msg_caller()
+    );
+    let override_provider =
+        OverrideProvider::try_from(override_provider).expect("ERROR: invalid override provider");
+    audit(AuditAction::SetOverrideProvider(
+        override_provider.redacted(),
+    ));
+    set_override_provider(override_provider);
+}
+
+#[update(name = "setMaintenanceMode", guard = "require_controller")]
+/// Suspends or resumes all methods making HTTP outcalls. Cost queries are not affected.
+fn set_maintenance_mode(enabled: bool) {
+    log!(
+        Priority::Info,
+        "[{}] Setting maintenance mode to {}",
+        ic_cdk::api::msg_caller(),
+        enabled
+    );
+    let mut suspensions = get_suspensions();
+    suspensions.all = enabled;
+    set_suspensions(suspensions);
+    audit(AuditAction::SetMaintenanceMode(enabled));
+}
+
+#[update(name = "setSuspendedMethods", guard = "require_controller")]
+/// Replaces the methods making HTTP outcalls that are suspended, e.g. `eth_sendRawTransaction`.
+/// Cost queries are not affected.
+///
+/// Panics if one of the methods does not make HTTP outcalls.
+fn set_suspended_methods(methods: Vec<String>) {
+    log!(
+        Priority::Info,
+        "[{}] Suspending methods: {}",
+        ic_cdk::api::msg_caller(),
+        methods.join(", ")
+    );
+    if let Some(method) = methods
+        .iter()
+        .find(|method| !SUSPENDABLE_METHODS.contains(&method.as_str()))
+    {
+        panic!("Method cannot be suspended: {}", method);
+    }
+    let mut suspensions = get_suspensions();
+    suspensions.methods = methods.iter().cloned().collect();
+    set_suspensions(suspensions);
+    audit(AuditAction::SetSuspendedMethods(methods));
+}
+
+#[update(name = "setCallerLimits", guard = "require_controller")]
+/// Replaces the limits on the usage of the canister by a single caller in a time window.
+///
+/// Panics if the time window is empty.
+fn set_caller_limits_endpoint(limits: evm_rpc_types::CallerLimits) {
+    log!(
+        Priority::Info,
+        "[{}] Setting caller limits: {:?}",
+        ic_cdk::api::msg_caller(),
+        limits
+    );
+    if limits.window_secs == 0 {
+        panic!("Invalid caller limits: time window must not be empty");
+    }
+    set_caller_limits(limits.clone());
+    audit(AuditAction::SetCallerLimits(limits));
+}
+
+#[update(name = "setCallerLimitExemptions", guard = "require_controller")]
+/// Replaces the principals that are not subject to the caller limits.
+fn set_caller_limit_exemptions_endpoint(principals: Vec<candid::Principal>) {
+    log!(
+        Priority::Info,
+        "[{}] Setting principals exempted from caller limits",
+        ic_cdk::api::msg_caller()
+    );
+    set_caller_limit_exemptions(principals.clone());
+    audit(AuditAction::SetCallerLimitExemptions(principals));
+}
+
+#[update(name = "setHeadTrackers", guard = "require_controller")]
+/// Replaces the chains whose head is tracked, which is then served by `getChainHead` and
+/// `getRecentHeaders` without HTTP outcalls. The HTTP outcalls to track the heads are paid by
+/// the canister.
+///
+/// Panics if the providers of a chain are invalid or if a chain is tracked more than once.
+fn set_head_trackers_endpoint(trackers: Vec<evm_rpc_types::HeadTracker>) {
+    let mut chain_ids = Vec::with_capacity(trackers.len());
+    for tracker in &trackers {
+        let chain_id =
+            match CandidRpcClient::new(tracker.source.clone(), tracker.config.clone(), now()) {
+                Ok(client) => client.chain_id(),
+                Err(err) => panic!("Invalid head tracker: {err}"),
+            };
+        if chain_ids.contains(&chain_id) {
+            panic!("Invalid head trackers: chain {chain_id} is tracked more than once");
+        }
+        chain_ids.push(chain_id);
+    }
+    log!(
+        Priority::Info,
+        "[{}] Setting head trackers for chains: {:?}",
+        ic_cdk::api::msg_caller(),
+        chain_ids
+    );
+    set_head_trackers(trackers, &chain_ids);
+    audit(AuditAction::SetHeadTrackers { chain_ids });
+}
+
+#[update(name = "setJsonRpcGateways", guard = "require_controller")]
+/// Replaces the chains whose JSON-RPC requests are served over HTTP at `/rpc/{chainId}`.
+///
+/// Requests are served like calls to the Candid endpoints made by the dedicated prepaid cycles
+/// account of the gateway of the chain, which is charged for them and whose limits apply.
+///
+/// Panics if the providers of a chain are invalid or if a chain is served more than once.
+fn set_json_rpc_gateways_endpoint(gateways: Vec<evm_rpc_types::JsonRpcGateway>) {
+    let mut gateways_by_chain_id = BTreeMap::new();
+    for gateway in gateways {
+        let chain_id =
+            match CandidRpcClient::new(gateway.source.clone(), gateway.config.clone(), now()) {
+                Ok(client) => client.chain_id(),
+                Err(err) => panic!("Invalid JSON-RPC gateway: {err}"),
+            };
+        if gateways_by_chain_id.insert(chain_id, gateway).is_some() {
+            panic!("Invalid JSON-RPC gateways: chain {chain_id} is served more than once");
+        }
+    }
+    let chain_ids: Vec<_> = gateways_by_chain_id.keys().copied().collect();
+    log!(
+        Priority::Info,
+        "[{}] Setting JSON-RPC gateways for chains: {:?}",
+        ic_cdk::api::msg_caller(),
+        chain_ids
+    );
+    set_json_rpc_gateways(gateways_by_chain_id);
+    audit(AuditAction::SetJsonRpcGateways { chain_ids });
+}
+
+#[update(name = "depositJsonRpcGateway")]
+/// Deposits the cycles attached to the call to the prepaid cycles account of the JSON-RPC gateway
+/// of the given chain and returns the new balance.
+fn deposit_json_rpc_gateway(chain_id: u64) -> u128 {
+    let amount = ic_cdk::api::msg_cycles_accept(ic_cdk::api::msg_cycles_available());
+    log!(
+        Priority::Info,
+        "[{}] Depositing {} cycles to the JSON-RPC gateway of chain {}",
+        ic_cdk::api::msg_caller(),
+        amount,
+        chain_id
+    );
+    credit_balance(gateway::gateway_account(chain_id), amount)
+}
+
+#[query(name = "getJsonRpcGatewayAccount")]
+/// Returns the principal of the prepaid cycles account of the JSON-RPC gateway of the given chain.
+fn get_json_rpc_gateway_account(chain_id: u64) -> candid::Principal {
+    gateway::gateway_account(chain_id)
+}
+
+#[query(name = "getChainHead")]
+/// Returns the latest, safe and finalized headers of a tracked chain, if known.
+fn get_chain_head_endpoint(chain_id: u64) -> Option<evm_rpc_types::ChainHead> {
+    get_chain_head(chain_id)
+}
+
+#[query(name = "getRecentHeaders")]
+/// Returns the recent headers of a tracked chain, by increasing block number.
+fn get_recent_headers_endpoint(chain_id: u64) -> Vec<evm_rpc_types::BlockHeader> {
+    get_recent_headers(chain_id)
+}
+
+#[query(name = "getCallerUsage")]
+/// Returns the usage of the canister by the given principal, or by the caller if none is given,
+/// in the current time window.
+fn get_caller_usage_endpoint(caller: Option<candid::Principal>) -> evm_rpc_types::CallerUsage {
+    get_caller_usage(
+        &caller.unwrap_or_else(ic_cdk::api::msg_caller),
+        ic_cdk::api::time(),
+    )
+}
+
+#[update(name = "setNumSubnetNodes", guard = "require_controller")]
+/// Sets the number of nodes in the subnet, which is used to compute the cycles cost of requests.
+fn set_num_subnet_nodes_endpoint(nodes: u32) {
+    log!(
+        Priority::Info,
+        "[{}] Setting number of nodes in subnet to {}",
+        ic_cdk::api::msg_caller(),
+        nodes
+    );
+    set_num_subnet_nodes(nodes);
+    audit(AuditAction::SetNodesInSubnet(nodes));
 }
 
 #[query(hidden = true)]
-fn http_request(request: HttpRequest) -> HttpResponse {
+fn http_request(request: HttpRequest) -> gateway::HttpResponse {
     if ic_cdk::api::in_replicated_execution() {
         ic_cdk::trap("Update call rejected");
     }
 
+    if is_json_rpc_request(&request) {
+        // JSON-RPC requests make HTTP outcalls, which requires an update call.
+        return if request.method == "OPTIONS" {
+            gateway::HttpResponse::preflight()
+        } else {
+            gateway::HttpResponse::upgrade()
+        };
+    }
+    serve_http_request(request).into()
+}
+
+#[update(hidden = true)]
+async fn http_request_update(request: HttpRequest) -> HttpResponse {
+    if is_json_rpc_request(&request) {
+        serve_json_rpc_request(request).await
+    } else {
+        HttpResponseBuilder::not_found().build()
+    }
+}
+
+fn serve_http_request(request: HttpRequest) -> HttpResponse {
     match request.path() {
         "/metrics" => {
             let mut writer = MetricsEncoder::new(vec![], ic_cdk::api::time() as i64 / 1_000_000);
@@ -1,19 +1,43 @@
-use crate::providers::SupportedRpcServiceUsage;
+use crate::caller_limits::CallerUsages;
+use crate::head_tracker::{ChainHeads, Header};
+use crate::providers::{
+    ApiKeyRotations, CircuitBreakers, RpcServiceHealth, RpcServiceOutcome,
+    SupportedRpcServiceUsage, FIRST_RUNTIME_PROVIDER_ID, PROVIDERS,
+};
+use crate::rpc_client::{
+    cache::{CacheKey, ResponseCache},
+    coalescing::{CoalescingKey, InFlightRequests, Role},
+    numeric::BlockNumber,
+    response_sizes::ResponseSizes,
+};
 use crate::{
     providers::SupportedRpcService,
-    types::{ApiKey, Metrics, OverrideProvider, ProviderId, StorableLogFilter},
+    types::{
+        ApiKey, ApiKeyId, ApiKeyRef, MetricApiKey, Metrics, OverrideProvider, Provider, ProviderId,
+        StorableAuditLogEntry, StorableCallerLimits, StorableChain, StorableHeadTrackers,
+        StorableJsonRpcGateways, StorableLogFilter, StorableLogSubscription,
+        StorableTransactionWatch, Suspensions,
+    },
 };
 use candid::Principal;
 use canhttp::http::json::{ConstantSizeId, Id};
 use canhttp::multi::Timestamp;
 use canlog::LogFilter;
+use evm_rpc_types::{
+    ApiKeyInfo, AuditAction, AuditLogEntry, BlockHeader, CallerLimit, CallerLimits, CallerUsage,
+    Chain, ChainHead, CircuitBreakerState, HeadTracker, JsonRpcGateway, LogSubscription,
+    TransactionWatch,
+};
 use ic_stable_structures::memory_manager::VirtualMemory;
 use ic_stable_structures::{
     memory_manager::{MemoryId, MemoryManager},
     DefaultMemoryImpl,
 };
-use ic_stable_structures::{Cell, StableBTreeMap};
+use ic_stable_structures::{Cell, Memory, StableBTreeMap};
+use std::any::Any;
 use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
 
 const IS_DEMO_ACTIVE_MEMORY_ID: MemoryId = MemoryId::new(4);
 const API_KEY_MAP_MEMORY_ID: MemoryId = MemoryId::new(5);
@@ -21,6 +45,23 @@ const MANAGE_API_KEYS_MEMORY_ID: MemoryId = MemoryId::new(6);
 const LOG_FILTER_MEMORY_ID: MemoryId = MemoryId::new(7);
 const OVERRIDE_PROVIDER_MEMORY_ID: MemoryId = MemoryId::new(8);
 const NUM_SUBNET_NODES_MEMORY_ID: MemoryId = MemoryId::new(9);
+const PROVIDER_REGISTRY_MEMORY_ID: MemoryId = MemoryId::new(10);
+const CHAIN_REGISTRY_MEMORY_ID: MemoryId = MemoryId::new(12);
+const API_KEYS_MEMORY_ID: MemoryId = MemoryId::new(13);
+const AUDIT_LOG_MEMORY_ID: MemoryId = MemoryId::new(14);
+const SUSPENSIONS_MEMORY_ID: MemoryId = MemoryId::new(15);
+const CALLER_LIMITS_MEMORY_ID: MemoryId = MemoryId::new(16);
+const CALLER_LIMIT_EXEMPTIONS_MEMORY_ID: MemoryId = MemoryId::new(17);
+const CYCLES_ACCOUNTS_MEMORY_ID: MemoryId = MemoryId::new(18);
+const LOG_SUBSCRIPTIONS_MEMORY_ID: MemoryId = MemoryId::new(19);
+const NEXT_LOG_SUBSCRIPTION_ID_MEMORY_ID: MemoryId = MemoryId::new(20);
+const TRANSACTION_WATCHES_MEMORY_ID: MemoryId = MemoryId::new(21);
+const NEXT_TRANSACTION_WATCH_ID_MEMORY_ID: MemoryId = MemoryId::new(22);
+const HEAD_TRACKERS_MEMORY_ID: MemoryId = MemoryId::new(23);
+const JSON_RPC_GATEWAYS_MEMORY_ID: MemoryId = MemoryId::new(24);
+const NEXT_PROVIDER_ID_MEMORY_ID: MemoryId = MemoryId::new(25);
+const SEEDED_PROVIDER_IDS_MEMORY_ID: MemoryId = MemoryId::new(26);
+const NEXT_API_KEY_ID_MEMORY_ID: MemoryId = MemoryId::new(27);
 
 type StableMemory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -28,15 +69,30 @@ thread_local! {
     // Unstable static data: these are reset when the canister is upgraded.
     pub static UNSTABLE_METRICS: RefCell<Metrics> = RefCell::new(Metrics::default());
     static UNSTABLE_HTTP_REQUEST_COUNTER: RefCell<ConstantSizeId> = const {RefCell::new(ConstantSizeId::ZERO)};
-    static UNSTABLE_RPC_SERVICE_OK_RESULTS_TIMESTAMPS: RefCell<SupportedRpcServiceUsage> =  RefCell::new(SupportedRpcServiceUsage::default());
+    static UNSTABLE_RPC_SERVICE_OUTCOMES_TIMESTAMPS: RefCell<SupportedRpcServiceUsage> =  RefCell::new(SupportedRpcServiceUsage::default());
+    static UNSTABLE_CIRCUIT_BREAKERS: RefCell<CircuitBreakers> = RefCell::new(CircuitBreakers::default());
+    static UNSTABLE_API_KEY_ROTATIONS: RefCell<ApiKeyRotations> = RefCell::new(ApiKeyRotations::default());
+    static UNSTABLE_CALLER_USAGES: RefCell<CallerUsages> = RefCell::new(CallerUsages::default());
+    static UNSTABLE_RESPONSE_CACHE: RefCell<ResponseCache> = RefCell::new(ResponseCache::default());
+    static UNSTABLE_IN_FLIGHT_REQUESTS: RefCell<InFlightRequests> = RefCell::new(InFlightRequests::default());
+    static UNSTABLE_RESPONSE_SIZES: RefCell<ResponseSizes> = RefCell::new(ResponseSizes::default());
+    static UNSTABLE_IS_POLLING_SUBSCRIPTIONS: RefCell<bool> = const {RefCell::new(false)};
+    static UNSTABLE_CHAIN_HEADS: RefCell<ChainHeads> = RefCell::new(ChainHeads::default());
+    static UNSTABLE_IS_TRACKING_HEADS: RefCell<bool> = const {RefCell::new(false)};
 
     // Stable static data: these are preserved when the canister is upgraded.
     static MEMORY_MANAGER: RefCell<MemoryManager<DefaultMemoryImpl>> =
         RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
     static IS_DEMO_ACTIVE: RefCell<Cell<bool, StableMemory>> =
         RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(IS_DEMO_ACTIVE_MEMORY_ID)), false));
+    // Legacy storage of a single API key per provider, superseded by `API_KEYS`.
     static API_KEY_MAP: RefCell<StableBTreeMap<ProviderId, ApiKey, StableMemory>> =
         RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(API_KEY_MAP_MEMORY_ID))));
+    static API_KEYS: RefCell<StableBTreeMap<(ProviderId, ApiKeyId), ApiKey, StableMemory>> =
+        RefCell::new(init_api_keys());
+    // ID of the next API key. IDs are never reused.
+    static NEXT_API_KEY_ID: RefCell<Cell<ApiKeyId, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(NEXT_API_KEY_ID_MEMORY_ID)), 0));
     static MANAGE_API_KEYS: RefCell<ic_stable_structures::Vec<Principal, StableMemory>> =
         RefCell::new(ic_stable_structures::Vec::init(MEMORY_MANAGER.with_borrow(|m| m.get(MANAGE_API_KEYS_MEMORY_ID))));
     static LOG_FILTER: RefCell<Cell<StorableLogFilter, StableMemory>> =
@@ -45,24 +101,294 @@ thread_local! {
         RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(OVERRIDE_PROVIDER_MEMORY_ID)), OverrideProvider::default()));
     static NUM_SUBNET_NODES: RefCell<Cell<u32, StableMemory>> =
         RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(NUM_SUBNET_NODES_MEMORY_ID)), crate::constants::NODES_IN_SUBNET));
+    static PROVIDER_REGISTRY: RefCell<StableBTreeMap<ProviderId, Provider, StableMemory>> =
+        RefCell::new(init_provider_registry());
+    // ID of the next provider added by the controllers. IDs are never reused.
+    static NEXT_PROVIDER_ID: RefCell<Cell<ProviderId, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(NEXT_PROVIDER_ID_MEMORY_ID)), 0));
+    static CHAIN_REGISTRY: RefCell<StableBTreeMap<u64, StorableChain, StableMemory>> =
+        RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(CHAIN_REGISTRY_MEMORY_ID))));
+    // Append-only log of administrative actions, indexed by entry ID.
+    static AUDIT_LOG: RefCell<StableBTreeMap<u64, StorableAuditLogEntry, StableMemory>> =
+        RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(AUDIT_LOG_MEMORY_ID))));
+    static SUSPENSIONS: RefCell<Cell<Suspensions, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(SUSPENSIONS_MEMORY_ID)), Suspensions::default()));
+    static CALLER_LIMITS: RefCell<Cell<StorableCallerLimits, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(CALLER_LIMITS_MEMORY_ID)), StorableCallerLimits::default()));
+    static CALLER_LIMIT_EXEMPTIONS: RefCell<ic_stable_structures::Vec<Principal, StableMemory>> =
+        RefCell::new(ic_stable_structures::Vec::init(MEMORY_MANAGER.with_borrow(|m| m.get(CALLER_LIMIT_EXEMPTIONS_MEMORY_ID))));
+    // Prepaid cycles balance of each caller.
+    static CYCLES_ACCOUNTS: RefCell<StableBTreeMap<Principal, u128, StableMemory>> =
+        RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(CYCLES_ACCOUNTS_MEMORY_ID))));
+    // Log subscriptions, indexed by subscription ID. IDs are never reused.
+    static LOG_SUBSCRIPTIONS: RefCell<StableBTreeMap<u64, StorableLogSubscription, StableMemory>> =
+        RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(LOG_SUBSCRIPTIONS_MEMORY_ID))));
+    static NEXT_LOG_SUBSCRIPTION_ID: RefCell<Cell<u64, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(NEXT_LOG_SUBSCRIPTION_ID_MEMORY_ID)), 0));
+    // Watches of sent transactions, indexed by watch ID. IDs are never reused.
+    static TRANSACTION_WATCHES: RefCell<StableBTreeMap<u64, StorableTransactionWatch, StableMemory>> =
+        RefCell::new(StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(TRANSACTION_WATCHES_MEMORY_ID))));
+    static NEXT_TRANSACTION_WATCH_ID: RefCell<Cell<u64, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(NEXT_TRANSACTION_WATCH_ID_MEMORY_ID)), 0));
+    static HEAD_TRACKERS: RefCell<Cell<StorableHeadTrackers, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(HEAD_TRACKERS_MEMORY_ID)), StorableHeadTrackers::default()));
+    static JSON_RPC_GATEWAYS: RefCell<Cell<StorableJsonRpcGateways, StableMemory>> =
+        RefCell::new(Cell::init(MEMORY_MANAGER.with_borrow(|m| m.get(JSON_RPC_GATEWAYS_MEMORY_ID)), StorableJsonRpcGateways::default()));
+}
+
+/// Initialize the provider registry, which is seeded with [`PROVIDERS`].
+fn init_provider_registry() -> StableBTreeMap<ProviderId, Provider, StableMemory> {
+    let mut registry =
+        StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(PROVIDER_REGISTRY_MEMORY_ID)));
+    let mut seeded_provider_ids =
+        StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(SEEDED_PROVIDER_IDS_MEMORY_ID)));
+    seed_providers(&mut registry, &mut seeded_provider_ids, PROVIDERS);
+    registry
+}
+
+/// Seed the provider registry with the given providers.
+///
+/// Each provider is only seeded once, so that providers added to [`PROVIDERS`] in a later version
+/// are added on upgrade, while providers removed by the controllers are not added back.
+/// Providers added by the controllers have IDs from [`FIRST_RUNTIME_PROVIDER_ID`] on, which
+/// are never used by [`PROVIDERS`]. A new provider is only skipped if its alias is already used
+/// in the registry.
+fn seed_providers<M: Memory>(
+    registry: &mut StableBTreeMap<ProviderId, Provider, M>,
+    seeded_provider_ids: &mut StableBTreeMap<ProviderId, (), M>,
+    providers: &[Provider],
+) {
+    for provider in providers {
+        if seeded_provider_ids.contains_key(&provider.provider_id) {
+            continue;
+        }
+        let aliases: BTreeSet<_> = registry.values().filter_map(|p| p.alias).collect();
+        if !provider.alias.is_some_and(|alias| aliases.contains(&alias)) {
+            registry.insert(provider.provider_id, provider.clone());
+        }
+        seeded_provider_ids.insert(provider.provider_id, ());
+    }
+}
+
+/// Initialize the API keys, migrating the API keys from the legacy `API_KEY_MAP` if needed.
+fn init_api_keys() -> StableBTreeMap<(ProviderId, ApiKeyId), ApiKey, StableMemory> {
+    let mut api_keys =
+        StableBTreeMap::init(MEMORY_MANAGER.with_borrow(|m| m.get(API_KEYS_MEMORY_ID)));
+    API_KEY_MAP.with_borrow_mut(|legacy| {
+        let provider_ids: Vec<_> = legacy.keys().collect();
+        for provider_id in provider_ids {
+            if let Some(api_key) = legacy.remove(&provider_id) {
+                api_keys.insert((provider_id, next_api_key_id()), api_key);
+            }
+        }
+    });
+    api_keys
+}
+
+pub fn get_provider(provider_id: ProviderId) -> Option<Provider> {
+    PROVIDER_REGISTRY.with_borrow(|registry| registry.get(&provider_id))
+}
+
+pub fn get_providers() -> Vec<Provider> {
+    PROVIDER_REGISTRY.with_borrow(|registry| registry.values().collect())
+}
+
+/// Allocate the ID of a new provider added by the controllers, which is at least
+/// [`FIRST_RUNTIME_PROVIDER_ID`] and higher than the ID of any provider that was ever
+/// in the registry.
+pub fn next_provider_id() -> ProviderId {
+    let max_provider_id = PROVIDER_REGISTRY.with_borrow(|registry| {
+        registry
+            .last_key_value()
+            .map(|(provider_id, _)| provider_id)
+    });
+    NEXT_PROVIDER_ID.with_borrow_mut(|next_id| {
+        let provider_id = max_provider_id
+            .map(|max_provider_id| max_provider_id + 1)
+            .unwrap_or_default()
+            .max(*next_id.get())
+            .max(FIRST_RUNTIME_PROVIDER_ID);
+        next_id.set(provider_id + 1);
+        provider_id
+    })
+}
+
+pub fn insert_provider(provider: Provider) {
+    PROVIDER_REGISTRY.with_borrow_mut(|registry| registry.insert(provider.provider_id, provider));
+}
+
+pub fn remove_provider(provider_id: ProviderId) -> Option<Provider> {
+    PROVIDER_REGISTRY.with_borrow_mut(|registry| registry.remove(&provider_id))
+}
+
+pub fn get_chain(chain_id: u64) -> Option<Chain> {
+    CHAIN_REGISTRY.with_borrow(|registry| registry.get(&chain_id).map(Chain::from))
+}
+
+pub fn get_chains() -> Vec<Chain> {
+    CHAIN_REGISTRY.with_borrow(|registry| registry.values().map(Chain::from).collect())
+}
+
+pub fn insert_chain(chain: Chain) {
+    CHAIN_REGISTRY.with_borrow_mut(|registry| registry.insert(chain.chain_id, chain.into()));
+}
+
+pub fn remove_chain(chain_id: u64) -> Option<Chain> {
+    CHAIN_REGISTRY.with_borrow_mut(|registry| registry.remove(&chain_id).map(Chain::from))
 }
 
+/// Returns the first API key of the provider.
 pub fn get_api_key(provider_id: ProviderId) -> Option<ApiKey> {
-    API_KEY_MAP.with_borrow_mut(|map| map.get(&provider_id))
+    get_api_keys(provider_id)
+        .into_iter()
+        .next()
+        .map(|(_, api_key)| api_key)
+}
+
+/// Returns the API keys of the provider, in the order in which they are used.
+pub fn get_api_keys(provider_id: ProviderId) -> Vec<(ApiKeyId, ApiKey)> {
+    API_KEYS.with_borrow(|api_keys| {
+        api_keys
+            .range((provider_id, ApiKeyId::MIN)..=(provider_id, ApiKeyId::MAX))
+            .map(|entry| (entry.key().1, entry.value()))
+            .collect()
+    })
 }
 
+/// Choose the API key to use for the next request to the provider, see [`ApiKeyRotations`].
+pub fn next_api_key(provider_id: ProviderId) -> Option<(ApiKeyId, ApiKey)> {
+    let api_keys = get_api_keys(provider_id);
+    let key_ids: Vec<_> = api_keys.iter().map(|(key_id, _)| *key_id).collect();
+    let key_id = UNSTABLE_API_KEY_ROTATIONS
+        .with_borrow(|rotations| rotations.select(provider_id, &key_ids))?;
+    api_keys.into_iter().find(|(id, _)| id == &key_id)
+}
+
+/// Replace all API keys of the provider with the given API key.
 pub fn insert_api_key(provider_id: ProviderId, api_key: ApiKey) {
-    API_KEY_MAP.with_borrow_mut(|map| map.insert(provider_id, api_key));
+    let key_id = next_api_key_id();
+    remove_api_key(provider_id);
+    API_KEYS.with_borrow_mut(|api_keys| api_keys.insert((provider_id, key_id), api_key));
+}
+
+/// Add an API key to the provider and returns its ID.
+pub fn add_api_key(provider_id: ProviderId, api_key: ApiKey) -> ApiKeyId {
+    let key_id = next_api_key_id();
+    API_KEYS.with_borrow_mut(|api_keys| api_keys.insert((provider_id, key_id), api_key));
+    key_id
+}
+
+/// Allocate the ID of a new API key, which is higher than the ID of any API key
+/// that was ever added.
+fn next_api_key_id() -> ApiKeyId {
+    NEXT_API_KEY_ID.with_borrow_mut(|next_id| {
+        let key_id = *next_id.get();
+        next_id.set(key_id + 1);
+        key_id
+    })
 }
 
+/// Remove all API keys of the provider.
 pub fn remove_api_key(provider_id: ProviderId) {
-    API_KEY_MAP.with_borrow_mut(|map| map.remove(&provider_id));
+    for (key_id, _) in get_api_keys(provider_id) {
+        remove_api_key_by_id(provider_id, key_id);
+    }
+}
+
+/// Remove the API key with the given ID and returns whether it existed.
+pub fn remove_api_key_by_id(provider_id: ProviderId, key_id: ApiKeyId) -> bool {
+    UNSTABLE_API_KEY_ROTATIONS.with_borrow_mut(|rotations| {
+        rotations.forget(&ApiKeyRef {
+            provider_id,
+            key_id,
+        })
+    });
+    API_KEYS.with_borrow_mut(|api_keys| api_keys.remove(&(provider_id, key_id)).is_some())
+}
+
+/// Record that a request was sent with the given API key, so that the next request to the provider
+/// uses the next API key.
+pub fn record_api_key_request(api_key: ApiKeyRef) {
+    UNSTABLE_API_KEY_ROTATIONS.with_borrow_mut(|rotations| rotations.record_request(api_key))
+}
+
+/// Record the HTTP status code of the response to a request sent with the given API key.
+///
+/// Returns `true` if the API key was rate limited as a result.
+pub fn record_api_key_status(api_key: ApiKeyRef, status: u16) -> bool {
+    UNSTABLE_API_KEY_ROTATIONS.with_borrow_mut(|rotations| rotations.record_status(api_key, status))
+}
+
+pub fn get_api_key_infos(provider_id: ProviderId) -> Vec<ApiKeyInfo> {
+    get_api_keys(provider_id)
+        .into_iter()
+        .map(|(key_id, _)| {
+            let api_key = ApiKeyRef {
+                provider_id,
+                key_id,
+            };
+            let metric_api_key = MetricApiKey::from(api_key);
+            let (requests, errors) = UNSTABLE_METRICS.with_borrow(|metrics| {
+                let requests = metrics
+                    .api_key_requests
+                    .get(&metric_api_key)
+                    .copied()
+                    .unwrap_or_default();
+                let errors = metrics
+                    .api_key_errors
+                    .iter()
+                    .filter(|((other, _), _)| other == &metric_api_key)
+                    .map(|(_, count)| count)
+                    .sum();
+                (requests, errors)
+            });
+            ApiKeyInfo {
+                key_id,
+                requests,
+                errors,
+                rate_limited: UNSTABLE_API_KEY_ROTATIONS
+                    .with_borrow(|rotations| rotations.is_rate_limited(&api_key)),
+            }
+        })
+        .collect()
+}
+
+/// Append an entry to the audit log and return its ID.
+pub fn append_audit_log_entry(caller: Principal, timestamp: u64, action: AuditAction) -> u64 {
+    AUDIT_LOG.with_borrow_mut(|log| {
+        let id = log.len();
+        log.insert(
+            id,
+            AuditLogEntry {
+                id,
+                timestamp,
+                caller,
+                action,
+            }
+            .into(),
+        );
+        id
+    })
+}
+
+/// Return at most `limit` entries of the audit log, starting with the entry with ID `start`.
+pub fn get_audit_log(start: u64, limit: u64) -> Vec<AuditLogEntry> {
+    AUDIT_LOG.with_borrow(|log| {
+        log.range(start..)
+            .take(limit as usize)
+            .map(|entry| AuditLogEntry::from(entry.value()))
+            .collect()
+    })
 }
 
 pub fn is_api_key_principal(principal: &Principal) -> bool {
     MANAGE_API_KEYS.with_borrow(|principals| principals.iter().any(|other| &other == principal))
 }
 
+pub fn get_api_key_principals() -> Vec<Principal> {
+    MANAGE_API_KEYS.with_borrow(|principals| principals.iter().collect())
+}
+
 pub fn set_api_key_principals(new_principals: Vec<Principal>) {
     MANAGE_API_KEYS.with_borrow_mut(|principals| {
         while !principals.is_empty() {
@@ -104,6 +430,342 @@ pub fn set_override_provider(provider: OverrideProvider) {
     });
 }
 
+pub fn get_suspensions() -> Suspensions {
+    SUSPENSIONS.with_borrow(|state| state.get().clone())
+}
+
+pub fn set_suspensions(suspensions: Suspensions) {
+    SUSPENSIONS.with_borrow_mut(|state| {
+        state.set(suspensions);
+    });
+}
+
+pub fn is_method_suspended(method: &str) -> bool {
+    SUSPENSIONS.with_borrow(|state| state.get().is_suspended(method))
+}
+
+pub fn get_caller_limits() -> CallerLimits {
+    CALLER_LIMITS.with_borrow(|state| state.get().clone().into())
+}
+
+pub fn set_caller_limits(limits: CallerLimits) {
+    CALLER_LIMITS.with_borrow_mut(|state| {
+        state.set(limits.into());
+    });
+}
+
+pub fn get_caller_limit_exemptions() -> Vec<Principal> {
+    CALLER_LIMIT_EXEMPTIONS.with_borrow(|principals| principals.iter().collect())
+}
+
+pub fn is_caller_limit_exempt(principal: &Principal) -> bool {
+    CALLER_LIMIT_EXEMPTIONS
+        .with_borrow(|principals| principals.iter().any(|other| &other == principal))
+}
+
+pub fn set_caller_limit_exemptions(new_principals: Vec<Principal>) {
+    CALLER_LIMIT_EXEMPTIONS.with_borrow_mut(|principals| {
+        while !principals.is_empty() {
+            principals.pop();
+        }
+        for principal in new_principals {
+            principals.push(&principal);
+        }
+    });
+}
+
+/// Record a call of the caller to a method making HTTP outcalls, see [`CallerUsages`].
+pub fn try_record_caller_request(caller: Principal, now: u64) -> Result<(), CallerLimit> {
+    let limits = get_caller_limits();
+    let exempt = is_caller_limit_exempt(&caller);
+    UNSTABLE_CALLER_USAGES
+        .with_borrow_mut(|usages| usages.try_record_request(caller, &limits, exempt, now))
+}
+
+/// Returns an error if the given usage would exceed the limits of the caller, see [`CallerUsages`].
+pub fn ensure_caller_within_limits(
+    caller: &Principal,
+    outcalls: u64,
+    cycles: u128,
+    now: u64,
+) -> Result<(), CallerLimit> {
+    let limits = get_caller_limits();
+    let exempt = is_caller_limit_exempt(caller);
+    UNSTABLE_CALLER_USAGES.with_borrow(|usages| {
+        usages.ensure_within_limits(caller, outcalls, cycles, &limits, exempt, now)
+    })
+}
+
+pub fn record_caller_outcall(caller: Principal, now: u64) {
+    let limits = get_caller_limits();
+    UNSTABLE_CALLER_USAGES.with_borrow_mut(|usages| usages.record_outcall(caller, &limits, now))
+}
+
+pub fn record_caller_cycles(caller: Principal, cycles: u128, now: u64) {
+    let limits = get_caller_limits();
+    UNSTABLE_CALLER_USAGES
+        .with_borrow_mut(|usages| usages.record_cycles(caller, cycles, &limits, now))
+}
+
+pub fn get_caller_usage(caller: &Principal, now: u64) -> CallerUsage {
+    let limits = get_caller_limits();
+    let exempt = is_caller_limit_exempt(caller);
+    UNSTABLE_CALLER_USAGES.with_borrow(|usages| usages.get(caller, &limits, exempt, now))
+}
+
+pub fn get_balance(principal: &Principal) -> u128 {
+    CYCLES_ACCOUNTS.with_borrow(|accounts| accounts.get(principal).unwrap_or_default())
+}
+
+/// Add the given amount of cycles to the balance of the principal and return the new balance.
+pub fn credit_balance(principal: Principal, amount: u128) -> u128 {
+    CYCLES_ACCOUNTS.with_borrow_mut(|accounts| {
+        let balance = accounts
+            .get(&principal)
+            .unwrap_or_default()
+            .saturating_add(amount);
+        if balance > 0 {
+            accounts.insert(principal, balance);
+        }
+        balance
+    })
+}
+
+/// Subtract the given amount of cycles from the balance of the principal.
+///
+/// Returns `false` without changing the balance if the balance is insufficient.
+pub fn try_debit_balance(principal: &Principal, amount: u128) -> bool {
+    CYCLES_ACCOUNTS.with_borrow_mut(|accounts| {
+        let balance = accounts.get(principal).unwrap_or_default();
+        match balance.checked_sub(amount) {
+            Some(0) => {
+                accounts.remove(principal);
+                true
+            }
+            Some(remaining) => {
+                accounts.insert(*principal, remaining);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Add a log subscription, whose ID is assigned by this function, and return its ID.
+pub fn insert_log_subscription(mut subscription: LogSubscription) -> u64 {
+    let id = NEXT_LOG_SUBSCRIPTION_ID.with_borrow_mut(|next_id| {
+        let id = *next_id.get();
+        next_id.set(id + 1);
+        id
+    });
+    subscription.id = id;
+    LOG_SUBSCRIPTIONS
+        .with_borrow_mut(|subscriptions| subscriptions.insert(id, subscription.into()));
+    id
+}
+
+pub fn get_log_subscription(id: u64) -> Option<LogSubscription> {
+    LOG_SUBSCRIPTIONS.with_borrow(|subscriptions| subscriptions.get(&id).map(LogSubscription::from))
+}
+
+pub fn get_log_subscriptions() -> Vec<LogSubscription> {
+    LOG_SUBSCRIPTIONS
+        .with_borrow(|subscriptions| subscriptions.values().map(LogSubscription::from).collect())
+}
+
+pub fn num_log_subscriptions() -> u64 {
+    LOG_SUBSCRIPTIONS.with_borrow(|subscriptions| subscriptions.len())
+}
+
+/// Update the log subscription with the given ID, unless it was removed in the meantime.
+pub fn update_log_subscription(id: u64, update: impl FnOnce(&mut LogSubscription)) {
+    LOG_SUBSCRIPTIONS.with_borrow_mut(|subscriptions| {
+        if let Some(subscription) = subscriptions.get(&id) {
+            let mut subscription = LogSubscription::from(subscription);
+            update(&mut subscription);
+            subscriptions.insert(id, subscription.into());
+        }
+    })
+}
+
+pub fn remove_log_subscription(id: u64) -> Option<LogSubscription> {
+    LOG_SUBSCRIPTIONS
+        .with_borrow_mut(|subscriptions| subscriptions.remove(&id).map(LogSubscription::from))
+}
+
+/// Add a transaction watch, whose ID is assigned by this function, and return its ID.
+pub fn insert_transaction_watch(mut watch: TransactionWatch) -> u64 {
+    let id = NEXT_TRANSACTION_WATCH_ID.with_borrow_mut(|next_id| {
+        let id = *next_id.get();
+        next_id.set(id + 1);
+        id
+    });
+    watch.id = id;
+    TRANSACTION_WATCHES.with_borrow_mut(|watches| watches.insert(id, watch.into()));
+    id
+}
+
+pub fn get_transaction_watch(id: u64) -> Option<TransactionWatch> {
+    TRANSACTION_WATCHES.with_borrow(|watches| watches.get(&id).map(TransactionWatch::from))
+}
+
+pub fn get_transaction_watches() -> Vec<TransactionWatch> {
+    TRANSACTION_WATCHES
+        .with_borrow(|watches| watches.values().map(TransactionWatch::from).collect())
+}
+
+pub fn num_transaction_watches() -> u64 {
+    TRANSACTION_WATCHES.with_borrow(|watches| watches.len())
+}
+
+/// Update the transaction watch with the given ID, unless it was removed in the meantime.
+pub fn update_transaction_watch(id: u64, update: impl FnOnce(&mut TransactionWatch)) {
+    TRANSACTION_WATCHES.with_borrow_mut(|watches| {
+        if let Some(watch) = watches.get(&id) {
+            let mut watch = TransactionWatch::from(watch);
+            update(&mut watch);
+            watches.insert(id, watch.into());
+        }
+    })
+}
+
+pub fn remove_transaction_watch(id: u64) -> Option<TransactionWatch> {
+    TRANSACTION_WATCHES.with_borrow_mut(|watches| watches.remove(&id).map(TransactionWatch::from))
+}
+
+/// Mark the log subscriptions and transaction watches as being polled.
+///
+/// Returns `false` if they are already being polled, e.g. because the previous polling
+/// did not complete yet.
+pub fn try_start_polling_subscriptions() -> bool {
+    UNSTABLE_IS_POLLING_SUBSCRIPTIONS
+        .with_borrow_mut(|is_polling| !std::mem::replace(is_polling, true))
+}
+
+pub fn stop_polling_subscriptions() {
+    UNSTABLE_IS_POLLING_SUBSCRIPTIONS.with_borrow_mut(|is_polling| *is_polling = false)
+}
+
+pub fn get_head_trackers() -> Vec<HeadTracker> {
+    HEAD_TRACKERS.with_borrow(|state| state.get().clone().into())
+}
+
+/// Replace the chains whose head is tracked, forgetting the headers of the other chains.
+pub fn set_head_trackers(trackers: Vec<HeadTracker>, chain_ids: &[u64]) {
+    HEAD_TRACKERS.with_borrow_mut(|state| {
+        state.set(trackers.into());
+    });
+    UNSTABLE_CHAIN_HEADS.with_borrow_mut(|heads| heads.retain(chain_ids));
+}
+
+/// See [`crate::head_tracker::ChainHeaders::missing_parent`].
+pub fn missing_parent_header(chain_id: u64, header: &Header) -> Option<u64> {
+    UNSTABLE_CHAIN_HEADS.with_borrow(|heads| heads.get(chain_id)?.missing_parent(header))
+}
+
+/// Record the headers of a tracked chain, see [`ChainHeads::record`].
+pub fn record_chain_headers(
+    chain_id: u64,
+    headers: impl IntoIterator<Item = Header>,
+    safe: Option<Header>,
+    finalized: Option<Header>,
+    now: u64,
+) -> u64 {
+    UNSTABLE_CHAIN_HEADS
+        .with_borrow_mut(|heads| heads.record(chain_id, headers, safe, finalized, now))
+}
+
+pub fn get_chain_head(chain_id: u64) -> Option<ChainHead> {
+    UNSTABLE_CHAIN_HEADS.with_borrow(|heads| heads.chain_head(chain_id))
+}
+
+pub fn get_recent_headers(chain_id: u64) -> Vec<BlockHeader> {
+    UNSTABLE_CHAIN_HEADS.with_borrow(|heads| {
+        heads
+            .get(chain_id)
+            .map(|chain| chain.recent().cloned().map(BlockHeader::from).collect())
+            .unwrap_or_default()
+    })
+}
+
+/// Mark the chain heads as being polled.
+///
+/// Returns `false` if they are already being polled.
+pub fn try_start_tracking_heads() -> bool {
+    UNSTABLE_IS_TRACKING_HEADS.with_borrow_mut(|is_tracking| !std::mem::replace(is_tracking, true))
+}
+
+pub fn stop_tracking_heads() {
+    UNSTABLE_IS_TRACKING_HEADS.with_borrow_mut(|is_tracking| *is_tracking = false)
+}
+
+pub fn get_json_rpc_gateway(chain_id: u64) -> Option<JsonRpcGateway> {
+    JSON_RPC_GATEWAYS.with_borrow(|state| state.get().get(chain_id).cloned())
+}
+
+/// Replace the JSON-RPC gateways, given by chain ID.
+pub fn set_json_rpc_gateways(gateways: BTreeMap<u64, JsonRpcGateway>) {
+    JSON_RPC_GATEWAYS.with_borrow_mut(|state| {
+        state.set(gateways.into());
+    });
+}
+
+pub fn get_cached_response(key: &CacheKey, now: u64) -> Option<String> {
+    UNSTABLE_RESPONSE_CACHE.with_borrow(|cache| cache.get(key, now).map(String::from))
+}
+
+/// Cache the response if the block that contains it is finalized, see [`ResponseCache`].
+pub fn cache_response(
+    key: CacheKey,
+    block_number: BlockNumber,
+    response: String,
+    now: u64,
+) -> bool {
+    UNSTABLE_RESPONSE_CACHE
+        .with_borrow_mut(|cache| cache.insert_if_finalized(key, block_number, response, now))
+}
+
+pub fn record_finalized_block(chain_id: u64, block_number: BlockNumber) {
+    UNSTABLE_RESPONSE_CACHE
+        .with_borrow_mut(|cache| cache.record_finalized_block(chain_id, block_number))
+}
+
+/// Join an identical in-flight request, see [`InFlightRequests::join`].
+pub fn join_in_flight_request(key: CoalescingKey, caller: Principal, now: u64) -> Role {
+    UNSTABLE_IN_FLIGHT_REQUESTS.with_borrow_mut(|requests| requests.join(key, caller, now))
+}
+
+pub fn complete_in_flight_request(
+    key: &CoalescingKey,
+    result: Rc<dyn Any>,
+    cycles_cost: u128,
+) -> usize {
+    UNSTABLE_IN_FLIGHT_REQUESTS
+        .with_borrow_mut(|requests| requests.complete(key, result, cycles_cost))
+}
+
+pub fn abandon_in_flight_request(key: &CoalescingKey) {
+    UNSTABLE_IN_FLIGHT_REQUESTS.with_borrow_mut(|requests| requests.abandon(key))
+}
+
+pub fn record_response_size(method: String, service: SupportedRpcService, num_bytes: u64) {
+    UNSTABLE_RESPONSE_SIZES.with_borrow_mut(|sizes| sizes.record(method, service, num_bytes))
+}
+
+pub fn learned_response_size_estimate(method: &str, service: SupportedRpcService) -> Option<u64> {
+    UNSTABLE_RESPONSE_SIZES.with_borrow(|sizes| sizes.estimate(method, service))
+}
+
+pub fn learned_response_size_estimates() -> Vec<(String, SupportedRpcService, u64)> {
+    UNSTABLE_RESPONSE_SIZES.with_borrow(|sizes| {
+        sizes
+            .estimates()
+            .into_iter()
+            .map(|(method, service, estimate)| (method.to_string(), service, estimate))
+            .collect()
+    })
+}
+
 pub fn next_request_id() -> Id {
     UNSTABLE_HTTP_REQUEST_COUNTER.with_borrow_mut(|counter| {
         let current_request_id = counter.get_and_increment();
@@ -121,24 +783,75 @@ pub fn set_num_subnet_nodes(nodes: u32) {
     });
 }
 
-pub fn record_ok_result(service: SupportedRpcService, now: Timestamp) {
-    UNSTABLE_RPC_SERVICE_OK_RESULTS_TIMESTAMPS
-        .with_borrow_mut(|access| access.record_evict(service, now));
+/// Record the outcome of a call to the given service.
+///
+/// Returns `true` if the circuit breaker of the service opened as a result.
+pub fn record_outcome(
+    service: SupportedRpcService,
+    outcome: RpcServiceOutcome,
+    now: Timestamp,
+) -> bool {
+    UNSTABLE_RPC_SERVICE_OUTCOMES_TIMESTAMPS
+        .with_borrow_mut(|access| access.record_outcome_evict(service, outcome, now));
+    UNSTABLE_CIRCUIT_BREAKERS
+        .with_borrow_mut(|breakers| breakers.record_outcome_evict(service, outcome, now))
 }
 
+/// Rank the given services from the most to the least preferred, excluding the services
+/// whose circuit breaker is open or already probed.
 pub fn rank_providers(
     services: &[SupportedRpcService],
+    num_required: usize,
     now: Timestamp,
 ) -> Vec<SupportedRpcService> {
-    UNSTABLE_RPC_SERVICE_OK_RESULTS_TIMESTAMPS
-        .with_borrow_mut(|access| access.rank_ascending_evict(services, now))
+    let ranked = UNSTABLE_RPC_SERVICE_OUTCOMES_TIMESTAMPS
+        .with_borrow_mut(|access| access.rank_ascending_evict(services, now));
+    let available = UNSTABLE_CIRCUIT_BREAKERS
+        .with_borrow_mut(|breakers| breakers.retain_available_evict(ranked, now));
+    UNSTABLE_RPC_SERVICE_OUTCOMES_TIMESTAMPS
+        .with_borrow_mut(|access| access.retain_included_evict(available, num_required, now))
+}
+
+/// Record that calls are made to the given services, where a call to a service whose circuit
+/// breaker is half-open probes the service.
+pub fn start_probes(services: impl IntoIterator<Item = SupportedRpcService>, now: Timestamp) {
+    UNSTABLE_CIRCUIT_BREAKERS.with_borrow_mut(|breakers| {
+        for service in services {
+            breakers.start_probe_evict(service, now);
+        }
+    })
+}
+
+pub fn get_circuit_breaker_state(
+    service: SupportedRpcService,
+    now: Timestamp,
+) -> CircuitBreakerState {
+    UNSTABLE_CIRCUIT_BREAKERS.with_borrow_mut(|breakers| breakers.state_evict(service, now))
+}
+
+pub fn get_providers_health(
+    services: &[SupportedRpcService],
+    now: Timestamp,
+) -> Vec<RpcServiceHealth> {
+    UNSTABLE_RPC_SERVICE_OUTCOMES_TIMESTAMPS
+        .with_borrow_mut(|access| access.health_evict(services, now))
 }
 
 #[cfg(test)]
 mod test {
     use candid::Principal;
+    use evm_rpc_types::{AuditAction, LogSubscription, Nat256, RpcServices};
+    use ic_stable_structures::{
+        memory_manager::{MemoryId, MemoryManager},
+        DefaultMemoryImpl, StableBTreeMap,
+    };
 
-    use crate::memory::{is_api_key_principal, set_api_key_principals};
+    use crate::memory::{
+        append_audit_log_entry, credit_balance, get_audit_log, get_balance, get_log_subscription,
+        get_log_subscriptions, insert_log_subscription, is_api_key_principal,
+        remove_log_subscription, seed_providers, set_api_key_principals, try_debit_balance,
+        update_log_subscription, PROVIDERS,
+    };
 
     #[test]
     fn test_api_key_principals() {
@@ -167,4 +880,128 @@ mod test {
         assert!(!is_api_key_principal(&principal1));
         assert!(!is_api_key_principal(&principal2));
     }
+
+    #[test]
+    fn test_audit_log() {
+        let caller =
+            Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+                .unwrap();
+        assert_eq!(get_audit_log(0, 10), vec![]);
+
+        for provider_id in 0..5 {
+            let id = append_audit_log_entry(
+                caller,
+                1_000 + provider_id,
+                AuditAction::RemoveProvider { provider_id },
+            );
+            assert_eq!(id, provider_id);
+        }
+
+        let all = get_audit_log(0, 10);
+        assert_eq!(all.len(), 5);
+        for (id, entry) in all.iter().enumerate() {
+            assert_eq!(entry.id, id as u64);
+            assert_eq!(entry.timestamp, 1_000 + id as u64);
+            assert_eq!(entry.caller, caller);
+            assert_eq!(
+                entry.action,
+                AuditAction::RemoveProvider {
+                    provider_id: id as u64
+                }
+            );
+        }
+        assert_eq!(get_audit_log(1, 2), all[1..3].to_vec());
+        assert_eq!(get_audit_log(4, 10), all[4..].to_vec());
+        assert_eq!(get_audit_log(5, 10), vec![]);
+    }
+
+    #[test]
+    fn test_cycles_accounts() {
+        let principal =
+            Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+                .unwrap();
+        assert_eq!(get_balance(&principal), 0);
+        assert!(try_debit_balance(&principal, 0));
+
+        assert_eq!(credit_balance(principal, 1_000), 1_000);
+        assert_eq!(credit_balance(principal, 500), 1_500);
+        assert!(!try_debit_balance(&principal, 1_501));
+        assert_eq!(get_balance(&principal), 1_500);
+
+        assert!(try_debit_balance(&principal, 1_000));
+        assert_eq!(get_balance(&principal), 500);
+        assert!(try_debit_balance(&principal, 500));
+        assert_eq!(get_balance(&principal), 0);
+    }
+
+    #[test]
+    fn test_log_subscriptions() {
+        let owner =
+            Principal::from_text("k5dlc-ijshq-lsyre-qvvpq-2bnxr-pb26c-ag3sc-t6zo5-rdavy-recje-zqe")
+                .unwrap();
+        let subscription = LogSubscription {
+            id: 42,
+            owner,
+            source: RpcServices::EthMainnet(None),
+            config: None,
+            addresses: vec![],
+            topics: None,
+            confirmations: 12,
+            callback: "on_logs".to_string(),
+            next_block: Nat256::from(1_000_u64),
+            last_error: None,
+        };
+
+        assert_eq!(insert_log_subscription(subscription.clone()), 0);
+        assert_eq!(insert_log_subscription(subscription.clone()), 1);
+        assert_eq!(get_log_subscription(0).unwrap().id, 0);
+
+        update_log_subscription(1, |subscription| {
+            subscription.next_block = Nat256::from(2_000_u64);
+        });
+        assert_eq!(
+            get_log_subscription(1).unwrap().next_block,
+            Nat256::from(2_000_u64)
+        );
+
+        assert_eq!(remove_log_subscription(1).map(|s| s.id), Some(1));
+        update_log_subscription(1, |_| panic!("BUG: subscription was removed"));
+        assert_eq!(get_log_subscription(1), None);
+
+        // IDs of removed subscriptions are not reused.
+        assert_eq!(insert_log_subscription(subscription), 2);
+        let ids: Vec<_> = get_log_subscriptions().into_iter().map(|s| s.id).collect();
+        assert_eq!(ids, vec![0, 2]);
+    }
+
+    #[test]
+    fn should_seed_each_provider_once() {
+        let memory_manager = MemoryManager::init(DefaultMemoryImpl::default());
+        let mut registry = StableBTreeMap::init(memory_manager.get(MemoryId::new(0)));
+        let mut seeded_provider_ids = StableBTreeMap::init(memory_manager.get(MemoryId::new(1)));
+
+        seed_providers(&mut registry, &mut seeded_provider_ids, &PROVIDERS[..2]);
+        // Provider removed by the controllers
+        registry.remove(&PROVIDERS[0].provider_id);
+        seed_providers(&mut registry, &mut seeded_provider_ids, &PROVIDERS[..3]);
+
+        let provider_ids: Vec<_> = registry.keys().collect();
+        assert_eq!(
+            provider_ids,
+            vec![PROVIDERS[1].provider_id, PROVIDERS[2].provider_id]
+        );
+    }
+
+    #[test]
+    fn should_seed_providers_by_id() {
+        let memory_manager = MemoryManager::init(DefaultMemoryImpl::default());
+        let mut registry = StableBTreeMap::init(memory_manager.get(MemoryId::new(0)));
+        let mut seeded_provider_ids = StableBTreeMap::init(memory_manager.get(MemoryId::new(1)));
+
+        // A new provider inserted before other providers is seeded on upgrade
+        seed_providers(&mut registry, &mut seeded_provider_ids, &PROVIDERS[1..3]);
+        seed_providers(&mut registry, &mut seeded_provider_ids, &PROVIDERS[..3]);
+
+        assert_eq!(registry.len(), 3);
+    }
 }
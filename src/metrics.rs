@@ -1,6 +1,16 @@
 use std::collections::HashMap;
 
-use crate::types::{MetricLabels, MetricValue};
+use crate::{
+    constants::SUSPENDABLE_METHODS,
+    memory::{
+        get_circuit_breaker_state, get_suspensions, learned_response_size_estimates,
+        num_log_subscriptions, num_transaction_watches,
+    },
+    providers::SERVICE_PROVIDER_MAP,
+    types::{MetricLabels, MetricProvider, MetricValue},
+};
+use canhttp::multi::Timestamp;
+use evm_rpc_types::CircuitBreakerState;
 
 #[macro_export]
 macro_rules! add_metric {
@@ -99,6 +109,11 @@ pub fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> st
             &m.inconsistent_responses,
             "Number of inconsistent RPC responses",
         );
+        w.counter_entries(
+            "evmrpc_inconsistent_response_fields",
+            &m.inconsistent_response_fields,
+            "Number of inconsistent results in which a response field differs between providers",
+        );
         w.counter_entries(
             "evmrpc_err_http_outcall",
             &m.err_http_outcall,
@@ -114,6 +129,119 @@ pub fn encode_metrics(w: &mut ic_metrics_encoder::MetricsEncoder<Vec<u8>>) -> st
             &m.err_no_consensus,
             "Number of HTTP outcalls with consensus errors",
         );
+        w.counter_entries(
+            "evmrpc_circuit_breaker_trips",
+            &m.circuit_breaker_trips,
+            "Number of times the circuit breaker of a provider opened",
+        );
+        w.counter_entries(
+            "evmrpc_api_key_requests",
+            &m.api_key_requests,
+            "Number of JSON-RPC requests sent with an API key",
+        );
+        w.counter_entries(
+            "evmrpc_api_key_errors",
+            &m.api_key_errors,
+            "Number of non-successful HTTP responses to requests sent with an API key",
+        );
+        w.counter_entries(
+            "evmrpc_caller_limit_rejections",
+            &m.caller_limit_rejections,
+            "Number of calls rejected because the caller exceeded one of its limits",
+        );
+        w.counter_entries(
+            "evmrpc_cache_hits",
+            &m.cache_hits,
+            "Number of requests served from the cache of immutable responses",
+        );
+        w.counter_entries(
+            "evmrpc_cache_misses",
+            &m.cache_misses,
+            "Number of cacheable requests that were not found in the cache of immutable responses",
+        );
+        w.counter_entries(
+            "evmrpc_coalesced_requests",
+            &m.coalesced_requests,
+            "Number of requests that waited for the result of an identical in-flight request",
+        );
+        w.counter_entries(
+            "evmrpc_get_logs_bisections",
+            &m.get_logs_bisections,
+            "Number of times the block range of eth_getLogs was halved because it was too large",
+        );
+        w.counter_entries(
+            "evmrpc_chain_reorgs",
+            &m.chain_reorgs,
+            "Number of reorganizations detected on tracked chains",
+        );
+        w.counter_entries(
+            "evmrpc_chain_reorged_blocks",
+            &m.chain_reorged_blocks,
+            "Number of recent blocks of tracked chains that were reorganized away",
+        );
+        w.counter_entries(
+            "evmrpc_json_rpc_gateway_requests",
+            &m.json_rpc_gateway_requests,
+            "Number of JSON-RPC requests served over HTTP",
+        );
+
+        w.encode_gauge(
+            "evmrpc_log_subscriptions",
+            num_log_subscriptions() as f64,
+            "Number of log subscriptions",
+        )?;
+        w.encode_gauge(
+            "evmrpc_transaction_watches",
+            num_transaction_watches() as f64,
+            "Number of watched transactions",
+        )?;
+
+        let suspensions = get_suspensions();
+        w.encode_gauge(
+            "evmrpc_maintenance_mode",
+            if suspensions.all { 1.0 } else { 0.0 },
+            "Whether all methods making HTTP outcalls are suspended",
+        )?;
+        let mut suspended_methods = w.gauge_vec(
+            "evmrpc_suspended_method",
+            "Whether a method making HTTP outcalls is suspended",
+        )?;
+        for method in SUSPENDABLE_METHODS {
+            let suspended = if suspensions.is_suspended(method) {
+                1.0
+            } else {
+                0.0
+            };
+            suspended_methods = suspended_methods.value(&[("method", *method)], suspended)?;
+        }
+
+        let mut response_size_estimates = w.gauge_vec(
+            "evmrpc_learned_response_size_estimate",
+            "Maximum response size in bytes learned from past responses of a provider",
+        )?;
+        for (method, service, estimate) in learned_response_size_estimates() {
+            let provider = MetricProvider::from(service);
+            let labels = [vec![("method", method.as_str())], provider.metric_labels()].concat();
+            response_size_estimates = response_size_estimates.value(&labels, estimate as f64)?;
+        }
+
+        let now = Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
+        let services: Vec<_> =
+            SERVICE_PROVIDER_MAP.with_borrow(|map| map.keys().copied().collect());
+        let mut circuit_breaker_state = w.gauge_vec(
+            "evmrpc_circuit_breaker_state",
+            "State of the circuit breaker of a provider (0: closed, 1: half-open, 2: open)",
+        )?;
+        for service in services {
+            let state = match get_circuit_breaker_state(service, now) {
+                CircuitBreakerState::Closed => 0.0,
+                CircuitBreakerState::HalfOpen => 1.0,
+                CircuitBreakerState::Open => 2.0,
+            };
+            let provider = MetricProvider::from(service);
+            circuit_breaker_state =
+                circuit_breaker_state.value(&provider.metric_labels(), state)?;
+        }
 
         Ok(())
     })
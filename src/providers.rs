@@ -3,28 +3,40 @@ mod tests;
 
 use crate::{
     constants::{
-        ARBITRUM_ONE_CHAIN_ID, BASE_MAINNET_CHAIN_ID, ETH_MAINNET_CHAIN_ID, ETH_SEPOLIA_CHAIN_ID,
-        OPTIMISM_MAINNET_CHAIN_ID,
+        API_KEY_REPLACE_STRING, ARBITRUM_ONE_CHAIN_ID, ARBITRUM_SEPOLIA_CHAIN_ID,
+        BASE_MAINNET_CHAIN_ID, BASE_SEPOLIA_CHAIN_ID, CONTENT_TYPE_HEADER_LOWERCASE,
+        ETH_HOODI_CHAIN_ID, ETH_MAINNET_CHAIN_ID, ETH_SEPOLIA_CHAIN_ID, OPTIMISM_MAINNET_CHAIN_ID,
+        OPTIMISM_SEPOLIA_CHAIN_ID,
     },
-    types::{Provider, ProviderId, ResolvedRpcService, RpcAccess, RpcAuth},
+    http::error::is_response_too_large_error,
+    memory::{self, get_provider, get_providers, insert_chain, insert_provider, remove_api_key},
+    types::{ApiKeyId, ApiKeyRef, Provider, ProviderId, ResolvedRpcService, RpcAccess, RpcAuth},
+    util::hostname_from_url,
 };
-use canhttp::multi::{TimedSizedMap, TimedSizedVec, Timestamp};
+use canhttp::multi::{TimedSizedMap, Timestamp};
 use evm_rpc_types::{
-    EthMainnetService, EthSepoliaService, L2MainnetService, ProviderError, RpcApi, RpcService,
+    Chain, CircuitBreakerState, EthHoodiService, EthMainnetService, EthSepoliaService,
+    L2MainnetService, L2TestnetService, ProviderArgs, ProviderError, RpcApi, RpcError, RpcService,
 };
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::num::NonZeroUsize;
 use std::time::Duration;
 
+/// Providers used to seed the provider registry when the canister is first installed.
+///
+/// Once seeded, the provider registry is managed by the controllers and this list is no longer used.
 pub const PROVIDERS: &[Provider] = &[
     Provider {
         provider_id: 0,
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://cloudflare-eth.com/v1/mainnet",
+                url: Cow::Borrowed("https://cloudflare-eth.com/v1/mainnet"),
             },
-            public_url: Some("https://cloudflare-eth.com/v1/mainnet"),
+            public_url: Some(Cow::Borrowed("https://cloudflare-eth.com/v1/mainnet")),
         },
         alias: Some(SupportedRpcService::EthMainnet(
             EthMainnetService::Cloudflare,
@@ -35,9 +47,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://rpc.ankr.com/eth/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/eth/{API_KEY}"),
             },
-            public_url: Some("https://rpc.ankr.com/eth"),
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/eth")),
         },
         alias: Some(SupportedRpcService::EthMainnet(EthMainnetService::Ankr)),
     },
@@ -45,7 +57,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 2,
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://ethereum-rpc.publicnode.com",
+            public_url: Cow::Borrowed("https://ethereum-rpc.publicnode.com"),
         },
         alias: Some(SupportedRpcService::EthMainnet(
             EthMainnetService::PublicNode,
@@ -56,9 +68,11 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://ethereum.blockpi.network/v1/rpc/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://ethereum.blockpi.network/v1/rpc/{API_KEY}"),
             },
-            public_url: Some("https://ethereum.public.blockpi.network/v1/rpc/public"),
+            public_url: Some(Cow::Borrowed(
+                "https://ethereum.public.blockpi.network/v1/rpc/public",
+            )),
         },
         alias: Some(SupportedRpcService::EthMainnet(EthMainnetService::BlockPi)),
     },
@@ -66,7 +80,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 4,
         chain_id: ETH_SEPOLIA_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://rpc.sepolia.org",
+            public_url: Cow::Borrowed("https://rpc.sepolia.org"),
         },
         alias: Some(SupportedRpcService::EthSepolia(EthSepoliaService::Sepolia)),
     },
@@ -75,9 +89,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_SEPOLIA_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://rpc.ankr.com/eth_sepolia/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/eth_sepolia/{API_KEY}"),
             },
-            public_url: Some("https://rpc.ankr.com/eth_sepolia"),
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/eth_sepolia")),
         },
         alias: Some(SupportedRpcService::EthSepolia(EthSepoliaService::Ankr)),
     },
@@ -86,7 +100,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_SEPOLIA_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://ethereum-sepolia.blockpi.network/v1/rpc/{API_KEY}",
+                url_pattern: Cow::Borrowed(
+                    "https://ethereum-sepolia.blockpi.network/v1/rpc/{API_KEY}",
+                ),
             },
             public_url: None,
         },
@@ -96,7 +112,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 7,
         chain_id: ETH_SEPOLIA_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://ethereum-sepolia-rpc.publicnode.com",
+            public_url: Cow::Borrowed("https://ethereum-sepolia-rpc.publicnode.com"),
         },
         alias: Some(SupportedRpcService::EthSepolia(
             EthSepoliaService::PublicNode,
@@ -107,9 +123,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://eth-mainnet.g.alchemy.com/v2",
+                url: Cow::Borrowed("https://eth-mainnet.g.alchemy.com/v2"),
             },
-            public_url: Some("https://eth-mainnet.g.alchemy.com/v2/demo"),
+            public_url: Some(Cow::Borrowed("https://eth-mainnet.g.alchemy.com/v2/demo")),
         },
         alias: Some(SupportedRpcService::EthMainnet(EthMainnetService::Alchemy)),
     },
@@ -118,9 +134,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ETH_SEPOLIA_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://eth-sepolia.g.alchemy.com/v2",
+                url: Cow::Borrowed("https://eth-sepolia.g.alchemy.com/v2"),
             },
-            public_url: Some("https://eth-sepolia.g.alchemy.com/v2/demo"),
+            public_url: Some(Cow::Borrowed("https://eth-sepolia.g.alchemy.com/v2/demo")),
         },
         alias: Some(SupportedRpcService::EthSepolia(EthSepoliaService::Alchemy)),
     },
@@ -129,9 +145,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ARBITRUM_ONE_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://rpc.ankr.com/arbitrum/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/arbitrum/{API_KEY}"),
             },
-            public_url: Some("https://rpc.ankr.com/arbitrum"),
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/arbitrum")),
         },
         alias: Some(SupportedRpcService::ArbitrumOne(L2MainnetService::Ankr)),
     },
@@ -140,9 +156,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ARBITRUM_ONE_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://arb-mainnet.g.alchemy.com/v2",
+                url: Cow::Borrowed("https://arb-mainnet.g.alchemy.com/v2"),
             },
-            public_url: Some("https://arb-mainnet.g.alchemy.com/v2/demo"),
+            public_url: Some(Cow::Borrowed("https://arb-mainnet.g.alchemy.com/v2/demo")),
         },
         alias: Some(SupportedRpcService::ArbitrumOne(L2MainnetService::Alchemy)),
     },
@@ -151,9 +167,11 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: ARBITRUM_ONE_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://arbitrum.blockpi.network/v1/rpc/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://arbitrum.blockpi.network/v1/rpc/{API_KEY}"),
             },
-            public_url: Some("https://arbitrum.public.blockpi.network/v1/rpc/public"),
+            public_url: Some(Cow::Borrowed(
+                "https://arbitrum.public.blockpi.network/v1/rpc/public",
+            )),
         },
         alias: Some(SupportedRpcService::ArbitrumOne(L2MainnetService::BlockPi)),
     },
@@ -161,7 +179,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 13,
         chain_id: ARBITRUM_ONE_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://arbitrum-one-rpc.publicnode.com",
+            public_url: Cow::Borrowed("https://arbitrum-one-rpc.publicnode.com"),
         },
         alias: Some(SupportedRpcService::ArbitrumOne(
             L2MainnetService::PublicNode,
@@ -172,9 +190,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: BASE_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://rpc.ankr.com/base/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/base/{API_KEY}"),
             },
-            public_url: Some("https://rpc.ankr.com/base"),
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/base")),
         },
         alias: Some(SupportedRpcService::BaseMainnet(L2MainnetService::Ankr)),
     },
@@ -183,9 +201,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: BASE_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://base-mainnet.g.alchemy.com/v2",
+                url: Cow::Borrowed("https://base-mainnet.g.alchemy.com/v2"),
             },
-            public_url: Some("https://base-mainnet.g.alchemy.com/v2/demo"),
+            public_url: Some(Cow::Borrowed("https://base-mainnet.g.alchemy.com/v2/demo")),
         },
         alias: Some(SupportedRpcService::BaseMainnet(L2MainnetService::Alchemy)),
     },
@@ -194,9 +212,11 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: BASE_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://base.blockpi.network/v1/rpc/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://base.blockpi.network/v1/rpc/{API_KEY}"),
             },
-            public_url: Some("https://base.public.blockpi.network/v1/rpc/public"),
+            public_url: Some(Cow::Borrowed(
+                "https://base.public.blockpi.network/v1/rpc/public",
+            )),
         },
         alias: Some(SupportedRpcService::BaseMainnet(L2MainnetService::BlockPi)),
     },
@@ -204,7 +224,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 17,
         chain_id: BASE_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://base-rpc.publicnode.com",
+            public_url: Cow::Borrowed("https://base-rpc.publicnode.com"),
         },
         alias: Some(SupportedRpcService::BaseMainnet(
             L2MainnetService::PublicNode,
@@ -215,9 +235,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: OPTIMISM_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://rpc.ankr.com/optimism/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/optimism/{API_KEY}"),
             },
-            public_url: Some("https://rpc.ankr.com/optimism"),
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/optimism")),
         },
         alias: Some(SupportedRpcService::OptimismMainnet(L2MainnetService::Ankr)),
     },
@@ -226,9 +246,9 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: OPTIMISM_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::BearerToken {
-                url: "https://opt-mainnet.g.alchemy.com/v2",
+                url: Cow::Borrowed("https://opt-mainnet.g.alchemy.com/v2"),
             },
-            public_url: Some("https://opt-mainnet.g.alchemy.com/v2/demo"),
+            public_url: Some(Cow::Borrowed("https://opt-mainnet.g.alchemy.com/v2/demo")),
         },
         alias: Some(SupportedRpcService::OptimismMainnet(
             L2MainnetService::Alchemy,
@@ -239,9 +259,11 @@ pub const PROVIDERS: &[Provider] = &[
         chain_id: OPTIMISM_MAINNET_CHAIN_ID,
         access: RpcAccess::Authenticated {
             auth: RpcAuth::UrlParameter {
-                url_pattern: "https://optimism.blockpi.network/v1/rpc/{API_KEY}",
+                url_pattern: Cow::Borrowed("https://optimism.blockpi.network/v1/rpc/{API_KEY}"),
             },
-            public_url: Some("https://optimism.public.blockpi.network/v1/rpc/public"),
+            public_url: Some(Cow::Borrowed(
+                "https://optimism.public.blockpi.network/v1/rpc/public",
+            )),
         },
         alias: Some(SupportedRpcService::OptimismMainnet(
             L2MainnetService::BlockPi,
@@ -251,7 +273,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 21,
         chain_id: OPTIMISM_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://optimism-rpc.publicnode.com",
+            public_url: Cow::Borrowed("https://optimism-rpc.publicnode.com"),
         },
         alias: Some(SupportedRpcService::OptimismMainnet(
             L2MainnetService::PublicNode,
@@ -261,7 +283,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 22,
         chain_id: ETH_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://eth.llamarpc.com",
+            public_url: Cow::Borrowed("https://eth.llamarpc.com"),
         },
         alias: Some(SupportedRpcService::EthMainnet(EthMainnetService::Llama)),
     },
@@ -269,7 +291,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 23,
         chain_id: ARBITRUM_ONE_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://arbitrum.llamarpc.com",
+            public_url: Cow::Borrowed("https://arbitrum.llamarpc.com"),
         },
         alias: Some(SupportedRpcService::ArbitrumOne(L2MainnetService::Llama)),
     },
@@ -277,7 +299,7 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 24,
         chain_id: BASE_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://base.llamarpc.com",
+            public_url: Cow::Borrowed("https://base.llamarpc.com"),
         },
         alias: Some(SupportedRpcService::BaseMainnet(L2MainnetService::Llama)),
     },
@@ -285,27 +307,285 @@ pub const PROVIDERS: &[Provider] = &[
         provider_id: 25,
         chain_id: OPTIMISM_MAINNET_CHAIN_ID,
         access: RpcAccess::Unauthenticated {
-            public_url: "https://optimism.llamarpc.com",
+            public_url: Cow::Borrowed("https://optimism.llamarpc.com"),
         },
         alias: Some(SupportedRpcService::OptimismMainnet(
             L2MainnetService::Llama,
         )),
     },
+    Provider {
+        provider_id: 26,
+        chain_id: ETH_HOODI_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::UrlParameter {
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/eth_hoodi/{API_KEY}"),
+            },
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/eth_hoodi")),
+        },
+        alias: Some(SupportedRpcService::EthHoodi(EthHoodiService::Ankr)),
+    },
+    Provider {
+        provider_id: 27,
+        chain_id: ETH_HOODI_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::BearerToken {
+                url: Cow::Borrowed("https://eth-hoodi.g.alchemy.com/v2"),
+            },
+            public_url: Some(Cow::Borrowed("https://eth-hoodi.g.alchemy.com/v2/demo")),
+        },
+        alias: Some(SupportedRpcService::EthHoodi(EthHoodiService::Alchemy)),
+    },
+    Provider {
+        provider_id: 28,
+        chain_id: ETH_HOODI_CHAIN_ID,
+        access: RpcAccess::Unauthenticated {
+            public_url: Cow::Borrowed("https://ethereum-hoodi-rpc.publicnode.com"),
+        },
+        alias: Some(SupportedRpcService::EthHoodi(EthHoodiService::PublicNode)),
+    },
+    Provider {
+        provider_id: 29,
+        chain_id: ARBITRUM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::UrlParameter {
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/arbitrum_sepolia/{API_KEY}"),
+            },
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/arbitrum_sepolia")),
+        },
+        alias: Some(SupportedRpcService::ArbitrumSepolia(L2TestnetService::Ankr)),
+    },
+    Provider {
+        provider_id: 30,
+        chain_id: ARBITRUM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::BearerToken {
+                url: Cow::Borrowed("https://arb-sepolia.g.alchemy.com/v2"),
+            },
+            public_url: Some(Cow::Borrowed("https://arb-sepolia.g.alchemy.com/v2/demo")),
+        },
+        alias: Some(SupportedRpcService::ArbitrumSepolia(
+            L2TestnetService::Alchemy,
+        )),
+    },
+    Provider {
+        provider_id: 31,
+        chain_id: ARBITRUM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Unauthenticated {
+            public_url: Cow::Borrowed("https://arbitrum-sepolia-rpc.publicnode.com"),
+        },
+        alias: Some(SupportedRpcService::ArbitrumSepolia(
+            L2TestnetService::PublicNode,
+        )),
+    },
+    Provider {
+        provider_id: 32,
+        chain_id: BASE_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::UrlParameter {
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/base_sepolia/{API_KEY}"),
+            },
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/base_sepolia")),
+        },
+        alias: Some(SupportedRpcService::BaseSepolia(L2TestnetService::Ankr)),
+    },
+    Provider {
+        provider_id: 33,
+        chain_id: BASE_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::BearerToken {
+                url: Cow::Borrowed("https://base-sepolia.g.alchemy.com/v2"),
+            },
+            public_url: Some(Cow::Borrowed("https://base-sepolia.g.alchemy.com/v2/demo")),
+        },
+        alias: Some(SupportedRpcService::BaseSepolia(L2TestnetService::Alchemy)),
+    },
+    Provider {
+        provider_id: 34,
+        chain_id: BASE_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Unauthenticated {
+            public_url: Cow::Borrowed("https://base-sepolia-rpc.publicnode.com"),
+        },
+        alias: Some(SupportedRpcService::BaseSepolia(
+            L2TestnetService::PublicNode,
+        )),
+    },
+    Provider {
+        provider_id: 35,
+        chain_id: OPTIMISM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::UrlParameter {
+                url_pattern: Cow::Borrowed("https://rpc.ankr.com/optimism_sepolia/{API_KEY}"),
+            },
+            public_url: Some(Cow::Borrowed("https://rpc.ankr.com/optimism_sepolia")),
+        },
+        alias: Some(SupportedRpcService::OptimismSepolia(L2TestnetService::Ankr)),
+    },
+    Provider {
+        provider_id: 36,
+        chain_id: OPTIMISM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Authenticated {
+            auth: RpcAuth::BearerToken {
+                url: Cow::Borrowed("https://opt-sepolia.g.alchemy.com/v2"),
+            },
+            public_url: Some(Cow::Borrowed("https://opt-sepolia.g.alchemy.com/v2/demo")),
+        },
+        alias: Some(SupportedRpcService::OptimismSepolia(
+            L2TestnetService::Alchemy,
+        )),
+    },
+    Provider {
+        provider_id: 37,
+        chain_id: OPTIMISM_SEPOLIA_CHAIN_ID,
+        access: RpcAccess::Unauthenticated {
+            public_url: Cow::Borrowed("https://optimism-sepolia-rpc.publicnode.com"),
+        },
+        alias: Some(SupportedRpcService::OptimismSepolia(
+            L2TestnetService::PublicNode,
+        )),
+    },
 ];
 
+/// Providers added by the controllers have IDs starting from this ID, so that they never collide
+/// with the IDs of providers added to [`PROVIDERS`] in later versions.
+pub const FIRST_RUNTIME_PROVIDER_ID: ProviderId = 1_000_000;
+
 thread_local! {
-    pub static PROVIDER_MAP: BTreeMap<ProviderId, Provider> =
-        PROVIDERS.iter()
-            .map(|provider| (provider.provider_id, provider.clone())).collect();
+    /// Provider of each supported RPC service, derived from the provider registry.
+    pub static SERVICE_PROVIDER_MAP: RefCell<BTreeMap<SupportedRpcService, ProviderId>> =
+        RefCell::new(service_provider_map());
+}
 
-    pub static SERVICE_PROVIDER_MAP: BTreeMap<SupportedRpcService, ProviderId> =
-        PROVIDERS.iter()
-            .filter_map(|provider| Some((provider.alias?, provider.provider_id)))
-            .collect();
+fn service_provider_map() -> BTreeMap<SupportedRpcService, ProviderId> {
+    get_providers()
+        .into_iter()
+        .filter_map(|provider| Some((provider.alias?, provider.provider_id)))
+        .collect()
 }
 
-pub fn find_provider(f: impl Fn(&Provider) -> bool) -> Option<&'static Provider> {
-    PROVIDERS.iter().find(|&provider| f(provider))
+pub fn find_provider(f: impl Fn(&Provider) -> bool) -> Option<Provider> {
+    get_providers().into_iter().find(|provider| f(provider))
+}
+
+/// Add a new provider to the provider registry and return its ID.
+pub fn add_provider(args: ProviderArgs) -> Result<ProviderId, String> {
+    let provider_id = memory::next_provider_id();
+    let provider = Provider::try_from_args(provider_id, args)?;
+    validate_provider(&provider)?;
+    insert_provider(provider);
+    SERVICE_PROVIDER_MAP.set(service_provider_map());
+    Ok(provider_id)
+}
+
+/// Replace an existing provider in the provider registry.
+pub fn update_provider(provider_id: ProviderId, args: ProviderArgs) -> Result<(), String> {
+    if get_provider(provider_id).is_none() {
+        return Err(format!("Provider not found: {}", provider_id));
+    }
+    let provider = Provider::try_from_args(provider_id, args)?;
+    validate_provider(&provider)?;
+    if let RpcAccess::Unauthenticated { .. } = provider.access {
+        remove_api_key(provider_id);
+    }
+    insert_provider(provider);
+    SERVICE_PROVIDER_MAP.set(service_provider_map());
+    Ok(())
+}
+
+/// Remove a provider and its API key.
+pub fn remove_provider(provider_id: ProviderId) -> Result<(), String> {
+    memory::remove_provider(provider_id)
+        .ok_or_else(|| format!("Provider not found: {}", provider_id))?;
+    remove_api_key(provider_id);
+    SERVICE_PROVIDER_MAP.set(service_provider_map());
+    Ok(())
+}
+
+/// Add or replace a chain in the chain registry.
+pub fn set_chain(chain: Chain) -> Result<(), String> {
+    if chain.name.trim().is_empty() {
+        return Err("Chain name must not be empty".to_string());
+    }
+    for provider_id in &chain.default_providers {
+        let provider = get_provider(*provider_id)
+            .ok_or_else(|| format!("Provider not found: {}", provider_id))?;
+        if provider.chain_id != chain.chain_id {
+            return Err(format!(
+                "Provider {} is not a provider of chain {}",
+                provider_id, chain.chain_id
+            ));
+        }
+    }
+    insert_chain(chain);
+    Ok(())
+}
+
+/// Remove a chain from the chain registry.
+pub fn remove_chain(chain_id: u64) -> Result<(), String> {
+    memory::remove_chain(chain_id)
+        .map(|_| ())
+        .ok_or_else(|| format!("Chain not found: {}", chain_id))
+}
+
+fn validate_provider(provider: &Provider) -> Result<(), String> {
+    fn validate_url(url: &str, is_url_pattern: bool) -> Result<(), String> {
+        if url.contains(API_KEY_REPLACE_STRING) != is_url_pattern {
+            return Err(if is_url_pattern {
+                format!("Missing {} in URL pattern: {}", API_KEY_REPLACE_STRING, url)
+            } else {
+                format!("Unexpected {} in URL: {}", API_KEY_REPLACE_STRING, url)
+            });
+        }
+        let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL {}: {}", url, e))?;
+        if parsed.scheme() != "https" {
+            return Err(format!("URL must use HTTPS: {}", url));
+        }
+        if hostname_from_url(url).is_none() {
+            return Err(format!("URL must have a domain name as host: {}", url));
+        }
+        Ok(())
+    }
+
+    match &provider.access {
+        RpcAccess::Authenticated { auth, public_url } => {
+            match auth {
+                RpcAuth::BearerToken { url } => validate_url(url, false)?,
+                RpcAuth::UrlParameter { url_pattern } => validate_url(url_pattern, true)?,
+                RpcAuth::Header { url, header_name } => {
+                    validate_url(url, false)?;
+                    let header_name = http::HeaderName::from_bytes(header_name.as_bytes())
+                        .map_err(|e| format!("Invalid header name {}: {}", header_name, e))?;
+                    // Headers set by the canister or the HTTP outcall itself
+                    let reserved = [CONTENT_TYPE_HEADER_LOWERCASE, "host", "authorization"];
+                    if reserved.contains(&header_name.as_str()) {
+                        return Err(format!("Reserved header name: {}", header_name));
+                    }
+                }
+                RpcAuth::BasicAuth { url } => validate_url(url, false)?,
+            }
+            if let Some(public_url) = public_url {
+                validate_url(public_url, false)?;
+            }
+        }
+        RpcAccess::Unauthenticated { public_url } => validate_url(public_url, false)?,
+    }
+
+    if let Some(alias) = provider.alias {
+        let alias_chain_id = get_known_chain_id(&RpcService::from(alias));
+        if alias_chain_id != Some(provider.chain_id) {
+            return Err(format!(
+                "Chain ID {} does not match the chain of {:?}",
+                provider.chain_id, alias
+            ));
+        }
+        let used_by_other_provider = SERVICE_PROVIDER_MAP.with_borrow(|map| {
+            map.get(&alias)
+                .is_some_and(|provider_id| provider_id != &provider.provider_id)
+        });
+        if used_by_other_provider {
+            return Err(format!("{:?} is already used by another provider", alias));
+        }
+    }
+    Ok(())
 }
 
 pub fn get_known_chain_id(service: &RpcService) -> Option<u64> {
@@ -317,19 +597,18 @@ pub fn get_known_chain_id(service: &RpcService) -> Option<u64> {
         RpcService::ArbitrumOne(_) => Some(ARBITRUM_ONE_CHAIN_ID),
         RpcService::BaseMainnet(_) => Some(BASE_MAINNET_CHAIN_ID),
         RpcService::OptimismMainnet(_) => Some(OPTIMISM_MAINNET_CHAIN_ID),
+        RpcService::EthHoodi(_) => Some(ETH_HOODI_CHAIN_ID),
+        RpcService::ArbitrumSepolia(_) => Some(ARBITRUM_SEPOLIA_CHAIN_ID),
+        RpcService::BaseSepolia(_) => Some(BASE_SEPOLIA_CHAIN_ID),
+        RpcService::OptimismSepolia(_) => Some(OPTIMISM_SEPOLIA_CHAIN_ID),
     }
 }
 
 pub fn resolve_rpc_service(service: RpcService) -> Result<ResolvedRpcService, ProviderError> {
     Ok(match service {
-        RpcService::Provider(id) => ResolvedRpcService::Provider({
-            PROVIDER_MAP.with(|provider_map| {
-                provider_map
-                    .get(&id)
-                    .cloned()
-                    .ok_or(ProviderError::ProviderNotFound)
-            })?
-        }),
+        RpcService::Provider(id) => {
+            ResolvedRpcService::Provider(get_provider(id).ok_or(ProviderError::ProviderNotFound)?)
+        }
         RpcService::Custom(RpcApi { url, headers }) => {
             ResolvedRpcService::Api(RpcApi { url, headers })
         }
@@ -348,34 +627,47 @@ pub fn resolve_rpc_service(service: RpcService) -> Result<ResolvedRpcService, Pr
         RpcService::OptimismMainnet(service) => ResolvedRpcService::Provider(
             lookup_provider_for_service(&SupportedRpcService::OptimismMainnet(service))?,
         ),
+        RpcService::EthHoodi(service) => ResolvedRpcService::Provider(lookup_provider_for_service(
+            &SupportedRpcService::EthHoodi(service),
+        )?),
+        RpcService::ArbitrumSepolia(service) => ResolvedRpcService::Provider(
+            lookup_provider_for_service(&SupportedRpcService::ArbitrumSepolia(service))?,
+        ),
+        RpcService::BaseSepolia(service) => ResolvedRpcService::Provider(
+            lookup_provider_for_service(&SupportedRpcService::BaseSepolia(service))?,
+        ),
+        RpcService::OptimismSepolia(service) => ResolvedRpcService::Provider(
+            lookup_provider_for_service(&SupportedRpcService::OptimismSepolia(service))?,
+        ),
     })
 }
 
 fn lookup_provider_for_service(service: &SupportedRpcService) -> Result<Provider, ProviderError> {
-    let provider_id = SERVICE_PROVIDER_MAP.with(|map| {
+    let provider_id = SERVICE_PROVIDER_MAP.with_borrow(|map| {
         map.get(service)
             .copied()
             .ok_or(ProviderError::MissingRequiredProvider)
     })?;
-    PROVIDER_MAP
-        .with(|map| map.get(&provider_id).cloned())
-        .ok_or(ProviderError::ProviderNotFound)
+    get_provider(provider_id).ok_or(ProviderError::ProviderNotFound)
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd, Serialize, Deserialize)]
 pub enum SupportedRpcService {
     EthMainnet(EthMainnetService),
     EthSepolia(EthSepoliaService),
     ArbitrumOne(L2MainnetService),
     BaseMainnet(L2MainnetService),
     OptimismMainnet(L2MainnetService),
+    EthHoodi(EthHoodiService),
+    ArbitrumSepolia(L2TestnetService),
+    BaseSepolia(L2TestnetService),
+    OptimismSepolia(L2TestnetService),
 }
 
 impl SupportedRpcService {
     pub fn new(service: &RpcService) -> Option<Self> {
         match service {
-            RpcService::Provider(id) => find_provider(|provider| &provider.provider_id == id)
-                .and_then(|provider| provider.alias),
+            RpcService::Provider(id) => get_provider(*id).and_then(|provider| provider.alias),
             RpcService::Custom(_) => None,
             RpcService::EthMainnet(service) => Some(SupportedRpcService::EthMainnet(*service)),
             RpcService::EthSepolia(service) => Some(SupportedRpcService::EthSepolia(*service)),
@@ -384,6 +676,14 @@ impl SupportedRpcService {
             RpcService::OptimismMainnet(service) => {
                 Some(SupportedRpcService::OptimismMainnet(*service))
             }
+            RpcService::EthHoodi(service) => Some(SupportedRpcService::EthHoodi(*service)),
+            RpcService::ArbitrumSepolia(service) => {
+                Some(SupportedRpcService::ArbitrumSepolia(*service))
+            }
+            RpcService::BaseSepolia(service) => Some(SupportedRpcService::BaseSepolia(*service)),
+            RpcService::OptimismSepolia(service) => {
+                Some(SupportedRpcService::OptimismSepolia(*service))
+            }
         }
     }
 
@@ -441,6 +741,38 @@ impl SupportedRpcService {
             SupportedRpcService::OptimismMainnet(L2MainnetService::Ankr),
         ]
     }
+
+    pub const fn eth_hoodi() -> &'static [SupportedRpcService] {
+        &[
+            SupportedRpcService::EthHoodi(EthHoodiService::PublicNode),
+            SupportedRpcService::EthHoodi(EthHoodiService::Ankr),
+            SupportedRpcService::EthHoodi(EthHoodiService::Alchemy),
+        ]
+    }
+
+    pub const fn arbitrum_sepolia() -> &'static [SupportedRpcService] {
+        &[
+            SupportedRpcService::ArbitrumSepolia(L2TestnetService::PublicNode),
+            SupportedRpcService::ArbitrumSepolia(L2TestnetService::Ankr),
+            SupportedRpcService::ArbitrumSepolia(L2TestnetService::Alchemy),
+        ]
+    }
+
+    pub const fn base_sepolia() -> &'static [SupportedRpcService] {
+        &[
+            SupportedRpcService::BaseSepolia(L2TestnetService::PublicNode),
+            SupportedRpcService::BaseSepolia(L2TestnetService::Ankr),
+            SupportedRpcService::BaseSepolia(L2TestnetService::Alchemy),
+        ]
+    }
+
+    pub const fn optimism_sepolia() -> &'static [SupportedRpcService] {
+        &[
+            SupportedRpcService::OptimismSepolia(L2TestnetService::PublicNode),
+            SupportedRpcService::OptimismSepolia(L2TestnetService::Ankr),
+            SupportedRpcService::OptimismSepolia(L2TestnetService::Alchemy),
+        ]
+    }
 }
 
 impl From<SupportedRpcService> for RpcService {
@@ -451,13 +783,81 @@ impl From<SupportedRpcService> for RpcService {
             SupportedRpcService::ArbitrumOne(service) => RpcService::ArbitrumOne(service),
             SupportedRpcService::BaseMainnet(service) => RpcService::BaseMainnet(service),
             SupportedRpcService::OptimismMainnet(service) => RpcService::OptimismMainnet(service),
+            SupportedRpcService::EthHoodi(service) => RpcService::EthHoodi(service),
+            SupportedRpcService::ArbitrumSepolia(service) => RpcService::ArbitrumSepolia(service),
+            SupportedRpcService::BaseSepolia(service) => RpcService::BaseSepolia(service),
+            SupportedRpcService::OptimismSepolia(service) => RpcService::OptimismSepolia(service),
         }
     }
 }
 
-/// Record when a supported RPC service was used.
+/// Outcome of a call to a supported RPC service.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Ord, PartialOrd)]
+pub enum RpcServiceOutcome {
+    /// The provider returned a successful result.
+    Ok,
+    /// The HTTPS outcall failed or the HTTP response was not a valid JSON-RPC response.
+    HttpError,
+    /// The provider returned a JSON-RPC error.
+    JsonRpcError,
+    /// The response exceeded the maximum response size.
+    ResponseTooLarge,
+    /// The provider returned a successful result that differed from the result of the majority.
+    Disagreement,
+}
+
+impl RpcServiceOutcome {
+    /// Classify an error returned by a provider.
+    ///
+    /// Returns `None` if the error is not attributable to the provider (e.g. too few cycles).
+    pub fn from_error(error: &RpcError) -> Option<Self> {
+        match error {
+            RpcError::HttpOutcallError(_) if is_response_too_large_error(error) => {
+                Some(RpcServiceOutcome::ResponseTooLarge)
+            }
+            RpcError::HttpOutcallError(_) => Some(RpcServiceOutcome::HttpError),
+            RpcError::JsonRpcError(_) => Some(RpcServiceOutcome::JsonRpcError),
+            RpcError::ProviderError(_) | RpcError::ValidationError(_) => None,
+        }
+    }
+}
+
+/// Health of a supported RPC service, computed from the outcomes of its recent calls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RpcServiceHealth {
+    pub ok: usize,
+    pub http_errors: usize,
+    pub json_rpc_errors: usize,
+    pub response_too_large: usize,
+    pub disagreements: usize,
+}
+
+impl RpcServiceHealth {
+    const MIN_DISAGREEMENTS_FOR_EXCLUSION: usize = 3;
+
+    /// Number of successful results minus the number of failures,
+    /// where disagreeing with the majority weighs twice as much as an error.
+    pub fn score(&self) -> i64 {
+        let errors = self.http_errors + self.json_rpc_errors + self.response_too_large;
+        self.ok as i64 - errors as i64 - 2 * self.disagreements as i64
+    }
+
+    /// Whether the provider keeps disagreeing with the majority of providers,
+    /// i.e., at least half of its successful results differed from the majority.
+    ///
+    /// Excluded providers are not chosen as default providers, unless not enough other providers
+    /// are available.
+    pub fn is_excluded(&self) -> bool {
+        self.disagreements >= Self::MIN_DISAGREEMENTS_FOR_EXCLUSION
+            && 2 * self.disagreements >= self.ok
+    }
+}
+
+/// Record the outcomes of calls to supported RPC services.
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct SupportedRpcServiceUsage(TimedSizedMap<SupportedRpcService, ()>);
+pub struct SupportedRpcServiceUsage(
+    BTreeMap<RpcServiceOutcome, TimedSizedMap<SupportedRpcService, ()>>,
+);
 
 impl Default for SupportedRpcServiceUsage {
     fn default() -> Self {
@@ -467,29 +867,302 @@ impl Default for SupportedRpcServiceUsage {
 
 impl SupportedRpcServiceUsage {
     pub fn new() -> SupportedRpcServiceUsage {
-        Self(TimedSizedMap::new(
-            Duration::from_secs(20 * 60),
-            NonZeroUsize::new(500).unwrap(),
-        ))
+        Self(BTreeMap::new())
     }
 
     pub fn record_evict(&mut self, service: SupportedRpcService, now: Timestamp) {
-        self.0.insert_evict(now, service, ());
+        self.record_outcome_evict(service, RpcServiceOutcome::Ok, now);
+    }
+
+    pub fn record_outcome_evict(
+        &mut self,
+        service: SupportedRpcService,
+        outcome: RpcServiceOutcome,
+        now: Timestamp,
+    ) {
+        self.0
+            .entry(outcome)
+            .or_insert_with(|| {
+                TimedSizedMap::new(
+                    Duration::from_secs(20 * 60),
+                    NonZeroUsize::new(500).unwrap(),
+                )
+            })
+            .insert_evict(now, service, ());
+    }
+
+    pub fn health_evict(
+        &mut self,
+        services: &[SupportedRpcService],
+        now: Timestamp,
+    ) -> Vec<RpcServiceHealth> {
+        self.0.values_mut().for_each(|outcomes| {
+            outcomes.evict_expired(services, now);
+        });
+        services
+            .iter()
+            .map(|service| {
+                let count = |outcome: RpcServiceOutcome| {
+                    self.0
+                        .get(&outcome)
+                        .and_then(|outcomes| outcomes.get(service))
+                        .map(|timestamps| timestamps.len())
+                        .unwrap_or_default()
+                };
+                RpcServiceHealth {
+                    ok: count(RpcServiceOutcome::Ok),
+                    http_errors: count(RpcServiceOutcome::HttpError),
+                    json_rpc_errors: count(RpcServiceOutcome::JsonRpcError),
+                    response_too_large: count(RpcServiceOutcome::ResponseTooLarge),
+                    disagreements: count(RpcServiceOutcome::Disagreement),
+                }
+            })
+            .collect()
     }
 
+    /// Rank the given services from the healthiest to the least healthy one.
+    ///
+    /// Services with the same health keep their relative order.
     pub fn rank_ascending_evict(
         &mut self,
         services: &[SupportedRpcService],
         now: Timestamp,
     ) -> Vec<SupportedRpcService> {
-        fn ascending_num_elements<V>(values: Option<&TimedSizedVec<V>>) -> impl Ord {
-            std::cmp::Reverse(values.map(|v| v.len()).unwrap_or_default())
+        let health = self.health_evict(services, now);
+        let mut ranked: Vec<_> = services.iter().copied().zip(health).collect();
+        ranked.sort_by_key(|(_service, health)| {
+            (health.is_excluded(), std::cmp::Reverse(health.score()))
+        });
+        ranked
+            .into_iter()
+            .map(|(service, _health)| service)
+            .collect()
+    }
+
+    /// Remove the excluded services, keeping the order of the other services, unless fewer than
+    /// `num_required` services would remain, in which case all services are kept.
+    pub fn retain_included_evict(
+        &mut self,
+        services: Vec<SupportedRpcService>,
+        num_required: usize,
+        now: Timestamp,
+    ) -> Vec<SupportedRpcService> {
+        let health = self.health_evict(&services, now);
+        let included: Vec<_> = services
+            .iter()
+            .zip(health)
+            .filter(|(_service, health)| !health.is_excluded())
+            .map(|(service, _health)| *service)
+            .collect();
+        if included.len() >= num_required {
+            included
+        } else {
+            services
+        }
+    }
+}
+
+/// Circuit breakers of the supported RPC services.
+///
+/// The circuit breaker of a service opens after [`CircuitBreakers::FAILURE_THRESHOLD`] consecutive
+/// HTTP failures, so that the service is not chosen as default provider during the cool-down period.
+/// Once the cool-down period is over, the circuit breaker is half-open and lets a single call
+/// through, whose outcome decides whether it closes again or opens for another cool-down period.
+/// Until the outcome of that call is recorded, or at most for [`CircuitBreakers::PROBE_TIMEOUT`],
+/// the service is not chosen by other calls.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CircuitBreakers {
+    consecutive_failures: BTreeMap<SupportedRpcService, u32>,
+    tripped: BTreeSet<SupportedRpcService>,
+    open: TimedSizedMap<SupportedRpcService, ()>,
+    probing: BTreeSet<SupportedRpcService>,
+    probes: TimedSizedMap<SupportedRpcService, ()>,
+}
+
+impl Default for CircuitBreakers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CircuitBreakers {
+    pub const FAILURE_THRESHOLD: u32 = 5;
+    pub const COOL_DOWN: Duration = Duration::from_secs(5 * 60);
+    /// Time after which a call probing a half-open service is assumed to have been lost,
+    /// e.g. because its outcome was not recorded.
+    pub const PROBE_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new() -> CircuitBreakers {
+        Self {
+            consecutive_failures: BTreeMap::new(),
+            tripped: BTreeSet::new(),
+            open: TimedSizedMap::new(Self::COOL_DOWN, NonZeroUsize::new(500).unwrap()),
+            probing: BTreeSet::new(),
+            probes: TimedSizedMap::new(Self::PROBE_TIMEOUT, NonZeroUsize::new(500).unwrap()),
+        }
+    }
+
+    /// Record the outcome of a call to the given service.
+    ///
+    /// Returns `true` if the circuit breaker of the service opened as a result.
+    pub fn record_outcome_evict(
+        &mut self,
+        service: SupportedRpcService,
+        outcome: RpcServiceOutcome,
+        now: Timestamp,
+    ) -> bool {
+        self.probing.remove(&service);
+        match outcome {
+            RpcServiceOutcome::Ok | RpcServiceOutcome::Disagreement => {
+                self.consecutive_failures.remove(&service);
+                self.tripped.remove(&service);
+                false
+            }
+            RpcServiceOutcome::HttpError => {
+                let failures = self.consecutive_failures.entry(service).or_default();
+                *failures += 1;
+                let failures = *failures;
+                let should_open = match self.state_evict(service, now) {
+                    CircuitBreakerState::Closed => failures >= Self::FAILURE_THRESHOLD,
+                    CircuitBreakerState::HalfOpen => true,
+                    // Calls started before the circuit breaker opened
+                    CircuitBreakerState::Open => false,
+                };
+                if should_open {
+                    self.tripped.insert(service);
+                    self.open.insert_evict(now, service, ());
+                }
+                should_open
+            }
+            // Not caused by the provider being unavailable
+            RpcServiceOutcome::JsonRpcError | RpcServiceOutcome::ResponseTooLarge => false,
+        }
+    }
+
+    pub fn state_evict(
+        &mut self,
+        service: SupportedRpcService,
+        now: Timestamp,
+    ) -> CircuitBreakerState {
+        if !self.tripped.contains(&service) {
+            return CircuitBreakerState::Closed;
+        }
+        self.open.evict_expired(&[service], now);
+        let num_open = self
+            .open
+            .get(&service)
+            .map(|timestamps| timestamps.len())
+            .unwrap_or_default();
+        if num_open > 0 {
+            CircuitBreakerState::Open
+        } else {
+            CircuitBreakerState::HalfOpen
+        }
+    }
+
+    /// Remove the services whose circuit breaker is open, or half-open with a call already probing
+    /// the service, keeping the order of the other services.
+    pub fn retain_available_evict(
+        &mut self,
+        services: Vec<SupportedRpcService>,
+        now: Timestamp,
+    ) -> Vec<SupportedRpcService> {
+        services
+            .into_iter()
+            .filter(|service| match self.state_evict(*service, now) {
+                CircuitBreakerState::Closed => true,
+                CircuitBreakerState::HalfOpen => !self.is_probing_evict(*service, now),
+                CircuitBreakerState::Open => false,
+            })
+            .collect()
+    }
+
+    /// Record that a call probing the given service is made if its circuit breaker is half-open,
+    /// so that the service is not chosen by other calls until the outcome of the call is recorded.
+    pub fn start_probe_evict(&mut self, service: SupportedRpcService, now: Timestamp) {
+        if self.state_evict(service, now) == CircuitBreakerState::HalfOpen
+            && !self.is_probing_evict(service, now)
+        {
+            self.probing.insert(service);
+            self.probes.insert_evict(now, service, ());
         }
+    }
 
-        self.0.evict_expired(services, now);
-        self.0
-            .sort_keys_by(services, ascending_num_elements)
+    fn is_probing_evict(&mut self, service: SupportedRpcService, now: Timestamp) -> bool {
+        if !self.probing.contains(&service) {
+            return false;
+        }
+        self.probes.evict_expired(&[service], now);
+        let num_probes = self
+            .probes
+            .get(&service)
+            .map(|timestamps| timestamps.len())
+            .unwrap_or_default();
+        num_probes > 0
+    }
+}
+
+/// Rotation of the API keys of each provider.
+///
+/// The API keys of a provider are used in a round-robin fashion. An API key that was rate limited
+/// by the provider (HTTP 429) is skipped until all API keys of the provider were rate limited,
+/// in which case all API keys are used again.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ApiKeyRotations {
+    last_used: BTreeMap<ProviderId, ApiKeyId>,
+    rate_limited: BTreeSet<ApiKeyRef>,
+}
+
+impl ApiKeyRotations {
+    /// Choose the API key to use for the next request to the given provider among the given
+    /// API keys, sorted by ID.
+    ///
+    /// The rotation only advances once a request is sent with the chosen API key,
+    /// see [`ApiKeyRotations::record_request`].
+    pub fn select(&self, provider_id: ProviderId, key_ids: &[ApiKeyId]) -> Option<ApiKeyId> {
+        let is_rate_limited = |key_id: &ApiKeyId| {
+            self.rate_limited.contains(&ApiKeyRef {
+                provider_id,
+                key_id: *key_id,
+            })
+        };
+        let all_rate_limited = key_ids.iter().all(is_rate_limited);
+        let last_used = self.last_used.get(&provider_id);
+        let (not_yet_used, used): (Vec<_>, Vec<_>) = key_ids
+            .iter()
+            .partition(|key_id| last_used.is_none_or(|last_used| key_id > &last_used));
+        not_yet_used
+            .into_iter()
+            .chain(used)
+            .find(|key_id| all_rate_limited || !is_rate_limited(key_id))
             .copied()
-            .collect()
+    }
+
+    /// Record that a request was sent with the given API key.
+    pub fn record_request(&mut self, api_key: ApiKeyRef) {
+        self.last_used.insert(api_key.provider_id, api_key.key_id);
+    }
+
+    /// Record that the provider responded with the given HTTP status code to a request sent with
+    /// the given API key.
+    ///
+    /// Returns `true` if the API key was rate limited as a result.
+    pub fn record_status(&mut self, api_key: ApiKeyRef, status: u16) -> bool {
+        if status == http::StatusCode::TOO_MANY_REQUESTS.as_u16() {
+            self.rate_limited.insert(api_key)
+        } else {
+            if http::StatusCode::from_u16(status).is_ok_and(|status| status.is_success()) {
+                self.rate_limited.remove(&api_key);
+            }
+            false
+        }
+    }
+
+    pub fn is_rate_limited(&self, api_key: &ApiKeyRef) -> bool {
+        self.rate_limited.contains(api_key)
+    }
+
+    pub fn forget(&mut self, api_key: &ApiKeyRef) {
+        self.rate_limited.remove(api_key);
     }
 }
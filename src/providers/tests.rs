@@ -34,7 +34,9 @@ mod static_map {
             match &provider.access {
                 RpcAccess::Authenticated { auth, public_url } => {
                     match auth {
-                        RpcAuth::BearerToken { url } => assert_not_url_pattern(url, provider),
+                        RpcAuth::BearerToken { url }
+                        | RpcAuth::Header { url, .. }
+                        | RpcAuth::BasicAuth { url } => assert_not_url_pattern(url, provider),
                         RpcAuth::UrlParameter { url_pattern } => {
                             assert_url_pattern(url_pattern, provider)
                         }
@@ -52,7 +54,7 @@ mod static_map {
 
     #[test]
     fn test_no_duplicate_service_providers() {
-        SERVICE_PROVIDER_MAP.with(|map| {
+        SERVICE_PROVIDER_MAP.with_borrow(|map| {
             assert_eq!(
                 map.len(),
                 map.keys().collect::<BTreeSet<_>>().len(),
@@ -68,7 +70,7 @@ mod static_map {
 
     #[test]
     fn test_service_provider_coverage() {
-        SERVICE_PROVIDER_MAP.with(|map| {
+        SERVICE_PROVIDER_MAP.with_borrow(|map| {
             let inverse_map: HashMap<_, _> = map.iter().map(|(k, v)| (v, k)).collect();
             for provider in PROVIDERS {
                 assert!(
@@ -83,7 +85,9 @@ mod static_map {
 
 mod supported_rpc_service {
     use crate::providers::SupportedRpcService;
-    use evm_rpc_types::{EthMainnetService, EthSepoliaService, L2MainnetService};
+    use evm_rpc_types::{
+        EthHoodiService, EthMainnetService, EthSepoliaService, L2MainnetService, L2TestnetService,
+    };
     use std::collections::BTreeSet;
 
     #[test]
@@ -136,11 +140,45 @@ mod supported_rpc_service {
                 .map(SupportedRpcService::OptimismMainnet),
             SupportedRpcService::optimism_mainnet(),
         );
+
+        assert_same_set(
+            EthHoodiService::all()
+                .iter()
+                .copied()
+                .map(SupportedRpcService::EthHoodi),
+            SupportedRpcService::eth_hoodi(),
+        );
+
+        assert_same_set(
+            L2TestnetService::all()
+                .iter()
+                .copied()
+                .map(SupportedRpcService::ArbitrumSepolia),
+            SupportedRpcService::arbitrum_sepolia(),
+        );
+
+        assert_same_set(
+            L2TestnetService::all()
+                .iter()
+                .copied()
+                .map(SupportedRpcService::BaseSepolia),
+            SupportedRpcService::base_sepolia(),
+        );
+
+        assert_same_set(
+            L2TestnetService::all()
+                .iter()
+                .copied()
+                .map(SupportedRpcService::OptimismSepolia),
+            SupportedRpcService::optimism_sepolia(),
+        );
     }
 }
 
 mod supported_rpc_service_usage {
-    use crate::providers::{SupportedRpcService, SupportedRpcServiceUsage};
+    use crate::providers::{
+        RpcServiceHealth, RpcServiceOutcome, SupportedRpcService, SupportedRpcServiceUsage,
+    };
     use canhttp::multi::Timestamp;
     use std::time::Duration;
 
@@ -209,13 +247,648 @@ mod supported_rpc_service_usage {
         }
     }
 
-    fn all_supported_providers() -> [&'static [SupportedRpcService]; 5] {
+    #[test]
+    fn should_rank_providers_with_errors_last() {
+        let mut usage = SupportedRpcServiceUsage::default();
+        let now = Timestamp::UNIX_EPOCH;
+        for supported_providers in all_supported_providers() {
+            usage.record_outcome_evict(supported_providers[0], RpcServiceOutcome::HttpError, now);
+            usage.record_outcome_evict(supported_providers[0], RpcServiceOutcome::HttpError, now);
+            usage.record_outcome_evict(
+                supported_providers[1],
+                RpcServiceOutcome::ResponseTooLarge,
+                now,
+            );
+        }
+
+        for supported_providers in all_supported_providers() {
+            let ordered = usage.rank_ascending_evict(supported_providers, now);
+            let expected_order = {
+                let mut expected = supported_providers[2..].to_vec();
+                expected.extend([supported_providers[1], supported_providers[0]]);
+                expected
+            };
+            assert_eq!(ordered, expected_order);
+        }
+    }
+
+    #[test]
+    fn should_exclude_providers_that_keep_disagreeing() {
+        let mut usage = SupportedRpcServiceUsage::default();
+        let now = Timestamp::UNIX_EPOCH;
+        for supported_providers in all_supported_providers() {
+            // Many errors but no disagreement
+            for _ in 0..10 {
+                usage.record_outcome_evict(
+                    supported_providers[0],
+                    RpcServiceOutcome::JsonRpcError,
+                    now,
+                );
+            }
+            for _ in 0..3 {
+                usage.record_evict(supported_providers[1], now);
+                usage.record_outcome_evict(
+                    supported_providers[1],
+                    RpcServiceOutcome::Disagreement,
+                    now,
+                );
+            }
+        }
+
+        for supported_providers in all_supported_providers() {
+            let health = usage.health_evict(&supported_providers[..2], now);
+            assert_eq!(
+                health,
+                vec![
+                    RpcServiceHealth {
+                        json_rpc_errors: 10,
+                        ..Default::default()
+                    },
+                    RpcServiceHealth {
+                        ok: 3,
+                        disagreements: 3,
+                        ..Default::default()
+                    }
+                ]
+            );
+            assert!(!health[0].is_excluded());
+            assert!(health[1].is_excluded());
+
+            let ordered = usage.rank_ascending_evict(supported_providers, now);
+            assert_eq!(ordered.last(), Some(&supported_providers[1]));
+
+            let num_others = supported_providers.len() - 1;
+            assert_eq!(
+                usage.retain_included_evict(ordered.clone(), num_others, now),
+                ordered[..num_others]
+            );
+            // Excluded providers are kept if not enough other providers are available
+            assert_eq!(
+                usage.retain_included_evict(ordered.clone(), num_others + 1, now),
+                ordered
+            );
+        }
+    }
+
+    #[test]
+    fn should_not_exclude_providers_that_mostly_agree() {
+        let health = RpcServiceHealth {
+            ok: 7,
+            disagreements: 3,
+            ..Default::default()
+        };
+        assert!(!health.is_excluded());
+
+        let health = RpcServiceHealth {
+            ok: 1,
+            disagreements: 2,
+            ..Default::default()
+        };
+        assert!(!health.is_excluded());
+    }
+
+    fn all_supported_providers() -> [&'static [SupportedRpcService]; 9] {
         [
             SupportedRpcService::eth_mainnet(),
             SupportedRpcService::eth_sepolia(),
             SupportedRpcService::arbitrum_one(),
             SupportedRpcService::base_mainnet(),
             SupportedRpcService::optimism_mainnet(),
+            SupportedRpcService::eth_hoodi(),
+            SupportedRpcService::arbitrum_sepolia(),
+            SupportedRpcService::base_sepolia(),
+            SupportedRpcService::optimism_sepolia(),
         ]
     }
 }
+
+mod circuit_breakers {
+    use crate::providers::{CircuitBreakers, RpcServiceOutcome, SupportedRpcService};
+    use canhttp::multi::Timestamp;
+    use evm_rpc_types::{CircuitBreakerState, EthMainnetService};
+    use std::time::Duration;
+
+    const ANKR: SupportedRpcService = SupportedRpcService::EthMainnet(EthMainnetService::Ankr);
+    const LLAMA: SupportedRpcService = SupportedRpcService::EthMainnet(EthMainnetService::Llama);
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn should_open_after_consecutive_failures() {
+        let mut breakers = CircuitBreakers::default();
+        let now = Timestamp::UNIX_EPOCH;
+
+        for _ in 1..CircuitBreakers::FAILURE_THRESHOLD {
+            assert!(!breakers.record_outcome_evict(ANKR, RpcServiceOutcome::HttpError, now));
+        }
+        assert_eq!(breakers.state_evict(ANKR, now), CircuitBreakerState::Closed);
+
+        assert!(breakers.record_outcome_evict(ANKR, RpcServiceOutcome::HttpError, now));
+        assert_eq!(breakers.state_evict(ANKR, now), CircuitBreakerState::Open);
+        assert_eq!(
+            breakers.state_evict(LLAMA, now),
+            CircuitBreakerState::Closed
+        );
+    }
+
+    #[test]
+    fn should_reset_failures_after_success() {
+        let mut breakers = CircuitBreakers::default();
+        let now = Timestamp::UNIX_EPOCH;
+
+        for _ in 0..2 * CircuitBreakers::FAILURE_THRESHOLD {
+            breakers.record_outcome_evict(ANKR, RpcServiceOutcome::HttpError, now);
+            breakers.record_outcome_evict(ANKR, RpcServiceOutcome::JsonRpcError, now);
+            breakers.record_outcome_evict(ANKR, RpcServiceOutcome::Ok, now);
+        }
+
+        assert_eq!(breakers.state_evict(ANKR, now), CircuitBreakerState::Closed);
+    }
+
+    #[test]
+    fn should_probe_half_open_after_cool_down() {
+        let mut breakers = tripped(ANKR);
+        let after_cool_down = Timestamp::from_unix_epoch(6 * MINUTE);
+        assert_eq!(
+            breakers.state_evict(ANKR, after_cool_down),
+            CircuitBreakerState::HalfOpen
+        );
+
+        // A single failure re-opens the circuit breaker
+        assert!(breakers.record_outcome_evict(ANKR, RpcServiceOutcome::HttpError, after_cool_down));
+        assert_eq!(
+            breakers.state_evict(ANKR, after_cool_down),
+            CircuitBreakerState::Open
+        );
+
+        let mut breakers = tripped(ANKR);
+        breakers.record_outcome_evict(ANKR, RpcServiceOutcome::Ok, after_cool_down);
+        assert_eq!(
+            breakers.state_evict(ANKR, after_cool_down),
+            CircuitBreakerState::Closed
+        );
+    }
+
+    #[test]
+    fn should_let_a_single_call_probe_half_open_provider() {
+        let mut breakers = tripped(ANKR);
+        let providers = vec![ANKR, LLAMA];
+        let after_cool_down = Timestamp::from_unix_epoch(6 * MINUTE);
+        assert_eq!(
+            breakers.retain_available_evict(providers.clone(), after_cool_down),
+            providers
+        );
+
+        breakers.start_probe_evict(ANKR, after_cool_down);
+        breakers.start_probe_evict(LLAMA, after_cool_down);
+        assert_eq!(
+            breakers.retain_available_evict(providers.clone(), after_cool_down),
+            vec![LLAMA]
+        );
+
+        // The provider can be probed again once the outcome of the probe is recorded
+        assert!(!breakers.record_outcome_evict(
+            ANKR,
+            RpcServiceOutcome::JsonRpcError,
+            after_cool_down
+        ));
+        assert_eq!(
+            breakers.state_evict(ANKR, after_cool_down),
+            CircuitBreakerState::HalfOpen
+        );
+        assert_eq!(
+            breakers.retain_available_evict(providers.clone(), after_cool_down),
+            providers
+        );
+
+        // or once the probe timed out
+        breakers.start_probe_evict(ANKR, after_cool_down);
+        let after_probe_timeout =
+            Timestamp::from_unix_epoch(7 * MINUTE + CircuitBreakers::PROBE_TIMEOUT);
+        assert_eq!(
+            breakers.retain_available_evict(providers.clone(), after_probe_timeout),
+            providers
+        );
+    }
+
+    #[test]
+    fn should_exclude_open_providers() {
+        let mut breakers = tripped(ANKR);
+        let providers = SupportedRpcService::eth_mainnet().to_vec();
+        assert!(providers.contains(&ANKR));
+
+        let available = breakers.retain_available_evict(providers.clone(), Timestamp::UNIX_EPOCH);
+        let expected: Vec<_> = providers
+            .iter()
+            .copied()
+            .filter(|service| service != &ANKR)
+            .collect();
+        assert_eq!(available, expected);
+
+        let after_cool_down = Timestamp::from_unix_epoch(6 * MINUTE);
+        assert_eq!(
+            breakers.retain_available_evict(providers.clone(), after_cool_down),
+            providers
+        );
+    }
+
+    fn tripped(service: SupportedRpcService) -> CircuitBreakers {
+        let mut breakers = CircuitBreakers::default();
+        for _ in 0..CircuitBreakers::FAILURE_THRESHOLD {
+            breakers.record_outcome_evict(
+                service,
+                RpcServiceOutcome::HttpError,
+                Timestamp::UNIX_EPOCH,
+            );
+        }
+        assert_eq!(
+            breakers.state_evict(service, Timestamp::UNIX_EPOCH),
+            CircuitBreakerState::Open
+        );
+        breakers
+    }
+}
+
+mod provider_registry {
+    use crate::constants::ETH_MAINNET_CHAIN_ID;
+    use crate::memory::{get_api_key, get_provider, insert_api_key};
+    use crate::providers::{
+        add_provider, remove_provider, resolve_rpc_service, update_provider, SupportedRpcService,
+        FIRST_RUNTIME_PROVIDER_ID, PROVIDERS, SERVICE_PROVIDER_MAP,
+    };
+    use crate::types::ApiKey;
+    use assert_matches::assert_matches;
+    use evm_rpc_types::{
+        EthMainnetService, ProviderArgs, ProviderError, RpcAccess, RpcAuth, RpcService,
+    };
+
+    #[test]
+    fn should_seed_registry_with_default_providers() {
+        for provider in PROVIDERS {
+            assert!(provider.provider_id < FIRST_RUNTIME_PROVIDER_ID);
+            assert_eq!(get_provider(provider.provider_id).as_ref(), Some(provider));
+        }
+    }
+
+    #[test]
+    fn should_add_provider() {
+        let provider_id = add_provider(unauthenticated("https://rpc.example.com", None)).unwrap();
+
+        assert_eq!(provider_id, FIRST_RUNTIME_PROVIDER_ID);
+        let provider = get_provider(provider_id).unwrap();
+        assert_eq!(provider.chain_id, ETH_MAINNET_CHAIN_ID);
+        assert_eq!(provider.api().url, "https://rpc.example.com");
+    }
+
+    #[test]
+    fn should_update_provider_and_service_provider_map() {
+        let alchemy = SupportedRpcService::EthMainnet(EthMainnetService::Alchemy);
+        let alchemy_provider_id = SERVICE_PROVIDER_MAP
+            .with_borrow(|map| map.get(&alchemy).copied())
+            .unwrap();
+        let provider_id = add_provider(unauthenticated("https://rpc.example.com", None)).unwrap();
+
+        // Alias already used by another provider
+        assert_matches!(
+            update_provider(
+                provider_id,
+                unauthenticated(
+                    "https://rpc.example.com",
+                    Some(RpcService::EthMainnet(EthMainnetService::Alchemy))
+                )
+            ),
+            Err(_)
+        );
+
+        remove_provider(alchemy_provider_id).unwrap();
+        assert_eq!(
+            resolve_rpc_service(RpcService::EthMainnet(EthMainnetService::Alchemy)).err(),
+            Some(ProviderError::MissingRequiredProvider)
+        );
+
+        update_provider(
+            provider_id,
+            unauthenticated(
+                "https://rpc.example.com",
+                Some(RpcService::EthMainnet(EthMainnetService::Alchemy)),
+            ),
+        )
+        .unwrap();
+        assert_eq!(
+            SERVICE_PROVIDER_MAP.with_borrow(|map| map.get(&alchemy).copied()),
+            Some(provider_id)
+        );
+    }
+
+    #[test]
+    fn should_remove_provider_and_api_key() {
+        let provider = PROVIDERS
+            .iter()
+            .find(|provider| provider.alias.is_some())
+            .unwrap();
+        insert_api_key(
+            provider.provider_id,
+            ApiKey::try_from("unit-test-key".to_string()).unwrap(),
+        );
+
+        remove_provider(provider.provider_id).unwrap();
+
+        assert_eq!(get_provider(provider.provider_id), None);
+        assert_eq!(get_api_key(provider.provider_id), None);
+        assert_eq!(
+            SERVICE_PROVIDER_MAP.with_borrow(|map| map.get(&provider.alias.unwrap()).copied()),
+            None
+        );
+        assert_matches!(remove_provider(provider.provider_id), Err(_));
+    }
+
+    #[test]
+    fn should_not_reuse_provider_id() {
+        let provider_id = add_provider(unauthenticated("https://rpc.example.com", None)).unwrap();
+        remove_provider(provider_id).unwrap();
+
+        let other_provider_id =
+            add_provider(unauthenticated("https://rpc.example.com", None)).unwrap();
+
+        assert_eq!(other_provider_id, provider_id + 1);
+    }
+
+    #[test]
+    fn should_reject_invalid_providers() {
+        for args in [
+            unauthenticated("http://rpc.example.com", None),
+            unauthenticated("https://127.0.0.1", None),
+            unauthenticated("https://{API_KEY}.example.com", None),
+            unauthenticated("not a url", None),
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::UrlParameter {
+                        url_pattern: "https://rpc.example.com".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::BearerToken {
+                        url: "https://rpc.example.com/{API_KEY}".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::Header {
+                        url: "https://rpc.example.com".to_string(),
+                        header_name: "x api key".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::Header {
+                        url: "https://rpc.example.com".to_string(),
+                        header_name: "Content-Type".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::Header {
+                        url: "https://rpc.example.com".to_string(),
+                        header_name: "Host".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::Header {
+                        url: "https://rpc.example.com".to_string(),
+                        header_name: "authorization".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                access: RpcAccess::Authenticated {
+                    auth: RpcAuth::BasicAuth {
+                        url: "http://rpc.example.com".to_string(),
+                    },
+                    public_url: None,
+                },
+                ..unauthenticated("https://rpc.example.com", None)
+            },
+            ProviderArgs {
+                chain_id: 2,
+                ..unauthenticated(
+                    "https://rpc.example.com",
+                    Some(RpcService::EthMainnet(EthMainnetService::Alchemy)),
+                )
+            },
+            unauthenticated("https://rpc.example.com", Some(RpcService::Provider(0))),
+        ] {
+            assert_matches!(add_provider(args.clone()), Err(_), "{:?}", args);
+        }
+    }
+
+    fn unauthenticated(url: &str, alias: Option<RpcService>) -> ProviderArgs {
+        ProviderArgs {
+            chain_id: ETH_MAINNET_CHAIN_ID,
+            access: RpcAccess::Unauthenticated {
+                public_url: url.to_string(),
+            },
+            alias,
+        }
+    }
+}
+
+mod chain_registry {
+    use crate::memory::{get_chain, get_chains};
+    use crate::providers::{add_provider, remove_chain, set_chain};
+    use assert_matches::assert_matches;
+    use evm_rpc_types::{Chain, ProviderArgs, RpcAccess};
+
+    const CHAIN_ID: u64 = 1_337;
+
+    #[test]
+    fn should_set_and_remove_chain() {
+        let provider_id = add_provider(provider_args(CHAIN_ID)).unwrap();
+        let chain = Chain {
+            chain_id: CHAIN_ID,
+            name: "Test chain".to_string(),
+            default_providers: vec![provider_id],
+            block_size_estimate: Some(8 * 1024),
+            max_block_range: Some(100),
+        };
+
+        set_chain(chain.clone()).unwrap();
+        assert_eq!(get_chain(CHAIN_ID), Some(chain.clone()));
+        assert_eq!(get_chains(), vec![chain]);
+
+        remove_chain(CHAIN_ID).unwrap();
+        assert_eq!(get_chain(CHAIN_ID), None);
+        assert_matches!(remove_chain(CHAIN_ID), Err(_));
+    }
+
+    #[test]
+    fn should_reject_invalid_chains() {
+        let other_chain_provider_id = add_provider(provider_args(CHAIN_ID + 1)).unwrap();
+        let chain = Chain {
+            chain_id: CHAIN_ID,
+            name: "Test chain".to_string(),
+            default_providers: vec![],
+            block_size_estimate: None,
+            max_block_range: None,
+        };
+
+        for invalid_chain in [
+            Chain {
+                name: " ".to_string(),
+                ..chain.clone()
+            },
+            Chain {
+                default_providers: vec![u64::MAX],
+                ..chain.clone()
+            },
+            Chain {
+                default_providers: vec![other_chain_provider_id],
+                ..chain.clone()
+            },
+        ] {
+            assert_matches!(
+                set_chain(invalid_chain.clone()),
+                Err(_),
+                "{:?}",
+                invalid_chain
+            );
+        }
+        assert_eq!(get_chain(CHAIN_ID), None);
+    }
+
+    fn provider_args(chain_id: u64) -> ProviderArgs {
+        ProviderArgs {
+            chain_id,
+            access: RpcAccess::Unauthenticated {
+                public_url: "https://rpc.example.com".to_string(),
+            },
+            alias: None,
+        }
+    }
+}
+
+mod api_key_rotations {
+    use crate::memory::{
+        add_api_key, get_api_key, get_api_keys, insert_api_key, next_api_key,
+        record_api_key_request, remove_api_key_by_id,
+    };
+    use crate::providers::ApiKeyRotations;
+    use crate::types::{ApiKey, ApiKeyRef};
+
+    const PROVIDER_ID: u64 = 1_000;
+
+    #[test]
+    fn should_rotate_round_robin() {
+        let mut rotations = ApiKeyRotations::default();
+        let key_ids = [0, 1, 2];
+
+        for expected_key_id in [0, 1, 2, 0, 1] {
+            let key_id = rotations.select(PROVIDER_ID, &key_ids).unwrap();
+            assert_eq!(key_id, expected_key_id);
+            rotations.record_request(api_key(key_id));
+        }
+    }
+
+    #[test]
+    fn should_not_rotate_when_no_request_sent() {
+        let rotations = ApiKeyRotations::default();
+
+        assert_eq!(rotations.select(PROVIDER_ID, &[0, 1]), Some(0));
+        assert_eq!(rotations.select(PROVIDER_ID, &[0, 1]), Some(0));
+        assert_eq!(rotations.select(PROVIDER_ID, &[]), None);
+    }
+
+    #[test]
+    fn should_skip_rate_limited_api_keys() {
+        let mut rotations = ApiKeyRotations::default();
+        let key_ids = [0, 1, 2];
+
+        assert!(rotations.record_status(api_key(1), 429));
+        assert!(!rotations.record_status(api_key(1), 429));
+        assert!(rotations.is_rate_limited(&api_key(1)));
+        for expected_key_id in [0, 2, 0, 2] {
+            let key_id = rotations.select(PROVIDER_ID, &key_ids).unwrap();
+            assert_eq!(key_id, expected_key_id);
+            rotations.record_request(api_key(key_id));
+        }
+
+        // Use all API keys again once all of them were rate limited
+        rotations.record_status(api_key(0), 429);
+        rotations.record_status(api_key(2), 429);
+        assert_eq!(rotations.select(PROVIDER_ID, &key_ids), Some(0));
+        rotations.record_request(api_key(0));
+        assert_eq!(rotations.select(PROVIDER_ID, &key_ids), Some(1));
+
+        assert!(!rotations.record_status(api_key(0), 500));
+        rotations.record_status(api_key(1), 429);
+        assert!(!rotations.record_status(api_key(1), 200));
+        assert!(!rotations.is_rate_limited(&api_key(1)));
+    }
+
+    #[test]
+    fn should_add_and_remove_individual_api_keys() {
+        insert_api_key(PROVIDER_ID, test_api_key("key0"));
+        let key_id_1 = add_api_key(PROVIDER_ID, test_api_key("key1"));
+        let key_id_2 = add_api_key(PROVIDER_ID, test_api_key("key2"));
+        assert_eq!(
+            get_api_keys(PROVIDER_ID)
+                .into_iter()
+                .map(|(key_id, _)| key_id)
+                .collect::<Vec<_>>(),
+            vec![0, key_id_1, key_id_2]
+        );
+
+        let (key_id, key) = next_api_key(PROVIDER_ID).unwrap();
+        assert_eq!((key_id, key.read()), (0, "key0"));
+        record_api_key_request(api_key(key_id));
+        let (key_id, key) = next_api_key(PROVIDER_ID).unwrap();
+        assert_eq!((key_id, key.read()), (key_id_1, "key1"));
+
+        assert!(remove_api_key_by_id(PROVIDER_ID, key_id_1));
+        assert!(!remove_api_key_by_id(PROVIDER_ID, key_id_1));
+        assert_eq!(next_api_key(PROVIDER_ID).unwrap().0, key_id_2);
+
+        insert_api_key(PROVIDER_ID, test_api_key("key3"));
+        assert_eq!(get_api_keys(PROVIDER_ID).len(), 1);
+        assert_eq!(get_api_key(PROVIDER_ID).unwrap().read(), "key3");
+    }
+
+    #[test]
+    fn should_not_reuse_api_key_ids() {
+        let key_id_1 = add_api_key(PROVIDER_ID, test_api_key("key1"));
+        let key_id_2 = add_api_key(PROVIDER_ID, test_api_key("key2"));
+        assert!(remove_api_key_by_id(PROVIDER_ID, key_id_2));
+
+        let key_id_3 = add_api_key(PROVIDER_ID, test_api_key("key3"));
+
+        assert!(key_id_1 < key_id_2 && key_id_2 < key_id_3);
+    }
+
+    fn api_key(key_id: u64) -> ApiKeyRef {
+        ApiKeyRef {
+            provider_id: PROVIDER_ID,
+            key_id,
+        }
+    }
+
+    fn test_api_key(key: &str) -> ApiKey {
+        ApiKey::try_from(key.to_string()).unwrap()
+    }
+}
@@ -34,6 +34,11 @@ impl<Unit> Amount<Unit> {
         self.0.to_be_bytes()
     }
 
+    /// Checked subtraction. Returns `None` if `rhs` is greater than `self`.
+    pub fn checked_sub(self, rhs: Self) -> Option<Self> {
+        self.0.checked_sub(rhs.0).map(Self::from_inner)
+    }
+
     /// Returns the display implementation of the inner value.
     /// Useful to avoid thousands of separators if value is used for example in URLs.
     /// ```
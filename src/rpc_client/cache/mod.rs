@@ -0,0 +1,135 @@
+//! Cache of responses that can no longer change, such as blocks and transaction receipts
+//! of finalized blocks.
+
+#[cfg(test)]
+mod tests;
+
+use crate::rpc_client::numeric::BlockNumber;
+use evm_rpc_types::RpcService;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Maximum number of cached responses.
+const MAX_ENTRIES: usize = 1_000;
+/// Maximum total size of the cached responses in bytes.
+const MAX_SIZE_BYTES: usize = 16 * 1024 * 1024;
+/// Maximum age of a cached response in nanoseconds.
+const MAX_AGE_NANOS: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Identifies a request whose response can be cached.
+///
+/// The queried providers are part of the key, so that a response is only returned to callers
+/// that would have queried the same providers.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CacheKey {
+    pub chain_id: u64,
+    pub providers: BTreeSet<RpcService>,
+    pub method: String,
+    /// Canonical JSON serialization of the request parameters.
+    pub params: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct CacheEntry {
+    response: String,
+    inserted_at: u64,
+}
+
+/// Bounded cache of responses that reached consensus and that can no longer change.
+///
+/// A response is only cached once the block that contains it is finalized, i.e. once the block
+/// number is at most the number of the latest finalized block known for the chain.
+/// The oldest responses are evicted first when the cache is full.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResponseCache {
+    entries: BTreeMap<CacheKey, CacheEntry>,
+    insertion_order: VecDeque<CacheKey>,
+    size_bytes: usize,
+    finalized_blocks: BTreeMap<u64, BlockNumber>,
+}
+
+impl ResponseCache {
+    pub fn get(&self, key: &CacheKey, now: u64) -> Option<&str> {
+        self.entries
+            .get(key)
+            .filter(|entry| now.saturating_sub(entry.inserted_at) < MAX_AGE_NANOS)
+            .map(|entry| entry.response.as_str())
+    }
+
+    /// Cache the response if the block that contains it is finalized.
+    ///
+    /// Returns `true` if the response was cached.
+    pub fn insert_if_finalized(
+        &mut self,
+        key: CacheKey,
+        block_number: BlockNumber,
+        response: String,
+        now: u64,
+    ) -> bool {
+        let is_finalized = self
+            .finalized_blocks
+            .get(&key.chain_id)
+            .is_some_and(|finalized| &block_number <= finalized);
+        if !is_finalized || response.len() > MAX_SIZE_BYTES {
+            return false;
+        }
+        self.remove(&key);
+        self.size_bytes += response.len();
+        self.insertion_order.push_back(key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                response,
+                inserted_at: now,
+            },
+        );
+        self.evict(now);
+        true
+    }
+
+    /// Record the number of the latest finalized block of the chain.
+    pub fn record_finalized_block(&mut self, chain_id: u64, block_number: BlockNumber) {
+        let finalized = self
+            .finalized_blocks
+            .entry(chain_id)
+            .or_insert(block_number);
+        if block_number > *finalized {
+            *finalized = block_number;
+        }
+    }
+
+    pub fn finalized_block(&self, chain_id: u64) -> Option<BlockNumber> {
+        self.finalized_blocks.get(&chain_id).copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn remove(&mut self, key: &CacheKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.size_bytes -= entry.response.len();
+            self.insertion_order.retain(|other| other != key);
+        }
+    }
+
+    fn evict(&mut self, now: u64) {
+        while let Some(oldest) = self.insertion_order.front() {
+            let entry = self.entries.get(oldest).expect("BUG: missing cache entry");
+            let is_expired = now.saturating_sub(entry.inserted_at) >= MAX_AGE_NANOS;
+            if !is_expired && self.entries.len() <= MAX_ENTRIES && self.size_bytes <= MAX_SIZE_BYTES
+            {
+                break;
+            }
+            let oldest = self.insertion_order.pop_front().expect("BUG: empty queue");
+            let entry = self
+                .entries
+                .remove(&oldest)
+                .expect("BUG: missing cache entry");
+            self.size_bytes -= entry.response.len();
+        }
+    }
+}
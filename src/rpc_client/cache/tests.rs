@@ -0,0 +1,85 @@
+use crate::rpc_client::cache::{CacheKey, ResponseCache, MAX_AGE_NANOS, MAX_ENTRIES};
+use crate::rpc_client::numeric::BlockNumber;
+use evm_rpc_types::{EthMainnetService, RpcService};
+use std::collections::BTreeSet;
+
+const MAINNET: u64 = 1;
+const SEPOLIA: u64 = 11155111;
+
+#[test]
+fn should_only_cache_finalized_responses() {
+    let mut cache = ResponseCache::default();
+
+    assert!(!cache.insert_if_finalized(key(MAINNET, 10), block(10), response(10), 0));
+    assert_eq!(cache.get(&key(MAINNET, 10), 0), None);
+
+    cache.record_finalized_block(MAINNET, block(10));
+    assert!(!cache.insert_if_finalized(key(MAINNET, 11), block(11), response(11), 0));
+    assert!(!cache.insert_if_finalized(key(SEPOLIA, 10), block(10), response(10), 0));
+    assert!(cache.insert_if_finalized(key(MAINNET, 10), block(10), response(10), 0));
+    assert_eq!(cache.get(&key(MAINNET, 10), 0), Some(response(10).as_str()));
+    assert_eq!(cache.get(&key(SEPOLIA, 10), 0), None);
+}
+
+#[test]
+fn should_only_move_finalized_block_forward() {
+    let mut cache = ResponseCache::default();
+
+    cache.record_finalized_block(MAINNET, block(10));
+    cache.record_finalized_block(MAINNET, block(5));
+    assert_eq!(cache.finalized_block(MAINNET), Some(block(10)));
+
+    cache.record_finalized_block(MAINNET, block(20));
+    assert_eq!(cache.finalized_block(MAINNET), Some(block(20)));
+    assert_eq!(cache.finalized_block(SEPOLIA), None);
+}
+
+#[test]
+fn should_not_return_expired_responses() {
+    let mut cache = ResponseCache::default();
+    cache.record_finalized_block(MAINNET, block(10));
+
+    assert!(cache.insert_if_finalized(key(MAINNET, 1), block(1), response(1), 0));
+    assert!(cache.get(&key(MAINNET, 1), MAX_AGE_NANOS - 1).is_some());
+    assert_eq!(cache.get(&key(MAINNET, 1), MAX_AGE_NANOS), None);
+
+    assert!(cache.insert_if_finalized(key(MAINNET, 2), block(2), response(2), MAX_AGE_NANOS));
+    assert_eq!(cache.len(), 1);
+}
+
+#[test]
+fn should_evict_oldest_responses_when_full() {
+    let mut cache = ResponseCache::default();
+    let num_entries = MAX_ENTRIES as u64 + 10;
+    cache.record_finalized_block(MAINNET, block(num_entries));
+
+    for number in 0..num_entries {
+        assert!(cache.insert_if_finalized(
+            key(MAINNET, number),
+            block(number),
+            response(number),
+            number
+        ));
+    }
+
+    assert_eq!(cache.len(), MAX_ENTRIES);
+    assert_eq!(cache.get(&key(MAINNET, 9), num_entries), None);
+    assert!(cache.get(&key(MAINNET, 10), num_entries).is_some());
+}
+
+fn key(chain_id: u64, number: u64) -> CacheKey {
+    CacheKey {
+        chain_id,
+        providers: BTreeSet::from([RpcService::EthMainnet(EthMainnetService::Ankr)]),
+        method: "eth_getBlockByNumber".to_string(),
+        params: format!("{{\"block\":\"{:#x}\"}}", number),
+    }
+}
+
+fn block(number: u64) -> BlockNumber {
+    BlockNumber::from(number)
+}
+
+fn response(number: u64) -> String {
+    format!("{{\"number\":\"{:#x}\"}}", number)
+}
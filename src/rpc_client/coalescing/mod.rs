@@ -0,0 +1,180 @@
+//! Coalesce identical requests that are in flight at the same time, so that only the first
+//! request makes HTTP outcalls and the others wait for its result.
+//!
+//! A caller can only reply to its own call, so a waiting request cannot simply be woken up
+//! once the result is available. Instead, it checks for the result once per round until the
+//! first request completed, was abandoned, or timed out.
+
+#[cfg(test)]
+mod tests;
+
+use candid::Principal;
+use evm_rpc_types::RpcService;
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet},
+    rc::Rc,
+};
+
+/// Maximum time in nanoseconds during which requests are coalesced with an in-flight request.
+pub const MAX_IN_FLIGHT_NANOS: u64 = 30 * 1_000_000_000;
+
+/// Maximum number of rounds a request waits for the in-flight request before making its own
+/// HTTP outcalls, which bounds the cost of waiting for an in-flight request that was lost,
+/// e.g. because it trapped.
+pub const MAX_WAITING_ROUNDS: u32 = 10;
+
+/// Identifies requests that are guaranteed to produce the same result.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CoalescingKey {
+    pub providers: BTreeSet<RpcService>,
+    pub method: String,
+    /// Canonical JSON serialization of the request parameters.
+    pub params: String,
+    /// Debug representation of the RPC config and of the estimated response size.
+    pub config: String,
+}
+
+/// Result of the in-flight request, shared with the requests waiting for it.
+#[derive(Clone)]
+pub struct Completion {
+    /// Type-erased result, which must be downcast to the result type of the request.
+    pub result: Rc<dyn Any>,
+    /// Caller of the in-flight request, who paid for the HTTP outcalls.
+    pub leader: Principal,
+    /// Cycles to charge each waiting request, i.e. its share of the cost of the HTTP outcalls.
+    pub share: u128,
+}
+
+#[derive(Clone, Default)]
+pub enum FollowerState {
+    #[default]
+    Waiting,
+    Completed(Completion),
+    Abandoned,
+}
+
+pub type FollowerSlot = Rc<RefCell<FollowerState>>;
+
+/// Role of a request with respect to identical in-flight requests.
+pub enum Role {
+    /// No identical request is in flight: make the HTTP outcalls.
+    Leader,
+    /// An identical request is in flight: wait for its result.
+    Follower { slot: FollowerSlot, deadline: u64 },
+}
+
+struct InFlightRequest {
+    leader: Principal,
+    started_at: u64,
+    followers: Vec<FollowerSlot>,
+}
+
+#[derive(Default)]
+pub struct InFlightRequests {
+    requests: BTreeMap<CoalescingKey, InFlightRequest>,
+}
+
+impl InFlightRequests {
+    /// Join an identical in-flight request or, if there is none, become the in-flight request.
+    ///
+    /// An in-flight request started more than [`MAX_IN_FLIGHT_NANOS`] ago is considered lost
+    /// and replaced.
+    pub fn join(&mut self, key: CoalescingKey, caller: Principal, now: u64) -> Role {
+        if let Some(request) = self.requests.get_mut(&key) {
+            let deadline = request.started_at.saturating_add(MAX_IN_FLIGHT_NANOS);
+            if now < deadline {
+                let slot = FollowerSlot::default();
+                request.followers.push(slot.clone());
+                return Role::Follower { slot, deadline };
+            }
+            self.abandon(&key);
+        }
+        self.requests.insert(
+            key,
+            InFlightRequest {
+                leader: caller,
+                started_at: now,
+                followers: Vec::new(),
+            },
+        );
+        Role::Leader
+    }
+
+    /// Share the result of the in-flight request with the waiting requests,
+    /// which are charged an equal share of `cycles_cost`.
+    ///
+    /// Returns the number of waiting requests.
+    pub fn complete(
+        &mut self,
+        key: &CoalescingKey,
+        result: Rc<dyn Any>,
+        cycles_cost: u128,
+    ) -> usize {
+        let Some(request) = self.requests.remove(key) else {
+            return 0;
+        };
+        let num_followers = request.followers.len();
+        let completion = Completion {
+            result,
+            leader: request.leader,
+            share: cycles_cost / (num_followers as u128 + 1),
+        };
+        for slot in request.followers {
+            *slot.borrow_mut() = FollowerState::Completed(completion.clone());
+        }
+        num_followers
+    }
+
+    /// Stop coalescing with the in-flight request, e.g. because it failed.
+    /// Waiting requests make their own HTTP outcalls.
+    pub fn abandon(&mut self, key: &CoalescingKey) {
+        if let Some(request) = self.requests.remove(key) {
+            for slot in request.followers {
+                *slot.borrow_mut() = FollowerState::Abandoned;
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+}
+
+/// Wait until the in-flight request completed, and return its result,
+/// or until it was abandoned, the deadline passed or [`MAX_WAITING_ROUNDS`] rounds passed,
+/// and return `None`.
+///
+/// Each round of waiting must be paid with `pay_round`, and `None` is returned as soon as
+/// a round cannot be paid.
+pub async fn wait_for_completion(
+    slot: FollowerSlot,
+    deadline: u64,
+    pay_round: impl Fn() -> bool,
+) -> Option<Completion> {
+    for round in 0.. {
+        match &*slot.borrow() {
+            FollowerState::Completed(completion) => return Some(completion.clone()),
+            FollowerState::Abandoned => return None,
+            FollowerState::Waiting
+                if round >= MAX_WAITING_ROUNDS || ic_cdk::api::time() >= deadline =>
+            {
+                return None
+            }
+            FollowerState::Waiting => {}
+        }
+        if !pay_round() {
+            return None;
+        }
+        // Cheapest way to give the in-flight request the opportunity to complete.
+        if ic_cdk_management_canister::raw_rand().await.is_err() {
+            return None;
+        }
+    }
+    None
+}
@@ -0,0 +1,88 @@
+use crate::rpc_client::coalescing::{
+    CoalescingKey, FollowerState, InFlightRequests, Role, MAX_IN_FLIGHT_NANOS,
+};
+use candid::Principal;
+use evm_rpc_types::{EthMainnetService, RpcService};
+use std::{collections::BTreeSet, rc::Rc};
+
+const LEADER: Principal = Principal::from_slice(&[1]);
+const FOLLOWER: Principal = Principal::from_slice(&[2]);
+
+fn key(params: &str) -> CoalescingKey {
+    CoalescingKey {
+        providers: BTreeSet::from([RpcService::EthMainnet(EthMainnetService::Ankr)]),
+        method: "eth_getBlockByNumber".to_string(),
+        params: params.to_string(),
+        config: "RpcConfig".to_string(),
+    }
+}
+
+#[test]
+fn should_share_result_with_followers() {
+    let mut requests = InFlightRequests::default();
+    assert!(matches!(
+        requests.join(key("finalized"), LEADER, 0),
+        Role::Leader
+    ));
+    assert!(matches!(
+        requests.join(key("latest"), LEADER, 0),
+        Role::Leader
+    ));
+    let slots: Vec<_> = (0..3)
+        .map(|_| match requests.join(key("finalized"), FOLLOWER, 1) {
+            Role::Follower { slot, deadline } => {
+                assert_eq!(deadline, MAX_IN_FLIGHT_NANOS);
+                slot
+            }
+            Role::Leader => panic!("BUG: expected follower"),
+        })
+        .collect();
+
+    assert_eq!(
+        requests.complete(&key("finalized"), Rc::new(42_u64), 1_000),
+        3
+    );
+
+    assert_eq!(requests.len(), 1);
+    for slot in slots {
+        match &*slot.borrow() {
+            FollowerState::Completed(completion) => {
+                assert_eq!(completion.result.downcast_ref::<u64>(), Some(&42));
+                assert_eq!(completion.leader, LEADER);
+                assert_eq!(completion.share, 250);
+            }
+            _ => panic!("BUG: expected completed request"),
+        }
+    }
+}
+
+#[test]
+fn should_abandon_followers() {
+    let mut requests = InFlightRequests::default();
+    requests.join(key("finalized"), LEADER, 0);
+    let Role::Follower { slot, .. } = requests.join(key("finalized"), FOLLOWER, 1) else {
+        panic!("BUG: expected follower")
+    };
+
+    requests.abandon(&key("finalized"));
+
+    assert!(matches!(&*slot.borrow(), FollowerState::Abandoned));
+    assert!(requests.is_empty());
+    assert_eq!(requests.complete(&key("finalized"), Rc::new(()), 1_000), 0);
+}
+
+#[test]
+fn should_replace_timed_out_request() {
+    let mut requests = InFlightRequests::default();
+    requests.join(key("finalized"), LEADER, 0);
+    let Role::Follower { slot, .. } = requests.join(key("finalized"), FOLLOWER, 1) else {
+        panic!("BUG: expected follower")
+    };
+
+    assert!(matches!(
+        requests.join(key("finalized"), FOLLOWER, MAX_IN_FLIGHT_NANOS),
+        Role::Leader
+    ));
+    assert!(matches!(&*slot.borrow(), FollowerState::Abandoned));
+    assert_eq!(requests.len(), 1);
+}
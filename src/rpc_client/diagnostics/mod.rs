@@ -0,0 +1,135 @@
+//! Summarize the differences between inconsistent results returned by providers.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    add_metric_entry,
+    types::{MetricResponseField, MetricRpcMethod},
+};
+use evm_rpc_types::{InconsistencyDiagnostics, MultiRpcResult, RpcResult, RpcService};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Record which fields differed between inconsistent results and,
+/// if `with_diagnostics` is set, return the results together with a summary of the differences.
+pub fn diagnose_result<T: Serialize + PartialEq>(
+    method: impl Into<MetricRpcMethod>,
+    result: MultiRpcResult<T>,
+    with_diagnostics: bool,
+) -> MultiRpcResult<T> {
+    match result {
+        MultiRpcResult::Inconsistent(results) => {
+            let diagnostics = inconsistency_diagnostics(&results);
+            let method = method.into();
+            diagnostics
+                .differing_fields
+                .iter()
+                .map(|field| metric_field(field))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .for_each(|field| {
+                    add_metric_entry!(
+                        inconsistent_response_fields,
+                        (method.clone(), MetricResponseField(field)),
+                        1
+                    )
+                });
+            if with_diagnostics {
+                MultiRpcResult::InconsistentWithDiagnostics {
+                    results,
+                    diagnostics,
+                }
+            } else {
+                MultiRpcResult::Inconsistent(results)
+            }
+        }
+        result => result,
+    }
+}
+
+pub fn inconsistency_diagnostics<T: Serialize + PartialEq>(
+    results: &[(RpcService, RpcResult<T>)],
+) -> InconsistencyDiagnostics {
+    let mut groups: Vec<(&RpcResult<T>, Vec<RpcService>)> = Vec::new();
+    for (service, result) in results {
+        match groups.iter_mut().find(|(other, _)| *other == result) {
+            Some((_, services)) => services.push(service.clone()),
+            None => groups.push((result, vec![service.clone()])),
+        }
+    }
+    groups.sort_by(|(_, left), (_, right)| right.len().cmp(&left.len()));
+
+    let values: Vec<Value> = results
+        .iter()
+        .filter_map(|(_, result)| result.as_ref().ok())
+        .filter_map(|value| serde_json::to_value(value).ok())
+        .collect();
+    let mut differing_fields = BTreeSet::new();
+    if let Some((first, others)) = values.split_first() {
+        for other in others {
+            collect_differing_fields(first, other, "$".to_string(), &mut differing_fields);
+        }
+    }
+
+    InconsistencyDiagnostics {
+        agreeing_groups: groups.into_iter().map(|(_, services)| services).collect(),
+        differing_fields: differing_fields.into_iter().collect(),
+    }
+}
+
+fn collect_differing_fields(
+    left: &Value,
+    right: &Value,
+    path: String,
+    fields: &mut BTreeSet<String>,
+) {
+    match (left, right) {
+        (Value::Object(left), Value::Object(right)) => {
+            let keys: BTreeSet<_> = left.keys().chain(right.keys()).collect();
+            for key in keys {
+                let field = format!("{path}.{key}");
+                match (left.get(key), right.get(key)) {
+                    (Some(left), Some(right)) => {
+                        collect_differing_fields(left, right, field, fields)
+                    }
+                    _ => {
+                        fields.insert(field);
+                    }
+                }
+            }
+        }
+        (Value::Array(left), Value::Array(right)) if left.len() == right.len() => {
+            for (index, (left, right)) in left.iter().zip(right).enumerate() {
+                collect_differing_fields(left, right, format!("{path}[{index}]"), fields);
+            }
+        }
+        _ => {
+            if left != right {
+                fields.insert(path);
+            }
+        }
+    }
+}
+
+/// Remove array indices from a field path, e.g. `$[2].topics[0]` becomes `$[].topics[]`.
+fn metric_field(path: &str) -> String {
+    let mut field = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                field.push(c);
+            }
+            ']' => {
+                in_index = false;
+                field.push(c);
+            }
+            _ if in_index => {}
+            _ => field.push(c),
+        }
+    }
+    field
+}
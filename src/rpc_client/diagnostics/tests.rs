@@ -0,0 +1,59 @@
+use crate::rpc_client::diagnostics::{inconsistency_diagnostics, metric_field};
+use evm_rpc_types::{EthMainnetService, ProviderError, RpcError, RpcService};
+use serde_json::json;
+
+const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+const LLAMA: RpcService = RpcService::EthMainnet(EthMainnetService::Llama);
+const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+
+#[test]
+fn should_group_agreeing_providers() {
+    let diagnostics = inconsistency_diagnostics(&[
+        (ANKR, Ok(json!({"number": "0x1"}))),
+        (
+            LLAMA,
+            Err(RpcError::ProviderError(ProviderError::NoPermission)),
+        ),
+        (PUBLIC_NODE, Ok(json!({"number": "0x1"}))),
+    ]);
+
+    assert_eq!(
+        diagnostics.agreeing_groups,
+        vec![vec![ANKR, PUBLIC_NODE], vec![LLAMA]]
+    );
+    assert_eq!(diagnostics.differing_fields, Vec::<String>::new());
+}
+
+#[test]
+fn should_find_differing_fields() {
+    let diagnostics = inconsistency_diagnostics(&[
+        (
+            ANKR,
+            Ok(json!({"hash": "0xa", "number": "0x1", "logs": [{"data": "0x"}]})),
+        ),
+        (
+            LLAMA,
+            Ok(json!({"hash": "0xb", "number": "0x1", "logs": [{"data": "0x01"}]})),
+        ),
+        (
+            PUBLIC_NODE,
+            Ok(json!({"hash": "0xa", "number": "0x1", "logs": [], "extra": true})),
+        ),
+    ]);
+
+    assert_eq!(
+        diagnostics.agreeing_groups,
+        vec![vec![ANKR], vec![LLAMA], vec![PUBLIC_NODE]]
+    );
+    assert_eq!(
+        diagnostics.differing_fields,
+        vec!["$.extra", "$.hash", "$.logs", "$.logs[0].data"]
+    );
+}
+
+#[test]
+fn should_remove_array_indices_from_metric_field() {
+    assert_eq!(metric_field("$"), "$");
+    assert_eq!(metric_field("$.hash"), "$.hash");
+    assert_eq!(metric_field("$[12].topics[0]"), "$[].topics[]");
+}
@@ -51,7 +51,22 @@ impl From<BTreeMap<Id, ResponseTransform>> for ResponseTransformEnvelope {
 }
 
 impl ResponseTransformEnvelope {
-    fn apply(&self, body: &mut Vec<u8>) {
+    /// Whether the transformation is required even if the response does not go through
+    /// consensus, i.e. for non-replicated HTTP outcalls, because the response is only
+    /// valid once transformed.
+    pub fn is_required_without_consensus(&self) -> bool {
+        match self {
+            ResponseTransformEnvelope::Single(transform) => matches!(
+                transform,
+                ResponseTransform::GetLogs
+                    | ResponseTransform::SendRawTransaction
+                    | ResponseTransform::Raw
+            ),
+            ResponseTransformEnvelope::Batch(_) => true,
+        }
+    }
+
+    pub fn apply(&self, body: &mut Vec<u8>) {
         match self {
             ResponseTransformEnvelope::Single(transform) => {
                 if let Ok(response) =
@@ -113,6 +128,8 @@ pub enum ResponseTransform {
     SendRawTransaction,
     #[n(7)]
     Raw,
+    #[n(8)]
+    BlockNumber,
 }
 
 impl ResponseTransform {
@@ -156,7 +173,7 @@ impl ResponseTransform {
             ResponseTransform::SendRawTransaction => {
                 sanitize_send_raw_transaction_result(response, Parser::new())
             }
-            Self::Call | Self::GetTransactionCount | Self::Raw => {
+            Self::Call | Self::GetTransactionCount | Self::BlockNumber | Self::Raw => {
                 response.map(canonicalize_response::<serde_json::Value>)
             }
         }
@@ -177,25 +194,44 @@ fn cleanup_response(args: TransformArgs) -> HttpRequestResult {
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ResponseSizeEstimate(u64);
+pub struct ResponseSizeEstimate {
+    num_bytes: u64,
+    learnable: bool,
+}
 
 impl ResponseSizeEstimate {
     pub fn new(num_bytes: u64) -> Self {
         assert!(num_bytes > 0);
         assert!(num_bytes <= MAX_PAYLOAD_SIZE);
-        Self(num_bytes)
+        Self {
+            num_bytes,
+            learnable: false,
+        }
+    }
+
+    /// Allow replacing this default estimate by an estimate learned from past responses,
+    /// i.e. when the caller did not give an estimate.
+    pub fn learnable(self) -> Self {
+        Self {
+            learnable: true,
+            ..self
+        }
+    }
+
+    pub fn is_learnable(self) -> bool {
+        self.learnable
     }
 
     /// Describes the expected (90th percentile) number of bytes in the HTTP response body.
     /// This number should be less than `MAX_PAYLOAD_SIZE`.
     pub fn get(self) -> u64 {
-        self.0
+        self.num_bytes
     }
 }
 
 impl fmt::Display for ResponseSizeEstimate {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.0)
+        write!(f, "{}", self.num_bytes)
     }
 }
 
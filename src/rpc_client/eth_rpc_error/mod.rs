@@ -1,9 +1,11 @@
 use crate::{
+    http::error::is_response_too_large_error,
     logs::Priority,
     rpc_client::json::{responses::SendRawTransactionResult, Hash},
 };
 use canhttp::http::json::{JsonRpcError, JsonRpcResponse};
 use canlog::log;
+use evm_rpc_types::RpcError;
 use serde::Deserialize;
 
 #[cfg(test)]
@@ -31,12 +33,26 @@ pub enum SendRawTransactionError {
     NonceTooHigh,
 }
 
+/// Possible errors returned by calling `eth_getLogs` endpoint for a block range that is too large,
+/// in which case the block range should be split.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GetLogsError {
+    /// The block range contains more logs than the provider returns.
+    TooManyResults,
+    /// The block range contains more blocks than the provider supports.
+    BlockRangeTooLarge,
+}
+
 pub trait ErrorParser {
     fn try_parse_send_raw_transaction_error(
         &self,
         code: i64,
         message: String,
     ) -> Option<SendRawTransactionError>;
+
+    fn try_parse_get_logs_error(&self, _code: i64, _message: String) -> Option<GetLogsError> {
+        None
+    }
 }
 
 struct GoEthereumParser;
@@ -63,6 +79,19 @@ impl ErrorParser for GoEthereumParser {
             _ => None,
         }
     }
+
+    // https://github.com/ethereum/go-ethereum/blob/5976e58415a633c24a0d903e8a60a3780abdfe59/eth/filters/api.go
+    fn try_parse_get_logs_error(&self, code: i64, message: String) -> Option<GetLogsError> {
+        match (code, message.to_lowercase()) {
+            (_, msg) if msg.contains("query returned more than") => {
+                Some(GetLogsError::TooManyResults)
+            }
+            (_, msg) if msg.contains("exceed maximum block range") => {
+                Some(GetLogsError::BlockRangeTooLarge)
+            }
+            _ => None,
+        }
+    }
 }
 
 struct NethermindParser;
@@ -90,6 +119,13 @@ impl ErrorParser for NethermindParser {
             _ => None,
         }
     }
+
+    fn try_parse_get_logs_error(&self, code: i64, message: String) -> Option<GetLogsError> {
+        match (code, message.to_lowercase()) {
+            (-32_005, msg) if msg.contains("limit exceeded") => Some(GetLogsError::TooManyResults),
+            _ => None,
+        }
+    }
 }
 
 struct ErigonParser;
@@ -115,6 +151,18 @@ impl ErrorParser for ErigonParser {
             _ => None,
         }
     }
+
+    fn try_parse_get_logs_error(&self, code: i64, message: String) -> Option<GetLogsError> {
+        match (code, message.to_lowercase()) {
+            (-32_000, msg) if msg.contains("query exceeds max results") => {
+                Some(GetLogsError::TooManyResults)
+            }
+            (-32_000, msg) if msg.contains("block range") && msg.contains("exceeds") => {
+                Some(GetLogsError::BlockRangeTooLarge)
+            }
+            _ => None,
+        }
+    }
 }
 
 struct BesuParser;
@@ -142,6 +190,47 @@ impl ErrorParser for BesuParser {
             _ => None,
         }
     }
+
+    fn try_parse_get_logs_error(&self, code: i64, message: String) -> Option<GetLogsError> {
+        match (code, message.to_lowercase()) {
+            (-32_005, msg) if msg.contains("exceeds maximum range limit") => {
+                Some(GetLogsError::BlockRangeTooLarge)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Errors of hosted providers, which limit the block range or the number of logs of
+/// `eth_getLogs` independently of the Ethereum client they run.
+struct HostedProviderParser;
+impl ErrorParser for HostedProviderParser {
+    fn try_parse_send_raw_transaction_error(
+        &self,
+        _code: i64,
+        _message: String,
+    ) -> Option<SendRawTransactionError> {
+        None
+    }
+
+    fn try_parse_get_logs_error(&self, _code: i64, message: String) -> Option<GetLogsError> {
+        let msg = message.to_lowercase();
+        // e.g. Alchemy: "Log response size exceeded. You can make eth_getLogs requests with up to
+        // a 2K block range and no limit on the response size, [...]"
+        if msg.contains("log response size exceeded") || msg.contains("too many logs") {
+            return Some(GetLogsError::TooManyResults);
+        }
+        // e.g. QuickNode: "eth_getLogs is limited to a 10,000 range",
+        // Ankr: "block range is too wide"
+        if msg.contains("is limited to a")
+            || msg.contains("block range is too")
+            || msg.contains("range too large")
+            || msg.contains("ranges over")
+        {
+            return Some(GetLogsError::BlockRangeTooLarge);
+        }
+        None
+    }
 }
 
 pub struct Parser {
@@ -156,6 +245,7 @@ impl Parser {
                 Box::new(NethermindParser),
                 Box::new(ErigonParser),
                 Box::new(BesuParser),
+                Box::new(HostedProviderParser),
             ],
         }
     }
@@ -177,6 +267,23 @@ impl ErrorParser for Parser {
             .iter()
             .find_map(|parser| parser.try_parse_send_raw_transaction_error(code, message.clone()))
     }
+
+    fn try_parse_get_logs_error(&self, code: i64, message: String) -> Option<GetLogsError> {
+        self.parsers
+            .iter()
+            .find_map(|parser| parser.try_parse_get_logs_error(code, message.clone()))
+    }
+}
+
+/// Whether the `eth_getLogs` request failed because its block range is too large, either because
+/// the provider rejected it or because the response exceeded the maximum response size.
+pub fn is_get_logs_range_too_large_error(error: &RpcError) -> bool {
+    match error {
+        RpcError::JsonRpcError(evm_rpc_types::JsonRpcError { code, message }) => Parser::new()
+            .try_parse_get_logs_error(*code, message.clone())
+            .is_some(),
+        error => is_response_too_large_error(error),
+    }
 }
 
 /// Sanitizes the response of `eth_sendRawTransaction` to hide implementation details of the various Ethereum clients
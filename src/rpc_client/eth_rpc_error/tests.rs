@@ -1,4 +1,8 @@
-use crate::rpc_client::eth_rpc_error::{sanitize_send_raw_transaction_result, Parser};
+use crate::rpc_client::eth_rpc_error::{
+    is_get_logs_range_too_large_error, sanitize_send_raw_transaction_result, ErrorParser,
+    GetLogsError, Parser,
+};
+use evm_rpc_types::{HttpOutcallError, JsonRpcError, LegacyRejectionCode, RpcError};
 use serde_json::json;
 
 #[test]
@@ -39,6 +43,67 @@ fn should_not_modify_response_when_deserialization_fails() {
     check_sanitize_send_raw_transaction_result(raw_response, unmodified_response);
 }
 
+#[test]
+fn should_parse_get_logs_errors() {
+    let parser = Parser::new();
+    for (code, message, expected) in [
+        (
+            -32_005,
+            "query returned more than 10000 results. Try with this block range [0x1, 0x2].",
+            Some(GetLogsError::TooManyResults),
+        ),
+        (
+            -32_602,
+            "Log response size exceeded. You can make eth_getLogs requests with up to a 2K block range and no limit on the response size.",
+            Some(GetLogsError::TooManyResults),
+        ),
+        (
+            -32_000,
+            "query exceeds max results 20000",
+            Some(GetLogsError::TooManyResults),
+        ),
+        (
+            -32_602,
+            "eth_getLogs is limited to a 10,000 range",
+            Some(GetLogsError::BlockRangeTooLarge),
+        ),
+        (
+            -32_000,
+            "block range is too wide",
+            Some(GetLogsError::BlockRangeTooLarge),
+        ),
+        (-32_000, "execution reverted", None),
+    ] {
+        assert_eq!(
+            parser.try_parse_get_logs_error(code, message.to_string()),
+            expected,
+            "{message}"
+        );
+    }
+}
+
+#[test]
+fn should_detect_get_logs_range_too_large_errors() {
+    assert!(is_get_logs_range_too_large_error(&RpcError::JsonRpcError(
+        JsonRpcError {
+            code: -32_005,
+            message: "query returned more than 10000 results".to_string(),
+        }
+    )));
+    assert!(is_get_logs_range_too_large_error(
+        &RpcError::HttpOutcallError(HttpOutcallError::IcError {
+            code: LegacyRejectionCode::SysFatal,
+            message: "Http body exceeds size limit of 2000000 bytes.".to_string(),
+        })
+    ));
+    assert!(!is_get_logs_range_too_large_error(&RpcError::JsonRpcError(
+        JsonRpcError {
+            code: -32_000,
+            message: "header not found".to_string(),
+        }
+    )));
+}
+
 fn check_sanitize_send_raw_transaction_result(
     raw_response: serde_json::Value,
     expected: serde_json::Value,
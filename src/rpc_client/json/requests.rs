@@ -3,6 +3,7 @@ use crate::rpc_client::{
     json::{responses::Data, FixedSizeData, Hash, JsonByte, StorageKey},
     numeric::{BlockNumber, ChainId, GasAmount, NumBlocks, TransactionNonce, Wei, WeiPerGas},
 };
+use evm_rpc_types::{Hex, Hex20, Hex32, HexByte, Nat256};
 use ic_ethereum_types::Address;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -138,6 +139,19 @@ impl From<evm_rpc_types::BlockTag> for BlockSpec {
     }
 }
 
+impl From<BlockSpec> for evm_rpc_types::BlockTag {
+    fn from(value: BlockSpec) -> Self {
+        match value {
+            BlockSpec::Number(n) => Self::Number(Nat256::from(n)),
+            BlockSpec::Tag(BlockTag::Latest) => Self::Latest,
+            BlockSpec::Tag(BlockTag::Safe) => Self::Safe,
+            BlockSpec::Tag(BlockTag::Finalized) => Self::Finalized,
+            BlockSpec::Tag(BlockTag::Earliest) => Self::Earliest,
+            BlockSpec::Tag(BlockTag::Pending) => Self::Pending,
+        }
+    }
+}
+
 impl Default for BlockSpec {
     fn default() -> Self {
         Self::Tag(BlockTag::default())
@@ -351,6 +365,66 @@ impl From<evm_rpc_types::TransactionRequest> for TransactionRequest {
     }
 }
 
+impl From<TransactionRequest> for evm_rpc_types::TransactionRequest {
+    fn from(
+        TransactionRequest {
+            tx_type,
+            nonce,
+            to,
+            from,
+            gas,
+            value,
+            input,
+            gas_price,
+            max_priority_fee_per_gas,
+            max_fee_per_gas,
+            max_fee_per_blob_gas,
+            access_list,
+            blob_versioned_hashes,
+            blobs,
+            chain_id,
+        }: TransactionRequest,
+    ) -> Self {
+        fn map_access_list(list: AccessList) -> evm_rpc_types::AccessList {
+            evm_rpc_types::AccessList(
+                list.0
+                    .into_iter()
+                    .map(|item| evm_rpc_types::AccessListEntry {
+                        address: Hex20::from(item.address.into_bytes()),
+                        storage_keys: item
+                            .storage_keys
+                            .into_iter()
+                            .map(|key| Hex32::from(key.into_bytes()))
+                            .collect(),
+                    })
+                    .collect(),
+            )
+        }
+        Self {
+            tx_type: tx_type.map(|t| HexByte::from(t.into_byte())),
+            nonce: nonce.map(Nat256::from),
+            to: to.map(|address| Hex20::from(address.into_bytes())),
+            from: from.map(|address| Hex20::from(address.into_bytes())),
+            gas: gas.map(Nat256::from),
+            value: value.map(Nat256::from),
+            input: input.map(Hex::from),
+            gas_price: gas_price.map(Nat256::from),
+            max_priority_fee_per_gas: max_priority_fee_per_gas.map(Nat256::from),
+            max_fee_per_gas: max_fee_per_gas.map(Nat256::from),
+            max_fee_per_blob_gas: max_fee_per_blob_gas.map(Nat256::from),
+            access_list: access_list.map(map_access_list),
+            blob_versioned_hashes: blob_versioned_hashes.map(|hashes| {
+                hashes
+                    .into_iter()
+                    .map(|h| Hex32::from(h.into_bytes()))
+                    .collect()
+            }),
+            blobs: blobs.map(|blobs| blobs.into_iter().map(Hex::from).collect()),
+            chain_id: chain_id.map(Nat256::from),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(transparent)]
 pub struct AccessList(pub Vec<AccessListItem>);
@@ -102,6 +102,36 @@ impl From<TransactionReceipt> for evm_rpc_types::TransactionReceipt {
     }
 }
 
+impl From<evm_rpc_types::TransactionReceipt> for TransactionReceipt {
+    fn from(value: evm_rpc_types::TransactionReceipt) -> Self {
+        Self {
+            block_hash: Hash::from(value.block_hash),
+            block_number: BlockNumber::from(value.block_number),
+            effective_gas_price: WeiPerGas::from(value.effective_gas_price),
+            cumulative_gas_used: GasAmount::from(value.cumulative_gas_used),
+            gas_used: GasAmount::from(value.gas_used),
+            status: value.status.map(|v| {
+                if v == Nat256::ZERO {
+                    TransactionStatus::Failure
+                } else {
+                    TransactionStatus::Success
+                }
+            }),
+            root: value.root.map(Hash::from),
+            transaction_hash: Hash::from(value.transaction_hash),
+            contract_address: value
+                .contract_address
+                .map(|address| Address::new(address.into())),
+            from: Address::new(value.from.into()),
+            logs: value.logs.into_iter().map(LogEntry::from).collect(),
+            logs_bloom: LogsBloom::new(value.logs_bloom.into()),
+            to: value.to.map(|address| Address::new(address.into())),
+            transaction_index: TransactionIndex::from(value.transaction_index),
+            tx_type: JsonByte::new(value.tx_type.into_byte()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, Eq, PartialEq)]
 #[serde(try_from = "ethnum::u256", into = "ethnum::u256")]
 pub enum TransactionStatus {
@@ -220,6 +250,26 @@ impl From<LogEntry> for evm_rpc_types::LogEntry {
     }
 }
 
+impl From<evm_rpc_types::LogEntry> for LogEntry {
+    fn from(value: evm_rpc_types::LogEntry) -> Self {
+        Self {
+            address: Address::new(value.address.into()),
+            topics: value
+                .topics
+                .into_iter()
+                .map(|t| FixedSizeData::new(t.into()))
+                .collect(),
+            data: Data::from(value.data),
+            block_number: value.block_number.map(BlockNumber::from),
+            transaction_hash: value.transaction_hash.map(Hash::from),
+            transaction_index: value.transaction_index.map(TransactionIndex::from),
+            block_hash: value.block_hash.map(Hash::from),
+            log_index: value.log_index.map(LogIndex::from),
+            removed: value.removed,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Block {
     /// Base fee per gas
@@ -339,6 +389,33 @@ impl From<Block> for evm_rpc_types::Block {
     }
 }
 
+impl From<evm_rpc_types::Block> for Block {
+    fn from(value: evm_rpc_types::Block) -> Self {
+        Self {
+            base_fee_per_gas: value.base_fee_per_gas.map(Wei::from),
+            number: BlockNumber::from(value.number),
+            difficulty: value.difficulty.map(Difficulty::from),
+            extra_data: Data::from(value.extra_data),
+            gas_limit: GasAmount::from(value.gas_limit),
+            gas_used: GasAmount::from(value.gas_used),
+            hash: Hash::from(value.hash),
+            logs_bloom: LogsBloom::new(value.logs_bloom.into()),
+            miner: Address::new(value.miner.into()),
+            mix_hash: Hash::from(value.mix_hash),
+            nonce: BlockNonce::from(value.nonce),
+            parent_hash: Hash::from(value.parent_hash),
+            receipts_root: Hash::from(value.receipts_root),
+            sha3_uncles: Hash::from(value.sha3_uncles),
+            size: NumBytes::from(value.size),
+            state_root: Hash::from(value.state_root),
+            timestamp: Timestamp::from(value.timestamp),
+            transactions: value.transactions.into_iter().map(Hash::from).collect(),
+            transactions_root: value.transactions_root.map(Hash::from),
+            uncles: value.uncles.into_iter().map(Hash::from).collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct FeeHistory {
     /// Lowest number block of the returned range.
@@ -379,6 +456,25 @@ impl From<FeeHistory> for evm_rpc_types::FeeHistory {
     }
 }
 
+impl From<evm_rpc_types::FeeHistory> for FeeHistory {
+    fn from(value: evm_rpc_types::FeeHistory) -> Self {
+        Self {
+            oldest_block: BlockNumber::from(value.oldest_block),
+            base_fee_per_gas: value
+                .base_fee_per_gas
+                .into_iter()
+                .map(WeiPerGas::from)
+                .collect(),
+            gas_used_ratio: value.gas_used_ratio,
+            reward: value
+                .reward
+                .into_iter()
+                .map(|x| x.into_iter().map(WeiPerGas::from).collect())
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub enum SendRawTransactionResult {
     Ok,
@@ -1,19 +1,31 @@
 use crate::{
     add_metric_entry,
     http::{
-        charging_policy_with_collateral, error::HttpClientError, http_client,
-        service_request_builder,
+        charge_cache_hit, charge_coalescing_round, charge_cycles, charging_policy_with_collateral,
+        error::HttpClientError, http_client, service_request_builder, ChargedCycles, Payer,
+    },
+    logs::Priority,
+    memory::{
+        abandon_in_flight_request, cache_response, complete_in_flight_request, credit_balance,
+        get_cached_response, get_chain, get_circuit_breaker_state, get_override_provider,
+        get_provider, join_in_flight_request, learned_response_size_estimate, rank_providers,
+        record_finalized_block, record_outcome, start_probes,
+    },
+    providers::{
+        resolve_rpc_service, RpcServiceOutcome, SupportedRpcService, SERVICE_PROVIDER_MAP,
     },
-    memory::{get_override_provider, rank_providers, record_ok_result},
-    providers::{resolve_rpc_service, SupportedRpcService},
     rpc_client::{
+        cache::CacheKey,
+        coalescing::{wait_for_completion, CoalescingKey, Role},
+        diagnostics::diagnose_result,
         eth_rpc::{
             ResponseSizeEstimate, ResponseTransform, ResponseTransformEnvelope, HEADER_SIZE_LIMIT,
         },
         json::responses::RawJson,
-        numeric::TransactionCount,
+        numeric::{BlockNumber, TransactionCount},
+        response_sizes::ResponseSizeKey,
     },
-    types::{MetricRpcMethod, MetricRpcService, ResolvedRpcService, RpcMethod},
+    types::{MetricProvider, MetricRpcMethod, MetricRpcService, ResolvedRpcService, RpcMethod},
 };
 use canhttp::{
     cycles::CyclesChargingPolicy,
@@ -22,11 +34,13 @@ use canhttp::{
         MultiResults, Reduce, ReduceWithEquality, ReduceWithThreshold, ReducedResult,
         ReductionError, Timestamp,
     },
-    MaxResponseBytesRequestExtension, TransformContextRequestExtension,
+    IsReplicatedRequestExtension, MaxResponseBytesRequestExtension,
+    TransformContextRequestExtension,
 };
+use canlog::log;
 use evm_rpc_types::{
-    ConsensusStrategy, JsonRpcError, MultiRpcResult, ProviderError, RpcConfig, RpcError, RpcResult,
-    RpcService, RpcServices,
+    CircuitBreakerState, ConsensusStrategy, JsonRpcError, MultiRpcResult, ProviderError, RpcConfig,
+    RpcError, RpcResult, RpcService, RpcServices,
 };
 use http::{Request, Response};
 use ic_management_canister_types::{
@@ -34,22 +48,30 @@ use ic_management_canister_types::{
 };
 use json::{
     requests::{
-        BlockSpec, EthCallParams, FeeHistoryParams, GetBlockByNumberParams, GetLogsParams,
-        GetTransactionCountParams,
+        BlockSpec, BlockTag, EthCallParams, FeeHistoryParams, GetBlockByNumberParams,
+        GetLogsParams, GetTransactionCountParams,
     },
     responses::{Block, Data, FeeHistory, LogEntry, SendRawTransactionResult, TransactionReceipt},
     Hash,
 };
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
-use std::{collections::BTreeSet, fmt::Debug};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Debug,
+    rc::Rc,
+};
 use tower::ServiceExt;
 
 pub mod amount;
+pub(crate) mod cache;
+pub(crate) mod coalescing;
+mod diagnostics;
 pub(crate) mod eth_rpc;
-mod eth_rpc_error;
+pub(crate) mod eth_rpc_error;
 pub(crate) mod json;
-mod numeric;
+pub(crate) mod numeric;
+pub(crate) mod response_sizes;
 
 #[cfg(test)]
 mod tests;
@@ -69,6 +91,10 @@ impl EthereumNetwork {
     pub const ARBITRUM: EthereumNetwork = EthereumNetwork(42161);
     pub const BASE: EthereumNetwork = EthereumNetwork(8453);
     pub const OPTIMISM: EthereumNetwork = EthereumNetwork(10);
+    pub const HOODI: EthereumNetwork = EthereumNetwork(560048);
+    pub const ARBITRUM_SEPOLIA: EthereumNetwork = EthereumNetwork(421614);
+    pub const BASE_SEPOLIA: EthereumNetwork = EthereumNetwork(84532);
+    pub const OPTIMISM_SEPOLIA: EthereumNetwork = EthereumNetwork(11155420);
 
     pub fn chain_id(&self) -> u64 {
         self.0
@@ -80,6 +106,8 @@ pub struct Providers {
     chain: EthereumNetwork,
     /// *Non-empty* set of providers to query.
     services: BTreeSet<RpcService>,
+    /// Other supported providers of the same chain, from the most to the least preferred.
+    fallback_services: Vec<RpcService>,
 }
 
 impl Providers {
@@ -113,14 +141,31 @@ impl Providers {
                 RpcServices::OptimismMainnet(services) => {
                     map_services(services, RpcService::OptimismMainnet)
                 }
+                RpcServices::EthHoodi(services) => map_services(services, RpcService::EthHoodi),
+                RpcServices::ArbitrumSepolia(services) => {
+                    map_services(services, RpcService::ArbitrumSepolia)
+                }
+                RpcServices::BaseSepolia(services) => {
+                    map_services(services, RpcService::BaseSepolia)
+                }
+                RpcServices::OptimismSepolia(services) => {
+                    map_services(services, RpcService::OptimismSepolia)
+                }
+                RpcServices::Chain { services, .. } => map_services(services, RpcService::Provider),
             }
         }
 
-        fn supported_providers(
+        /// Default providers of the chain, from the most to the least preferred.
+        ///
+        /// Providers that keep disagreeing with the majority are excluded, unless fewer than
+        /// `num_required` providers would remain.
+        fn default_providers(
             source: &RpcServices,
-        ) -> (EthereumNetwork, &'static [SupportedRpcService]) {
-            match source {
-                RpcServices::Custom { chain_id, .. } => (EthereumNetwork::from(*chain_id), &[]),
+            num_required: usize,
+            now: Timestamp,
+        ) -> Result<(EthereumNetwork, Vec<RpcService>), ProviderError> {
+            let (chain, supported_providers) = match source {
+                RpcServices::Custom { chain_id, .. } => (EthereumNetwork::from(*chain_id), &[][..]),
                 RpcServices::EthMainnet(_) => {
                     (EthereumNetwork::MAINNET, SupportedRpcService::eth_mainnet())
                 }
@@ -138,42 +183,140 @@ impl Providers {
                     EthereumNetwork::OPTIMISM,
                     SupportedRpcService::optimism_mainnet(),
                 ),
-            }
+                RpcServices::EthHoodi(_) => {
+                    (EthereumNetwork::HOODI, SupportedRpcService::eth_hoodi())
+                }
+                RpcServices::ArbitrumSepolia(_) => (
+                    EthereumNetwork::ARBITRUM_SEPOLIA,
+                    SupportedRpcService::arbitrum_sepolia(),
+                ),
+                RpcServices::BaseSepolia(_) => (
+                    EthereumNetwork::BASE_SEPOLIA,
+                    SupportedRpcService::base_sepolia(),
+                ),
+                RpcServices::OptimismSepolia(_) => (
+                    EthereumNetwork::OPTIMISM_SEPOLIA,
+                    SupportedRpcService::optimism_sepolia(),
+                ),
+                RpcServices::Chain { chain_id, services } => {
+                    let chain = get_chain(*chain_id).ok_or_else(|| {
+                        ProviderError::InvalidRpcConfig(format!("Unknown chain ID {}", chain_id))
+                    })?;
+                    for provider_id in services.iter().flatten() {
+                        let provider = get_provider(*provider_id).ok_or_else(|| {
+                            ProviderError::InvalidRpcConfig(format!(
+                                "Unknown provider ID {}",
+                                provider_id
+                            ))
+                        })?;
+                        if provider.chain_id != *chain_id {
+                            return Err(ProviderError::InvalidRpcConfig(format!(
+                                "Provider {} is not a provider of chain {}",
+                                provider_id, chain_id
+                            )));
+                        }
+                    }
+                    let providers: Vec<_> = chain
+                        .default_providers
+                        .into_iter()
+                        .filter_map(get_provider)
+                        .filter(|provider| provider.chain_id == *chain_id)
+                        .collect();
+                    // Providers that are supported RPC services are ranked by their observed
+                    // health and take the places of the supported RPC services in the order
+                    // of the chain, while the other providers keep their places.
+                    let supported_providers: Vec<_> = providers
+                        .iter()
+                        .filter_map(|provider| provider.alias)
+                        .collect();
+                    let num_required =
+                        num_required.saturating_sub(providers.len() - supported_providers.len());
+                    let mut ranked =
+                        rank_providers(&supported_providers, num_required, now).into_iter();
+                    let default_providers = providers
+                        .iter()
+                        .filter_map(|provider| match provider.alias {
+                            Some(_) => {
+                                let alias = ranked.next()?;
+                                providers
+                                    .iter()
+                                    .find(|provider| provider.alias == Some(alias))
+                                    .map(|provider| RpcService::Provider(provider.provider_id))
+                            }
+                            None => Some(RpcService::Provider(provider.provider_id)),
+                        })
+                        .collect();
+                    return Ok((EthereumNetwork::from(*chain_id), default_providers));
+                }
+            };
+            // Supported providers may have been removed from the provider registry
+            let supported_providers: Vec<_> = SERVICE_PROVIDER_MAP.with_borrow(|map| {
+                supported_providers
+                    .iter()
+                    .filter(|service| map.contains_key(service))
+                    .copied()
+                    .collect()
+            });
+            let default_providers = rank_providers(&supported_providers, num_required, now)
+                .into_iter()
+                .map(RpcService::from)
+                .collect();
+            Ok((chain, default_providers))
         }
 
-        let (chain, supported_providers) = supported_providers(&source);
+        // Number of default providers that are queried if no providers are specified
+        let num_required = match strategy {
+            ConsensusStrategy::Equality => Self::DEFAULT_NUM_PROVIDERS_FOR_EQUALITY,
+            ConsensusStrategy::Threshold { total, .. }
+            | ConsensusStrategy::BlockLag { total, .. } => total.unwrap_or_default() as usize,
+        };
+        let (chain, default_providers) = default_providers(&source, num_required, now)?;
         let user_input = user_defined_providers(source);
-        let providers = choose_providers(user_input, supported_providers, strategy, now)?;
+        let providers = choose_providers(user_input, &default_providers, strategy)?;
 
         if providers.is_empty() {
             return Err(ProviderError::ProviderNotFound);
         }
 
+        // A half-open circuit breaker only lets the call probing the provider through,
+        // so half-open providers are not kept as fallback providers.
+        start_probes(providers.iter().filter_map(SupportedRpcService::new), now);
+        let fallback_services = default_providers
+            .into_iter()
+            .filter(|service| !providers.contains(service))
+            .filter(|service| {
+                SupportedRpcService::new(service).is_none_or(|supported| {
+                    get_circuit_breaker_state(supported, now) == CircuitBreakerState::Closed
+                })
+            })
+            .collect();
+
         Ok(Self {
             chain,
             services: providers,
+            fallback_services,
         })
     }
 }
 
 fn choose_providers(
     user_input: Option<Vec<RpcService>>,
-    supported_providers: &[SupportedRpcService],
+    default_providers: &[RpcService],
     strategy: ConsensusStrategy,
-    now: Timestamp,
 ) -> Result<BTreeSet<RpcService>, ProviderError> {
     match strategy {
         ConsensusStrategy::Equality => Ok(user_input
             .unwrap_or_else(|| {
-                rank_providers(supported_providers, now)
-                    .into_iter()
+                default_providers
+                    .iter()
                     .take(Providers::DEFAULT_NUM_PROVIDERS_FOR_EQUALITY)
-                    .map(RpcService::from)
+                    .cloned()
                     .collect()
             })
             .into_iter()
             .collect()),
-        ConsensusStrategy::Threshold { total, min } => {
+        ConsensusStrategy::Threshold { total, min }
+        | ConsensusStrategy::BlockLag { total, min, .. } => {
             // Ensure that
             // 0 < min <= total <= all_providers.len()
             if min == 0 {
@@ -196,17 +339,17 @@ fn choose_providers(
                         )));
                     }
 
-                    let all_providers_len = supported_providers.len();
+                    let all_providers_len = default_providers.len();
                     if total > all_providers_len as u8 {
                         return Err(ProviderError::InvalidRpcConfig(format!(
                             "total {} is greater than the number of all supported providers {}",
                             total, all_providers_len
                         )));
                     }
-                    let providers: BTreeSet<_> = rank_providers(supported_providers, now)
-                        .into_iter()
+                    let providers: BTreeSet<_> = default_providers
+                        .iter()
                         .take(total as usize)
-                        .map(RpcService::from)
+                        .cloned()
                         .collect();
                     assert_eq!(providers.len(), total as usize, "BUG: duplicate providers");
                     Ok(providers)
@@ -239,6 +382,7 @@ fn choose_providers(
 pub struct EthRpcClient {
     providers: Providers,
     config: RpcConfig,
+    payer: Payer,
 }
 
 impl EthRpcClient {
@@ -252,15 +396,34 @@ impl EthRpcClient {
         Ok(Self {
             providers: Providers::new(source, strategy, now)?,
             config,
+            payer: Payer::default(),
         })
     }
 
+    /// Charge the HTTP outcalls to the given payer instead of the caller.
+    pub fn with_payer(mut self, payer: Payer) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    /// Account paying for the HTTP outcalls.
+    pub fn payer(&self) -> Payer {
+        self.payer
+    }
+
     fn chain(&self) -> EthereumNetwork {
         self.providers.chain
     }
 
+    pub fn chain_id(&self) -> u64 {
+        self.chain().chain_id()
+    }
+
     fn response_size_estimate(&self, estimate: u64) -> ResponseSizeEstimate {
-        ResponseSizeEstimate::new(self.config.response_size_estimate.unwrap_or(estimate))
+        match self.config.response_size_estimate {
+            Some(estimate) => ResponseSizeEstimate::new(estimate),
+            None => ResponseSizeEstimate::new(estimate).learnable(),
+        }
     }
 
     fn reduction_strategy(&self) -> ReductionStrategy {
@@ -273,12 +436,77 @@ impl EthRpcClient {
         )
     }
 
+    fn diagnostics(&self) -> bool {
+        self.config.diagnostics.unwrap_or_default()
+    }
+
+    fn is_replicated(&self) -> bool {
+        self.config.is_replicated.unwrap_or(true)
+    }
+
+    /// Whether responses may be recorded as the state of the chain, e.g. cached or recorded as
+    /// the latest finalized block.
+    ///
+    /// Responses of custom providers may not, since custom providers may claim to be providers
+    /// of any chain. Responses of non-replicated HTTP outcalls may not either, since they were
+    /// returned by a single node.
+    pub fn can_record_chain_state(&self) -> bool {
+        self.is_replicated()
+            && !self
+                .providers
+                .services
+                .iter()
+                .any(|service| matches!(service, RpcService::Custom(_)))
+    }
+
+    /// Cache the response once the block with the number returned by `block_number` is finalized,
+    /// see [`Self::can_record_chain_state`].
+    fn caching<Output>(
+        &self,
+        block_number: fn(&Output) -> Option<BlockNumber>,
+    ) -> Option<ResponseCaching<Output>> {
+        if !self.can_record_chain_state() {
+            return None;
+        }
+        Some(ResponseCaching {
+            chain_id: self.chain().chain_id(),
+            block_number,
+            is_finalized_block: false,
+        })
+    }
+
+    /// Requests are only coalesced with identical requests made with the same config.
+    fn coalescing(&self) -> String {
+        format!("{:?}", self.config)
+    }
+
+    fn fallback(&self) -> Option<FallbackProviders> {
+        if !self.config.fallback_providers.unwrap_or_default() {
+            return None;
+        }
+        match self.config.response_consensus.as_ref()? {
+            ConsensusStrategy::Equality => None,
+            ConsensusStrategy::Threshold { min, .. } | ConsensusStrategy::BlockLag { min, .. } => {
+                Some(FallbackProviders {
+                    min: *min as usize,
+                    services: self.providers.fallback_services.clone(),
+                })
+            }
+        }
+    }
+
     pub fn eth_get_logs(
         self,
         params: GetLogsParams,
     ) -> MultiRpcRequest<(GetLogsParams,), Vec<LogEntry>> {
         let response_size_estimate = self.response_size_estimate(1024 + HEADER_SIZE_LIMIT);
         let reduction = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
+        let block_heads = matches!(reduction, ReductionStrategy::ByBlockLag(_))
+            && params.to_block == BlockSpec::Tag(BlockTag::Latest);
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthGetLogs,
@@ -287,6 +515,35 @@ impl EthRpcClient {
             ResponseTransform::GetLogs,
             reduction,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
+        .with_block_heads(block_heads)
+    }
+
+    pub fn eth_block_number(self) -> MultiRpcRequest<[(); 0], BlockNumber> {
+        let response_size_estimate = self.response_size_estimate(256 + HEADER_SIZE_LIMIT);
+        let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
+        MultiRpcRequest::new(
+            self.providers.services,
+            RpcMethod::EthBlockNumber,
+            // Serialized as an empty array of parameters
+            [],
+            response_size_estimate,
+            ResponseTransform::BlockNumber,
+            reduction_strategy,
+        )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 
     pub fn eth_get_block_by_number(
@@ -296,11 +553,30 @@ impl EthRpcClient {
         let expected_block_size = match self.chain() {
             EthereumNetwork::SEPOLIA => 12 * 1024,
             EthereumNetwork::MAINNET => 24 * 1024,
-            _ => 24 * 1024, // Default for unknown networks
+            EthereumNetwork::HOODI => 12 * 1024,
+            EthereumNetwork::BASE_SEPOLIA => 16 * 1024,
+            EthereumNetwork::ARBITRUM_SEPOLIA | EthereumNetwork::OPTIMISM_SEPOLIA => 8 * 1024,
+            chain => get_chain(chain.chain_id())
+                .and_then(|chain| chain.block_size_estimate)
+                .unwrap_or(24 * 1024), // Default for unknown networks
         };
         let response_size_estimate =
             self.response_size_estimate(expected_block_size + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
+        let caching = match &block {
+            BlockSpec::Number(_) => self.caching(|block: &Block| Some(block.number)),
+            BlockSpec::Tag(BlockTag::Finalized) => self
+                .caching(|block: &Block| Some(block.number))
+                .map(|caching| ResponseCaching {
+                    is_finalized_block: true,
+                    ..caching
+                }),
+            BlockSpec::Tag(_) => None,
+        };
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthGetBlockByNumber,
@@ -312,6 +588,12 @@ impl EthRpcClient {
             ResponseTransform::GetBlockByNumber,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
+        .with_caching(caching)
     }
 
     pub fn eth_get_transaction_receipt(
@@ -320,6 +602,13 @@ impl EthRpcClient {
     ) -> MultiRpcRequest<(Hash,), Option<TransactionReceipt>> {
         let response_size_estimate = self.response_size_estimate(700 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
+        let caching = self.caching(|receipt: &Option<TransactionReceipt>| {
+            receipt.as_ref().map(|receipt| receipt.block_number)
+        });
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthGetTransactionReceipt,
@@ -328,6 +617,12 @@ impl EthRpcClient {
             ResponseTransform::GetTransactionReceipt,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
+        .with_caching(caching)
     }
 
     pub fn eth_fee_history(
@@ -337,6 +632,10 @@ impl EthRpcClient {
         // A typical response is slightly above 300 bytes.
         let response_size_estimate = self.response_size_estimate(512 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthFeeHistory,
@@ -345,6 +644,11 @@ impl EthRpcClient {
             ResponseTransform::FeeHistory,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 
     pub fn eth_send_raw_transaction(
@@ -355,6 +659,10 @@ impl EthRpcClient {
         // since we submit the same transaction from multiple nodes.
         let response_size_estimate = self.response_size_estimate(256 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthSendRawTransaction,
@@ -363,6 +671,11 @@ impl EthRpcClient {
             ResponseTransform::SendRawTransaction,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 
     pub fn eth_get_transaction_count(
@@ -371,6 +684,10 @@ impl EthRpcClient {
     ) -> MultiRpcRequest<GetTransactionCountParams, TransactionCount> {
         let response_size_estimate = self.response_size_estimate(50 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthGetTransactionCount,
@@ -379,11 +696,20 @@ impl EthRpcClient {
             ResponseTransform::GetTransactionCount,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 
     pub fn eth_call(self, params: EthCallParams) -> MultiRpcRequest<EthCallParams, Data> {
         let response_size_estimate = self.response_size_estimate(256 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
         MultiRpcRequest::new(
             self.providers.services,
             RpcMethod::EthCall,
@@ -392,6 +718,11 @@ impl EthRpcClient {
             ResponseTransform::Call,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 
     pub fn multi_request(
@@ -401,6 +732,10 @@ impl EthRpcClient {
     ) -> MultiRpcRequest<Option<&Value>, RawJson> {
         let response_size_estimate = self.response_size_estimate(256 + HEADER_SIZE_LIMIT);
         let reduction_strategy = self.reduction_strategy();
+        let diagnostics = self.diagnostics();
+        let fallback = self.fallback();
+        let coalescing = self.coalescing();
+        let is_replicated = self.is_replicated();
         MultiRpcRequest::new(
             self.providers.services,
             method,
@@ -409,6 +744,11 @@ impl EthRpcClient {
             ResponseTransform::Raw,
             reduction_strategy,
         )
+        .with_diagnostics(diagnostics)
+        .with_fallback(fallback)
+        .with_coalescing(coalescing)
+        .with_is_replicated(is_replicated)
+        .with_payer(self.payer)
     }
 }
 
@@ -419,9 +759,55 @@ pub struct MultiRpcRequest<Params, Output> {
     response_size_estimate: ResponseSizeEstimate,
     transform: ResponseTransformEnvelope,
     reduction_strategy: ReductionStrategy,
+    diagnostics: bool,
+    fallback: Option<FallbackProviders>,
+    caching: Option<ResponseCaching<Output>>,
+    coalescing: Option<String>,
+    is_replicated: bool,
+    block_heads: bool,
+    charged_cycles: ChargedCycles,
+    payer: Payer,
     _marker: std::marker::PhantomData<Output>,
 }
 
+/// Caching of a response that can no longer change once the block that contains it is finalized,
+/// see [`crate::rpc_client::cache::ResponseCache`].
+pub struct ResponseCaching<Output> {
+    chain_id: u64,
+    /// Number of the block that contains the response, if any.
+    block_number: fn(&Output) -> Option<BlockNumber>,
+    /// Whether the response is the latest finalized block, whose number is recorded
+    /// instead of caching the response.
+    is_finalized_block: bool,
+}
+
+/// Providers to query in a second round if too few providers returned a successful result
+/// for the consensus threshold to be reached.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FallbackProviders {
+    /// Minimum number of providers that must return the same result.
+    min: usize,
+    /// Candidate providers, from the most to the least preferred.
+    services: Vec<RpcService>,
+}
+
+impl FallbackProviders {
+    /// Providers to query in the second round if only `num_ok_results` of the providers
+    /// queried in the first round returned a successful result.
+    fn providers_to_query(
+        &self,
+        queried: &BTreeSet<RpcService>,
+        num_ok_results: usize,
+    ) -> BTreeSet<RpcService> {
+        self.services
+            .iter()
+            .filter(|service| !queried.contains(service))
+            .take(self.min.saturating_sub(num_ok_results))
+            .cloned()
+            .collect()
+    }
+}
+
 impl<Params, Output> MultiRpcRequest<Params, Output> {
     pub fn new(
         providers: BTreeSet<RpcService>,
@@ -438,19 +824,241 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
             response_size_estimate,
             transform: transform.into(),
             reduction_strategy,
+            diagnostics: false,
+            fallback: None,
+            caching: None,
+            coalescing: None,
+            is_replicated: true,
+            block_heads: false,
+            charged_cycles: ChargedCycles::default(),
+            payer: Payer::default(),
             _marker: Default::default(),
         }
     }
+
+    /// Return inconsistent results together with a summary of their differences.
+    pub fn with_diagnostics(mut self, diagnostics: bool) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Query additional providers if too few providers returned a successful result.
+    pub fn with_fallback(mut self, fallback: Option<FallbackProviders>) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Serve the response from the cache, and cache it once it can no longer change.
+    pub fn with_caching(mut self, caching: Option<ResponseCaching<Output>>) -> Self {
+        self.caching = caching;
+        self
+    }
+
+    /// Wait for the result of an identical in-flight request made with the given config,
+    /// instead of making HTTP outcalls.
+    pub fn with_coalescing(mut self, config: String) -> Self {
+        self.coalescing = Some(config);
+        self
+    }
+
+    /// Make HTTP outcalls with a single node instead of all nodes of the subnet.
+    pub fn with_is_replicated(mut self, is_replicated: bool) -> Self {
+        self.is_replicated = is_replicated;
+        self
+    }
+
+    /// Query the latest block number of each provider beforehand, at which its result is
+    /// observed when reducing results with [`ReduceWithBlockLag`].
+    pub fn with_block_heads(mut self, block_heads: bool) -> Self {
+        self.block_heads = block_heads;
+        self
+    }
+
+    /// Charge the HTTP outcalls to the given payer instead of the caller.
+    pub fn with_payer(mut self, payer: Payer) -> Self {
+        self.payer = payer;
+        self
+    }
+
+    fn block_heads_request(&self) -> MultiRpcRequest<[(); 0], BlockNumber> {
+        // Heads of providers lagging behind by a few blocks are expected and must not be
+        // recorded as disagreements, so the heads are compared like the results.
+        let reduction_strategy = match &self.reduction_strategy {
+            ReductionStrategy::ByBlockLag(reduction) => {
+                ReductionStrategy::ByBlockLag(reduction.clone())
+            }
+            _ => ReductionStrategy::ByEquality(ReduceWithEquality),
+        };
+        let mut request = MultiRpcRequest::new(
+            self.providers.clone(),
+            RpcMethod::EthBlockNumber,
+            // Serialized as an empty array of parameters
+            [],
+            ResponseSizeEstimate::new(256 + HEADER_SIZE_LIMIT),
+            ResponseTransform::BlockNumber,
+            reduction_strategy,
+        )
+        .with_is_replicated(self.is_replicated)
+        .with_payer(self.payer);
+        request.charged_cycles = self.charged_cycles.clone();
+        request
+    }
 }
 
 impl<Params, Output> MultiRpcRequest<Params, Output> {
     pub async fn send_and_reduce(self) -> MultiRpcResult<Output>
     where
         Params: Serialize + Clone + Debug,
-        Output: Debug + Serialize + DeserializeOwned + PartialEq,
+        Output: Debug + Serialize + DeserializeOwned + PartialEq + Clone + BlockIndexed + 'static,
     {
-        let result = self.parallel_call().await.reduce(self.reduction_strategy);
-        process_result(self.method, result)
+        let now = ic_cdk::api::time();
+        let cache_key = self.cache_key();
+        if let Some(key) = &cache_key {
+            let cached = get_cached_response(key, now)
+                .and_then(|response| serde_json::from_str::<Output>(&response).ok());
+            let method = MetricRpcMethod::from(self.method.clone());
+            match cached {
+                Some(output) => {
+                    add_metric_entry!(cache_hits, method, 1);
+                    return MultiRpcResult::Consistent(
+                        charge_cache_hit(self.payer).map(|()| output),
+                    );
+                }
+                None => add_metric_entry!(cache_misses, method, 1),
+            }
+        }
+
+        let Some(coalescing_key) = self.coalescing_key() else {
+            return self.call_and_reduce(cache_key, now).await;
+        };
+        let payer = self.payer.principal();
+        match join_in_flight_request(coalescing_key.clone(), payer, now) {
+            Role::Leader => {
+                let mut in_flight = InFlightGuard(Some(coalescing_key));
+                let result = self.call_and_reduce(cache_key, now).await;
+                // Only a successful consistent result is shared, since waiting requests
+                // could get a different result with their own HTTP outcalls.
+                if let (MultiRpcResult::Consistent(Ok(_)), Some(key)) =
+                    (&result, in_flight.0.take())
+                {
+                    let cycles_cost = self.charged_cycles.get();
+                    complete_in_flight_request(&key, Rc::new(result.clone()), cycles_cost);
+                }
+                result
+            }
+            Role::Follower { slot, deadline } => {
+                add_metric_entry!(
+                    coalesced_requests,
+                    MetricRpcMethod::from(self.method.clone()),
+                    1
+                );
+                let completion = wait_for_completion(slot, deadline, || {
+                    charge_coalescing_round(self.payer).is_ok()
+                })
+                .await;
+                let Some((result, completion)) = completion.and_then(|completion| {
+                    let result = completion
+                        .result
+                        .downcast_ref::<MultiRpcResult<Output>>()
+                        .cloned()?;
+                    Some((result, completion))
+                }) else {
+                    return self.call_and_reduce(cache_key, ic_cdk::api::time()).await;
+                };
+                if completion.share > 0 {
+                    match charge_cycles(self.payer, completion.share) {
+                        Ok(0) => {}
+                        // The leader is only refunded with cycles that were actually debited.
+                        Ok(debited) => credit_balance(completion.leader, debited),
+                        Err(e) => return MultiRpcResult::Consistent(Err(e)),
+                    }
+                }
+                result
+            }
+        }
+    }
+
+    /// Make the HTTP outcalls, reduce their results and cache the reduced result if possible.
+    async fn call_and_reduce(&self, cache_key: Option<CacheKey>, now: u64) -> MultiRpcResult<Output>
+    where
+        Params: Serialize + Clone + Debug,
+        Output: Debug + Serialize + DeserializeOwned + PartialEq + Clone + BlockIndexed,
+    {
+        let heads = if self.block_heads {
+            let heads_request = self.block_heads_request();
+            let (heads, _errors) = heads_request
+                .parallel_call(&heads_request.providers)
+                .await
+                .into_inner();
+            Some(heads)
+        } else {
+            None
+        };
+        let results = self.parallel_call(&self.providers).await;
+        let results = self.call_fallback_providers(results).await;
+        let unanimous = cache_key.as_ref().and_then(|_| unanimous_result(&results));
+        let result = match (&self.reduction_strategy, &heads) {
+            (ReductionStrategy::ByBlockLag(reduction), Some(heads)) => {
+                reduction.reduce_at_heads(results, heads)
+            }
+            (reduction, _) => reduction.reduce(results),
+        };
+        let result = process_result(self.method.clone(), result);
+
+        if let Some(caching) = &self.caching {
+            if caching.is_finalized_block {
+                if let MultiRpcResult::Consistent(Ok(block)) = &result {
+                    if let Some(number) = (caching.block_number)(block) {
+                        record_finalized_block(caching.chain_id, number);
+                    }
+                }
+            } else if let (Some(key), Some(output)) = (cache_key, unanimous) {
+                if let (Some(number), Ok(response)) = (
+                    (caching.block_number)(&output),
+                    serde_json::to_string(&output),
+                ) {
+                    cache_response(key, number, response, now);
+                }
+            }
+        }
+        diagnose_result(self.method.clone(), result, self.diagnostics)
+    }
+
+    /// Key identifying identical requests, if the request can be coalesced.
+    ///
+    /// Requests paid by the canister are never coalesced, since the canister cannot pay
+    /// its share of the HTTP outcalls to the caller of the in-flight request.
+    fn coalescing_key(&self) -> Option<CoalescingKey>
+    where
+        Params: Serialize,
+    {
+        if self.payer == Payer::Canister {
+            return None;
+        }
+        let config = self.coalescing.as_ref()?;
+        Some(CoalescingKey {
+            providers: self.providers.clone(),
+            method: self.method.clone().name(),
+            params: serde_json::to_string(&self.params).ok()?,
+            config: format!("{config} {}", self.response_size_estimate),
+        })
+    }
+
+    /// Key of the response in the cache, if the response can be cached.
+    fn cache_key(&self) -> Option<CacheKey>
+    where
+        Params: Serialize,
+    {
+        let caching = self
+            .caching
+            .as_ref()
+            .filter(|caching| !caching.is_finalized_block)?;
+        Some(CacheKey {
+            chain_id: caching.chain_id,
+            providers: self.providers.clone(),
+            method: self.method.clone().name(),
+            params: serde_json::to_string(&self.params).ok()?,
+        })
     }
 
     /// Query all providers in parallel and return all results.
@@ -458,36 +1066,152 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
     /// (e.g., if different providers gave different responses).
     /// This method is useful for querying data that is critical for the system to ensure that there is no single point of failure,
     /// e.g., ethereum logs upon which ckETH will be minted.
-    async fn parallel_call(&self) -> MultiResults<RpcService, Output, RpcError>
+    async fn parallel_call(
+        &self,
+        providers: &BTreeSet<RpcService>,
+    ) -> MultiResults<RpcService, Output, RpcError>
     where
         Params: Serialize + Clone + Debug,
-        Output: Debug + DeserializeOwned,
+        Output: Debug + Serialize + DeserializeOwned + PartialEq,
     {
-        let requests = self.create_json_rpc_requests();
+        let requests = self.create_json_rpc_requests(providers);
 
-        let client = http_client(true).map_result(extract_json_rpc_response);
+        let client = http_client(true, self.charged_cycles.clone(), self.payer)
+            .map_result(extract_json_rpc_response);
 
         let (requests, errors) = requests.into_inner();
         let (_client, mut results) = canhttp::multi::parallel_call(client, requests).await;
         results.add_errors(errors);
-        let now = Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time());
-        results
-            .ok_results()
-            .keys()
-            .filter_map(SupportedRpcService::new)
-            .for_each(|service| record_ok_result(service, now));
+        let results = self.record_outcomes(
+            results,
+            Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time()),
+        );
         assert_eq!(
             results.len(),
-            self.providers.len(),
+            providers.len(),
             "BUG: expected 1 result per provider"
         );
         results
     }
 
+    /// Top up the results with the results of fallback providers if too few providers
+    /// returned a successful result for the consensus threshold to be reached.
+    ///
+    /// Fallback providers are only queried if the cycles available to the payer cover their cost.
+    async fn call_fallback_providers(
+        &self,
+        mut results: MultiResults<RpcService, Output, RpcError>,
+    ) -> MultiResults<RpcService, Output, RpcError>
+    where
+        Params: Serialize + Clone + Debug,
+        Output: Debug + Serialize + DeserializeOwned + PartialEq,
+    {
+        let Some(fallback) = &self.fallback else {
+            return results;
+        };
+        let num_ok_results = results.ok_results().len();
+        if num_ok_results >= fallback.min {
+            // The consensus threshold can only fail to be reached because of disagreements
+            return results;
+        }
+        let providers = fallback.providers_to_query(&self.providers, num_ok_results);
+        if providers.is_empty() {
+            return results;
+        }
+        match self.cycles_cost_for(&providers).await {
+            Ok(cycles_cost) if cycles_cost <= self.payer.available_cycles() => {}
+            _ => return results,
+        }
+
+        log!(
+            Priority::Info,
+            "Querying fallback providers {:?} for {}",
+            providers,
+            self.method.clone().name()
+        );
+        let (ok_results, errors) = self.parallel_call(&providers).await.into_inner();
+        for (service, result) in ok_results {
+            results.insert_once(service, Ok(result));
+        }
+        results.add_errors(errors);
+        results
+    }
+
+    /// Record the outcome of the call to each supported provider, which is used to rank providers.
+    ///
+    /// Outcomes of non-replicated HTTP outcalls are not recorded, since they are made by a single
+    /// node and a single faulty node must not be able to affect the ranking of providers.
+    fn record_outcomes(
+        &self,
+        results: MultiResults<RpcService, Output, RpcError>,
+        now: Timestamp,
+    ) -> MultiResults<RpcService, Output, RpcError>
+    where
+        Output: PartialEq,
+    {
+        if !self.is_replicated {
+            return results;
+        }
+        let (ok_results, errors) = results.into_inner();
+        let disagreeing = if self.reduction_strategy.compares_exact_results() {
+            disagreeing_providers(&ok_results)
+        } else {
+            BTreeSet::new()
+        };
+        for service in ok_results.keys() {
+            if let Some(supported) = SupportedRpcService::new(service) {
+                let outcome = if disagreeing.contains(service) {
+                    RpcServiceOutcome::Disagreement
+                } else {
+                    RpcServiceOutcome::Ok
+                };
+                record_outcome_and_observe(supported, outcome, now);
+            }
+        }
+        for (service, error) in errors.iter() {
+            if let (Some(supported), Some(outcome)) = (
+                SupportedRpcService::new(service),
+                RpcServiceOutcome::from_error(error),
+            ) {
+                record_outcome_and_observe(supported, outcome, now);
+            }
+        }
+
+        let mut results = MultiResults::default();
+        for (service, result) in ok_results {
+            results.insert_once(service, Ok(result));
+        }
+        results.add_errors(errors);
+        results
+    }
+
     /// Estimate the exact cycles cost for the given request.
     ///
+    /// With fallback providers, the estimate includes the cost of the second round in which
+    /// fallback providers are queried if all providers of the first round failed.
+    ///
     /// *IMPORTANT*: the method is *synchronous* in a canister environment.
     pub async fn cycles_cost(&self) -> RpcResult<u128>
+    where
+        Params: Serialize + Clone + Debug,
+    {
+        let mut cycles_cost = self.cycles_cost_for(&self.providers).await?;
+        if let Some(fallback) = &self.fallback {
+            let providers = fallback.providers_to_query(&self.providers, 0);
+            if !providers.is_empty() {
+                cycles_cost += self.cycles_cost_for(&providers).await?;
+            }
+        }
+        if self.block_heads {
+            let heads_request = self.block_heads_request();
+            cycles_cost += heads_request
+                .cycles_cost_for(&heads_request.providers)
+                .await?;
+        }
+        Ok(cycles_cost)
+    }
+
+    async fn cycles_cost_for(&self, providers: &BTreeSet<RpcService>) -> RpcResult<u128>
     where
         Params: Serialize + Clone + Debug,
     {
@@ -497,7 +1221,7 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
             Ok(Response::new(request))
         }
 
-        let requests = self.create_json_rpc_requests();
+        let requests = self.create_json_rpc_requests(providers);
 
         let client = service_request_builder()
             .service_fn(extract_request)
@@ -519,7 +1243,7 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
         }
         assert_eq!(
             requests.len(),
-            self.providers.len(),
+            providers.len(),
             "BUG: expected 1 result per provider"
         );
 
@@ -535,6 +1259,7 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
 
     fn create_json_rpc_requests(
         &self,
+        providers: &BTreeSet<RpcService>,
     ) -> MultiResults<RpcService, Request<JsonRpcRequest<Params>>, RpcError>
     where
         Params: Clone,
@@ -544,22 +1269,31 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
             minicbor::encode(&self.transform, &mut buf).unwrap();
             buf
         };
-        let effective_size_estimate = self.response_size_estimate.get();
         let mut requests = MultiResults::default();
-        for provider in self.providers.iter() {
+        for provider in providers.iter() {
+            let effective_size_estimate = self.effective_response_size_estimate(provider);
             let request = resolve_rpc_service(provider.clone())
                 .map_err(RpcError::from)
                 .and_then(|rpc_service| rpc_service.post(&get_override_provider()))
                 .map(|builder| {
-                    builder
+                    let builder = builder
                         .max_response_bytes(effective_size_estimate)
-                        .transform_context(TransformContext {
-                            function: TransformFunc(candid::Func {
-                                method: "cleanup_response".to_string(),
-                                principal: ic_cdk::api::canister_self(),
-                            }),
-                            context: transform_op.clone(),
-                        })
+                        .is_replicated(self.is_replicated);
+                    // Without consensus, the response only needs to be transformed if it is
+                    // otherwise not valid.
+                    let builder =
+                        if self.is_replicated || self.transform.is_required_without_consensus() {
+                            builder.transform_context(TransformContext {
+                                function: TransformFunc(candid::Func {
+                                    method: "cleanup_response".to_string(),
+                                    principal: ic_cdk::api::canister_self(),
+                                }),
+                                context: transform_op.clone(),
+                            })
+                        } else {
+                            builder
+                        };
+                    builder
                         .body(JsonRpcRequest::new(
                             self.method.clone().name(),
                             self.params.clone(),
@@ -575,12 +1309,45 @@ impl<Params, Output> MultiRpcRequest<Params, Output> {
                     request
                         .extensions_mut()
                         .insert(MetricRpcMethod::from(self.method.clone()));
+                    // Record the size of the response body, from which response size estimates
+                    // are learned.
+                    if let Some(key) = self.response_size_key(provider) {
+                        request.extensions_mut().insert(key);
+                    }
                     request
                 });
             requests.insert_once(provider.clone(), request);
         }
         requests
     }
+
+    /// Key under which the size of the response of the provider is recorded, unless the method
+    /// is a custom method, the provider is a custom provider, or the HTTP outcall is not
+    /// replicated, in which case the response is returned by a single node.
+    fn response_size_key(&self, provider: &RpcService) -> Option<ResponseSizeKey> {
+        if !self.is_replicated || matches!(self.method, RpcMethod::Custom(_)) {
+            return None;
+        }
+        Some(ResponseSizeKey {
+            method: self.method.clone().name(),
+            service: SupportedRpcService::new(provider)?,
+        })
+    }
+
+    /// The estimate learned from past responses of the provider, unless the caller gave
+    /// an estimate or not enough responses of the provider were recorded.
+    fn effective_response_size_estimate(&self, provider: &RpcService) -> u64 {
+        if self.response_size_estimate.is_learnable()
+            && !matches!(self.method, RpcMethod::Custom(_))
+        {
+            if let Some(estimate) = SupportedRpcService::new(provider).and_then(|supported| {
+                learned_response_size_estimate(&self.method.clone().name(), supported)
+            }) {
+                return estimate;
+            }
+        }
+        self.response_size_estimate.get()
+    }
 }
 
 fn extract_json_rpc_response<O>(result: RpcResult<HttpJsonRpcResponse<O>>) -> RpcResult<O> {
@@ -596,6 +1363,80 @@ fn extract_json_rpc_response<O>(result: RpcResult<HttpJsonRpcResponse<O>>) -> Rp
 pub enum ReductionStrategy {
     ByEquality(ReduceWithEquality),
     ByThreshold(ReduceWithThreshold),
+    ByBlockLag(ReduceWithBlockLag),
+}
+
+fn record_outcome_and_observe(
+    service: SupportedRpcService,
+    outcome: RpcServiceOutcome,
+    now: Timestamp,
+) {
+    if record_outcome(service, outcome, now) {
+        log!(
+            Priority::Info,
+            "Circuit breaker of {:?} opened after consecutive failures",
+            service
+        );
+        add_metric_entry!(circuit_breaker_trips, MetricProvider::from(service), 1);
+    }
+}
+
+impl ReductionStrategy {
+    /// Whether agreeing results must be exactly equal.
+    fn compares_exact_results(&self) -> bool {
+        match self {
+            ReductionStrategy::ByEquality(_) | ReductionStrategy::ByThreshold(_) => true,
+            ReductionStrategy::ByBlockLag(_) => false,
+        }
+    }
+}
+
+/// Abandons the in-flight request when dropped before it completed, e.g. if the call trapped,
+/// so that waiting requests make their own HTTP outcalls.
+struct InFlightGuard(Option<CoalescingKey>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        if let Some(key) = self.0.take() {
+            abandon_in_flight_request(&key);
+        }
+    }
+}
+
+/// The result returned by all providers, if they all returned the same successful result.
+fn unanimous_result<T: PartialEq + Clone>(
+    results: &MultiResults<RpcService, T, RpcError>,
+) -> Option<T> {
+    let ok_results = results.ok_results();
+    let mut values = ok_results.values();
+    let first = values.next()?;
+    (ok_results.len() == results.len() && values.all(|value| value == first)).then(|| first.clone())
+}
+
+/// Providers whose successful result differs from the result returned by a strict majority,
+/// i.e. more than half, of the providers, which must consist of at least two providers.
+///
+/// Returns no provider if there is no such majority.
+fn disagreeing_providers<T: PartialEq>(results: &BTreeMap<RpcService, T>) -> BTreeSet<RpcService> {
+    let mut groups: Vec<(&T, Vec<&RpcService>)> = Vec::new();
+    for (service, result) in results {
+        match groups.iter_mut().find(|(other, _)| *other == result) {
+            Some((_, services)) => services.push(service),
+            None => groups.push((result, vec![service])),
+        }
+    }
+    groups.sort_by(|(_, left), (_, right)| right.len().cmp(&left.len()));
+    match groups.as_slice() {
+        [(_, majority), others @ ..]
+            if majority.len() >= 2 && 2 * majority.len() > results.len() =>
+        {
+            others
+                .iter()
+                .flat_map(|(_, services)| services.iter().map(|&service| service.clone()))
+                .collect()
+        }
+        _ => BTreeSet::new(),
+    }
 }
 
 impl From<ConsensusStrategy> for ReductionStrategy {
@@ -605,11 +1446,18 @@ impl From<ConsensusStrategy> for ReductionStrategy {
             ConsensusStrategy::Threshold { total: _, min } => {
                 ReductionStrategy::ByThreshold(ReduceWithThreshold::new(min))
             }
+            ConsensusStrategy::BlockLag {
+                total: _,
+                min,
+                max_block_lag,
+            } => ReductionStrategy::ByBlockLag(ReduceWithBlockLag::new(min, max_block_lag)),
         }
     }
 }
 
-impl<T: PartialEq + Serialize> Reduce<RpcService, T, RpcError> for ReductionStrategy {
+impl<T: PartialEq + Serialize + Clone + BlockIndexed> Reduce<RpcService, T, RpcError>
+    for ReductionStrategy
+{
     fn reduce(
         &self,
         results: MultiResults<RpcService, T, RpcError>,
@@ -617,10 +1465,166 @@ impl<T: PartialEq + Serialize> Reduce<RpcService, T, RpcError> for ReductionStra
         match self {
             ReductionStrategy::ByEquality(r) => r.reduce(results),
             ReductionStrategy::ByThreshold(r) => r.reduce(results),
+            ReductionStrategy::ByBlockLag(r) => r.reduce(results),
         }
     }
 }
 
+/// A result that was observed at some block height and that can be restricted to
+/// what was known at a lower block height.
+///
+/// Used by [`ReduceWithBlockLag`] to compare results of providers that are not exactly in sync.
+pub trait BlockIndexed: Sized {
+    /// The block height at which the result was observed, if any.
+    fn block_height(&self) -> Option<BlockNumber> {
+        None
+    }
+
+    /// Restrict the result to what was known at the given block height,
+    /// which is at most [`BlockIndexed::block_height`].
+    ///
+    /// Returns `None` if the result cannot be restricted to that height.
+    fn at_block_height(self, _height: BlockNumber) -> Option<Self> {
+        Some(self)
+    }
+}
+
+impl BlockIndexed for Block {
+    fn block_height(&self) -> Option<BlockNumber> {
+        Some(self.number)
+    }
+
+    fn at_block_height(self, height: BlockNumber) -> Option<Self> {
+        (self.number == height).then_some(self)
+    }
+}
+
+/// Logs do not tell up to which block height they were observed, which is given by the head
+/// reported by the provider instead, see [`ReduceWithBlockLag::reduce_at_heads`].
+impl BlockIndexed for Vec<LogEntry> {
+    fn at_block_height(self, height: BlockNumber) -> Option<Self> {
+        Some(
+            self.into_iter()
+                .filter(|log| log.block_number.is_some_and(|number| number <= height))
+                .collect(),
+        )
+    }
+}
+
+/// The latest block number reported by a provider, which was the latest block number at any
+/// lower block height.
+impl BlockIndexed for BlockNumber {
+    fn block_height(&self) -> Option<BlockNumber> {
+        Some(*self)
+    }
+
+    fn at_block_height(self, height: BlockNumber) -> Option<Self> {
+        (height <= self).then_some(height)
+    }
+}
+
+impl BlockIndexed for Option<TransactionReceipt> {}
+impl BlockIndexed for FeeHistory {}
+impl BlockIndexed for SendRawTransactionResult {}
+impl BlockIndexed for TransactionCount {}
+impl BlockIndexed for Data {}
+impl BlockIndexed for RawJson {}
+
+/// Reduce block-indexed results of providers whose block heights differ by at most a given lag.
+///
+/// The reduction proceeds as follows, for each block height at which a result was observed,
+/// from the lowest to the highest:
+/// 1. Ignore the results observed below that height or more than `max_block_lag` blocks above it.
+/// 2. Restrict the other results to that height, see [`BlockIndexed::at_block_height`].
+///    Results that cannot be restricted (e.g. a block with a higher number) are ignored.
+/// 3. The most common restricted result is returned if it is supported by at least `min` providers.
+///
+/// If no result has a block height, the results are reduced as with [`ReduceWithThreshold`].
+#[derive(Clone)]
+pub struct ReduceWithBlockLag {
+    min: u8,
+    max_block_lag: u64,
+}
+
+impl ReduceWithBlockLag {
+    pub fn new(min: u8, max_block_lag: u64) -> Self {
+        Self { min, max_block_lag }
+    }
+
+    /// Reduce results whose block height is the head reported by the provider beforehand,
+    /// e.g. logs up to the latest block.
+    pub fn reduce_at_heads<T: PartialEq + Serialize + Clone + BlockIndexed>(
+        &self,
+        results: MultiResults<RpcService, T, RpcError>,
+        heads: &BTreeMap<RpcService, BlockNumber>,
+    ) -> ReducedResult<RpcService, T, RpcError> {
+        self.reduce_at_heights(results, |service, _value| heads.get(service).copied())
+    }
+
+    fn reduce_at_heights<T: PartialEq + Serialize + Clone + BlockIndexed>(
+        &self,
+        results: MultiResults<RpcService, T, RpcError>,
+        block_height: impl Fn(&RpcService, &T) -> Option<BlockNumber>,
+    ) -> ReducedResult<RpcService, T, RpcError> {
+        let min = self.min as usize;
+        let max_block_lag = BlockNumber::from(self.max_block_lag);
+        let is_within_lag = |base: BlockNumber, height: BlockNumber| {
+            height
+                .checked_sub(base)
+                .is_some_and(|lag| lag <= max_block_lag)
+        };
+
+        let heights: BTreeMap<RpcService, BlockNumber> = results
+            .ok_results()
+            .iter()
+            .filter_map(|(service, value)| Some((service.clone(), block_height(service, value)?)))
+            .collect();
+        if heights.is_empty() {
+            return ReduceWithThreshold::new(self.min).reduce(results);
+        }
+        let base_heights: BTreeSet<BlockNumber> = heights.values().copied().collect();
+
+        for base_height in base_heights {
+            let mut groups: Vec<(T, usize)> = Vec::new();
+            for (service, value) in results.ok_results() {
+                if heights
+                    .get(service)
+                    .is_some_and(|height| !is_within_lag(base_height, *height))
+                {
+                    continue;
+                }
+                if let Some(value) = value.clone().at_block_height(base_height) {
+                    match groups.iter_mut().find(|(other, _)| other == &value) {
+                        Some((_, count)) => *count += 1,
+                        None => groups.push((value, 1)),
+                    }
+                }
+            }
+            groups.sort_by(|(_, left), (_, right)| right.cmp(left));
+
+            match groups.as_slice() {
+                [(_, first), (_, second), ..] if first == second && *first >= min => {
+                    return Err(ReductionError::InconsistentResults(results))
+                }
+                [(value, count), ..] if *count >= min => return Ok(value.clone()),
+                _ => {}
+            }
+        }
+        Err(ReductionError::InconsistentResults(results))
+    }
+}
+
+impl<T: PartialEq + Serialize + Clone + BlockIndexed> Reduce<RpcService, T, RpcError>
+    for ReduceWithBlockLag
+{
+    fn reduce(
+        &self,
+        results: MultiResults<RpcService, T, RpcError>,
+    ) -> ReducedResult<RpcService, T, RpcError> {
+        self.reduce_at_heights(results, |_service, value| value.block_height())
+    }
+}
+
 fn process_result<T>(
     method: impl Into<MetricRpcMethod> + Clone,
     result: ReducedResult<RpcService, T, RpcError>,
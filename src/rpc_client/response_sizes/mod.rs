@@ -0,0 +1,72 @@
+//! Learn the size of the responses of each provider from past responses, to derive the maximum
+//! number of bytes of a response when the caller did not give an estimate.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    providers::SupportedRpcService,
+    rpc_client::eth_rpc::{HEADER_SIZE_LIMIT, MAX_PAYLOAD_SIZE},
+};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Number of most recent response sizes kept per method and provider.
+const MAX_SAMPLES: usize = 100;
+/// Minimum number of response sizes before an estimate is derived.
+const MIN_SAMPLES: usize = 10;
+/// Percentile of the response sizes on which the estimate is based.
+const PERCENTILE: usize = 95;
+/// Margin added to the percentile, in percent.
+const MARGIN_PERCENT: u64 = 25;
+
+/// Method and provider of an HTTP outcall whose response size is recorded, which is stored
+/// as an extension of the request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResponseSizeKey {
+    pub method: String,
+    pub service: SupportedRpcService,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResponseSizes {
+    samples: BTreeMap<(String, SupportedRpcService), VecDeque<u64>>,
+}
+
+impl ResponseSizes {
+    /// Record the size in bytes of a response body returned by the provider.
+    pub fn record(&mut self, method: String, service: SupportedRpcService, num_bytes: u64) {
+        let samples = self.samples.entry((method, service)).or_default();
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(num_bytes);
+    }
+
+    /// Estimated maximum number of bytes of a response of the provider, including headers,
+    /// if enough responses were recorded.
+    pub fn estimate(&self, method: &str, service: SupportedRpcService) -> Option<u64> {
+        let samples = self.samples.get(&(method.to_string(), service))?;
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        let mut sorted: Vec<u64> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let index = (sorted.len() * PERCENTILE).div_ceil(100) - 1;
+        let percentile = sorted[index];
+        let estimate = percentile
+            .saturating_add(percentile.saturating_mul(MARGIN_PERCENT) / 100)
+            .saturating_add(HEADER_SIZE_LIMIT);
+        Some(estimate.clamp(1, MAX_PAYLOAD_SIZE))
+    }
+
+    /// All estimates, by method and provider.
+    pub fn estimates(&self) -> Vec<(&str, SupportedRpcService, u64)> {
+        self.samples
+            .keys()
+            .filter_map(|(method, service)| {
+                self.estimate(method, *service)
+                    .map(|estimate| (method.as_str(), *service, estimate))
+            })
+            .collect()
+    }
+}
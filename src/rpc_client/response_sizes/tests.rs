@@ -0,0 +1,55 @@
+use crate::{
+    providers::SupportedRpcService,
+    rpc_client::{eth_rpc::HEADER_SIZE_LIMIT, response_sizes::ResponseSizes},
+};
+use evm_rpc_types::EthMainnetService;
+
+const ANKR: SupportedRpcService = SupportedRpcService::EthMainnet(EthMainnetService::Ankr);
+const LLAMA: SupportedRpcService = SupportedRpcService::EthMainnet(EthMainnetService::Llama);
+
+#[test]
+fn should_not_estimate_without_enough_samples() {
+    let mut sizes = ResponseSizes::default();
+    for _ in 0..9 {
+        sizes.record("eth_getLogs".to_string(), ANKR, 1_000);
+    }
+
+    assert_eq!(sizes.estimate("eth_getLogs", ANKR), None);
+    assert!(sizes.estimates().is_empty());
+}
+
+#[test]
+fn should_estimate_from_high_percentile_with_margin() {
+    let mut sizes = ResponseSizes::default();
+    for num_bytes in 1..=100 {
+        sizes.record("eth_getLogs".to_string(), ANKR, num_bytes * 100);
+    }
+    sizes.record("eth_getLogs".to_string(), LLAMA, 1);
+
+    assert_eq!(
+        sizes.estimate("eth_getLogs", ANKR),
+        Some(9_500 + 2_375 + HEADER_SIZE_LIMIT)
+    );
+    assert_eq!(sizes.estimate("eth_getLogs", LLAMA), None);
+    assert_eq!(sizes.estimate("eth_feeHistory", ANKR), None);
+    assert_eq!(
+        sizes.estimates(),
+        vec![("eth_getLogs", ANKR, 9_500 + 2_375 + HEADER_SIZE_LIMIT)]
+    );
+}
+
+#[test]
+fn should_only_keep_most_recent_samples() {
+    let mut sizes = ResponseSizes::default();
+    for _ in 0..100 {
+        sizes.record("eth_getLogs".to_string(), ANKR, 1_000_000);
+    }
+    for _ in 0..100 {
+        sizes.record("eth_getLogs".to_string(), ANKR, 100);
+    }
+
+    assert_eq!(
+        sizes.estimate("eth_getLogs", ANKR),
+        Some(125 + HEADER_SIZE_LIMIT)
+    );
+}
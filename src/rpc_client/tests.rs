@@ -6,7 +6,9 @@ use evm_rpc_types::{MultiRpcResult, ProviderError, RpcError};
 mod eth_rpc_client {
     use crate::rpc_client::EthRpcClient;
     use canhttp::multi::Timestamp;
-    use evm_rpc_types::{EthMainnetService, ProviderError, RpcService, RpcServices};
+    use evm_rpc_types::{
+        EthMainnetService, ProviderError, RpcApi, RpcConfig, RpcService, RpcServices,
+    };
     use maplit::btreeset;
 
     #[test]
@@ -21,6 +23,10 @@ mod eth_rpc_client {
             RpcServices::ArbitrumOne(Some(vec![])),
             RpcServices::BaseMainnet(Some(vec![])),
             RpcServices::OptimismMainnet(Some(vec![])),
+            RpcServices::EthHoodi(Some(vec![])),
+            RpcServices::ArbitrumSepolia(Some(vec![])),
+            RpcServices::BaseSepolia(Some(vec![])),
+            RpcServices::OptimismSepolia(Some(vec![])),
         ] {
             assert_eq!(
                 EthRpcClient::new(empty_source, None, Timestamp::default()),
@@ -37,6 +43,10 @@ mod eth_rpc_client {
             RpcServices::ArbitrumOne(None),
             RpcServices::BaseMainnet(None),
             RpcServices::OptimismMainnet(None),
+            RpcServices::EthHoodi(None),
+            RpcServices::ArbitrumSepolia(None),
+            RpcServices::BaseSepolia(None),
+            RpcServices::OptimismSepolia(None),
         ] {
             let client = EthRpcClient::new(empty_source, None, Timestamp::default()).unwrap();
             assert!(!client.providers.services.is_empty());
@@ -63,6 +73,28 @@ mod eth_rpc_client {
             }
         );
     }
+
+    #[test]
+    fn should_only_record_chain_state_of_replicated_outcalls_to_supported_providers() {
+        let client = |source: RpcServices, is_replicated: bool| {
+            let config = RpcConfig {
+                is_replicated: Some(is_replicated),
+                ..Default::default()
+            };
+            EthRpcClient::new(source, Some(config), Timestamp::default()).unwrap()
+        };
+        let custom = RpcServices::Custom {
+            chain_id: 1,
+            services: vec![RpcApi {
+                url: "https://example.com".to_string(),
+                headers: None,
+            }],
+        };
+
+        assert!(client(RpcServices::EthMainnet(None), true).can_record_chain_state());
+        assert!(!client(RpcServices::EthMainnet(None), false).can_record_chain_state());
+        assert!(!client(custom, true).can_record_chain_state());
+    }
 }
 
 mod eth_get_transaction_receipt {
@@ -238,13 +270,21 @@ mod eth_get_transaction_count {
 
 mod providers {
     use crate::arbitrary::{arb_custom_rpc_services, arb_rpc_services};
+    use crate::constants::ETH_MAINNET_CHAIN_ID;
+    use crate::memory::record_outcome;
+    use crate::providers::{
+        add_provider, set_chain, CircuitBreakers, RpcServiceOutcome, SupportedRpcService,
+        SERVICE_PROVIDER_MAP,
+    };
     use crate::rpc_client::Providers;
     use assert_matches::assert_matches;
     use canhttp::multi::Timestamp;
     use evm_rpc_types::{
-        ConsensusStrategy, EthMainnetService, EthSepoliaService, L2MainnetService, ProviderError,
-        RpcServices,
+        Chain, ConsensusStrategy, EthHoodiService, EthMainnetService, EthSepoliaService,
+        L2MainnetService, L2TestnetService, ProviderArgs, ProviderError, RpcAccess, RpcApi,
+        RpcService, RpcServices,
     };
+    use maplit::btreeset;
     use proptest::arbitrary::any;
     use proptest::proptest;
 
@@ -296,6 +336,168 @@ mod providers {
         }
     }
 
+    #[test]
+    fn should_use_other_supported_providers_as_fallback() {
+        let providers = Providers::new(
+            RpcServices::EthMainnet(Some(vec![
+                EthMainnetService::Ankr,
+                EthMainnetService::PublicNode,
+            ])),
+            ConsensusStrategy::Threshold {
+                total: None,
+                min: 2,
+            },
+            Timestamp::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            providers.fallback_services,
+            vec![
+                RpcService::EthMainnet(EthMainnetService::BlockPi),
+                RpcService::EthMainnet(EthMainnetService::Llama),
+                RpcService::EthMainnet(EthMainnetService::Alchemy),
+                RpcService::EthMainnet(EthMainnetService::Cloudflare),
+            ]
+        );
+
+        let providers = Providers::new(
+            RpcServices::Custom {
+                chain_id: 1,
+                services: vec![RpcApi {
+                    url: "https://rpc.example.com".to_string(),
+                    headers: None,
+                }],
+            },
+            ConsensusStrategy::Equality,
+            Timestamp::default(),
+        )
+        .unwrap();
+
+        assert_eq!(providers.fallback_services, vec![]);
+    }
+
+    #[test]
+    fn should_use_default_providers_of_chain() {
+        const CHAIN_ID: u64 = 1_337;
+        let [provider1, provider2, other_chain_provider] =
+            [CHAIN_ID, CHAIN_ID, CHAIN_ID + 1].map(|chain_id| {
+                add_provider(ProviderArgs {
+                    chain_id,
+                    access: RpcAccess::Unauthenticated {
+                        public_url: "https://rpc.example.com".to_string(),
+                    },
+                    alias: None,
+                })
+                .unwrap()
+            });
+        let chain_source = |services| RpcServices::Chain {
+            chain_id: CHAIN_ID,
+            services,
+        };
+
+        assert_matches!(
+            Providers::new(
+                chain_source(None),
+                ConsensusStrategy::Equality,
+                Timestamp::default()
+            ),
+            Err(ProviderError::InvalidRpcConfig(_))
+        );
+
+        set_chain(Chain {
+            chain_id: CHAIN_ID,
+            name: "Test chain".to_string(),
+            default_providers: vec![provider2, provider1],
+            block_size_estimate: None,
+            max_block_range: None,
+        })
+        .unwrap();
+
+        let providers = Providers::new(
+            chain_source(None),
+            ConsensusStrategy::Threshold {
+                total: Some(1),
+                min: 1,
+            },
+            Timestamp::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            providers.services,
+            btreeset! {RpcService::Provider(provider2)}
+        );
+        assert_eq!(
+            providers.fallback_services,
+            vec![RpcService::Provider(provider1)]
+        );
+
+        assert_matches!(
+            Providers::new(
+                chain_source(Some(vec![provider1, other_chain_provider])),
+                ConsensusStrategy::Equality,
+                Timestamp::default()
+            ),
+            Err(ProviderError::InvalidRpcConfig(_))
+        );
+        assert_matches!(
+            Providers::new(
+                chain_source(Some(vec![provider1, u64::MAX])),
+                ConsensusStrategy::Equality,
+                Timestamp::default()
+            ),
+            Err(ProviderError::InvalidRpcConfig(_))
+        );
+    }
+
+    #[test]
+    fn should_rank_default_providers_of_chain() {
+        let provider_id = |service: EthMainnetService| {
+            SERVICE_PROVIDER_MAP
+                .with_borrow(|map| map.get(&SupportedRpcService::EthMainnet(service)).copied())
+                .unwrap()
+        };
+        let [ankr, llama] = [EthMainnetService::Ankr, EthMainnetService::Llama].map(provider_id);
+        let other_provider = add_provider(ProviderArgs {
+            chain_id: ETH_MAINNET_CHAIN_ID,
+            access: RpcAccess::Unauthenticated {
+                public_url: "https://rpc.example.com".to_string(),
+            },
+            alias: None,
+        })
+        .unwrap();
+        set_chain(Chain {
+            chain_id: ETH_MAINNET_CHAIN_ID,
+            name: "Ethereum".to_string(),
+            default_providers: vec![ankr, other_provider, llama],
+            block_size_estimate: None,
+            max_block_range: None,
+        })
+        .unwrap();
+        for _ in 0..CircuitBreakers::FAILURE_THRESHOLD {
+            record_outcome(
+                SupportedRpcService::EthMainnet(EthMainnetService::Ankr),
+                RpcServiceOutcome::HttpError,
+                Timestamp::default(),
+            );
+        }
+
+        let providers = Providers::new(
+            RpcServices::Chain {
+                chain_id: ETH_MAINNET_CHAIN_ID,
+                services: None,
+            },
+            ConsensusStrategy::Equality,
+            Timestamp::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            providers.services,
+            btreeset! {RpcService::Provider(llama), RpcService::Provider(other_provider)}
+        );
+    }
+
     #[test]
     fn should_fail_when_threshold_unspecified_with_default_providers() {
         let strategy = ConsensusStrategy::Threshold {
@@ -309,6 +511,10 @@ mod providers {
             RpcServices::ArbitrumOne(None),
             RpcServices::BaseMainnet(None),
             RpcServices::OptimismMainnet(None),
+            RpcServices::EthHoodi(None),
+            RpcServices::ArbitrumSepolia(None),
+            RpcServices::BaseSepolia(None),
+            RpcServices::OptimismSepolia(None),
         ] {
             let providers =
                 Providers::new(default_services, strategy.clone(), Timestamp::default());
@@ -340,6 +546,22 @@ mod providers {
                     RpcServices::OptimismMainnet(None),
                     L2MainnetService::all().len(),
                 ),
+                (
+                    RpcServices::EthHoodi(None),
+                    EthHoodiService::all().len(),
+                ),
+                (
+                    RpcServices::ArbitrumSepolia(None),
+                    L2TestnetService::all().len(),
+                ),
+                (
+                    RpcServices::BaseSepolia(None),
+                    L2TestnetService::all().len(),
+                ),
+                (
+                    RpcServices::OptimismSepolia(None),
+                    L2TestnetService::all().len(),
+                ),
             ] {
                 let strategy = ConsensusStrategy::Threshold {
                     total: Some((max_total + 1) as u8),
@@ -371,6 +593,384 @@ mod providers {
     }
 }
 
+mod block_lag {
+    use crate::rpc_client::amount::Amount;
+    use crate::rpc_client::json::responses::{Block, Data, LogEntry};
+    use crate::rpc_client::json::{Hash, LogsBloom};
+    use crate::rpc_client::numeric::BlockNumber;
+    use crate::rpc_client::ReduceWithBlockLag;
+    use assert_matches::assert_matches;
+    use canhttp::multi::{MultiResults, Reduce, ReductionError};
+    use evm_rpc_types::{EthMainnetService, RpcError, RpcService};
+    use ic_ethereum_types::Address;
+    use std::collections::BTreeMap;
+
+    const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+    const LLAMA: RpcService = RpcService::EthMainnet(EthMainnetService::Llama);
+    const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+
+    #[test]
+    fn should_return_lowest_agreed_block_within_lag() {
+        let results = multi_results(vec![
+            (ANKR, block(10, 0xa)),
+            (LLAMA, block(11, 0xb)),
+            (PUBLIC_NODE, block(11, 0xb)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 2).reduce(results);
+
+        assert_eq!(reduced, Ok(block(11, 0xb)));
+    }
+
+    #[test]
+    fn should_not_count_providers_ahead_as_agreeing() {
+        let results = multi_results(vec![
+            (ANKR, block(10, 0xa)),
+            (LLAMA, block(11, 0xb)),
+            (PUBLIC_NODE, block(12, 0xc)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 2).reduce(results);
+
+        assert_matches!(reduced, Err(ReductionError::InconsistentResults(_)));
+    }
+
+    #[test]
+    fn should_ignore_stale_provider() {
+        let results = multi_results(vec![
+            (ANKR, block(5, 0xa)),
+            (LLAMA, block(10, 0xb)),
+            (PUBLIC_NODE, block(10, 0xb)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 1).reduce(results);
+
+        assert_eq!(reduced, Ok(block(10, 0xb)));
+    }
+
+    #[test]
+    fn should_fail_when_blocks_exceed_lag() {
+        let results = multi_results(vec![(ANKR, block(10, 0xa)), (LLAMA, block(20, 0xb))]);
+
+        let reduced = ReduceWithBlockLag::new(2, 5).reduce(results);
+
+        assert_matches!(reduced, Err(ReductionError::InconsistentResults(_)));
+    }
+
+    #[test]
+    fn should_fail_when_blocks_at_same_height_differ() {
+        let results = multi_results(vec![
+            (ANKR, block(10, 0xa)),
+            (LLAMA, block(10, 0xb)),
+            (PUBLIC_NODE, block(11, 0xc)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 1).reduce(results);
+
+        assert_matches!(reduced, Err(ReductionError::InconsistentResults(_)));
+    }
+
+    #[test]
+    fn should_keep_logs_up_to_lowest_head() {
+        let results = multi_results(vec![
+            (ANKR, vec![log_entry(8), log_entry(10)]),
+            (LLAMA, vec![log_entry(8), log_entry(10), log_entry(11)]),
+            (PUBLIC_NODE, vec![log_entry(8)]),
+        ]);
+        let heads = BTreeMap::from([
+            (ANKR, BlockNumber::from(10_u32)),
+            (LLAMA, BlockNumber::from(11_u32)),
+            (PUBLIC_NODE, BlockNumber::from(9_u32)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(3, 2).reduce_at_heads(results, &heads);
+
+        assert_eq!(reduced, Ok(vec![log_entry(8)]));
+    }
+
+    #[test]
+    fn should_ignore_logs_of_stale_provider() {
+        let results = multi_results(vec![
+            (ANKR, vec![log_entry(8), log_entry(10)]),
+            (LLAMA, vec![log_entry(8), log_entry(10), log_entry(11)]),
+            (PUBLIC_NODE, vec![]),
+        ]);
+        let heads = BTreeMap::from([
+            (ANKR, BlockNumber::from(10_u32)),
+            (LLAMA, BlockNumber::from(11_u32)),
+            (PUBLIC_NODE, BlockNumber::from(5_u32)),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 2).reduce_at_heads(results, &heads);
+
+        assert_eq!(reduced, Ok(vec![log_entry(8), log_entry(10)]));
+    }
+
+    #[test]
+    fn should_reduce_by_threshold_without_block_height() {
+        let results = multi_results(vec![
+            (ANKR, Vec::<LogEntry>::new()),
+            (LLAMA, Vec::new()),
+            (PUBLIC_NODE, vec![log_entry(8)]),
+        ]);
+
+        let reduced = ReduceWithBlockLag::new(2, 0).reduce(results);
+
+        assert_eq!(reduced, Ok(vec![]));
+    }
+
+    fn multi_results<T>(results: Vec<(RpcService, T)>) -> MultiResults<RpcService, T, RpcError> {
+        MultiResults::from_non_empty_iter(
+            results
+                .into_iter()
+                .map(|(service, value)| (service, Ok(value))),
+        )
+    }
+
+    fn block(number: u32, hash: u8) -> Block {
+        Block {
+            base_fee_per_gas: None,
+            number: BlockNumber::from(number),
+            difficulty: None,
+            extra_data: Data(vec![]),
+            gas_limit: Amount::ZERO,
+            gas_used: Amount::ZERO,
+            hash: Hash::new([hash; 32]),
+            logs_bloom: LogsBloom::new([0; 256]),
+            miner: Address::new([0; 20]),
+            mix_hash: Hash::new([0; 32]),
+            nonce: Amount::ZERO,
+            parent_hash: Hash::new([0; 32]),
+            receipts_root: Hash::new([0; 32]),
+            sha3_uncles: Hash::new([0; 32]),
+            size: Amount::ZERO,
+            state_root: Hash::new([0; 32]),
+            timestamp: Amount::ZERO,
+            transactions: vec![],
+            transactions_root: None,
+            uncles: vec![],
+        }
+    }
+
+    fn log_entry(block_number: u32) -> LogEntry {
+        LogEntry {
+            address: Address::new([0; 20]),
+            topics: vec![],
+            data: Data(vec![]),
+            block_number: Some(BlockNumber::from(block_number)),
+            transaction_hash: None,
+            transaction_index: None,
+            block_hash: None,
+            log_index: None,
+            removed: false,
+        }
+    }
+}
+
+mod fallback_providers {
+    use crate::rpc_client::FallbackProviders;
+    use evm_rpc_types::{EthMainnetService, RpcService};
+    use std::collections::BTreeSet;
+
+    const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+    const LLAMA: RpcService = RpcService::EthMainnet(EthMainnetService::Llama);
+    const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+    const CLOUDFLARE: RpcService = RpcService::EthMainnet(EthMainnetService::Cloudflare);
+
+    #[test]
+    fn should_query_missing_number_of_providers() {
+        let fallback = FallbackProviders {
+            min: 2,
+            services: vec![LLAMA, ANKR, CLOUDFLARE, PUBLIC_NODE],
+        };
+        let queried = BTreeSet::from([ANKR, PUBLIC_NODE]);
+
+        assert_eq!(
+            fallback.providers_to_query(&queried, 1),
+            BTreeSet::from([LLAMA])
+        );
+        assert_eq!(fallback.providers_to_query(&queried, 2), BTreeSet::new());
+    }
+
+    #[test]
+    fn should_include_worst_case_fallback_round_in_cost() {
+        // The cost estimate includes the providers queried if all providers failed
+        let fallback = FallbackProviders {
+            min: 3,
+            services: vec![LLAMA, ANKR, CLOUDFLARE],
+        };
+        let queried = BTreeSet::from([ANKR, PUBLIC_NODE]);
+
+        assert_eq!(
+            fallback.providers_to_query(&queried, 0),
+            BTreeSet::from([LLAMA, CLOUDFLARE])
+        );
+    }
+}
+
+mod disagreeing_providers {
+    use crate::rpc_client::disagreeing_providers;
+    use evm_rpc_types::{EthMainnetService, RpcService};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+    const LLAMA: RpcService = RpcService::EthMainnet(EthMainnetService::Llama);
+    const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+    const CLOUDFLARE: RpcService = RpcService::EthMainnet(EthMainnetService::Cloudflare);
+
+    #[test]
+    fn should_find_providers_disagreeing_with_majority() {
+        let results = BTreeMap::from([(ANKR, 1), (LLAMA, 2), (PUBLIC_NODE, 1), (CLOUDFLARE, 1)]);
+
+        assert_eq!(disagreeing_providers(&results), BTreeSet::from([LLAMA]));
+    }
+
+    #[test]
+    fn should_not_find_disagreeing_providers_without_majority() {
+        assert_eq!(
+            disagreeing_providers(&BTreeMap::from([(ANKR, 1), (LLAMA, 2)])),
+            BTreeSet::new()
+        );
+        assert_eq!(
+            disagreeing_providers(&BTreeMap::from([
+                (ANKR, 1),
+                (LLAMA, 2),
+                (PUBLIC_NODE, 1),
+                (CLOUDFLARE, 2)
+            ])),
+            BTreeSet::new()
+        );
+        assert_eq!(
+            disagreeing_providers(&BTreeMap::from([
+                (ANKR, 1),
+                (LLAMA, 2),
+                (PUBLIC_NODE, 1),
+                (CLOUDFLARE, 3)
+            ])),
+            BTreeSet::new()
+        );
+        assert_eq!(
+            disagreeing_providers(&BTreeMap::from([(ANKR, 1), (LLAMA, 1)])),
+            BTreeSet::new()
+        );
+    }
+}
+
+mod non_replicated_outcalls {
+    use crate::memory::get_providers_health;
+    use crate::providers::{RpcServiceHealth, SupportedRpcService};
+    use crate::rpc_client::eth_rpc::{ResponseSizeEstimate, ResponseTransform};
+    use crate::rpc_client::numeric::{BlockNumber, TransactionCount};
+    use crate::rpc_client::{MultiRpcRequest, ReduceWithBlockLag, ReductionStrategy};
+    use crate::types::RpcMethod;
+    use canhttp::multi::{MultiResults, ReduceWithEquality, Timestamp};
+    use evm_rpc_types::{EthMainnetService, HttpOutcallError, RpcError, RpcService};
+    use std::collections::BTreeSet;
+
+    const ANKR: RpcService = RpcService::EthMainnet(EthMainnetService::Ankr);
+    const LLAMA: RpcService = RpcService::EthMainnet(EthMainnetService::Llama);
+
+    fn request(is_replicated: bool) -> MultiRpcRequest<(), TransactionCount> {
+        MultiRpcRequest::new(
+            BTreeSet::from([ANKR, LLAMA]),
+            RpcMethod::EthGetTransactionCount,
+            (),
+            ResponseSizeEstimate::new(256),
+            ResponseTransform::GetTransactionCount,
+            ReductionStrategy::ByEquality(ReduceWithEquality),
+        )
+        .with_is_replicated(is_replicated)
+    }
+
+    fn results() -> MultiResults<RpcService, TransactionCount, RpcError> {
+        MultiResults::from_non_empty_iter(vec![
+            (ANKR, Ok(TransactionCount::new(1))),
+            (
+                LLAMA,
+                Err(RpcError::HttpOutcallError(
+                    HttpOutcallError::InvalidHttpJsonRpcResponse {
+                        status: 500,
+                        body: String::new(),
+                        parsing_error: None,
+                    },
+                )),
+            ),
+        ])
+    }
+
+    fn health() -> Vec<RpcServiceHealth> {
+        get_providers_health(
+            &[
+                SupportedRpcService::EthMainnet(EthMainnetService::Ankr),
+                SupportedRpcService::EthMainnet(EthMainnetService::Llama),
+            ],
+            Timestamp::from_nanos_since_unix_epoch(1),
+        )
+    }
+
+    #[test]
+    fn should_not_record_outcomes_of_non_replicated_outcalls() {
+        let now = Timestamp::from_nanos_since_unix_epoch(0);
+
+        assert_eq!(request(false).record_outcomes(results(), now).len(), 2);
+        assert_eq!(health(), vec![RpcServiceHealth::default(); 2]);
+
+        assert_eq!(request(true).record_outcomes(results(), now).len(), 2);
+        let health = health();
+        assert_eq!(health[0].ok, 1);
+        assert_eq!(health[1].http_errors, 1);
+    }
+
+    #[test]
+    fn should_not_record_lagging_heads_as_disagreements() {
+        const PUBLIC_NODE: RpcService = RpcService::EthMainnet(EthMainnetService::PublicNode);
+        let request = MultiRpcRequest::<(), TransactionCount>::new(
+            BTreeSet::from([ANKR, LLAMA, PUBLIC_NODE]),
+            RpcMethod::EthGetTransactionCount,
+            (),
+            ResponseSizeEstimate::new(256),
+            ResponseTransform::GetTransactionCount,
+            ReductionStrategy::ByBlockLag(ReduceWithBlockLag::new(2, 2)),
+        )
+        .with_block_heads(true);
+        let heads = MultiResults::from_non_empty_iter(vec![
+            (ANKR, Ok(BlockNumber::from(100_u64))),
+            (LLAMA, Ok(BlockNumber::from(100_u64))),
+            (PUBLIC_NODE, Ok(BlockNumber::from(101_u64))),
+        ]);
+
+        request
+            .block_heads_request()
+            .record_outcomes(heads, Timestamp::from_nanos_since_unix_epoch(0));
+
+        let health = get_providers_health(
+            &[
+                SupportedRpcService::EthMainnet(EthMainnetService::Ankr),
+                SupportedRpcService::EthMainnet(EthMainnetService::Llama),
+                SupportedRpcService::EthMainnet(EthMainnetService::PublicNode),
+            ],
+            Timestamp::from_nanos_since_unix_epoch(1),
+        );
+        assert!(health
+            .iter()
+            .all(|health| health.ok == 1 && health.disagreements == 0));
+    }
+
+    #[test]
+    fn should_not_record_response_sizes_of_non_replicated_outcalls() {
+        assert_eq!(request(false).response_size_key(&ANKR), None);
+        assert_eq!(
+            request(true)
+                .response_size_key(&ANKR)
+                .map(|key| (key.method, key.service)),
+            Some((
+                "eth_getTransactionCount".to_string(),
+                SupportedRpcService::EthMainnet(EthMainnetService::Ankr)
+            ))
+        );
+    }
+}
+
 #[test]
 fn test_process_result_mapping() {
     use evm_rpc_types::{EthMainnetService, RpcService};
@@ -0,0 +1,457 @@
+//! Poll log subscriptions and transaction watches, and push the results to the subscribers.
+//!
+//! The logs of a block are only delivered once enough blocks were mined on top of it, so that
+//! logs of blocks that are reorganized away are never delivered. Similarly, a transaction is only
+//! reported as confirmed once the block containing it has enough confirmations or is finalized.
+//! The subscriber is charged the cost of each HTTP outcall from its prepaid cycles account, whose
+//! balance must not fall below a minimum for its subscriptions to be polled.
+
+#[cfg(test)]
+mod tests;
+
+use crate::{
+    candid_rpc::{resolve_get_logs_rpc_config, CandidRpcClient},
+    constants::{
+        DROPPED_TRANSACTION_TIMEOUT_SECS, MAX_LOG_SUBSCRIPTIONS_PER_OWNER,
+        MAX_TRANSACTION_WATCHES_PER_OWNER, MINIMUM_SUBSCRIPTION_BALANCE_CYCLES, REDACTED_STRING,
+    },
+    http::Payer,
+    logs::Priority,
+    memory::{
+        get_balance, get_log_subscriptions, get_transaction_watches, is_demo_active,
+        is_method_suspended, remove_log_subscription, remove_transaction_watch,
+        stop_polling_subscriptions, try_start_polling_subscriptions, update_log_subscription,
+        update_transaction_watch,
+    },
+};
+use candid::Principal;
+use canhttp::multi::Timestamp;
+use canlog::log;
+use evm_rpc_types::{
+    BlockTag, GetLogsArgs, GetTransactionCountArgs, HttpHeader, LogSubscription, LogsNotification,
+    MultiRpcResult, Nat256, RpcApi, RpcConfig, RpcServices, SubscribeError, SubscribeLogsArgs,
+    TransactionNotification, TransactionOutcome, TransactionWatch, ValidationError,
+    WatchTransactionArgs,
+};
+
+const NANOS_PER_SEC: u64 = 1_000_000_000;
+
+/// Check that the arguments describe a valid log subscription of the given owner,
+/// who already has `num_subscriptions` log subscriptions.
+pub fn validate_subscription(
+    args: &SubscribeLogsArgs,
+    num_subscriptions: u64,
+    now: Timestamp,
+) -> Result<(), SubscribeError> {
+    if num_subscriptions >= MAX_LOG_SUBSCRIPTIONS_PER_OWNER {
+        return Err(SubscribeError::TooManySubscriptions {
+            maximum: MAX_LOG_SUBSCRIPTIONS_PER_OWNER,
+        });
+    }
+    validate_callback(&args.callback)?;
+    u64::try_from(args.from_block.clone()).map_err(SubscribeError::InvalidSubscription)?;
+    let config = resolve_get_logs_rpc_config(&args.source, args.config.clone());
+    CandidRpcClient::new(args.source.clone(), Some(RpcConfig::from(config)), now)
+        .map_err(SubscribeError::InvalidSubscription)?;
+    Ok(())
+}
+
+/// Check that the arguments describe a valid transaction watch of the given owner,
+/// who already has `num_watches` transaction watches.
+pub fn validate_transaction_watch(
+    args: &WatchTransactionArgs,
+    num_watches: u64,
+    now: Timestamp,
+) -> Result<(), SubscribeError> {
+    if num_watches >= MAX_TRANSACTION_WATCHES_PER_OWNER {
+        return Err(SubscribeError::TooManySubscriptions {
+            maximum: MAX_TRANSACTION_WATCHES_PER_OWNER,
+        });
+    }
+    validate_callback(&args.callback)?;
+    if args.sender.is_some() != args.nonce.is_some() {
+        return Err(SubscribeError::InvalidSubscription(
+            ValidationError::Custom("The sender and the nonce must be given together".to_string())
+                .into(),
+        ));
+    }
+    CandidRpcClient::new(args.source.clone(), args.config.clone(), now)
+        .map_err(SubscribeError::InvalidSubscription)?;
+    Ok(())
+}
+
+/// Check that a subscriber with the given balance, including the cycles attached to the call,
+/// can pay for polling its subscriptions.
+pub fn validate_subscriber_balance(balance: u128) -> Result<(), SubscribeError> {
+    let minimum = minimum_subscriber_balance();
+    if balance < minimum {
+        return Err(SubscribeError::InsufficientBalance { balance, minimum });
+    }
+    Ok(())
+}
+
+/// Minimum prepaid cycles balance of a subscriber, which is 0 in demo mode since no cycles
+/// are charged.
+fn minimum_subscriber_balance() -> u128 {
+    if is_demo_active() {
+        0
+    } else {
+        MINIMUM_SUBSCRIPTION_BALANCE_CYCLES
+    }
+}
+
+fn validate_callback(callback: &str) -> Result<(), SubscribeError> {
+    if callback.is_empty() {
+        return Err(SubscribeError::InvalidSubscription(
+            ValidationError::Custom("The callback method must not be empty".to_string()).into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Poll all log subscriptions and then all transaction watches, one after the other.
+///
+/// Log subscriptions, respectively transaction watches, are paused while `eth_getLogs`,
+/// respectively `eth_getTransactionReceipt`, is suspended by the controllers.
+pub async fn poll_subscriptions() {
+    let Some(_guard) = PollingGuard::new() else {
+        return;
+    };
+    if !is_method_suspended("eth_getLogs") {
+        poll_log_subscriptions().await;
+    }
+    if !is_method_suspended("eth_getTransactionReceipt") {
+        poll_transaction_watches().await;
+    }
+}
+
+async fn poll_log_subscriptions() {
+    for subscription in get_log_subscriptions() {
+        let id = subscription.id;
+        if let Err(error) = validate_subscriber_balance(get_balance(&subscription.owner)) {
+            log!(
+                Priority::Info,
+                "[poll_log_subscriptions]: removing log subscription {id}: {error}"
+            );
+            remove_log_subscription(id);
+            continue;
+        }
+        let result = poll_log_subscription(subscription).await;
+        if let Err(error) = &result {
+            log!(
+                Priority::Info,
+                "[poll_log_subscriptions]: log subscription {id} failed: {error}"
+            );
+        }
+        update_log_subscription(id, |subscription| match result {
+            Ok(next_block) => {
+                subscription.next_block = next_block;
+                subscription.last_error = None;
+            }
+            Err(error) => subscription.last_error = Some(error),
+        });
+    }
+}
+
+/// Deliver the logs of the next confirmed block range, if any, and return the first block whose
+/// logs were not delivered yet.
+async fn poll_log_subscription(subscription: LogSubscription) -> Result<Nat256, String> {
+    let Ok(next_block) = u64::try_from(subscription.next_block.clone()) else {
+        // The logs of the last possible block were delivered.
+        return Ok(subscription.next_block);
+    };
+    let config = resolve_get_logs_rpc_config(&subscription.source, subscription.config.clone());
+    let max_block_range = config.max_block_range_or_default();
+    let rpc_config = Some(RpcConfig::from(config));
+    let client = || new_client(subscription.owner, &subscription.source, &rpc_config);
+
+    let latest_block = match client()?.eth_get_block_by_number(BlockTag::Latest).await {
+        MultiRpcResult::Consistent(Ok(block)) => {
+            u64::try_from(block.number).map_err(|e| format!("{e}"))?
+        }
+        result => return Err(error_message(result)),
+    };
+    let Some((from_block, to_block)) = next_block_range(
+        next_block,
+        latest_block,
+        subscription.confirmations,
+        max_block_range,
+    ) else {
+        return Ok(subscription.next_block);
+    };
+
+    let args = GetLogsArgs {
+        from_block: Some(BlockTag::Number(Nat256::from(from_block))),
+        to_block: Some(BlockTag::Number(Nat256::from(to_block))),
+        addresses: subscription.addresses.clone(),
+        topics: subscription.topics.clone(),
+    };
+    let logs = match client()?.eth_get_logs(args).await {
+        MultiRpcResult::Consistent(Ok(logs)) => logs,
+        result => return Err(error_message(result)),
+    };
+
+    if !logs.is_empty() {
+        let notification = LogsNotification {
+            subscription_id: subscription.id,
+            logs,
+            from_block: Nat256::from(from_block),
+            to_block: Nat256::from(to_block),
+        };
+        ic_cdk::call::Call::bounded_wait(subscription.owner, &subscription.callback)
+            .with_arg(&notification)
+            .await
+            .map_err(|e| format!("Failed to deliver logs: {e}"))?;
+    }
+    Ok(block_after(to_block))
+}
+
+async fn poll_transaction_watches() {
+    for mut watch in get_transaction_watches() {
+        let id = watch.id;
+        if let Err(error) = validate_subscriber_balance(get_balance(&watch.owner)) {
+            log!(
+                Priority::Info,
+                "[poll_transaction_watches]: removing transaction watch {id}: {error}"
+            );
+            remove_transaction_watch(id);
+            continue;
+        }
+        let result = match poll_transaction_watch(&mut watch).await {
+            Ok(Some(outcome)) => notify_transaction_outcome(&watch, outcome)
+                .await
+                .map(|()| true),
+            Ok(None) => Ok(false),
+            Err(error) => Err(error),
+        };
+        match result {
+            Ok(true) => {
+                remove_transaction_watch(id);
+            }
+            Ok(false) => update_transaction_watch(id, |w| {
+                w.receipt = watch.receipt;
+                w.last_error = None;
+            }),
+            Err(error) => {
+                log!(
+                    Priority::Info,
+                    "[poll_transaction_watches]: transaction watch {id} failed: {error}"
+                );
+                update_transaction_watch(id, |w| {
+                    w.receipt = watch.receipt;
+                    w.last_error = Some(error);
+                })
+            }
+        }
+    }
+}
+
+/// Fetch the receipt of the watched transaction, which is recorded in the watch, and return
+/// the outcome of the transaction once it is final.
+async fn poll_transaction_watch(
+    watch: &mut TransactionWatch,
+) -> Result<Option<TransactionOutcome>, String> {
+    let client = || new_client(watch.owner, &watch.source, &watch.config);
+    let hash = watch.transaction_hash.clone();
+
+    let receipt = match client()?.eth_get_transaction_receipt(hash).await {
+        MultiRpcResult::Consistent(Ok(receipt)) => receipt,
+        result => return Err(error_message(result)),
+    };
+    let Some(receipt) = receipt else {
+        if let Some(previous) = watch.receipt.take() {
+            log!(
+                Priority::Info,
+                "[poll_transaction_watch]: receipt of transaction {} in block {} was reorganized away",
+                watch.transaction_hash,
+                previous.block_hash
+            );
+        }
+        return pending_transaction_outcome(watch).await;
+    };
+    if let Some(previous) = &watch.receipt {
+        if previous.block_hash != receipt.block_hash {
+            log!(
+                Priority::Info,
+                "[poll_transaction_watch]: transaction {} moved from block {} to block {}",
+                watch.transaction_hash,
+                previous.block_hash,
+                receipt.block_hash
+            );
+        }
+    }
+    watch.receipt = Some(receipt.clone());
+
+    let block_tag = match watch.confirmations {
+        Some(_) => BlockTag::Latest,
+        None => BlockTag::Finalized,
+    };
+    let head_block = match client()?.eth_get_block_by_number(block_tag).await {
+        MultiRpcResult::Consistent(Ok(block)) => {
+            u64::try_from(block.number).map_err(|e| format!("{e}"))?
+        }
+        result => return Err(error_message(result)),
+    };
+    let receipt_block = u64::try_from(receipt.block_number.clone()).map_err(|e| format!("{e}"))?;
+    if is_confirmed(
+        receipt_block,
+        head_block,
+        watch.confirmations.unwrap_or_default(),
+    ) {
+        return Ok(Some(TransactionOutcome::Confirmed(receipt)));
+    }
+    Ok(None)
+}
+
+/// Outcome of a transaction without receipt, if it was replaced or dropped.
+async fn pending_transaction_outcome(
+    watch: &TransactionWatch,
+) -> Result<Option<TransactionOutcome>, String> {
+    if let (Some(sender), Some(nonce)) = (&watch.sender, &watch.nonce) {
+        // Only a finalized transaction count reliably indicates that the nonce was used
+        // by another transaction.
+        let args = GetTransactionCountArgs {
+            address: sender.clone(),
+            block: BlockTag::Finalized,
+        };
+        let transaction_count = match new_client(watch.owner, &watch.source, &watch.config)?
+            .eth_get_transaction_count(args)
+            .await
+        {
+            MultiRpcResult::Consistent(Ok(transaction_count)) => transaction_count,
+            result => return Err(error_message(result)),
+        };
+        if transaction_count.as_ref() > nonce.as_ref() {
+            return Ok(Some(TransactionOutcome::Replaced));
+        }
+    }
+    if is_dropped(watch.created_at, ic_cdk::api::time()) {
+        return Ok(Some(TransactionOutcome::Dropped));
+    }
+    Ok(None)
+}
+
+async fn notify_transaction_outcome(
+    watch: &TransactionWatch,
+    outcome: TransactionOutcome,
+) -> Result<(), String> {
+    let notification = TransactionNotification {
+        watch_id: watch.id,
+        transaction_hash: watch.transaction_hash.clone(),
+        outcome,
+    };
+    ic_cdk::call::Call::bounded_wait(watch.owner, &watch.callback)
+        .with_arg(&notification)
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to notify transaction outcome: {e}"))
+}
+
+/// Whether the block containing a transaction has at least `confirmations` blocks mined
+/// on top of it, given the number of the latest, or finalized, block.
+pub fn is_confirmed(receipt_block: u64, head_block: u64, confirmations: u64) -> bool {
+    head_block >= receipt_block.saturating_add(confirmations)
+}
+
+/// Whether a transaction without receipt is considered dropped, given the time at which
+/// its watch was created.
+pub fn is_dropped(created_at: u64, now: u64) -> bool {
+    now.saturating_sub(created_at) >= DROPPED_TRANSACTION_TIMEOUT_SECS * NANOS_PER_SEC
+}
+
+/// Number of the block following the given block, which does not fit in a `u64` if the given
+/// block is the last possible block.
+pub fn block_after(block: u64) -> Nat256 {
+    Nat256::from(u128::from(block) + 1)
+}
+
+/// Block range whose logs are delivered next, i.e. at most `max_block_range` blocks starting with
+/// `next_block` that have at least `confirmations` blocks mined on top of them.
+pub fn next_block_range(
+    next_block: u64,
+    latest_block: u64,
+    confirmations: u64,
+    max_block_range: u32,
+) -> Option<(u64, u64)> {
+    let last_confirmed_block = latest_block.checked_sub(confirmations)?;
+    if next_block > last_confirmed_block || max_block_range == 0 {
+        return None;
+    }
+    let to_block = next_block
+        .saturating_add(max_block_range as u64 - 1)
+        .min(last_confirmed_block);
+    Some((next_block, to_block))
+}
+
+/// Returns the source of a log subscription or transaction watch where the URLs and the header values of custom providers
+/// are redacted, since they may contain credentials.
+pub fn redacted_source(source: RpcServices) -> RpcServices {
+    match source {
+        RpcServices::Custom { chain_id, services } => RpcServices::Custom {
+            chain_id,
+            services: services
+                .into_iter()
+                .map(|api| RpcApi {
+                    url: url::Url::parse(&api.url)
+                        .ok()
+                        .and_then(|url| {
+                            let host = url.host_str()?;
+                            Some(format!("{}://{host}/{REDACTED_STRING}", url.scheme()))
+                        })
+                        .unwrap_or_else(|| REDACTED_STRING.to_string()),
+                    headers: api.headers.map(|headers| {
+                        headers
+                            .into_iter()
+                            .map(|header| HttpHeader {
+                                name: header.name,
+                                value: REDACTED_STRING.to_string(),
+                            })
+                            .collect()
+                    }),
+                })
+                .collect(),
+        },
+        source => source,
+    }
+}
+
+fn error_message<T>(result: MultiRpcResult<T>) -> String {
+    match result {
+        MultiRpcResult::Consistent(Ok(_)) => "BUG: unexpected success".to_string(),
+        MultiRpcResult::Consistent(Err(e)) => format!("{e}"),
+        MultiRpcResult::Inconsistent(_) | MultiRpcResult::InconsistentWithDiagnostics { .. } => {
+            "Providers returned inconsistent results".to_string()
+        }
+    }
+}
+
+/// Client whose HTTP outcalls are paid by the owner of a subscription.
+fn new_client(
+    owner: Principal,
+    source: &evm_rpc_types::RpcServices,
+    config: &Option<RpcConfig>,
+) -> Result<CandidRpcClient, String> {
+    CandidRpcClient::new(source.clone(), config.clone(), now())
+        .map(|client| client.with_payer(Payer::Account(owner)))
+        .map_err(|e| format!("{e}"))
+}
+
+/// Prevents the subscriptions from being polled concurrently, e.g. if polling takes longer
+/// than the polling interval.
+struct PollingGuard;
+
+impl PollingGuard {
+    fn new() -> Option<Self> {
+        try_start_polling_subscriptions().then_some(PollingGuard)
+    }
+}
+
+impl Drop for PollingGuard {
+    fn drop(&mut self) {
+        stop_polling_subscriptions();
+    }
+}
+
+fn now() -> Timestamp {
+    Timestamp::from_nanos_since_unix_epoch(ic_cdk::api::time())
+}
@@ -1,21 +1,27 @@
 #[cfg(test)]
 mod tests;
 
-use crate::constants::{API_KEY_MAX_SIZE, API_KEY_REPLACE_STRING, MESSAGE_FILTER_MAX_SIZE};
-use crate::memory::get_api_key;
+use crate::constants::{
+    API_KEY_MAX_SIZE, API_KEY_REPLACE_STRING, DEFAULT_CALLER_LIMITS_WINDOW_SECS,
+    MESSAGE_FILTER_MAX_SIZE, REDACTED_STRING,
+};
+use crate::memory::next_api_key;
 use crate::providers::SupportedRpcService;
 use crate::util::hostname_from_url;
-use crate::validate::validate_api_key;
+use crate::validate::{validate_api_key, validate_basic_auth_api_key, validate_header_api_key};
+use base64::prelude::{Engine, BASE64_STANDARD};
 use candid::CandidType;
 use canlog::{LogFilter, RegexSubstitution};
 use derive_more::{From, Into};
-use evm_rpc_types::{LegacyRejectionCode, RpcApi, RpcError, ValidationError};
+use evm_rpc_types::{
+    CallerLimit, LegacyRejectionCode, RpcApi, RpcError, RpcService, ValidationError,
+};
 use ic_management_canister_types::HttpHeader;
 use ic_stable_structures::storable::Bound;
 use ic_stable_structures::Storable;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -26,26 +32,40 @@ pub enum ResolvedRpcService {
 
 impl ResolvedRpcService {
     pub fn api(&self, override_provider: &OverrideProvider) -> Result<RpcApi, RpcError> {
-        let initial_api = match self {
-            Self::Api(api) => api.clone(),
-            Self::Provider(provider) => provider.api(),
+        self.api_with_key(override_provider).map(|(api, _)| api)
+    }
+
+    fn api_with_key(
+        &self,
+        override_provider: &OverrideProvider,
+    ) -> Result<(RpcApi, Option<ApiKeyRef>), RpcError> {
+        let (initial_api, api_key) = match self {
+            Self::Api(api) => (api.clone(), None),
+            Self::Provider(provider) => provider.api_with_key(),
         };
-        override_provider.apply(initial_api).map_err(|regex_error| {
-            RpcError::ValidationError(ValidationError::Custom(format!(
+        let api = override_provider
+            .apply(initial_api)
+            .map_err(|regex_error| {
+                RpcError::ValidationError(ValidationError::Custom(format!(
                 "BUG: regex should have been validated when initially set. Error: {regex_error}"
             )))
-        })
+            })?;
+        Ok((api, api_key))
     }
 
     pub fn post(
         &self,
         override_provider: &OverrideProvider,
     ) -> Result<http::request::Builder, RpcError> {
-        let api = self.api(override_provider)?;
+        let (api, api_key) = self.api_with_key(override_provider)?;
         let mut request_builder = http::Request::post(api.url);
         for HttpHeader { name, value } in api.headers.unwrap_or_default() {
             request_builder = request_builder.header(name, value);
         }
+        // Store the used API key for usage when recording metrics
+        if let Some(api_key) = api_key {
+            request_builder = request_builder.extension(api_key);
+        }
         Ok(request_builder)
     }
 }
@@ -149,6 +169,95 @@ impl MetricLabels for MetricHttpStatusCode {
     }
 }
 
+/// Path of a field that differed between inconsistent responses,
+/// where array indices are omitted to bound the number of distinct labels.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricResponseField(pub String);
+
+impl MetricLabels for MetricResponseField {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("field", &self.0)]
+    }
+}
+
+/// Supported provider, e.g. `EthMainnet(Ankr)`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricProvider(pub String);
+
+impl From<SupportedRpcService> for MetricProvider {
+    fn from(service: SupportedRpcService) -> Self {
+        Self(format!("{:?}", service))
+    }
+}
+
+impl MetricLabels for MetricProvider {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("provider", &self.0)]
+    }
+}
+
+/// API key of a provider, identified by its ID.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricApiKey {
+    pub provider_id: String,
+    pub key_id: String,
+}
+
+impl From<ApiKeyRef> for MetricApiKey {
+    fn from(api_key: ApiKeyRef) -> Self {
+        Self {
+            provider_id: api_key.provider_id.to_string(),
+            key_id: api_key.key_id.to_string(),
+        }
+    }
+}
+
+impl MetricLabels for MetricApiKey {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![
+            ("provider_id", &self.provider_id),
+            ("api_key_id", &self.key_id),
+        ]
+    }
+}
+
+/// Chain whose head is tracked, identified by its chain ID.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricChainId(pub String);
+
+impl From<u64> for MetricChainId {
+    fn from(chain_id: u64) -> Self {
+        Self(chain_id.to_string())
+    }
+}
+
+impl MetricLabels for MetricChainId {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("chain_id", &self.0)]
+    }
+}
+
+/// Reason for rejecting a call because the caller exceeded one of its limits.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, CandidType, Deserialize)]
+pub struct MetricCallerLimit(pub String);
+
+impl From<CallerLimit> for MetricCallerLimit {
+    fn from(limit: CallerLimit) -> Self {
+        let reason = match limit {
+            CallerLimit::Requests => "requests",
+            CallerLimit::Outcalls => "outcalls",
+            CallerLimit::Cycles => "cycles",
+        };
+        Self(reason.to_string())
+    }
+}
+
+impl MetricLabels for MetricCallerLimit {
+    fn metric_labels(&self) -> Vec<(&str, &str)> {
+        vec![("reason", &self.0)]
+    }
+}
+
 impl MetricLabels for LegacyRejectionCode {
     fn metric_labels(&self) -> Vec<(&str, &str)> {
         let code = match self {
@@ -171,6 +280,8 @@ pub struct Metrics {
     pub responses: HashMap<(MetricRpcMethod, MetricRpcService, MetricHttpStatusCode), u64>,
     #[serde(rename = "inconsistentResponses")]
     pub inconsistent_responses: HashMap<(MetricRpcMethod, MetricRpcService), u64>,
+    #[serde(rename = "inconsistentResponseFields")]
+    pub inconsistent_response_fields: HashMap<(MetricRpcMethod, MetricResponseField), u64>,
     #[serde(rename = "cyclesCharged")]
     pub cycles_charged: HashMap<(MetricRpcMethod, MetricRpcService), u128>,
     #[serde(rename = "errHttpOutcall")]
@@ -179,11 +290,34 @@ pub struct Metrics {
     pub err_max_response_size_exceeded: HashMap<(MetricRpcMethod, MetricRpcService), u64>,
     #[serde(rename = "errNoConsensus")]
     pub err_no_consensus: HashMap<(MetricRpcMethod, MetricRpcService), u64>,
+    #[serde(rename = "circuitBreakerTrips")]
+    pub circuit_breaker_trips: HashMap<MetricProvider, u64>,
+    #[serde(rename = "apiKeyRequests")]
+    pub api_key_requests: HashMap<MetricApiKey, u64>,
+    #[serde(rename = "apiKeyErrors")]
+    pub api_key_errors: HashMap<(MetricApiKey, MetricHttpStatusCode), u64>,
+    #[serde(rename = "callerLimitRejections")]
+    pub caller_limit_rejections: HashMap<MetricCallerLimit, u64>,
+    #[serde(rename = "cacheHits")]
+    pub cache_hits: HashMap<MetricRpcMethod, u64>,
+    #[serde(rename = "cacheMisses")]
+    pub cache_misses: HashMap<MetricRpcMethod, u64>,
+    #[serde(rename = "coalescedRequests")]
+    pub coalesced_requests: HashMap<MetricRpcMethod, u64>,
+    #[serde(rename = "getLogsBisections")]
+    pub get_logs_bisections: HashMap<MetricRpcMethod, u64>,
+    #[serde(rename = "chainReorgs")]
+    pub chain_reorgs: HashMap<MetricChainId, u64>,
+    #[serde(rename = "chainReorgedBlocks")]
+    pub chain_reorged_blocks: HashMap<MetricChainId, u64>,
+    #[serde(rename = "jsonRpcGatewayRequests")]
+    pub json_rpc_gateway_requests: HashMap<MetricRpcMethod, u64>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum RpcMethod {
     EthCall,
+    EthBlockNumber,
     EthFeeHistory,
     EthGetLogs,
     EthGetBlockByNumber,
@@ -197,6 +331,7 @@ impl RpcMethod {
     pub fn name(self) -> String {
         match self {
             RpcMethod::EthCall => "eth_call".to_string(),
+            RpcMethod::EthBlockNumber => "eth_blockNumber".to_string(),
             RpcMethod::EthFeeHistory => "eth_feeHistory".to_string(),
             RpcMethod::EthGetLogs => "eth_getLogs".to_string(),
             RpcMethod::EthGetBlockByNumber => "eth_getBlockByNumber".to_string(),
@@ -212,6 +347,16 @@ impl RpcMethod {
 pub struct ApiKey(String);
 
 impl ApiKey {
+    /// Validate the API key according to how it is used by the given authentication scheme.
+    pub fn try_new(key: String, auth: &RpcAuth) -> Result<ApiKey, String> {
+        match auth {
+            RpcAuth::BearerToken { .. } | RpcAuth::UrlParameter { .. } => validate_api_key(&key)?,
+            RpcAuth::Header { .. } => validate_header_api_key(&key)?,
+            RpcAuth::BasicAuth { .. } => validate_basic_auth_api_key(&key)?,
+        }
+        Ok(ApiKey(key))
+    }
+
     /// Explicitly read API key (use sparingly)
     pub fn read(&self) -> &str {
         &self.0
@@ -274,8 +419,215 @@ impl Storable for StorableLogFilter {
     };
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableChain(evm_rpc_types::Chain);
+
+impl Storable for StorableChain {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `Chain`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `Chain`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Methods suspended by the controllers, which fail without making any HTTP outcall.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Suspensions {
+    /// Maintenance mode, in which all methods are suspended.
+    pub all: bool,
+    pub methods: BTreeSet<String>,
+}
+
+impl Suspensions {
+    pub fn is_suspended(&self, method: &str) -> bool {
+        self.all || self.methods.contains(method)
+    }
+}
+
+impl Storable for Suspensions {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `Suspensions`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `Suspensions`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableCallerLimits(evm_rpc_types::CallerLimits);
+
+impl Default for StorableCallerLimits {
+    fn default() -> Self {
+        Self(evm_rpc_types::CallerLimits {
+            window_secs: DEFAULT_CALLER_LIMITS_WINDOW_SECS,
+            max_requests: None,
+            max_outcalls: None,
+            max_cycles: None,
+        })
+    }
+}
+
+impl Storable for StorableCallerLimits {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `CallerLimits`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `CallerLimits`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableAuditLogEntry(evm_rpc_types::AuditLogEntry);
+
+impl Storable for StorableAuditLogEntry {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `AuditLogEntry`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `AuditLogEntry`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Chains whose head is tracked, see [`crate::head_tracker`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableHeadTrackers(Vec<evm_rpc_types::HeadTracker>);
+
+impl Storable for StorableHeadTrackers {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `HeadTrackers`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `HeadTrackers`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Chains served over HTTP, by chain ID, see [`crate::gateway`].
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableJsonRpcGateways(BTreeMap<u64, evm_rpc_types::JsonRpcGateway>);
+
+impl StorableJsonRpcGateways {
+    pub fn get(&self, chain_id: u64) -> Option<&evm_rpc_types::JsonRpcGateway> {
+        self.0.get(&chain_id)
+    }
+}
+
+impl Storable for StorableJsonRpcGateways {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `JsonRpcGateways`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `JsonRpcGateways`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableLogSubscription(evm_rpc_types::LogSubscription);
+
+impl Storable for StorableLogSubscription {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `LogSubscription`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `LogSubscription`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, From, Into)]
+pub struct StorableTransactionWatch(evm_rpc_types::TransactionWatch);
+
+impl Storable for StorableTransactionWatch {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `TransactionWatch`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `TransactionWatch`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
 pub type ProviderId = u64;
 
+pub type ApiKeyId = u64;
+
+/// API key used for a request, stored as a request extension to record the usage of the API key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ApiKeyRef {
+    pub provider_id: ProviderId,
+    pub key_id: ApiKeyId,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ConstHeader {
     pub name: &'static str,
@@ -292,7 +644,7 @@ impl<'a> From<&'a ConstHeader> for HttpHeader {
 }
 
 /// Internal RPC provider representation.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Provider {
     pub provider_id: ProviderId,
     pub chain_id: u64,
@@ -301,68 +653,139 @@ pub struct Provider {
 }
 
 impl Provider {
+    pub fn try_from_args(
+        provider_id: ProviderId,
+        args: evm_rpc_types::ProviderArgs,
+    ) -> Result<Self, String> {
+        let alias = match args.alias {
+            None => None,
+            Some(service @ (RpcService::Provider(_) | RpcService::Custom(_))) => {
+                return Err(format!("Invalid provider alias: {:?}", service))
+            }
+            Some(service) => SupportedRpcService::new(&service),
+        };
+        Ok(Self {
+            provider_id,
+            chain_id: args.chain_id,
+            access: RpcAccess::from(args.access),
+            alias,
+        })
+    }
+
     pub fn api(&self) -> evm_rpc_types::RpcApi {
+        self.api_with_key().0
+    }
+
+    /// Returns the API to use for the next request to the provider, together with the used API key
+    /// if any, see [`next_api_key`].
+    pub fn api_with_key(&self) -> (evm_rpc_types::RpcApi, Option<ApiKeyRef>) {
         match &self.access {
-            RpcAccess::Authenticated { auth, public_url } => match get_api_key(self.provider_id) {
-                Some(api_key) => match auth {
-                    RpcAuth::BearerToken { url } => evm_rpc_types::RpcApi {
-                        url: url.to_string(),
-                        headers: Some(vec![evm_rpc_types::HttpHeader {
-                            name: "Authorization".to_string(),
-                            value: format!("Bearer {}", api_key.read()),
-                        }]),
-                    },
-                    RpcAuth::UrlParameter { url_pattern } => evm_rpc_types::RpcApi {
-                        url: url_pattern.replace(API_KEY_REPLACE_STRING, api_key.read()),
+            RpcAccess::Authenticated { auth, public_url } => match next_api_key(self.provider_id) {
+                Some((key_id, api_key)) => {
+                    let api_key_ref = ApiKeyRef {
+                        provider_id: self.provider_id,
+                        key_id,
+                    };
+                    (Self::authenticated_api(auth, &api_key), Some(api_key_ref))
+                }
+                None => (
+                    evm_rpc_types::RpcApi {
+                        url: public_url
+                            .as_deref()
+                            .unwrap_or_else(|| {
+                                panic!(
+                                    "API key not yet initialized for provider: {}",
+                                    self.provider_id
+                                )
+                            })
+                            .to_string(),
                         headers: None,
                     },
-                },
-                None => evm_rpc_types::RpcApi {
-                    url: public_url
-                        .unwrap_or_else(|| {
-                            panic!(
-                                "API key not yet initialized for provider: {}",
-                                self.provider_id
-                            )
-                        })
-                        .to_string(),
+                    None,
+                ),
+            },
+            RpcAccess::Unauthenticated { public_url } => (
+                evm_rpc_types::RpcApi {
+                    url: public_url.to_string(),
                     headers: None,
                 },
+                None,
+            ),
+        }
+    }
+
+    fn authenticated_api(auth: &RpcAuth, api_key: &ApiKey) -> evm_rpc_types::RpcApi {
+        match auth {
+            RpcAuth::BearerToken { url } => evm_rpc_types::RpcApi {
+                url: url.to_string(),
+                headers: Some(vec![evm_rpc_types::HttpHeader {
+                    name: "Authorization".to_string(),
+                    value: format!("Bearer {}", api_key.read()),
+                }]),
             },
-            RpcAccess::Unauthenticated { public_url } => evm_rpc_types::RpcApi {
-                url: public_url.to_string(),
+            RpcAuth::UrlParameter { url_pattern } => evm_rpc_types::RpcApi {
+                url: url_pattern.replace(API_KEY_REPLACE_STRING, api_key.read()),
                 headers: None,
             },
+            RpcAuth::Header { url, header_name } => evm_rpc_types::RpcApi {
+                url: url.to_string(),
+                headers: Some(vec![evm_rpc_types::HttpHeader {
+                    name: header_name.to_string(),
+                    value: api_key.read().to_string(),
+                }]),
+            },
+            RpcAuth::BasicAuth { url } => evm_rpc_types::RpcApi {
+                url: url.to_string(),
+                headers: Some(vec![evm_rpc_types::HttpHeader {
+                    name: "Authorization".to_string(),
+                    value: format!("Basic {}", BASE64_STANDARD.encode(api_key.read())),
+                }]),
+            },
         }
     }
 
     pub fn hostname(&self) -> Option<String> {
         hostname_from_url(match &self.access {
-            RpcAccess::Authenticated { auth, .. } => match auth {
-                RpcAuth::BearerToken { url } => url,
-                RpcAuth::UrlParameter { url_pattern } => url_pattern,
-            },
+            RpcAccess::Authenticated { auth, .. } => auth.url(),
             RpcAccess::Unauthenticated { public_url } => public_url,
         })
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RpcAccess {
     Authenticated {
         auth: RpcAuth,
         /// Public URL to use when the API key is not available.
-        public_url: Option<&'static str>,
+        public_url: Option<Cow<'static, str>>,
     },
     Unauthenticated {
-        public_url: &'static str,
+        public_url: Cow<'static, str>,
     },
 }
 
+impl From<evm_rpc_types::RpcAccess> for RpcAccess {
+    fn from(access: evm_rpc_types::RpcAccess) -> Self {
+        match access {
+            evm_rpc_types::RpcAccess::Authenticated { auth, public_url } => {
+                RpcAccess::Authenticated {
+                    auth: RpcAuth::from(auth),
+                    public_url: public_url.map(Cow::Owned),
+                }
+            }
+            evm_rpc_types::RpcAccess::Unauthenticated { public_url } => {
+                RpcAccess::Unauthenticated {
+                    public_url: Cow::Owned(public_url),
+                }
+            }
+        }
+    }
+}
+
 impl RpcAccess {
-    pub fn public_url(&self) -> Option<&'static str> {
+    pub fn public_url(&self) -> Option<&str> {
         match self {
-            RpcAccess::Authenticated { public_url, .. } => *public_url,
+            RpcAccess::Authenticated { public_url, .. } => public_url.as_deref(),
             RpcAccess::Unauthenticated { public_url } => Some(public_url),
         }
     }
@@ -399,6 +822,20 @@ impl OverrideProvider {
             }
         }
     }
+
+    /// Returns the override provider where the replacement of the URL is redacted,
+    /// since it may contain credentials.
+    pub fn redacted(&self) -> evm_rpc_types::OverrideProvider {
+        evm_rpc_types::OverrideProvider {
+            override_url: self
+                .override_url
+                .as_ref()
+                .map(|substitution| RegexSubstitution {
+                    pattern: substitution.pattern.clone(),
+                    replacement: REDACTED_STRING.to_string(),
+                }),
+        }
+    }
 }
 
 impl TryFrom<evm_rpc_types::OverrideProvider> for OverrideProvider {
@@ -434,14 +871,73 @@ impl Storable for OverrideProvider {
     const BOUND: Bound = Bound::Unbounded;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl Storable for Provider {
+    fn to_bytes(&self) -> Cow<'_, [u8]> {
+        serde_json::to_vec(self)
+            .expect("Error while serializing `Provider`")
+            .into()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.to_bytes().into_owned()
+    }
+
+    fn from_bytes(bytes: Cow<[u8]>) -> Self {
+        serde_json::from_slice(&bytes).expect("Error while deserializing `Provider`")
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum RpcAuth {
     /// API key will be used in an Authorization header as Bearer token, e.g.,
     /// `Authorization: Bearer API_KEY`
     BearerToken {
-        url: &'static str,
+        url: Cow<'static, str>,
     },
     UrlParameter {
-        url_pattern: &'static str,
+        url_pattern: Cow<'static, str>,
+    },
+    /// API key will be used as the value of a custom header, e.g., `x-api-key: API_KEY`
+    Header {
+        url: Cow<'static, str>,
+        header_name: Cow<'static, str>,
     },
+    /// API key of the form `USERNAME:PASSWORD` will be used for HTTP basic authentication, e.g.,
+    /// `Authorization: Basic BASE64(USERNAME:PASSWORD)`
+    BasicAuth {
+        url: Cow<'static, str>,
+    },
+}
+
+impl RpcAuth {
+    pub fn url(&self) -> &str {
+        match self {
+            RpcAuth::BearerToken { url } => url,
+            RpcAuth::UrlParameter { url_pattern } => url_pattern,
+            RpcAuth::Header { url, .. } => url,
+            RpcAuth::BasicAuth { url } => url,
+        }
+    }
+}
+
+impl From<evm_rpc_types::RpcAuth> for RpcAuth {
+    fn from(auth: evm_rpc_types::RpcAuth) -> Self {
+        match auth {
+            evm_rpc_types::RpcAuth::BearerToken { url } => RpcAuth::BearerToken {
+                url: Cow::Owned(url),
+            },
+            evm_rpc_types::RpcAuth::UrlParameter { url_pattern } => RpcAuth::UrlParameter {
+                url_pattern: Cow::Owned(url_pattern),
+            },
+            evm_rpc_types::RpcAuth::Header { url, header_name } => RpcAuth::Header {
+                url: Cow::Owned(url),
+                header_name: Cow::Owned(header_name),
+            },
+            evm_rpc_types::RpcAuth::BasicAuth { url } => RpcAuth::BasicAuth {
+                url: Cow::Owned(url),
+            },
+        }
+    }
 }
@@ -220,3 +220,113 @@ mod override_provider {
         }
     }
 }
+
+mod provider_api {
+    use crate::memory::insert_api_key;
+    use crate::types::{ApiKey, Provider, RpcAccess, RpcAuth};
+    use evm_rpc_types::{HttpHeader, RpcApi};
+    use std::borrow::Cow;
+
+    #[test]
+    fn should_use_api_key_in_custom_header() {
+        let auth = RpcAuth::Header {
+            url: Cow::Borrowed("https://rpc.example.com"),
+            header_name: Cow::Borrowed("x-api-key"),
+        };
+        let provider = provider_with_api_key(1_000, auth, "a1/b2+c3==");
+
+        assert_eq!(
+            provider.api(),
+            RpcApi {
+                url: "https://rpc.example.com".to_string(),
+                headers: Some(vec![HttpHeader {
+                    name: "x-api-key".to_string(),
+                    value: "a1/b2+c3==".to_string(),
+                }]),
+            }
+        );
+    }
+
+    #[test]
+    fn should_use_api_key_for_basic_auth() {
+        let auth = RpcAuth::BasicAuth {
+            url: Cow::Borrowed("https://rpc.example.com"),
+        };
+        let provider = provider_with_api_key(1_001, auth, "Aladdin:open sesame");
+
+        assert_eq!(
+            provider.api(),
+            RpcApi {
+                url: "https://rpc.example.com".to_string(),
+                headers: Some(vec![HttpHeader {
+                    name: "Authorization".to_string(),
+                    value: "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_string(),
+                }]),
+            }
+        );
+        assert!(!format!("{:?}", provider.api()).contains("QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+    }
+
+    #[test]
+    fn should_validate_api_key_according_to_auth() {
+        let header = RpcAuth::Header {
+            url: Cow::Borrowed("https://rpc.example.com"),
+            header_name: Cow::Borrowed("x-api-key"),
+        };
+        let basic_auth = RpcAuth::BasicAuth {
+            url: Cow::Borrowed("https://rpc.example.com"),
+        };
+
+        assert!(ApiKey::try_new("a1/b2".to_string(), &header).is_ok());
+        assert!(ApiKey::try_new("a1\r\nb2".to_string(), &header).is_err());
+        assert!(ApiKey::try_new("user:pass".to_string(), &basic_auth).is_ok());
+        assert!(ApiKey::try_new("user".to_string(), &basic_auth).is_err());
+    }
+
+    fn provider_with_api_key(provider_id: u64, auth: RpcAuth, api_key: &str) -> Provider {
+        let provider = Provider {
+            provider_id,
+            chain_id: 1,
+            access: RpcAccess::Authenticated {
+                auth: auth.clone(),
+                public_url: None,
+            },
+            alias: None,
+        };
+        insert_api_key(
+            provider_id,
+            ApiKey::try_new(api_key.to_string(), &auth).unwrap(),
+        );
+        provider
+    }
+}
+
+mod suspensions {
+    use crate::types::Suspensions;
+    use ic_stable_structures::Storable;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn should_suspend_selected_methods() {
+        let suspensions = Suspensions {
+            all: false,
+            methods: BTreeSet::from(["eth_sendRawTransaction".to_string()]),
+        };
+
+        assert!(suspensions.is_suspended("eth_sendRawTransaction"));
+        assert!(!suspensions.is_suspended("eth_getLogs"));
+        assert!(!Suspensions::default().is_suspended("eth_sendRawTransaction"));
+        assert_eq!(Suspensions::from_bytes(suspensions.to_bytes()), suspensions);
+    }
+
+    #[test]
+    fn should_suspend_all_methods_in_maintenance_mode() {
+        let suspensions = Suspensions {
+            all: true,
+            methods: BTreeSet::new(),
+        };
+
+        assert!(suspensions.is_suspended("eth_sendRawTransaction"));
+        assert!(suspensions.is_suspended("request"));
+    }
+}
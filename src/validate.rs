@@ -15,6 +15,39 @@ pub fn validate_api_key(api_key: &str) -> Result<(), &'static str> {
     }
 }
 
+/// Validate an API key sent as the value of an HTTP header.
+pub fn validate_header_api_key(api_key: &str) -> Result<(), &'static str> {
+    if api_key.is_empty() {
+        Err("API key must not be an empty string")
+    } else if api_key.len() > 200 {
+        Err("API key must be <= 200 characters")
+    } else if !api_key.chars().all(|char| char.is_ascii_graphic()) {
+        Err("Invalid character in API key")
+    } else {
+        Ok(())
+    }
+}
+
+/// Validate an API key of the form `USERNAME:PASSWORD` used for HTTP basic authentication.
+pub fn validate_basic_auth_api_key(api_key: &str) -> Result<(), &'static str> {
+    if api_key.len() > 200 {
+        return Err("API key must be <= 200 characters");
+    }
+    match api_key.split_once(':') {
+        Some((username, password)) if !username.is_empty() && !password.is_empty() => {
+            if api_key
+                .chars()
+                .all(|char| char.is_ascii_graphic() || char == ' ')
+            {
+                Ok(())
+            } else {
+                Err("Invalid character in API key")
+            }
+        }
+        _ => Err("API key must be of the form USERNAME:PASSWORD"),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -40,4 +73,36 @@ mod test {
             Err("Invalid character in API key")
         );
     }
+
+    #[test]
+    pub fn test_validate_header_api_key() {
+        assert_eq!(validate_header_api_key("abc/def+=="), Ok(()));
+        assert_eq!(
+            validate_header_api_key(""),
+            Err("API key must not be an empty string")
+        );
+        assert_eq!(
+            validate_header_api_key("abc def"),
+            Err("Invalid character in API key")
+        );
+        assert_eq!(
+            validate_header_api_key("abc\r\nx-injected: 1"),
+            Err("Invalid character in API key")
+        );
+    }
+
+    #[test]
+    pub fn test_validate_basic_auth_api_key() {
+        assert_eq!(validate_basic_auth_api_key("user:pass:word"), Ok(()));
+        for invalid in ["", "user", "user:", ":pass"] {
+            assert_eq!(
+                validate_basic_auth_api_key(invalid),
+                Err("API key must be of the form USERNAME:PASSWORD")
+            );
+        }
+        assert_eq!(
+            validate_basic_auth_api_key("user:pass\n"),
+            Err("Invalid character in API key")
+        );
+    }
 }
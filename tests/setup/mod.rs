@@ -7,14 +7,23 @@ use evm_rpc::{
     types::{ProviderId, RpcAccess},
 };
 use evm_rpc_client::{AlloyResponseConverter, ClientBuilder, EvmRpcClient, NoRetry};
-use evm_rpc_types::{InstallArgs, Provider, RpcResult, RpcService};
+use evm_rpc_types::{
+    AuditLogEntry, ChainHead, Config, GetAuditLogArgs, GetLogsArgs, GetLogsPage, GetLogsRpcConfig,
+    HeadTracker, InstallArgs, JsonRpcGateway, LogSubscription, MultiRpcResult, Provider, RpcResult,
+    RpcService, RpcServices, SubscribeError, SubscribeLogsArgs, TransactionWatch,
+    WatchTransactionArgs, WithdrawError,
+};
 use ic_canister_runtime::{CyclesWalletRuntime, Runtime};
 use ic_http_types::{HttpRequest, HttpResponse};
 use ic_metrics_assert::{MetricsAssert, PocketIcAsyncHttpQuery};
 use ic_pocket_canister_runtime::{MockHttpOutcalls, PocketIcRuntime};
 use ic_test_utilities_load_wasm::load_wasm;
 use num_traits::ToPrimitive;
-use pocket_ic::{nonblocking::PocketIc, ErrorCode, PocketIcBuilder, RejectResponse};
+use pocket_ic::{
+    common::rest::{CanisterHttpReply, CanisterHttpResponse, MockCanisterHttpResponse},
+    nonblocking::PocketIc,
+    ErrorCode, PocketIcBuilder, RejectResponse,
+};
 use serde::de::DeserializeOwned;
 use std::{
     env::{set_var, var},
@@ -23,6 +32,9 @@ use std::{
     time::Duration,
 };
 
+/// Maximum number of rounds executed to run the timers that are due.
+const MAX_TICKS: usize = 20;
+
 #[derive(Clone)]
 pub struct EvmRpcSetup {
     pub env: Arc<PocketIc>,
@@ -135,6 +147,243 @@ impl EvmRpcSetup {
         )
     }
 
+    /// Deposits the given amount of cycles to the prepaid cycles account of the wallet canister.
+    pub async fn deposit(&self, cycles: u128) -> u128 {
+        self.new_mock_http_runtime_with_wallet(MockHttpOutcalls::never())
+            .update_call(self.evm_rpc_canister_id, "deposit", (), cycles)
+            .await
+            .unwrap()
+    }
+
+    /// Withdraws the given amount of cycles from the prepaid cycles account of the wallet canister.
+    pub async fn withdraw(&self, amount: u128) -> Result<u128, WithdrawError> {
+        self.new_mock_http_runtime_with_wallet(MockHttpOutcalls::never())
+            .update_call(self.evm_rpc_canister_id, "withdraw", (amount,), 0)
+            .await
+            .unwrap()
+    }
+
+    /// Subscribes the wallet canister to logs, attaching the given amount of cycles.
+    pub async fn subscribe_logs(
+        &self,
+        args: SubscribeLogsArgs,
+        cycles: u128,
+    ) -> Result<u64, SubscribeError> {
+        self.new_mock_http_runtime_with_wallet(MockHttpOutcalls::never())
+            .update_call(self.evm_rpc_canister_id, "subscribeLogs", (args,), cycles)
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_log_subscriptions(
+        &self,
+        owner: Principal,
+        caller: Principal,
+    ) -> Vec<LogSubscription> {
+        self.call_query(
+            "getLogSubscriptions",
+            Encode!(&Some(owner)).unwrap(),
+            caller,
+        )
+        .await
+    }
+
+    /// Watches a transaction on behalf of the wallet canister, attaching the given amount of cycles.
+    pub async fn watch_transaction(
+        &self,
+        args: WatchTransactionArgs,
+        cycles: u128,
+    ) -> Result<u64, SubscribeError> {
+        self.new_mock_http_runtime_with_wallet(MockHttpOutcalls::never())
+            .update_call(
+                self.evm_rpc_canister_id,
+                "watchTransaction",
+                (args,),
+                cycles,
+            )
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_transaction_watches(
+        &self,
+        owner: Principal,
+        caller: Principal,
+    ) -> Vec<TransactionWatch> {
+        self.call_query(
+            "getTransactionWatches",
+            Encode!(&Some(owner)).unwrap(),
+            caller,
+        )
+        .await
+    }
+
+    /// Advances the time by the given duration and executes the timers that are then due.
+    pub async fn advance_time_and_run_timers(&self, duration: Duration) {
+        self.advance_time_and_run_timers_with_http_outcalls(duration, |_| {
+            panic!("Unexpected HTTP outcall")
+        })
+        .await
+    }
+
+    /// Advances the time by the given duration and executes the timers that are then due,
+    /// answering the HTTP outcalls they make with the JSON-RPC response to the JSON-RPC request.
+    pub async fn advance_time_and_run_timers_with_http_outcalls(
+        &self,
+        duration: Duration,
+        respond: impl Fn(&serde_json::Value) -> serde_json::Value,
+    ) {
+        self.env.advance_time(duration).await;
+        for _ in 0..MAX_TICKS {
+            self.env.tick().await;
+            for request in self.env.get_canister_http().await {
+                let json_request: serde_json::Value = serde_json::from_slice(&request.body)
+                    .expect("failed to parse JSON-RPC request");
+                let body = serde_json::to_vec(&respond(&json_request)).unwrap();
+                self.env
+                    .mock_canister_http_response(MockCanisterHttpResponse {
+                        subnet_id: request.subnet_id,
+                        request_id: request.request_id,
+                        response: CanisterHttpResponse::CanisterHttpReply(CanisterHttpReply {
+                            status: 200,
+                            headers: vec![],
+                            body,
+                        }),
+                        additional_responses: vec![],
+                    })
+                    .await;
+            }
+        }
+    }
+
+    // Not supported by the `evm_rpc_client::EvmRpcClient`
+    pub async fn eth_get_logs_max_cycles_cost(
+        &self,
+        (source, config, args): (RpcServices, Option<GetLogsRpcConfig>, GetLogsArgs),
+    ) -> RpcResult<u128> {
+        self.call_query::<RpcResult<Nat>>(
+            "eth_getLogsMaxCyclesCost",
+            Encode!(&source, &config, &args).unwrap(),
+            Principal::anonymous(),
+        )
+        .await
+        .map(|cycles_cost| cycles_cost.0.to_u128().unwrap())
+    }
+
+    // Not supported by the `evm_rpc_client::EvmRpcClient`
+    pub async fn eth_get_logs_paginated(
+        &self,
+        mocks: impl Into<MockHttpOutcalls>,
+        (source, config, args): (RpcServices, Option<GetLogsRpcConfig>, GetLogsArgs),
+        cycles: u128,
+    ) -> MultiRpcResult<GetLogsPage> {
+        self.new_mock_http_runtime_with_wallet(mocks)
+            .update_call(
+                self.evm_rpc_canister_id,
+                "eth_getLogsPaginated",
+                (source, config, args),
+                cycles,
+            )
+            .await
+            .unwrap()
+    }
+
+    pub async fn set_json_rpc_gateways(&self, gateways: Vec<JsonRpcGateway>) {
+        self.call_update::<()>(
+            "setJsonRpcGateways",
+            Encode!(&gateways).unwrap(),
+            self.controller,
+        )
+        .await
+    }
+
+    /// Deposits the given amount of cycles to the prepaid cycles account of the JSON-RPC gateway
+    /// of the given chain.
+    pub async fn deposit_json_rpc_gateway(&self, chain_id: u64, cycles: u128) -> u128 {
+        self.new_mock_http_runtime_with_wallet(MockHttpOutcalls::never())
+            .update_call(
+                self.evm_rpc_canister_id,
+                "depositJsonRpcGateway",
+                (chain_id,),
+                cycles,
+            )
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_json_rpc_gateway_account(&self, chain_id: u64) -> Principal {
+        self.call_query(
+            "getJsonRpcGatewayAccount",
+            Encode!(&chain_id).unwrap(),
+            Principal::anonymous(),
+        )
+        .await
+    }
+
+    /// Sends the given JSON-RPC request over HTTP to `/rpc/{chainId}` as the HTTP gateway does,
+    /// i.e. with an update call of the anonymous principal, and returns the JSON-RPC response.
+    pub async fn json_rpc_http_request(
+        &self,
+        mocks: impl Into<MockHttpOutcalls>,
+        chain_id: u64,
+        request: serde_json::Value,
+    ) -> serde_json::Value {
+        let response = self.json_rpc_http_response(mocks, chain_id, request).await;
+        serde_json::from_slice(&response.body).expect("failed to parse JSON-RPC response")
+    }
+
+    /// Sends a JSON-RPC request over HTTP and returns the HTTP response, whose body is empty
+    /// if the request is a notification.
+    pub async fn json_rpc_http_response(
+        &self,
+        mocks: impl Into<MockHttpOutcalls>,
+        chain_id: u64,
+        request: serde_json::Value,
+    ) -> HttpResponse {
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            url: format!("/rpc/{chain_id}"),
+            headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+            body: serde_bytes::ByteBuf::from(serde_json::to_vec(&request).unwrap()),
+        };
+        let response: HttpResponse =
+            PocketIcRuntime::new(self.env.as_ref(), Principal::anonymous())
+                .with_http_mocks(mocks.into())
+                .update_call(
+                    self.evm_rpc_canister_id,
+                    "http_request_update",
+                    (request,),
+                    0,
+                )
+                .await
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+        response
+    }
+
+    pub async fn set_head_trackers(&self, trackers: Vec<HeadTracker>, caller: Principal) {
+        self.call_update::<()>("setHeadTrackers", Encode!(&trackers).unwrap(), caller)
+            .await
+    }
+
+    pub async fn get_chain_head(&self, chain_id: u64) -> Option<ChainHead> {
+        self.call_query(
+            "getChainHead",
+            Encode!(&chain_id).unwrap(),
+            Principal::anonymous(),
+        )
+        .await
+    }
+
+    pub async fn get_balance(&self, principal: Principal) -> u128 {
+        self.call_query(
+            "getBalance",
+            Encode!(&Some(principal)).unwrap(),
+            Principal::anonymous(),
+        )
+        .await
+    }
+
     pub async fn evm_rpc_canister_cycles_balance(&self) -> u128 {
         self.env
             .canister_status(self.evm_rpc_canister_id, Some(self.controller))
@@ -179,6 +428,15 @@ impl EvmRpcSetup {
         self
     }
 
+    pub async fn get_audit_log(
+        &self,
+        args: GetAuditLogArgs,
+        caller: Principal,
+    ) -> Vec<AuditLogEntry> {
+        self.call_query("getAuditLog", Encode!(&args).unwrap(), caller)
+            .await
+    }
+
     pub async fn http_get_logs(&self, priority: &str) -> Vec<LogEntry<Priority>> {
         let request = HttpRequest {
             method: "".to_string(),
@@ -221,6 +479,31 @@ impl EvmRpcSetup {
         .await
     }
 
+    pub async fn get_config(&self) -> Config {
+        self.call_query("getConfig", Encode!().unwrap(), Principal::anonymous())
+            .await
+    }
+
+    pub async fn set_demo(&self, demo: bool, caller: Principal) {
+        self.call_update::<()>("setDemo", Encode!(&demo).unwrap(), caller)
+            .await
+    }
+
+    pub async fn set_num_subnet_nodes(&self, nodes: u32, caller: Principal) {
+        self.call_update::<()>("setNumSubnetNodes", Encode!(&nodes).unwrap(), caller)
+            .await
+    }
+
+    pub async fn set_maintenance_mode(&self, enabled: bool, caller: Principal) {
+        self.call_update::<()>("setMaintenanceMode", Encode!(&enabled).unwrap(), caller)
+            .await
+    }
+
+    pub async fn set_suspended_methods(&self, methods: &[&str], caller: Principal) {
+        self.call_update::<()>("setSuspendedMethods", Encode!(&methods).unwrap(), caller)
+            .await
+    }
+
     pub async fn check_metrics(self) -> MetricsAssert<Self> {
         MetricsAssert::from_async_http_query(self).await
     }
@@ -8,9 +8,12 @@ use candid::{CandidType, Encode, Principal};
 use canhttp::http::json::{ConstantSizeId, Id};
 use evm_rpc_client::{DoubleCycles, EvmRpcEndpoint, NoRetry, RequestBuilder};
 use evm_rpc_types::{
-    BlockTag, ConsensusStrategy, EthMainnetService, EthSepoliaService, GetLogsRpcConfig, Hex,
-    Hex32, HttpOutcallError, InstallArgs, JsonRpcError, LegacyRejectionCode, MultiRpcResult,
-    Nat256, ProviderError, RpcApi, RpcError, RpcResult, RpcService, RpcServices, ValidationError,
+    AuditAction, BlockHeader, BlockTag, ChainHead, Config, ConsensusStrategy, EthMainnetService,
+    EthSepoliaService, GetAuditLogArgs, GetLogsRpcConfig, HeadTracker, Hex, Hex32,
+    HttpOutcallError, InstallArgs, JsonRpcError, JsonRpcGateway, LegacyRejectionCode,
+    LogSubscription, MultiRpcResult, Nat256, ProviderError, RpcApi, RpcConfig, RpcError, RpcResult,
+    RpcService, RpcServices, SubscribeError, SubscribeLogsArgs, ValidationError,
+    WatchTransactionArgs,
 };
 use ic_canister_runtime::CyclesWalletRuntime;
 use ic_error_types::RejectCode;
@@ -22,7 +25,7 @@ use ic_pocket_canister_runtime::{
 use pocket_ic::{common::rest::CanisterHttpResponse, ErrorCode};
 use serde::de::DeserializeOwned;
 use serde_json::{json, Value};
-use std::{fmt::Debug, iter};
+use std::{fmt::Debug, iter, time::Duration};
 use strum::IntoEnumIterator;
 
 const DEFAULT_CALLER_TEST_ID: Principal =
@@ -325,6 +328,128 @@ async fn eth_get_logs_should_fail_when_block_range_too_large() {
     }
 }
 
+#[tokio::test]
+async fn eth_get_logs_paginated_should_query_block_range_in_chunks() {
+    fn mocks(chunks: &[(u64, u64)]) -> MockHttpOutcallsBuilder {
+        let mut mocks = MockHttpOutcallsBuilder::new();
+        let mut ids = 0..;
+        for (from_block, to_block) in chunks {
+            // One request per provider
+            for _ in 0..3 {
+                let id = ids.next().unwrap();
+                mocks = mocks
+                    .given(
+                        get_logs_request(
+                            BlockNumberOrTag::Number(*from_block),
+                            BlockNumberOrTag::Number(*to_block),
+                        )
+                        .with_id(id),
+                    )
+                    .respond_with(get_logs_response().with_id(id));
+            }
+        }
+        mocks
+    }
+
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let args = |from_block: u64, to_block: u64| evm_rpc_types::GetLogsArgs {
+        from_block: Some(BlockTag::Number(from_block.into())),
+        to_block: Some(BlockTag::Number(to_block.into())),
+        addresses: vec![address!("0xdac17f958d2ee523a2206206994597c13d831ec7").into()],
+        topics: None,
+    };
+    let config = GetLogsRpcConfig {
+        max_block_range: Some(500),
+        ..Default::default()
+    };
+
+    let page = setup
+        .eth_get_logs_paginated(
+            mocks(&[(0, 499), (500, 999)]),
+            (RpcServices::EthMainnet(None), Some(config), args(0, 999)),
+            10_000_000_000_000,
+        )
+        .await
+        .expect_consistent()
+        .unwrap();
+    // The same log is returned for both chunks
+    assert_eq!(page.logs.len(), 1);
+    assert_eq!(page.logs[0].block_number, Some(0x11dc77e_u64.into()));
+    assert_eq!(page.next_from_block, None);
+}
+
+#[tokio::test]
+async fn eth_get_logs_paginated_should_validate_block_range() {
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+
+    for (from_block, to_block) in [
+        (Some(BlockTag::Latest), Some(BlockTag::Number(1_u8.into()))),
+        (Some(BlockTag::Number(1_u8.into())), None),
+        (
+            Some(BlockTag::Number(2_u8.into())),
+            Some(BlockTag::Number(1_u8.into())),
+        ),
+    ] {
+        let result = setup
+            .eth_get_logs_paginated(
+                MockHttpOutcalls::never(),
+                (
+                    RpcServices::EthMainnet(None),
+                    None,
+                    evm_rpc_types::GetLogsArgs {
+                        from_block,
+                        to_block,
+                        addresses: vec![
+                            address!("0xdac17f958d2ee523a2206206994597c13d831ec7").into()
+                        ],
+                        topics: None,
+                    },
+                ),
+                10_000_000_000_000,
+            )
+            .await
+            .expect_consistent();
+        assert_matches!(
+            result,
+            Err(RpcError::ValidationError(ValidationError::Custom(_)))
+        );
+    }
+}
+
+#[tokio::test]
+async fn eth_get_logs_max_cycles_cost_should_include_bisection_requests() {
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let client = setup
+        .client(MockHttpOutcalls::never())
+        .with_rpc_sources(RpcServices::EthMainnet(None))
+        .with_candid()
+        .build();
+
+    for (from_block, to_block, max_num_requests) in
+        [(10_u64, 10_u64, 1), (10, 11, 3), (10, 110, 15)]
+    {
+        let args = evm_rpc_types::GetLogsArgs {
+            from_block: Some(BlockTag::Number(from_block.into())),
+            to_block: Some(BlockTag::Number(to_block.into())),
+            addresses: vec![address!("0xdac17f958d2ee523a2206206994597c13d831ec7").into()],
+            topics: None,
+        };
+        let cycles_cost = client
+            .get_logs(args.clone())
+            .request_cost()
+            .send()
+            .await
+            .unwrap();
+        assert!(cycles_cost > 0);
+
+        let max_cycles_cost = setup
+            .eth_get_logs_max_cycles_cost((RpcServices::EthMainnet(None), None, args))
+            .await;
+
+        assert_eq!(max_cycles_cost, Ok(cycles_cost * max_num_requests));
+    }
+}
+
 #[tokio::test]
 async fn eth_get_block_by_number_should_succeed() {
     fn mocks(offset: u64) -> MockHttpOutcallsBuilder {
@@ -544,6 +669,59 @@ async fn eth_get_block_by_number_should_be_consistent_when_total_difficulty_inco
     assert_eq!(response.header.total_difficulty, None);
 }
 
+#[tokio::test]
+async fn eth_get_block_by_number_should_succeed_with_non_replicated_outcalls() {
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let source = RpcServices::EthMainnet(Some(vec![
+        EthMainnetService::Ankr,
+        EthMainnetService::PublicNode,
+    ]));
+    let non_replicated = RpcConfig {
+        is_replicated: Some(false),
+        ..Default::default()
+    };
+
+    let client = setup
+        .client(MockHttpOutcalls::never())
+        .with_rpc_sources(source.clone())
+        .build();
+    let replicated_cost = client
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .request_cost()
+        .send()
+        .await
+        .unwrap();
+    let non_replicated_cost = setup
+        .client(MockHttpOutcalls::never())
+        .with_rpc_sources(source.clone())
+        .with_rpc_config(non_replicated.clone())
+        .build()
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .request_cost()
+        .send()
+        .await
+        .unwrap();
+    assert!(0 < non_replicated_cost && non_replicated_cost < replicated_cost);
+
+    let mocks = MockHttpOutcallsBuilder::new()
+        .given(get_block_by_number_request().with_id(0))
+        .respond_with(get_block_by_number_response().with_id(0))
+        .given(get_block_by_number_request().with_id(1))
+        .respond_with(get_block_by_number_response().with_id(1));
+    let response = setup
+        .client(mocks)
+        .with_rpc_sources(source)
+        .with_rpc_config(non_replicated)
+        .build()
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .send()
+        .await
+        .expect_consistent()
+        .unwrap();
+
+    assert_eq!(response.number(), 18_722_845_u64);
+}
+
 #[tokio::test]
 async fn eth_get_transaction_receipt_should_succeed() {
     fn mocks(
@@ -1012,6 +1190,48 @@ async fn candid_rpc_should_err_without_cycles() {
     }
 }
 
+#[tokio::test]
+async fn candid_rpc_should_charge_prepaid_balance_when_too_few_cycles_attached() {
+    const DEPOSIT: u128 = 100_000_000_000_000;
+
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let wallet = setup.wallet_canister_id;
+
+    assert_eq!(setup.deposit(DEPOSIT).await, DEPOSIT);
+    assert_eq!(setup.get_balance(wallet).await, DEPOSIT);
+
+    let mocks = MockHttpOutcallsBuilder::new()
+        .given(get_block_by_number_request().with_id(0))
+        .respond_with(get_block_by_number_response().with_id(0))
+        .given(get_block_by_number_request().with_id(1))
+        .respond_with(get_block_by_number_response().with_id(1))
+        .given(get_block_by_number_request().with_id(2))
+        .respond_with(get_block_by_number_response().with_id(2));
+    let result = setup
+        .client(mocks)
+        .with_rpc_sources(RpcServices::EthMainnet(None))
+        .build()
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .with_cycles(0)
+        .send()
+        .await
+        .expect_consistent();
+    assert_matches!(result, Ok(_));
+
+    let balance = setup.get_balance(wallet).await;
+    assert!(
+        0 < balance && balance < DEPOSIT,
+        "expected the balance {balance} to be charged for the call"
+    );
+
+    assert_eq!(
+        setup.withdraw(balance + 1).await,
+        Err(evm_rpc_types::WithdrawError::InsufficientBalance { balance })
+    );
+    assert_eq!(setup.withdraw(balance).await, Ok(0));
+    assert_eq!(setup.get_balance(wallet).await, 0);
+}
+
 #[tokio::test]
 async fn candid_rpc_should_err_when_service_unavailable() {
     let setup = EvmRpcSetup::new().await.mock_api_keys().await;
@@ -2166,6 +2386,206 @@ async fn upgrade_should_change_manage_api_key_principals() {
         .await;
 }
 
+#[tokio::test]
+async fn should_record_administrative_actions_in_audit_log() {
+    let setup = EvmRpcSetup::with_args(InstallArgs {
+        demo: Some(true),
+        manage_api_keys: Some(vec![ADDITIONAL_TEST_ID]),
+        ..Default::default()
+    })
+    .await;
+    setup
+        .update_api_keys(&[(0, Some("api-key".to_string()))], ADDITIONAL_TEST_ID)
+        .await;
+
+    let audit_log = setup
+        .get_audit_log(GetAuditLogArgs::default(), setup.controller)
+        .await;
+    assert_eq!(
+        audit_log
+            .iter()
+            .map(|entry| (entry.id, entry.caller, entry.action.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (0, setup.controller, AuditAction::SetDemo(true)),
+            (
+                1,
+                setup.controller,
+                AuditAction::SetApiKeyPrincipals(vec![ADDITIONAL_TEST_ID])
+            ),
+            (
+                2,
+                ADDITIONAL_TEST_ID,
+                AuditAction::UpdateApiKeys {
+                    provider_ids: vec![0]
+                }
+            ),
+        ]
+    );
+    assert!(audit_log
+        .windows(2)
+        .all(|entries| entries[0].timestamp <= entries[1].timestamp));
+
+    // API key principals may also read the audit log
+    assert_eq!(
+        setup
+            .get_audit_log(
+                GetAuditLogArgs {
+                    start: Some(1),
+                    limit: Some(1),
+                },
+                ADDITIONAL_TEST_ID,
+            )
+            .await,
+        audit_log[1..2]
+    );
+    assert_eq!(
+        setup
+            .get_audit_log(
+                GetAuditLogArgs {
+                    start: Some(3),
+                    limit: None,
+                },
+                setup.controller,
+            )
+            .await,
+        vec![]
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "You are not authorized")]
+async fn should_prevent_unauthorized_get_audit_log() {
+    let setup = EvmRpcSetup::new().await;
+    setup
+        .get_audit_log(GetAuditLogArgs::default(), setup.caller)
+        .await;
+}
+
+#[tokio::test]
+async fn should_update_config() {
+    let setup = EvmRpcSetup::new().await;
+    let initial_config = setup.get_config().await;
+    assert!(!initial_config.demo);
+    assert!(!initial_config.maintenance_mode);
+    assert_eq!(
+        initial_config.nodes_in_subnet,
+        setup.get_nodes_in_subnet().await
+    );
+
+    setup.set_demo(true, setup.controller).await;
+    setup.set_num_subnet_nodes(13, setup.controller).await;
+    setup.set_maintenance_mode(true, setup.controller).await;
+
+    assert_eq!(
+        setup.get_config().await,
+        Config {
+            demo: true,
+            nodes_in_subnet: 13,
+            maintenance_mode: true,
+            ..initial_config
+        }
+    );
+    assert_eq!(setup.get_nodes_in_subnet().await, 13);
+    assert_eq!(
+        setup
+            .request_cost(
+                RpcService::EthMainnet(EthMainnetService::PublicNode),
+                r#"{"jsonrpc":"2.0","id":0,"method":"test"}"#,
+                1000
+            )
+            .await
+            .unwrap(),
+        0_u128
+    );
+    assert_eq!(
+        setup
+            .get_audit_log(GetAuditLogArgs::default(), setup.controller)
+            .await
+            .into_iter()
+            .map(|entry| entry.action)
+            .collect::<Vec<_>>(),
+        vec![
+            AuditAction::SetDemo(true),
+            AuditAction::SetNodesInSubnet(13),
+            AuditAction::SetMaintenanceMode(true),
+        ]
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "You are not authorized")]
+async fn should_prevent_unauthorized_config_update() {
+    let setup = EvmRpcSetup::new().await;
+    setup.set_demo(true, setup.caller).await;
+}
+
+#[tokio::test]
+async fn should_suspend_and_resume_methods() {
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+
+    setup
+        .set_suspended_methods(&["eth_getBlockByNumber"], setup.controller)
+        .await;
+    assert_eq!(
+        setup.get_config().await.suspended_methods,
+        vec!["eth_getBlockByNumber".to_string()]
+    );
+
+    let client = setup.client(MockHttpOutcalls::never()).build();
+    let result = client
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .send()
+        .await
+        .expect_consistent();
+    assert_eq!(
+        result,
+        Err(RpcError::ProviderError(ProviderError::Suspended {
+            method: "eth_getBlockByNumber".to_string()
+        }))
+    );
+    // Cost queries are not affected
+    let cycles_cost = client
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .request_cost()
+        .send()
+        .await
+        .unwrap();
+    assert!(cycles_cost > 0);
+
+    setup.set_suspended_methods(&[], setup.controller).await;
+    assert_eq!(
+        setup.get_config().await.suspended_methods,
+        Vec::<String>::new()
+    );
+
+    let mocks = MockHttpOutcallsBuilder::new()
+        .given(get_block_by_number_request().with_id(0))
+        .respond_with(get_block_by_number_response().with_id(0))
+        .given(get_block_by_number_request().with_id(1))
+        .respond_with(get_block_by_number_response().with_id(1))
+        .given(get_block_by_number_request().with_id(2))
+        .respond_with(get_block_by_number_response().with_id(2));
+    let block = setup
+        .client(mocks)
+        .build()
+        .get_block_by_number(BlockNumberOrTag::Latest)
+        .send()
+        .await
+        .expect_consistent()
+        .unwrap();
+    assert_eq!(block.header.number, 0x11db01d);
+}
+
+#[tokio::test]
+#[should_panic(expected = "Method cannot be suspended: eth_getBalance")]
+async fn should_not_suspend_unknown_method() {
+    let setup = EvmRpcSetup::new().await;
+    setup
+        .set_suspended_methods(&["eth_getBalance"], setup.controller)
+        .await;
+}
+
 #[tokio::test]
 async fn should_reject_http_request_in_replicated_mode() {
     let request = HttpRequest {
@@ -2188,6 +2608,303 @@ async fn should_reject_http_request_in_replicated_mode() {
     );
 }
 
+#[tokio::test]
+async fn json_rpc_gateway_should_charge_gateway_account() {
+    const DEPOSIT: u128 = 100_000_000_000_000;
+    let block_number =
+        json!({"jsonrpc": "2.0", "id": 1, "method": "eth_blockNumber", "params": []});
+
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    setup
+        .set_json_rpc_gateways(vec![JsonRpcGateway {
+            source: RpcServices::EthMainnet(None),
+            config: None,
+        }])
+        .await;
+    let account = setup.get_json_rpc_gateway_account(1).await;
+
+    let response = setup
+        .json_rpc_http_request(
+            MockHttpOutcalls::never(),
+            1,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "eth_chainId", "params": []}),
+        )
+        .await;
+    assert_eq!(response["result"], json!("0x1"));
+
+    // The gateway account is empty, so that no HTTP outcalls are made
+    let response = setup
+        .json_rpc_http_request(MockHttpOutcalls::never(), 1, block_number.clone())
+        .await;
+    assert_eq!(response["error"]["code"], json!(-32000));
+
+    assert_eq!(setup.deposit_json_rpc_gateway(1, DEPOSIT).await, DEPOSIT);
+    assert_eq!(setup.get_balance(account).await, DEPOSIT);
+
+    // Advance ID by 3 to account for the request with insufficient cycles, for which only the
+    // request IDs were generated.
+    let mocks = MockHttpOutcallsBuilder::new()
+        .given(block_number_request().with_id(3))
+        .respond_with(block_number_response().with_id(3))
+        .given(block_number_request().with_id(4))
+        .respond_with(block_number_response().with_id(4))
+        .given(block_number_request().with_id(5))
+        .respond_with(block_number_response().with_id(5));
+    let response = setup.json_rpc_http_request(mocks, 1, block_number).await;
+    assert_eq!(
+        response,
+        json!({"jsonrpc": "2.0", "id": 1, "result": "0x11db01d"})
+    );
+
+    let balance = setup.get_balance(account).await;
+    assert!(
+        0 < balance && balance < DEPOSIT,
+        "expected the balance {balance} of the gateway account to be charged for the request"
+    );
+    assert_eq!(setup.get_balance(setup.wallet_canister_id).await, 0);
+    assert_eq!(setup.get_balance(Principal::anonymous()).await, 0);
+}
+
+#[tokio::test]
+async fn json_rpc_gateway_should_not_answer_notifications() {
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    setup
+        .set_json_rpc_gateways(vec![JsonRpcGateway {
+            source: RpcServices::EthMainnet(None),
+            config: None,
+        }])
+        .await;
+    let chain_id_notification = json!({"jsonrpc": "2.0", "method": "eth_chainId", "params": []});
+
+    let response = setup
+        .json_rpc_http_response(MockHttpOutcalls::never(), 1, chain_id_notification.clone())
+        .await;
+    assert!(response.body.is_empty());
+
+    let response = setup
+        .json_rpc_http_response(
+            MockHttpOutcalls::never(),
+            1,
+            json!([chain_id_notification, chain_id_notification]),
+        )
+        .await;
+    assert!(response.body.is_empty());
+
+    let response = setup
+        .json_rpc_http_request(
+            MockHttpOutcalls::never(),
+            1,
+            json!([
+                chain_id_notification,
+                {"jsonrpc": "2.0", "id": 2, "method": "eth_chainId", "params": []}
+            ]),
+        )
+        .await;
+    assert_eq!(
+        response,
+        json!([{"jsonrpc": "2.0", "id": 2, "result": "0x1"}])
+    );
+}
+
+#[tokio::test]
+async fn subscribe_logs_should_require_minimum_balance() {
+    const MINIMUM_BALANCE: u128 = 10_000_000_000;
+
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let wallet = setup.wallet_canister_id;
+    let args = SubscribeLogsArgs {
+        source: RpcServices::EthMainnet(None),
+        config: None,
+        addresses: vec![MOCK_ADDRESS.into()],
+        topics: None,
+        from_block: Nat256::from(18_722_845_u64),
+        confirmations: 12,
+        callback: "onLogs".to_string(),
+    };
+
+    assert_eq!(
+        setup
+            .subscribe_logs(args.clone(), MINIMUM_BALANCE - 1)
+            .await,
+        Err(SubscribeError::InsufficientBalance {
+            balance: MINIMUM_BALANCE - 1,
+            minimum: MINIMUM_BALANCE,
+        })
+    );
+    assert_eq!(setup.get_balance(wallet).await, 0);
+    assert_eq!(
+        setup.get_log_subscriptions(wallet, setup.controller).await,
+        vec![]
+    );
+
+    let id = setup
+        .subscribe_logs(args.clone(), MINIMUM_BALANCE)
+        .await
+        .unwrap();
+    assert_eq!(setup.get_balance(wallet).await, MINIMUM_BALANCE);
+    assert_eq!(
+        setup.get_log_subscriptions(wallet, setup.controller).await,
+        vec![LogSubscription {
+            id,
+            owner: wallet,
+            source: args.source,
+            config: args.config,
+            addresses: args.addresses,
+            topics: args.topics,
+            confirmations: args.confirmations,
+            callback: args.callback,
+            next_block: args.from_block,
+            last_error: None,
+        }]
+    );
+    assert_eq!(
+        setup
+            .get_log_subscriptions(wallet, Principal::anonymous())
+            .await,
+        vec![]
+    );
+
+    // The subscription is removed once the balance cannot pay for polling the logs
+    setup.withdraw(1_000_000_000).await.unwrap();
+    setup
+        .advance_time_and_run_timers(Duration::from_secs(60))
+        .await;
+    assert_eq!(
+        setup.get_log_subscriptions(wallet, setup.controller).await,
+        vec![]
+    );
+}
+
+#[tokio::test]
+async fn watch_transaction_should_require_minimum_balance() {
+    const MINIMUM_BALANCE: u128 = 10_000_000_000;
+
+    let setup = EvmRpcSetup::new().await.mock_api_keys().await;
+    let wallet = setup.wallet_canister_id;
+    let args = WatchTransactionArgs {
+        source: RpcServices::EthMainnet(None),
+        config: None,
+        transaction_hash: MOCK_TRANSACTION_HASH.into(),
+        sender: None,
+        nonce: None,
+        confirmations: Some(12),
+        callback: "onTransaction".to_string(),
+    };
+
+    assert_eq!(
+        setup
+            .watch_transaction(args.clone(), MINIMUM_BALANCE - 1)
+            .await,
+        Err(SubscribeError::InsufficientBalance {
+            balance: MINIMUM_BALANCE - 1,
+            minimum: MINIMUM_BALANCE,
+        })
+    );
+    assert_eq!(setup.get_balance(wallet).await, 0);
+    assert_eq!(
+        setup
+            .get_transaction_watches(wallet, setup.controller)
+            .await,
+        vec![]
+    );
+
+    let id = setup
+        .watch_transaction(args.clone(), MINIMUM_BALANCE)
+        .await
+        .unwrap();
+    assert_eq!(setup.get_balance(wallet).await, MINIMUM_BALANCE);
+    assert_matches!(
+        setup.get_transaction_watches(wallet, setup.controller).await.as_slice(),
+        [watch] if watch.id == id
+            && watch.owner == wallet
+            && watch.transaction_hash == args.transaction_hash
+            && watch.confirmations == args.confirmations
+            && watch.callback == args.callback
+            && watch.receipt.is_none()
+            && watch.last_error.is_none()
+    );
+    assert_eq!(
+        setup
+            .get_transaction_watches(wallet, Principal::anonymous())
+            .await,
+        vec![]
+    );
+
+    // The watch is removed once the balance cannot pay for polling the transaction
+    setup.withdraw(1_000_000_000).await.unwrap();
+    setup
+        .advance_time_and_run_timers(Duration::from_secs(60))
+        .await;
+    assert_eq!(
+        setup
+            .get_transaction_watches(wallet, setup.controller)
+            .await,
+        vec![]
+    );
+}
+
+#[tokio::test]
+async fn should_track_chain_head() {
+    let setup = EvmRpcSetup::new().await;
+    setup
+        .set_head_trackers(
+            vec![HeadTracker {
+                source: RpcServices::EthMainnet(Some(vec![EthMainnetService::PublicNode])),
+                config: None,
+            }],
+            setup.controller,
+        )
+        .await;
+    assert_eq!(setup.get_chain_head(1).await, None);
+
+    setup
+        .advance_time_and_run_timers_with_http_outcalls(Duration::from_secs(15), |request| {
+            assert_eq!(request["method"], "eth_getBlockByNumber");
+            let mut response = match request["params"][0].as_str() {
+                Some("latest") | Some("finalized") => get_block_by_number_response_body(),
+                // Not all providers support the `safe` block tag
+                _ => json!({
+                    "jsonrpc": "2.0",
+                    "error": {"code": -32602, "message": "invalid block tag"},
+                }),
+            };
+            response["id"] = request["id"].clone();
+            response
+        })
+        .await;
+
+    let header = BlockHeader {
+        number: Nat256::from(18_722_845_u64),
+        hash: b256!("0xc3674be7b9d95580d7f23c03d32e946f2b453679ee6505e3a778f003c5a3cfae").into(),
+        parent_hash: b256!("0x43325027f6adf9befb223f8ae80db057daddcd7b48e41f60cd94bfa8877181ae")
+            .into(),
+    };
+    assert_matches!(
+        setup.get_chain_head(1).await,
+        Some(ChainHead {
+            latest,
+            safe: None,
+            finalized: Some(finalized),
+            ..
+        }) if latest == header && finalized == header
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "You are not authorized")]
+async fn should_prevent_unauthorized_set_head_trackers() {
+    let setup = EvmRpcSetup::new().await;
+    setup
+        .set_head_trackers(
+            vec![HeadTracker {
+                source: RpcServices::EthMainnet(None),
+                config: None,
+            }],
+            setup.caller,
+        )
+        .await;
+}
+
 #[tokio::test]
 async fn should_retrieve_logs() {
     let setup = EvmRpcSetup::new().await;
@@ -3034,6 +3751,16 @@ fn fee_history_request() -> JsonRpcRequestMatcher {
         .with_id(0)
 }
 
+fn block_number_request() -> JsonRpcRequestMatcher {
+    JsonRpcRequestMatcher::with_method("eth_blockNumber")
+        .with_params(json!([]))
+        .with_id(0)
+}
+
+fn block_number_response() -> JsonRpcResponse {
+    JsonRpcResponse::from(json!({"jsonrpc": "2.0", "result": "0x11db01d", "id": 0}))
+}
+
 fn get_block_by_number_request() -> JsonRpcRequestMatcher {
     JsonRpcRequestMatcher::with_method("eth_getBlockByNumber")
         .with_params(json!(["latest", false]))
@@ -3095,7 +3822,11 @@ fn fee_history_response() -> JsonRpcResponse {
 }
 
 fn get_block_by_number_response() -> JsonRpcResponse {
-    JsonRpcResponse::from(json!({
+    JsonRpcResponse::from(get_block_by_number_response_body())
+}
+
+fn get_block_by_number_response_body() -> Value {
+    json!({
         "jsonrpc": "2.0",
         "result": {
             "baseFeePerGas": "0xd7232aa34",
@@ -3119,7 +3850,7 @@ fn get_block_by_number_response() -> JsonRpcResponse {
             "transactionsRoot": "0x93a1ad3d067009259b508cc95fde63b5efd7e9d8b55754314c173fdde8c0826a",
         },
         "id" : ConstantSizeId::ZERO.to_string(),
-    }))
+    })
 }
 
 fn get_logs_response() -> JsonRpcResponse {